@@ -28,7 +28,7 @@ pub struct ArrowPointerIns {
     pub direction: f32,
 }
 
-static MAIN_SHADER: Shader = Shader {
+pub(crate) static MAIN_SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -10,7 +10,7 @@ struct BackgroundIns {
     radius: f32,
 }
 
-static SHADER: Shader = Shader {
+pub(crate) static SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -17,7 +17,7 @@ struct BgIns {
     down: f32,
 }
 
-static SHADER: Shader = Shader {
+pub(crate) static SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -123,6 +123,15 @@ impl Button {
             let rect = cx.end_padding_box();
 
             self.bg_area = cx.add_instances(&SHADER, &[BgIns { base: QuadIns::from_rect(rect), ..Default::default() }]);
+            cx.add_accessibility_node(
+                self.component_id,
+                AccessibilityNodeProps {
+                    role: AccessibilityRole::Button,
+                    label: label.to_string(),
+                    area: self.bg_area,
+                    ..Default::default()
+                },
+            );
         }
         cx.end_padding_box();
 
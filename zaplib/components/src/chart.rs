@@ -338,6 +338,7 @@ pub struct Chart {
     zoom_enabled: bool,
     pan_enabled: bool,
     panning: bool,
+    gestures: GestureRecognizer,
 
     /// Set to `None` when we're viewing the whole chart (e.g. initial state,
     /// and when clicking the "reset view" button) which means that the axes
@@ -351,15 +352,43 @@ pub struct Chart {
 
 impl Chart {
     pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> ChartEvent {
-        match event.hits_pointer(cx, self.component_id, self.texture_area.get_rect_for_first_instance(cx)) {
+        let hit_event = event.hits_pointer_multi_touch(cx, self.component_id, self.texture_area.get_rect_for_first_instance(cx));
+        match self.gestures.handle(cx, &hit_event) {
+            GestureEvent::TransformStart => {
+                // Stop single-finger panning; the remaining finger shouldn't pan after the gesture ends either.
+                self.panning = false;
+            }
+            GestureEvent::Transform(te) => {
+                if self.zoom_pan.is_none() {
+                    self.zoom_pan = Some(self.bounds);
+                }
+
+                if let Some(zoom_pan) = &mut self.zoom_pan {
+                    if self.zoom_enabled {
+                        // Same as for scrolling below, but keeping the point between the fingers in place.
+                        zoom_pan.size *= te.scale;
+                        zoom_pan.pos = te.rel_center - te.scale * (te.rel_center - zoom_pan.pos);
+                    }
+                    if self.pan_enabled {
+                        zoom_pan.pos += te.pan;
+                    }
+                }
+
+                cx.request_draw();
+                return ChartEvent::None;
+            }
+            _ => (),
+        }
+
+        match hit_event {
             Event::PointerDown(pd) => {
-                if self.pan_enabled {
+                if self.pan_enabled && !self.gestures.is_transforming() {
                     self.last_pointer_pos = pd.rel;
                     self.panning = true;
                 }
             }
             Event::PointerMove(pm) => {
-                if self.pan_enabled {
+                if self.panning {
                     let delta_pan = pm.rel - self.last_pointer_pos;
                     self.last_pointer_pos = pm.rel;
 
@@ -11,7 +11,7 @@ struct CheckboxIns {
     down: f32,
 }
 
-static SHADER: Shader = Shader {
+pub(crate) static SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -79,6 +79,7 @@ pub struct Checkbox {
     component_id: ComponentId,
     area: Area,
     animator: Animator,
+    tab_index: Option<u32>,
 }
 
 #[derive(Clone, PartialEq)]
@@ -123,6 +124,12 @@ const ANIM_DOWN: Anim = Anim {
 };
 
 impl Checkbox {
+    /// Order in which the checkbox gets focus when pressing Tab; see [`Cx::add_key_focusable`].
+    #[must_use]
+    pub fn with_tab_index(self, tab_index: u32) -> Self {
+        Self { tab_index: Some(tab_index), ..self }
+    }
+
     fn animator_animate(&mut self, cx: &mut Cx) {
         let checkbox = self.area.get_first_mut::<CheckboxIns>(cx);
         checkbox.hover = self.animator.get_float(0);
@@ -144,11 +151,8 @@ impl Checkbox {
 
         match event.hits_pointer(cx, self.component_id, self.area.get_rect_for_first_instance(cx)) {
             Event::PointerDown(_pe) => {
-                let checkbox = self.area.get_first::<CheckboxIns>(cx);
-                if checkbox.checked < 1.0 {
-                    self.animator.play_anim(cx, ANIM_DOWN);
-                }
-                return CheckboxEvent::Toggled;
+                cx.set_key_focus(Some(self.component_id));
+                return self.toggle(cx);
             }
             Event::PointerHover(pe) => {
                 cx.set_hover_mouse_cursor(MouseCursor::Hand);
@@ -165,6 +169,13 @@ impl Checkbox {
             _ => (),
         }
 
+        match event.hits_keyboard(cx, self.component_id) {
+            Event::KeyFocus(_) => self.animator.play_anim(cx, ANIM_HOVER),
+            Event::KeyFocusLost(_) => self.animator.play_anim(cx, ANIM_DEFAULT),
+            Event::KeyDown(ke) if ke.key_code == KeyCode::Space => return self.toggle(cx),
+            _ => (),
+        }
+
         if let Event::NextFrame = event {
             self.manual_animate(cx);
         }
@@ -172,6 +183,14 @@ impl Checkbox {
         CheckboxEvent::None
     }
 
+    fn toggle(&mut self, cx: &mut Cx) -> CheckboxEvent {
+        let checkbox = self.area.get_first::<CheckboxIns>(cx);
+        if checkbox.checked < 1.0 {
+            self.animator.play_anim(cx, ANIM_DOWN);
+        }
+        CheckboxEvent::Toggled
+    }
+
     pub fn draw(&mut self, cx: &mut Cx, checked: bool, loaded: bool, errored: bool, label: &str, fade_in_time: f64) {
         cx.begin_shader_group(&[&SHADER, &TEXT_INS_SHADER]);
 
@@ -198,6 +217,17 @@ impl Checkbox {
                     ..Default::default()
                 }],
             );
+            cx.add_key_focusable(self.component_id, self.tab_index);
+            cx.add_accessibility_node(
+                self.component_id,
+                AccessibilityNodeProps {
+                    role: AccessibilityRole::Checkbox,
+                    label: label.to_string(),
+                    checked: Some(checked),
+                    area: self.area,
+                    ..Default::default()
+                },
+            );
 
             cx.add_box(LayoutSize::new(Width::Fix(20.), Height::Fix(0.)));
             let draw_str_props = TextInsProps {
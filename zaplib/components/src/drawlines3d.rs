@@ -1,3 +1,4 @@
+use crate::*;
 use zaplib::*;
 
 #[repr(C)]
@@ -102,23 +103,35 @@ struct DrawLines3dUniforms {
 pub struct DrawLines3dOptions {
     /// Custom transformation to do on all vertices
     pub vertex_transform: Mat4,
+    /// Skip line segments that are outside of this frustum, so they don't get uploaded to the GPU; typically from
+    /// [`Viewport3D::get_frustum`]. Skipped segments are counted in [`RenderStats::culled_instances`].
+    pub frustum: Option<Frustum3d>,
 }
 
 impl Default for DrawLines3dOptions {
     fn default() -> Self {
-        Self { vertex_transform: Mat4::identity() }
+        Self { vertex_transform: Mat4::identity(), frustum: None }
     }
 }
 pub struct DrawLines3d {}
 
 impl DrawLines3d {
     pub fn draw(cx: &mut Cx, data: &[DrawLines3dInstance], options: DrawLines3dOptions) {
-        let area = cx.add_instances(&SHADER, data);
+        let area = if let Some(frustum) = options.frustum {
+            let frustum = frustum.transform(&options.vertex_transform);
+            let visible = frustum.cull_instances(cx, data, |line| {
+                let center = (line.position_start + line.position_end) * 0.5;
+                (center, (line.position_end - line.position_start).length() * 0.5 + line.scale)
+            });
+            cx.add_instances(&SHADER, &visible)
+        } else {
+            cx.add_instances(&SHADER, data)
+        };
         area.write_user_uniforms(cx, DrawLines3dUniforms { vertex_transform: options.vertex_transform });
     }
 }
 
-static SHADER: Shader = Shader {
+pub(crate) static SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -1,6 +1,7 @@
+use crate::*;
 use zaplib::*;
 
-static SHADER: Shader = Shader {
+pub(crate) static SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -94,11 +95,14 @@ pub struct DrawPoints3dOptions {
     pub point_style: DrawPoints3dStyle,
     /// Custom transformation to do on all vertices
     pub vertex_transform: Mat4,
+    /// Skip points that are outside of this frustum, so they don't get uploaded to the GPU; typically from
+    /// [`Viewport3D::get_frustum`]. Skipped points are counted in [`RenderStats::culled_instances`].
+    pub frustum: Option<Frustum3d>,
 }
 
 impl Default for DrawPoints3dOptions {
     fn default() -> Self {
-        Self { use_screen_space: false, point_style: DrawPoints3dStyle::Quad, vertex_transform: Mat4::identity() }
+        Self { use_screen_space: false, point_style: DrawPoints3dStyle::Quad, vertex_transform: Mat4::identity(), frustum: None }
     }
 }
 
@@ -109,7 +113,20 @@ impl DrawPoints3d {
     /// Following Webviz's implementation, points can be rendered in either world or screen space using the `use_screen_space`
     /// flag. Regardless of the render space, all points are rendered as billboards, facing the camera.
     pub fn draw(cx: &mut Cx, data: &[DrawPoints3dInstance], options: DrawPoints3dOptions) -> Area {
-        let area = cx.add_instances(&SHADER, data);
+        let area = if let Some(frustum) = options.frustum {
+            let frustum = frustum.transform(&options.vertex_transform);
+            // Use the full size as radius, to also cover the corners of the quads.
+            let visible = frustum.cull_instances(cx, data, |point| {
+                if options.use_screen_space {
+                    (point.position, frustum.pixels_to_world(point.position, point.size))
+                } else {
+                    (point.position, point.size)
+                }
+            });
+            cx.add_instances(&SHADER, &visible)
+        } else {
+            cx.add_instances(&SHADER, data)
+        };
 
         let rect = cx.get_box_rect();
         area.write_user_uniforms(
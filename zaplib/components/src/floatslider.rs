@@ -1,6 +1,6 @@
 use zaplib::*;
 
-static BACKGROUND_SHADER: Shader = Shader {
+pub(crate) static BACKGROUND_SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -27,7 +27,7 @@ static BACKGROUND_SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
-static KNOB_SHADER: Shader = Shader {
+pub(crate) static KNOB_SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -90,6 +90,7 @@ pub struct FloatSlider {
     step: Option<f32>,
     area: Area,
     dragging: bool,
+    tab_index: Option<u32>,
 }
 
 pub struct FloatSliderBackgroundRange {
@@ -165,11 +166,18 @@ impl Default for FloatSlider {
             step: Default::default(),
             area: Default::default(),
             dragging: Default::default(),
+            tab_index: Default::default(),
         }
     }
 }
 
 impl FloatSlider {
+    /// Order in which the slider gets focus when pressing Tab; see [`Cx::add_key_focusable`].
+    #[must_use]
+    pub fn with_tab_index(self, tab_index: u32) -> Self {
+        Self { tab_index: Some(tab_index), ..self }
+    }
+
     fn animate(&mut self, cx: &mut Cx) {
         let slider = self.area.get_first_mut::<FloatSliderIns>(cx);
         slider.hover = self.animator.get_float(0);
@@ -216,6 +224,7 @@ impl FloatSlider {
                 }
             }
             Event::PointerDown(pe) => {
+                cx.set_key_focus(Some(self.component_id));
                 self.animator.play_anim(cx, ANIM_DOWN);
                 cx.set_down_mouse_cursor(MouseCursor::Arrow);
                 self.dragging = true;
@@ -246,9 +255,52 @@ impl FloatSlider {
             }
             _ => (),
         }
+
+        match event.hits_keyboard(cx, self.component_id) {
+            Event::KeyFocus(_) => self.animator.play_anim(cx, ANIM_HOVER),
+            Event::KeyFocusLost(_) => self.animator.play_anim(cx, ANIM_DEFAULT),
+            Event::KeyDown(ke) => {
+                let step = self.step.filter(|step| *step > 0.0).unwrap_or((self.max - self.min) / 100.);
+                let scaled_value = match ke.key_code {
+                    KeyCode::ArrowLeft | KeyCode::ArrowDown => self.scaled_value - step,
+                    KeyCode::ArrowRight | KeyCode::ArrowUp => self.scaled_value + step,
+                    KeyCode::Home => self.min,
+                    KeyCode::End => self.max,
+                    _ => return FloatSliderEvent::None,
+                };
+                return self.handle_key(cx, scaled_value);
+            }
+            Event::KeyUp(ke) => {
+                if matches!(
+                    ke.key_code,
+                    KeyCode::ArrowLeft
+                        | KeyCode::ArrowDown
+                        | KeyCode::ArrowRight
+                        | KeyCode::ArrowUp
+                        | KeyCode::Home
+                        | KeyCode::End
+                ) {
+                    return FloatSliderEvent::DoneChanging;
+                }
+            }
+            _ => (),
+        }
         FloatSliderEvent::None
     }
 
+    fn handle_key(&mut self, cx: &mut Cx, scaled_value: f32) -> FloatSliderEvent {
+        let scaled_value = scaled_value.min(self.max).max(self.min);
+        #[allow(clippy::float_cmp)]
+        if scaled_value == self.scaled_value {
+            return FloatSliderEvent::None;
+        }
+        self.scaled_value = scaled_value;
+        self.norm_value = (scaled_value - self.min) / (self.max - self.min);
+        let slider = self.area.get_first_mut::<FloatSliderIns>(cx);
+        slider.norm_value = self.norm_value;
+        FloatSliderEvent::Change { scaled_value }
+    }
+
     pub fn draw(
         &mut self,
         cx: &mut Cx,
@@ -296,6 +348,7 @@ impl FloatSlider {
             &[FloatSliderIns { base: QuadIns::from_rect(rect), norm_value: self.norm_value, ..Default::default() }],
         );
         self.area.write_user_uniforms(cx, HOR_PAD);
+        cx.add_key_focusable(self.component_id, self.tab_index);
 
         self.animator.draw(cx, ANIM_DEFAULT);
         self.animate(cx);
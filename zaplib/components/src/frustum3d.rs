@@ -0,0 +1,143 @@
+//! Skipping 3d instances that are outside of the camera's view, so they don't get uploaded to the GPU.
+//!
+//! Get a [`Frustum3d`] using [`crate::Viewport3D::get_frustum`], and pass it into e.g.
+//! [`crate::DrawPoints3dOptions::frustum`], or use [`Frustum3d::cull_instances`] before calling
+//! [`Cx::add_mesh_instances`] yourself.
+
+use zaplib::*;
+
+/// The part of 3d space that is visible with a particular camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum3d {
+    /// Left, right, bottom, top, near, and far planes. The normal (`x`, `y`, `z`) is normalized and points inwards,
+    /// so `plane.dot(vec4(p.x, p.y, p.z, 1.))` is the distance of `p` to the plane, and negative outside.
+    planes: [Vec4; 6],
+    /// Row of the view projection matrix that computes `w` in clip space, which is the depth for a perspective
+    /// projection, and 1 for an orthographic one.
+    w_row: Vec4,
+    /// How big a logical pixel on the screen is in world units, per unit of `w`.
+    pixel_size_per_w: f32,
+}
+
+impl Frustum3d {
+    /// The frustum for a camera `view` matrix and a `projection` matrix, as set in [`PassMatrixMode`], for a
+    /// viewport that is `viewport_height` logical pixels high.
+    pub fn new(view: &Mat4, projection: &Mat4, viewport_height: f32) -> Self {
+        let view_projection = Mat4::mul(view, projection);
+        let row = |i: usize| {
+            let v = &view_projection.v;
+            vec4(v[i], v[4 + i], v[8 + i], v[12 + i])
+        };
+        // See "Fast Extraction of Viewing Frustum Planes from the World-View-Projection Matrix" by Gil Gribb and
+        // Klaus Hartmann; clip space goes from -w to w for x, y, and z.
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let planes = [w + x, w - x, w + y, w - y, w + z, w - z];
+        Self { planes: planes.map(normalize_plane), w_row: w, pixel_size_per_w: 2. / (projection.v[5] * viewport_height) }
+    }
+
+    /// The same frustum, but for positions that get transformed by `matrix` first, like
+    /// [`crate::DrawPoints3dOptions::vertex_transform`].
+    #[must_use]
+    pub fn transform(&self, matrix: &Mat4) -> Self {
+        // A plane transforms as a row vector multiplied by the matrix.
+        let transform_row = |row: Vec4| {
+            let column = |j: usize| vec4(matrix.v[4 * j], matrix.v[4 * j + 1], matrix.v[4 * j + 2], matrix.v[4 * j + 3]);
+            vec4(row.dot(column(0)), row.dot(column(1)), row.dot(column(2)), row.dot(column(3)))
+        };
+        Self {
+            planes: self.planes.map(|plane| normalize_plane(transform_row(plane))),
+            w_row: transform_row(self.w_row),
+            pixel_size_per_w: self.pixel_size_per_w,
+        }
+    }
+
+    /// If any part of the sphere might be visible.
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        let center = vec4(center.x, center.y, center.z, 1.);
+        self.planes.iter().all(|plane| plane.dot(center) >= -radius)
+    }
+
+    /// If any part of the axis-aligned box between `min` and `max` might be visible.
+    pub fn intersects_box(&self, min: Vec3, max: Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            // Only the corner furthest along the normal needs to be inside.
+            let corner = vec4(
+                if plane.x >= 0. { max.x } else { min.x },
+                if plane.y >= 0. { max.y } else { min.y },
+                if plane.z >= 0. { max.z } else { min.z },
+                1.,
+            );
+            plane.dot(corner) >= 0.
+        })
+    }
+
+    /// How big something of `pixels` logical pixels on the screen is in world units at `position`; for culling
+    /// things that have a size in pixels, like [`crate::DrawPoints3dOptions::use_screen_space`].
+    pub fn pixels_to_world(&self, position: Vec3, pixels: f32) -> f32 {
+        let w = self.w_row.dot(vec4(position.x, position.y, position.z, 1.));
+        pixels * self.pixel_size_per_w * w.abs()
+    }
+
+    /// The `instances` for which the sphere returned by `bounding_sphere` (as center and radius) might be visible.
+    /// The number of instances that were left out gets added to [`RenderStats::culled_instances`].
+    pub fn cull_instances<T: Copy>(&self, cx: &mut Cx, instances: &[T], bounding_sphere: impl Fn(&T) -> (Vec3, f32)) -> Vec<T> {
+        let visible: Vec<T> = instances
+            .iter()
+            .filter(|instance| {
+                let (center, radius) = bounding_sphere(instance);
+                self.intersects_sphere(center, radius)
+            })
+            .copied()
+            .collect();
+        cx.add_culled_instances(instances.len() - visible.len());
+        visible
+    }
+}
+
+fn normalize_plane(plane: Vec4) -> Vec4 {
+    let length = plane.to_vec3().length();
+    if length > 0. {
+        plane / length
+    } else {
+        plane
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frustum_perspective() {
+        let projection = Mat4::perspective(90., 2., 0.1, 100.);
+        let frustum = Frustum3d::new(&Mat4::identity(), &projection, 100.);
+
+        assert!(frustum.intersects_sphere(vec3(0., 0., -10.), 0.));
+        assert!(frustum.intersects_sphere(vec3(19., 0., -10.), 0.));
+        assert!(!frustum.intersects_sphere(vec3(21., 0., -10.), 0.));
+        assert!(frustum.intersects_sphere(vec3(21., 0., -10.), 1.));
+        assert!(!frustum.intersects_sphere(vec3(0., 11., -10.), 0.));
+        assert!(!frustum.intersects_sphere(vec3(0., 0., 10.), 1.));
+        assert!(!frustum.intersects_sphere(vec3(0., 0., -110.), 1.));
+
+        assert!(frustum.intersects_box(vec3(-30., -1., -11.), vec3(-15., 1., -9.)));
+        assert!(!frustum.intersects_box(vec3(-30., -1., -11.), vec3(-25., 1., -9.)));
+
+        // A pixel at a depth of 10 is 2 * 10 / 100 world units, since the vertical field of view is 90 degrees.
+        assert!((frustum.pixels_to_world(vec3(0., 0., -10.), 5.) - 1.).abs() < 1e-5);
+
+        let moved = frustum.transform(&Mat4::translation(0., 0., -20.));
+        assert!(!moved.intersects_sphere(vec3(0., 0., 25.), 0.));
+        assert!(moved.intersects_sphere(vec3(0., 0., 10.), 0.));
+    }
+
+    #[test]
+    fn test_frustum_orthographic() {
+        let projection = Mat4::orthographic(10., 1., -100., 100.);
+        let frustum = Frustum3d::new(&Mat4::identity(), &projection, 100.);
+
+        assert!(frustum.intersects_sphere(vec3(4., -4., 50.), 0.));
+        assert!(!frustum.intersects_sphere(vec3(6., 0., -50.), 0.));
+        assert!((frustum.pixels_to_world(vec3(0., 0., -50.), 10.) - 1.).abs() < 1e-5);
+    }
+}
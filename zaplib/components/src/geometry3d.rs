@@ -37,6 +37,11 @@ impl Geometry3d {
     "#
     );
 
+    /// A vertex with `geom_id` set to 0.0.
+    pub fn new(geom_pos: Vec3, geom_normal: Vec3, geom_uv: Vec2) -> Self {
+        Self { geom_pos, geom_id: 0., geom_normal, geom_uv }
+    }
+
     /// 3d cube.
     pub fn cube(
         width: f32,
@@ -53,8 +53,20 @@ mod viewport3d;
 pub use crate::viewport3d::*;
 mod fps_counter;
 pub use crate::fps_counter::*;
+mod profiler_overlay;
+pub use crate::profiler_overlay::*;
+mod render_stats_overlay;
+pub use crate::render_stats_overlay::*;
 mod geometry3d;
 pub use crate::geometry3d::*;
+mod material;
+pub use crate::material::*;
+mod scene3d;
+pub use crate::scene3d::*;
+mod picking3d;
+pub use crate::picking3d::*;
+mod frustum3d;
+pub use crate::frustum3d::*;
 
 mod chart;
 pub use crate::chart::*;
@@ -67,3 +79,53 @@ pub use crate::arrow_pointer::*;
 
 mod internal;
 pub(crate) use crate::internal::*;
+
+#[cfg(test)]
+mod tests {
+    use zaplib::*;
+    use zaplib_shader_compiler::{generate_shader_ast::ShaderAstGenerator, generate_wgsl};
+
+    #[test]
+    fn generates_valid_wgsl_for_all_shaders() {
+        let shaders: &[(&str, &Shader)] = &[
+            ("arrow_pointer::MAIN_SHADER", &crate::arrow_pointer::MAIN_SHADER),
+            ("background::SHADER", &crate::background::SHADER),
+            ("button::SHADER", &crate::button::SHADER),
+            ("checkbox::SHADER", &crate::checkbox::SHADER),
+            ("drawlines3d::SHADER", &crate::drawlines3d::SHADER),
+            ("drawpoints3d::SHADER", &crate::drawpoints3d::SHADER),
+            ("floatslider::BACKGROUND_SHADER", &crate::floatslider::BACKGROUND_SHADER),
+            ("floatslider::KNOB_SHADER", &crate::floatslider::KNOB_SHADER),
+            ("foldcaption::SHADER", &crate::foldcaption::SHADER),
+            ("fps_counter::SHADER", &crate::fps_counter::SHADER),
+            ("internal::desktopbutton::SHADER", &crate::internal::desktopbutton::SHADER),
+            ("internal::scrollbar::SHADER", &crate::internal::scrollbar::SHADER),
+            ("internal::tabclose::SHADER", &crate::internal::tabclose::SHADER),
+            ("material::UNLIT_SHADER", &crate::material::UNLIT_SHADER),
+            ("material::LAMBERT_SHADER", &crate::material::LAMBERT_SHADER),
+            ("material::BLINN_PHONG_SHADER", &crate::material::BLINN_PHONG_SHADER),
+            ("material::PBR_SHADER", &crate::material::PBR_SHADER),
+            ("profiler_overlay::SHADER", &crate::profiler_overlay::SHADER),
+            ("scrollshadow::SHADER", &crate::scrollshadow::SHADER),
+            ("skybox::SHADER", &crate::skybox::SHADER),
+            ("tab::SHADER", &crate::tab::SHADER),
+            ("texteditor::SHADER_INDENT_LINES", &crate::texteditor::SHADER_INDENT_LINES),
+            ("texteditor::SHADER_CURSOR", &crate::texteditor::SHADER_CURSOR),
+            ("texteditor::SHADER_SELECTION", &crate::texteditor::SHADER_SELECTION),
+            ("texteditor::SHADER_PAREN_PAIR", &crate::texteditor::SHADER_PAREN_PAIR),
+            ("texteditor::SHADER_SEARCH_MARKER", &crate::texteditor::SHADER_SEARCH_MARKER),
+            ("texteditor::SHADER_MESSAGE_MARKER", &crate::texteditor::SHADER_MESSAGE_MARKER),
+        ];
+        let shader_ast_generator = ShaderAstGenerator::new();
+        for &(name, shader) in shaders {
+            let shader_ast = shader_ast_generator
+                .generate_shader_ast(shader.code_to_concatenate)
+                .unwrap_or_else(|err| panic!("{}: {}", name, err.format_for_console(shader.code_to_concatenate)));
+            let wgsl = generate_wgsl::generate_shader(&shader_ast);
+            let module = naga::front::wgsl::parse_str(&wgsl).unwrap_or_else(|err| panic!("{}: {:?}\n{}", name, err, wgsl));
+            naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::empty())
+                .validate(&module)
+                .unwrap_or_else(|err| panic!("{}: {:?}\n{}", name, err, wgsl));
+        }
+    }
+}
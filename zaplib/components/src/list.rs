@@ -3,6 +3,7 @@ use zaplib::*;
 
 #[derive(Default)]
 pub struct List {
+    component_id: ComponentId,
     pub multi_select: bool,
     pub list_items: Vec<ListItem>,
     pub scroll_item_in_view: Option<usize>,
@@ -21,6 +22,7 @@ pub struct ListItem {
     area: Area,
     pub animator: Animator,
     pub is_selected: bool,
+    label: String,
 }
 
 impl ListItem {
@@ -31,6 +33,17 @@ impl ListItem {
     pub fn set_area(&mut self, area: Area) {
         self.area = area;
     }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Describes the item in the accessibility tree; see [`Cx::get_accessibility_tree`]. Call this
+    /// when drawing the item, typically with the text that is drawn for it.
+    pub fn set_label(&mut self, label: &str) {
+        self.label.clear();
+        self.label.push_str(label);
+    }
 }
 
 #[derive(Debug)]
@@ -105,6 +118,10 @@ impl List {
 
     pub fn begin_list(&mut self, cx: &mut Cx, view: &mut ScrollView, tail_list: bool, row_height: f32) {
         view.begin_view(cx, LayoutSize::FILL);
+        cx.begin_accessibility_node(
+            self.component_id,
+            AccessibilityNodeProps { role: AccessibilityRole::List, area: view.area(), ..Default::default() },
+        );
         self.set_visible_range_and_scroll(cx, view, tail_list, row_height);
     }
 
@@ -114,6 +131,19 @@ impl List {
     }
 
     pub fn end_list(&mut self, cx: &mut Cx, view: &mut ScrollView) {
+        for item in &self.list_items[self.start_item..self.end_item] {
+            cx.add_accessibility_node(
+                item.component_id,
+                AccessibilityNodeProps {
+                    role: AccessibilityRole::ListItem,
+                    label: item.label.clone(),
+                    selected: Some(item.is_selected),
+                    area: item.area,
+                    ..Default::default()
+                },
+            );
+        }
+        cx.end_accessibility_node();
         view.end_view(cx);
         if let Some(set_scroll_pos) = self.set_scroll_pos {
             view.set_scroll_pos(cx, set_scroll_pos);
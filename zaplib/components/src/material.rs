@@ -0,0 +1,323 @@
+//! Reusable shading for 3d meshes, like the ones generated by [`Geometry3d`].
+
+use crate::*;
+use zaplib::*;
+
+/// Per-instance data for drawing a mesh with a [`Material`].
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct MeshIns {
+    /// Model matrix, for positioning, rotating, and scaling the mesh. Normals get transformed using the same
+    /// matrix, so lighting is only correct when scaling uniformly.
+    pub transform: Mat4,
+    /// Color of the mesh (not premultiplied).
+    pub color: Vec4,
+}
+
+impl Default for MeshIns {
+    fn default() -> Self {
+        Self { transform: Mat4::identity(), color: COLOR_WHITE }
+    }
+}
+
+impl MeshIns {
+    pub fn new(transform: Mat4, color: Vec4) -> Self {
+        Self { transform, color }
+    }
+}
+
+/// A light that shines in the same direction everywhere, like the sun.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectionalLight {
+    /// The direction that the light travels in.
+    pub direction: Vec3,
+    /// Color of the light; the alpha channel is ignored.
+    pub color: Vec4,
+    pub intensity: f32,
+}
+
+/// A light that shines in all directions from a single position, like a light bulb.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointLight {
+    pub position: Vec3,
+    /// Color of the light; the alpha channel is ignored.
+    pub color: Vec4,
+    pub intensity: f32,
+    /// Distance at which the light has faded out completely.
+    pub range: f32,
+}
+
+/// The lights that shine on meshes drawn with a [`Material`]. Typically set per [`Viewport3D`], using
+/// [`Viewport3D::lighting`].
+///
+/// Only the first [`Lighting::MAX_DIRECTIONAL_LIGHTS`] directional lights and [`Lighting::MAX_POINT_LIGHTS`] point
+/// lights are used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lighting {
+    /// Light that reaches all surfaces equally, so that the sides facing away from other lights don't turn
+    /// completely black. The alpha channel is ignored.
+    pub ambient: Vec4,
+    pub directional_lights: Vec<DirectionalLight>,
+    pub point_lights: Vec<PointLight>,
+}
+
+impl Lighting {
+    pub const MAX_DIRECTIONAL_LIGHTS: usize = 2;
+    pub const MAX_POINT_LIGHTS: usize = 2;
+}
+
+impl Default for Lighting {
+    /// Some dim ambient light, and a white light shining from the top left.
+    fn default() -> Self {
+        Self {
+            ambient: vec4(0.2, 0.2, 0.2, 1.),
+            directional_lights: vec![DirectionalLight { direction: vec3(1., -2., -1.), color: COLOR_WHITE, intensity: 0.8 }],
+            point_lights: vec![],
+        }
+    }
+}
+
+/// How a mesh reacts to [`Lighting`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Material {
+    /// Just [`MeshIns::color`], without any lighting.
+    Unlit,
+    /// Diffuse lighting only, for matte surfaces.
+    Lambert,
+    /// Diffuse lighting with specular highlights, for shiny surfaces. Higher `shininess` gives smaller
+    /// highlights, and `specular` (from 0 to 1) determines how bright they are.
+    BlinnPhong { shininess: f32, specular: f32 },
+    /// Physically based shading, using the common metallic/roughness model. Both are from 0 to 1.
+    Pbr { metallic: f32, roughness: f32 },
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::Lambert
+    }
+}
+
+/// A directional or point light in [`LightingUniforms`].
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+struct LightUniforms {
+    /// Direction for directional lights; position and range for point lights.
+    direction_or_position: Vec4,
+    /// Premultiplied by the intensity; zero for unused lights.
+    color: Vec4,
+}
+
+/// Keep in sync with the uniforms in [`MESH_SHADER`].
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+struct LightingUniforms {
+    ambient: Vec4,
+    directional_lights: [LightUniforms; Lighting::MAX_DIRECTIONAL_LIGHTS],
+    point_lights: [LightUniforms; Lighting::MAX_POINT_LIGHTS],
+    material_params: Vec4,
+}
+
+impl Material {
+    /// The [`Shader`] for this material, which works with [`Geometry3d`] and [`MeshIns`]. You can use this
+    /// directly with [`Cx::add_mesh_instances`], as long as you call [`Material::write_uniforms`] on the
+    /// resulting [`Area`]; or just use [`Material::draw`].
+    pub fn shader(&self) -> &'static Shader {
+        match self {
+            Material::Unlit => &UNLIT_SHADER,
+            Material::Lambert => &LAMBERT_SHADER,
+            Material::BlinnPhong { .. } => &BLINN_PHONG_SHADER,
+            Material::Pbr { .. } => &PBR_SHADER,
+        }
+    }
+
+    /// Draw `instances` of `gpu_geometry` with this material, lit by `lighting`.
+    pub fn draw(&self, cx: &mut Cx, lighting: &Lighting, gpu_geometry: GpuGeometry, instances: &[MeshIns]) -> Area {
+        let area = cx.add_mesh_instances(self.shader(), instances, gpu_geometry);
+        self.write_uniforms(cx, area, lighting);
+        area
+    }
+
+    /// Write the material parameters and `lighting` to an [`Area`] that was drawn with [`Material::shader`].
+    pub fn write_uniforms(&self, cx: &mut Cx, area: Area, lighting: &Lighting) {
+        let mut uniforms = LightingUniforms {
+            ambient: lighting.ambient,
+            material_params: match *self {
+                Material::Unlit | Material::Lambert => Vec4::default(),
+                Material::BlinnPhong { shininess, specular } => vec4(shininess, specular, 0., 0.),
+                Material::Pbr { metallic, roughness } => vec4(metallic, roughness, 0., 0.),
+            },
+            ..LightingUniforms::default()
+        };
+        // Unused lights have a zero color, but still need a valid direction and range to avoid dividing by zero.
+        for (index, uniform) in uniforms.directional_lights.iter_mut().enumerate() {
+            *uniform = match lighting.directional_lights.get(index) {
+                Some(DirectionalLight { direction, color, intensity }) => LightUniforms {
+                    direction_or_position: vec4(direction.x, direction.y, direction.z, 0.),
+                    color: *color * *intensity,
+                },
+                None => LightUniforms { direction_or_position: vec4(0., -1., 0., 0.), color: Vec4::default() },
+            };
+        }
+        for (index, uniform) in uniforms.point_lights.iter_mut().enumerate() {
+            *uniform = match lighting.point_lights.get(index) {
+                Some(PointLight { position, color, intensity, range }) => LightUniforms {
+                    direction_or_position: vec4(position.x, position.y, position.z, range.max(0.0001)),
+                    color: *color * *intensity,
+                },
+                None => LightUniforms { direction_or_position: vec4(0., 0., 0., 1.), color: Vec4::default() },
+            };
+        }
+        area.write_user_uniforms(cx, uniforms);
+    }
+}
+
+/// Shared code for all materials. Lighting is computed in view space, so we don't need the camera position.
+const MESH_SHADER: CodeFragment = code_fragment!(
+    r#"
+    instance transform: mat4;
+    instance color: vec4;
+
+    uniform ambient_light: vec4;
+    uniform directional_light_0_direction: vec4;
+    uniform directional_light_0_color: vec4;
+    uniform directional_light_1_direction: vec4;
+    uniform directional_light_1_color: vec4;
+    uniform point_light_0_position: vec4;
+    uniform point_light_0_color: vec4;
+    uniform point_light_1_position: vec4;
+    uniform point_light_1_color: vec4;
+    uniform material_params: vec4;
+
+    varying view_pos: vec3;
+    varying view_normal: vec3;
+
+    fn vertex() -> vec4 {
+        let view_position = camera_view * (transform * vec4(geom_pos, 1.));
+        view_pos = view_position.xyz;
+        view_normal = (camera_view * (transform * vec4(geom_normal, 0.))).xyz;
+        return camera_projection * view_position;
+    }
+    "#
+);
+
+/// Adds up the light of all the lights, using a `shade` function defined by the material.
+const LIGHTING_SHADER: CodeFragment = code_fragment!(
+    r#"
+    fn directional_light(albedo: vec3, normal: vec3, view_dir: vec3, direction: vec4, light_color: vec4) -> vec3 {
+        let to_light = -(camera_view * vec4(direction.xyz, 0.)).xyz;
+        return shade(albedo, normal, view_dir, normalize(to_light), light_color.rgb);
+    }
+
+    fn point_light(albedo: vec3, normal: vec3, view_dir: vec3, position: vec4, light_color: vec4) -> vec3 {
+        let to_light = (camera_view * vec4(position.xyz, 1.)).xyz - view_pos;
+        let falloff = clamp(1. - length(to_light) / position.w, 0., 1.);
+        return shade(albedo, normal, view_dir, normalize(to_light), light_color.rgb * falloff * falloff);
+    }
+
+    fn pixel() -> vec4 {
+        let albedo = color.rgb;
+        let normal = normalize(view_normal);
+        let view_dir = normalize(-view_pos);
+        let result = ambient_light.rgb * albedo;
+        result += directional_light(albedo, normal, view_dir, directional_light_0_direction, directional_light_0_color);
+        result += directional_light(albedo, normal, view_dir, directional_light_1_direction, directional_light_1_color);
+        result += point_light(albedo, normal, view_dir, point_light_0_position, point_light_0_color);
+        result += point_light(albedo, normal, view_dir, point_light_1_position, point_light_1_color);
+        return vec4(clamp(result, vec3(0.), vec3(1.)) * color.a, color.a);
+    }
+    "#
+);
+
+pub(crate) static UNLIT_SHADER: Shader = Shader {
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        Geometry3d::SHADER,
+        MESH_SHADER,
+        code_fragment!(
+            r#"
+            fn pixel() -> vec4 {
+                return vec4(color.rgb * color.a, color.a);
+            }
+            "#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+pub(crate) static LAMBERT_SHADER: Shader = Shader {
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        Geometry3d::SHADER,
+        MESH_SHADER,
+        code_fragment!(
+            r#"
+            fn shade(albedo: vec3, normal: vec3, view_dir: vec3, light_dir: vec3, light_color: vec3) -> vec3 {
+                return albedo * light_color * max(dot(normal, light_dir), 0.);
+            }
+            "#
+        ),
+        LIGHTING_SHADER,
+    ],
+    ..Shader::DEFAULT
+};
+
+pub(crate) static BLINN_PHONG_SHADER: Shader = Shader {
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        Geometry3d::SHADER,
+        MESH_SHADER,
+        code_fragment!(
+            r#"
+            fn shade(albedo: vec3, normal: vec3, view_dir: vec3, light_dir: vec3, light_color: vec3) -> vec3 {
+                let n_dot_l = max(dot(normal, light_dir), 0.);
+                let halfway = normalize(light_dir + view_dir);
+                let specular = material_params.y * pow(max(dot(normal, halfway), 0.), material_params.x);
+                return (albedo * n_dot_l + vec3(specular * step(0.0001, n_dot_l))) * light_color;
+            }
+            "#
+        ),
+        LIGHTING_SHADER,
+    ],
+    ..Shader::DEFAULT
+};
+
+/// Cook-Torrance, with the GGX normal distribution and Schlick's approximations for geometry and fresnel;
+/// see e.g. <https://learnopengl.com/PBR/Theory>.
+pub(crate) static PBR_SHADER: Shader = Shader {
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        Geometry3d::SHADER,
+        MESH_SHADER,
+        code_fragment!(
+            r#"
+            fn shade(albedo: vec3, normal: vec3, view_dir: vec3, light_dir: vec3, light_color: vec3) -> vec3 {
+                let metallic = material_params.x;
+                let roughness = max(material_params.y, 0.04);
+                let halfway = normalize(light_dir + view_dir);
+                let n_dot_l = max(dot(normal, light_dir), 0.);
+                let n_dot_v = max(dot(normal, view_dir), 0.0001);
+                let n_dot_h = max(dot(normal, halfway), 0.);
+                let h_dot_v = max(dot(halfway, view_dir), 0.);
+
+                let alpha = roughness * roughness;
+                let alpha2 = alpha * alpha;
+                let denominator = n_dot_h * n_dot_h * (alpha2 - 1.) + 1.;
+                let distribution = alpha2 / (PI * denominator * denominator);
+
+                let k = (roughness + 1.) * (roughness + 1.) / 8.;
+                let geometry = (n_dot_v / (n_dot_v * (1. - k) + k)) * (n_dot_l / (n_dot_l * (1. - k) + k));
+
+                let f0 = mix(vec3(0.04), albedo, metallic);
+                let fresnel = f0 + (vec3(1.) - f0) * pow(1. - h_dot_v, 5.);
+
+                let specular = fresnel * (distribution * geometry / (4. * n_dot_v * max(n_dot_l, 0.0001)));
+                let diffuse = (vec3(1.) - fresnel) * (1. - metallic) * albedo / PI;
+                // Multiply by PI so light intensities look about the same as with the other materials.
+                return (diffuse + specular) * light_color * n_dot_l * PI;
+            }
+            "#
+        ),
+        LIGHTING_SHADER,
+    ],
+    ..Shader::DEFAULT
+};
@@ -0,0 +1,341 @@
+//! Finding which 3d object is under the pointer, by casting a ray from the camera.
+//!
+//! Get a [`Ray3d`] for a pointer position using [`Viewport3D::get_pointer_ray`], and intersect it with the
+//! objects that were added to a [`Picker3d`] using [`Picker3d::pick`].
+
+use std::rc::Rc;
+
+use crate::*;
+use zaplib::*;
+
+/// A half-line in 3d space, starting at [`Ray3d::origin`] and going in [`Ray3d::direction`].
+///
+/// Intersection functions return how far along the ray the hit is, in multiples of [`Ray3d::direction`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Ray3d {
+    pub origin: Vec3,
+    pub direction: Vec3,
+    /// How big a logical pixel on the screen is at the origin, for picking objects that have a size in pixels,
+    /// like [`DrawPoints3dOptions::use_screen_space`]. Set by [`Viewport3D::get_pointer_ray`]; see
+    /// [`Ray3d::pixel_size_at`].
+    pub pixel_size: f32,
+    /// How much bigger a logical pixel gets per unit of distance from the origin, with a perspective projection.
+    pub pixel_size_per_distance: f32,
+}
+
+impl Ray3d {
+    /// A ray with a normalized `direction`, so intersection distances are in world units.
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self { origin, direction: direction.normalize(), pixel_size: 0., pixel_size_per_distance: 0. }
+    }
+
+    /// How big a logical pixel on the screen is at `t` along the ray.
+    pub fn pixel_size_at(&self, t: f32) -> f32 {
+        self.pixel_size + self.pixel_size_per_distance * t
+    }
+
+    /// The position at `t` along the ray.
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+
+    /// Transform the ray by `matrix`. The direction doesn't get normalized, so that `t` values are the same
+    /// as for the original ray.
+    #[must_use]
+    pub fn transform(&self, matrix: &Mat4) -> Self {
+        Self {
+            origin: matrix.transform_vec4(vec4(self.origin.x, self.origin.y, self.origin.z, 1.)).to_vec3(),
+            direction: matrix.transform_vec4(vec4(self.direction.x, self.direction.y, self.direction.z, 0.)).to_vec3(),
+            pixel_size: self.pixel_size,
+            pixel_size_per_distance: self.pixel_size_per_distance,
+        }
+    }
+
+    /// Where the ray first hits a sphere, or 0 if the origin is inside the sphere.
+    pub fn intersect_sphere(&self, center: Vec3, radius: f32) -> Option<f32> {
+        let offset = self.origin - center;
+        let a = self.direction.dot(self.direction);
+        let b = offset.dot(self.direction);
+        let c = offset.dot(offset) - radius * radius;
+        if c <= 0. {
+            return Some(0.);
+        }
+        let discriminant = b * b - a * c;
+        if discriminant < 0. || b > 0. {
+            return None;
+        }
+        Some((-b - discriminant.sqrt()) / a)
+    }
+
+    /// Where the ray hits a triangle, from either side. Uses the Möller–Trumbore algorithm.
+    pub fn intersect_triangle(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+        let edge_ab = b - a;
+        let edge_ac = c - a;
+        let p = Vec3::cross(self.direction, edge_ac);
+        let determinant = edge_ab.dot(p);
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        let inverse_determinant = 1. / determinant;
+        let offset = self.origin - a;
+        let u = offset.dot(p) * inverse_determinant;
+        if !(0. ..=1.).contains(&u) {
+            return None;
+        }
+        let q = Vec3::cross(offset, edge_ab);
+        let v = self.direction.dot(q) * inverse_determinant;
+        if v < 0. || u + v > 1. {
+            return None;
+        }
+        let t = edge_ac.dot(q) * inverse_determinant;
+        (t >= 0.).then(|| t)
+    }
+
+    /// The `t` along the ray that is closest to `point`.
+    pub fn closest_to_point(&self, point: Vec3) -> f32 {
+        ((point - self.origin).dot(self.direction) / self.direction.dot(self.direction)).max(0.)
+    }
+
+    /// The `t` along the ray and the fraction along the segment (from 0 to 1) of the points where the ray
+    /// and the segment are closest to each other.
+    pub fn closest_to_segment(&self, start: Vec3, end: Vec3) -> (f32, f32) {
+        // See "Real-Time Collision Detection" by Christer Ericson, section 5.1.9.
+        let segment = end - start;
+        let offset = self.origin - start;
+        let a = self.direction.dot(self.direction);
+        let b = self.direction.dot(segment);
+        let c = self.direction.dot(offset);
+        let e = segment.dot(segment);
+        let f = segment.dot(offset);
+        if e <= f32::EPSILON {
+            return ((-c / a).max(0.), 0.);
+        }
+        let denominator = a * e - b * b;
+        let t = if denominator > f32::EPSILON { ((b * f - c * e) / denominator).max(0.) } else { 0. };
+        let s = (b * t + f) / e;
+        if s < 0. {
+            ((-c / a).max(0.), 0.)
+        } else if s > 1. {
+            (((b - c) / a).max(0.), 1.)
+        } else {
+            (t, s)
+        }
+    }
+}
+
+/// Triangles for picking with a [`Picker3d`], with a bounding sphere to quickly skip meshes that the ray
+/// doesn't come near. Wrapped in an [`Rc`] so it can be added every frame without copying.
+#[derive(Clone, Debug, Default)]
+pub struct PickMesh {
+    positions: Vec<Vec3>,
+    triangles: Vec<[u32; 3]>,
+    bounding_center: Vec3,
+    bounding_radius: f32,
+}
+
+impl PickMesh {
+    /// Create a [`PickMesh`] from `triangles` that index into `positions`. Panics if any of the indices is out
+    /// of bounds.
+    pub fn new(positions: Vec<Vec3>, triangles: Vec<[u32; 3]>) -> Rc<Self> {
+        if let Some(index) = triangles.iter().flatten().find(|&&index| index as usize >= positions.len()) {
+            panic!("PickMesh triangle index {} is out of bounds for {} positions", index, positions.len());
+        }
+        let (min, max) =
+            positions.iter().fold((Vec3::all(f32::INFINITY), Vec3::all(f32::NEG_INFINITY)), |(min, max), position| {
+                (
+                    vec3(min.x.min(position.x), min.y.min(position.y), min.z.min(position.z)),
+                    vec3(max.x.max(position.x), max.y.max(position.y), max.z.max(position.z)),
+                )
+            });
+        let bounding_center = if positions.is_empty() { Vec3::default() } else { (min + max) * 0.5 };
+        let bounding_radius = positions.iter().map(|position| (*position - bounding_center).length()).fold(0., f32::max);
+        Rc::new(Self { positions, triangles, bounding_center, bounding_radius })
+    }
+
+    /// Get the triangles from a [`Geometry`] in the [`Geometry3d`] vertex format.
+    pub fn from_geometry3d(geometry: &Geometry) -> Rc<Self> {
+        let floats_per_vertex = std::mem::size_of::<Geometry3d>() / std::mem::size_of::<f32>();
+        let positions = geometry
+            .vertices_f32_slice()
+            .chunks_exact(floats_per_vertex)
+            .map(|vertex| vec3(vertex[0], vertex[1], vertex[2]))
+            .collect();
+        let triangles =
+            geometry.indices_u32_slice().chunks_exact(3).map(|triangle| [triangle[0], triangle[1], triangle[2]]).collect();
+        Self::new(positions, triangles)
+    }
+}
+
+/// Result of [`Picker3d::pick`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickHit3d {
+    /// The id that the object was added with.
+    pub id: u64,
+    /// Index of the triangle, point, or line segment that was hit, within the object.
+    pub index: usize,
+    /// Distance from [`Ray3d::origin`], in multiples of [`Ray3d::direction`].
+    pub distance: f32,
+    /// Position of the hit in world space.
+    pub point: Vec3,
+}
+
+enum PickObject {
+    Mesh {
+        mesh: Rc<PickMesh>,
+        transform: Mat4,
+        inverse_transform: Mat4,
+    },
+    /// Positions with a radius in world units, or in pixels if `screen_space` is set.
+    Points {
+        points: Vec<(Vec3, f32)>,
+        screen_space: bool,
+    },
+    /// Segments with a radius in world units.
+    Lines {
+        segments: Vec<(Vec3, Vec3, f32)>,
+    },
+}
+
+/// Collection of objects that can be picked using a [`Ray3d`]. Typically you clear this at the start of drawing,
+/// and add the same objects that you draw in the [`Viewport3D`].
+#[derive(Default)]
+pub struct Picker3d {
+    objects: Vec<(u64, PickObject)>,
+}
+
+impl Picker3d {
+    pub fn clear(&mut self) {
+        self.objects.clear();
+    }
+
+    /// Add a mesh, positioned in the world using `transform`, like [`MeshIns::transform`].
+    pub fn add_mesh(&mut self, id: u64, mesh: &Rc<PickMesh>, transform: Mat4) {
+        self.objects.push((id, PickObject::Mesh { mesh: Rc::clone(mesh), transform, inverse_transform: transform.invert() }));
+    }
+
+    /// Add points as drawn with [`DrawPoints3d::draw`]. They are picked as spheres, so screen space points are
+    /// picked a bit more generously than they are drawn.
+    pub fn add_points(&mut self, id: u64, data: &[DrawPoints3dInstance], options: &DrawPoints3dOptions) {
+        let points =
+            data.iter().map(|point| (transform_point(&options.vertex_transform, point.position), point.size * 0.5)).collect();
+        self.objects.push((id, PickObject::Points { points, screen_space: options.use_screen_space }));
+    }
+
+    /// Add line segments as drawn with [`DrawLines3d::draw`].
+    pub fn add_lines(&mut self, id: u64, data: &[DrawLines3dInstance], options: &DrawLines3dOptions) {
+        let segments = data
+            .iter()
+            .map(|line| {
+                let start = transform_point(&options.vertex_transform, line.position_start);
+                let end = transform_point(&options.vertex_transform, line.position_end);
+                (start, end, line.scale * 0.5)
+            })
+            .collect();
+        self.objects.push((id, PickObject::Lines { segments }));
+    }
+
+    /// Find the closest object that `ray` hits.
+    pub fn pick(&self, ray: &Ray3d) -> Option<PickHit3d> {
+        let mut closest_hit: Option<PickHit3d> = None;
+        let mut add_hit = |hit: PickHit3d| {
+            if closest_hit.map_or(true, |closest_hit| hit.distance < closest_hit.distance) {
+                closest_hit = Some(hit);
+            }
+        };
+
+        for (id, object) in &self.objects {
+            match object {
+                PickObject::Mesh { mesh, transform, inverse_transform } => {
+                    // Intersect in the mesh's own coordinates, so we don't have to transform all the triangles. `t` is the
+                    // same in both coordinate systems, since the ray direction doesn't get normalized.
+                    let local_ray = ray.transform(inverse_transform);
+                    if local_ray.intersect_sphere(mesh.bounding_center, mesh.bounding_radius).is_none() {
+                        continue;
+                    }
+                    for (index, triangle) in mesh.triangles.iter().enumerate() {
+                        let [a, b, c] = triangle.map(|index| mesh.positions[index as usize]);
+                        if let Some(t) = local_ray.intersect_triangle(a, b, c) {
+                            add_hit(PickHit3d {
+                                id: *id,
+                                index,
+                                distance: t,
+                                point: transform_point(transform, local_ray.at(t)),
+                            });
+                        }
+                    }
+                }
+                PickObject::Points { points, screen_space } => {
+                    for (index, &(position, radius)) in points.iter().enumerate() {
+                        let t = ray.closest_to_point(position);
+                        let radius = if *screen_space { radius * ray.pixel_size_at(t) } else { radius };
+                        if (ray.at(t) - position).length() <= radius {
+                            add_hit(PickHit3d { id: *id, index, distance: t, point: ray.at(t) });
+                        }
+                    }
+                }
+                PickObject::Lines { segments } => {
+                    for (index, &(start, end, radius)) in segments.iter().enumerate() {
+                        let (t, s) = ray.closest_to_segment(start, end);
+                        if (ray.at(t) - Vec3::from_lerp(start, end, s)).length() <= radius {
+                            add_hit(PickHit3d { id: *id, index, distance: t, point: ray.at(t) });
+                        }
+                    }
+                }
+            }
+        }
+        closest_hit
+    }
+}
+
+fn transform_point(matrix: &Mat4, point: Vec3) -> Vec3 {
+    matrix.transform_vec4(vec4(point.x, point.y, point.z, 1.)).to_vec3()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersects_shapes() {
+        let ray = Ray3d::new(vec3(0., 0., 10.), vec3(0., 0., -1.));
+        assert_eq!(ray.intersect_sphere(vec3(0., 0., 0.), 1.), Some(9.));
+        assert_eq!(ray.intersect_sphere(vec3(0., 0., 20.), 1.), None);
+        assert_eq!(ray.intersect_triangle(vec3(-1., -1., 0.), vec3(1., -1., 0.), vec3(0., 1., 0.)), Some(10.));
+        assert_eq!(ray.intersect_triangle(vec3(1., 1., 0.), vec3(2., 1., 0.), vec3(1., 2., 0.)), None);
+        assert_eq!(ray.closest_to_point(vec3(1., 0., 5.)), 5.);
+        assert_eq!(ray.closest_to_segment(vec3(-1., 0., 4.), vec3(1., 0., 4.)), (6., 0.5));
+        assert_eq!(ray.closest_to_segment(vec3(1., 0., 4.), vec3(3., 0., 4.)), (6., 0.));
+    }
+
+    #[test]
+    fn picks_closest_object() {
+        let quad = PickMesh::new(
+            vec![vec3(-1., -1., 0.), vec3(1., -1., 0.), vec3(1., 1., 0.), vec3(-1., 1., 0.)],
+            vec![[0, 1, 2], [0, 2, 3]],
+        );
+        let mut picker = Picker3d::default();
+        picker.add_mesh(1, &quad, Mat4::translation(0., 0., -5.));
+        picker.add_mesh(2, &quad, Mat4::translation(0., 0., -2.));
+        picker.add_mesh(3, &quad, Mat4::translation(5., 0., 0.));
+
+        let hit = picker.pick(&Ray3d::new(vec3(0.5, 0., 0.), vec3(0., 0., -1.))).unwrap();
+        assert_eq!((hit.id, hit.distance, hit.point), (2, 2., vec3(0.5, 0., -2.)));
+        assert!(picker.pick(&Ray3d::new(vec3(0., 0., 0.), vec3(0., 0., 1.))).is_none());
+
+        let point =
+            DrawPoints3dInstance { position: vec3(0., 0., -1.), color: Vec3::default(), size: 0.2, user_info: Vec2::default() };
+        picker.add_points(4, &[point], &DrawPoints3dOptions::default());
+        let hit = picker.pick(&Ray3d::new(vec3(0.05, 0., 0.), vec3(0., 0., -1.))).unwrap();
+        assert_eq!((hit.id, hit.index, hit.distance), (4, 0, 1.));
+
+        let line = DrawLines3dInstance::from_segment(vec3(0., -1., -0.5), vec3(0., 1., -0.5), Vec4::default(), 0.2);
+        picker.add_lines(5, &[line], &DrawLines3dOptions::default());
+        assert_eq!(picker.pick(&Ray3d::new(vec3(0.05, 0., 0.), vec3(0., 0., -1.))).unwrap().id, 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "PickMesh triangle index 3 is out of bounds for 3 positions")]
+    fn panics_on_out_of_bounds_triangle_indices() {
+        PickMesh::new(vec![vec3(0., 0., 0.), vec3(1., 0., 0.), vec3(0., 1., 0.)], vec![[0, 1, 3]]);
+    }
+}
@@ -0,0 +1,158 @@
+//! In-app view of the frame profiler; see [`Cx::set_profiler_enabled`].
+
+use crate::*;
+use zaplib::*;
+
+const TOP_PADDING: f32 = 5.;
+const ZONE_HEIGHT: f32 = 14.;
+/// Frames are drawn at least this long (in seconds), so that fast frames look fast.
+const MIN_FRAME_DURATION: f64 = 1. / 60.;
+/// Zones narrower than this (in pixels) don't get a label.
+const MIN_LABEL_WIDTH: f32 = 40.;
+
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+struct ProfileZoneIns {
+    quad: QuadIns,
+    color: Vec4,
+}
+
+pub(crate) static SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        code_fragment!(
+            r#"
+            instance color: vec4;
+
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * rect_size);
+                df.box(vec2(0.5), rect_size - 1., 1.);
+                return df.fill(color);
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+const TEXT_PROPS: TextInsProps =
+    TextInsProps { text_style: TextStyle { font_size: 7., ..TEXT_STYLE_NORMAL }, draw_depth: 1., ..TextInsProps::DEFAULT };
+
+/// Shows the zones of the last frame recorded by the frame profiler as a flame chart, similar to
+/// [`crate::FpsCounter`]. Has buttons for turning the profiler on and off, and for saving the recorded
+/// frames as a Chrome trace (see [`Cx::profile_to_chrome_trace_json`]).
+#[derive(Default)]
+pub struct ProfilerOverlay {
+    enable_button: Button,
+    export_button: Button,
+}
+
+impl ProfilerOverlay {
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        if let Event::NextFrame = event {
+            if cx.is_profiler_enabled() {
+                cx.request_draw();
+                cx.request_next_frame();
+            }
+        }
+
+        if let ButtonEvent::Clicked = self.enable_button.handle(cx, event) {
+            cx.set_profiler_enabled(!cx.is_profiler_enabled());
+            if cx.is_profiler_enabled() {
+                cx.request_next_frame();
+            }
+            cx.request_draw();
+        }
+
+        if let ButtonEvent::Clicked = self.export_button.handle(cx, event) {
+            let json = cx.profile_to_chrome_trace_json();
+            if let Err(err) = cx.file_write("profile.json", json.as_bytes()) {
+                log!("Error saving profile: {}", err);
+            }
+        }
+    }
+
+    pub fn size(&self, cx: &Cx) -> f32 {
+        if cx.is_profiler_enabled() {
+            240.
+        } else {
+            80.
+        }
+    }
+
+    /// Color for a zone, based on its name, so the same zones always get the same color.
+    fn zone_color(name: &str) -> Vec4 {
+        let hash = name.bytes().fold(0u32, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as u32));
+        Vec4::from_hsva(Vec4 { x: (hash % 360) as f32 / 360., y: 0.5, z: 0.7, w: 1. })
+    }
+
+    fn draw_flame_chart(&mut self, cx: &mut Cx, frame: &ProfileFrame) {
+        let rect = cx.get_box_rect();
+        let frame_duration = frame.duration.max(MIN_FRAME_DURATION);
+        let instances: Vec<ProfileZoneIns> = frame
+            .zones
+            .iter()
+            .map(|zone| {
+                let x = ((zone.start - frame.start) / frame_duration) as f32 * rect.size.x;
+                let width = ((zone.duration / frame_duration) as f32 * rect.size.x).max(1.);
+                ProfileZoneIns {
+                    quad: QuadIns::from_rect(Rect {
+                        pos: rect.pos + vec2(x, zone.depth as f32 * ZONE_HEIGHT),
+                        size: vec2(width, ZONE_HEIGHT),
+                    }),
+                    color: Self::zone_color(zone.name),
+                }
+            })
+            .collect();
+        cx.add_instances(&SHADER, &instances);
+
+        for (zone, instance) in frame.zones.iter().zip(&instances) {
+            if instance.quad.rect_size.x >= MIN_LABEL_WIDTH {
+                TextIns::draw_str(cx, zone.name, instance.quad.rect_pos + vec2(2., 2.), &TEXT_PROPS);
+            }
+        }
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx) {
+        cx.begin_row(Width::Fill, Height::Fill);
+        let third_of_width = cx.get_width_left() / 3.;
+        if cx.is_profiler_enabled() {
+            let frame = cx.get_profile_frames().back().cloned();
+
+            cx.begin_row(Width::Fix(third_of_width), Height::Fill);
+            if let Some(frame) = &frame {
+                self.draw_flame_chart(cx, frame);
+            }
+            cx.end_row();
+
+            cx.begin_row(Width::Fix(third_of_width), Height::Fill);
+            if let Some(frame) = &frame {
+                TextIns::draw_str(
+                    cx,
+                    &format!("frame {}: {:.2} ms", frame.frame_index, frame.duration * 1000.),
+                    cx.get_box_origin() + Vec2 { x: 0., y: TOP_PADDING },
+                    &TextInsProps::DEFAULT,
+                );
+                TextIns::draw_str(
+                    cx,
+                    &format!("{} zones", frame.zones.len()),
+                    cx.get_box_origin() + Vec2 { x: 0., y: TOP_PADDING + cx.get_height_left() / 2. },
+                    &TextInsProps::DEFAULT,
+                );
+            }
+            cx.end_row();
+        }
+
+        cx.begin_row(Width::Fix(third_of_width), Height::Fill);
+        cx.begin_center_y_align();
+        self.enable_button.draw(cx, if cx.is_profiler_enabled() { "Hide profiler" } else { "Show profiler" });
+        if cx.is_profiler_enabled() {
+            self.export_button.draw(cx, "Export");
+        }
+        cx.end_center_y_align();
+        cx.end_row();
+
+        cx.end_row();
+    }
+}
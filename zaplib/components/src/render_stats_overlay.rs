@@ -0,0 +1,94 @@
+//! In-app view of [`RenderStats`].
+
+use crate::*;
+use zaplib::*;
+
+const LINE_HEIGHT: f32 = 12.;
+/// Number of shaders with the most instances to show.
+const MAX_SHADERS: usize = 5;
+
+const TEXT_PROPS: TextInsProps =
+    TextInsProps { text_style: TextStyle { font_size: 7., ..TEXT_STYLE_NORMAL }, ..TextInsProps::DEFAULT };
+
+/// Shows the [`RenderStats`] of the last painted frame, similar to [`crate::FpsCounter`].
+#[derive(Default)]
+pub struct RenderStatsOverlay {
+    enable_button: Button,
+    enabled: bool,
+}
+
+impl RenderStatsOverlay {
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        if let Event::NextFrame = event {
+            if self.enabled {
+                cx.request_draw();
+                cx.request_next_frame();
+            }
+        }
+
+        if let ButtonEvent::Clicked = self.enable_button.handle(cx, event) {
+            self.enabled = !self.enabled;
+            if self.enabled {
+                cx.request_next_frame();
+            }
+            cx.request_draw();
+        }
+    }
+
+    pub fn size(&self) -> f32 {
+        if self.enabled {
+            240.
+        } else {
+            80.
+        }
+    }
+
+    fn draw_lines(cx: &mut Cx, lines: &[String]) {
+        let origin = cx.get_box_origin();
+        for (index, line) in lines.iter().enumerate() {
+            TextIns::draw_str(cx, line, origin + Vec2 { x: 0., y: index as f32 * LINE_HEIGHT }, &TEXT_PROPS);
+        }
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx) {
+        cx.begin_row(Width::Fill, Height::Fill);
+        let third_of_width = cx.get_width_left() / 3.;
+        if self.enabled {
+            let stats = cx.get_render_stats().clone();
+
+            cx.begin_row(Width::Fix(third_of_width), Height::Fill);
+            Self::draw_lines(
+                cx,
+                &[
+                    format!("{} passes, {} views", stats.passes, stats.views),
+                    format!("{} draw calls ({} batched)", stats.draw_calls, stats.batched_draw_calls),
+                    format!("{} instances ({} culled)", stats.instances, stats.culled_instances),
+                    format!("{} B instances uploaded", stats.instance_bytes_uploaded),
+                    format!("{} B uniforms uploaded", stats.uniform_bytes_uploaded),
+                    format!("{} textures uploaded", stats.texture_uploads),
+                    format!("{} shaders compiled", stats.shader_compiles),
+                ],
+            );
+            cx.end_row();
+
+            cx.begin_row(Width::Fix(third_of_width), Height::Fill);
+            let mut instances_per_shader: Vec<_> = stats.instances_per_shader.iter().collect();
+            instances_per_shader.sort_by(|a, b| b.1.cmp(a.1));
+            let lines: Vec<String> = instances_per_shader
+                .into_iter()
+                .take(MAX_SHADERS)
+                .map(|(name, instances)| format!("{}: {}", name, instances))
+                .collect();
+            Self::draw_lines(cx, &lines);
+            cx.end_row();
+        }
+
+        cx.begin_row(Width::Fix(third_of_width), Height::Fill);
+        cx.begin_center_y_align();
+        self.enable_button.draw(cx, if self.enabled { "Hide stats" } else { "Show stats" });
+        cx.end_center_y_align();
+        cx.end_row();
+
+        cx.end_row();
+    }
+}
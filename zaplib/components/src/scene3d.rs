@@ -0,0 +1,537 @@
+//! Loading 3d scenes from glTF 2.0 (`.gltf`/`.glb`) and Wavefront OBJ files.
+//!
+//! Load a [`Scene3d`] using [`Scene3d::load_gltf`] or [`Scene3d::load_obj`] (which is fine to do on a
+//! separate thread), upload it once using [`GpuScene3d::new`], and then draw it every frame in a
+//! [`Viewport3D`] using [`GpuScene3d::draw`].
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::*;
+use zaplib::*;
+
+/// Part of a [`SceneMesh`] with a single material, in the [`Geometry3d`] vertex format.
+#[derive(Clone)]
+pub struct MeshPrimitive {
+    pub geometry: Geometry,
+    /// Base color from the file, or white if it doesn't specify one.
+    pub color: Vec4,
+    pub material: Material,
+}
+
+/// A mesh, which can be used by multiple [`SceneNode`]s.
+#[derive(Clone)]
+pub struct SceneMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<MeshPrimitive>,
+}
+
+/// A node in the hierarchy of a [`Scene3d`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneNode {
+    pub name: Option<String>,
+    /// Transform relative to the parent node.
+    pub transform: Mat4,
+    /// Index into [`Scene3d::meshes`].
+    pub mesh: Option<usize>,
+    /// Indices into [`Scene3d::nodes`].
+    pub children: Vec<usize>,
+}
+
+/// Meshes and a hierarchy of nodes that position them, loaded from a file.
+#[derive(Clone, Default)]
+pub struct Scene3d {
+    pub meshes: Vec<SceneMesh>,
+    pub nodes: Vec<SceneNode>,
+    /// Indices into [`Scene3d::nodes`] of the nodes without a parent.
+    pub root_nodes: Vec<usize>,
+}
+
+impl Scene3d {
+    /// Parse a glTF 2.0 file; either binary (`.glb`) or JSON (`.gltf`). Buffers embedded as `data:` URIs are
+    /// decoded directly, and other buffers are read using `load_uri`.
+    ///
+    /// Only triangle primitives are loaded; points and lines are skipped. Missing normals are computed from
+    /// the triangles. Each primitive gets a [`Material::Pbr`] with the metallic and roughness factors from
+    /// the file; textures are not supported yet.
+    pub fn from_gltf(bytes: &[u8], mut load_uri: impl FnMut(&str) -> std::io::Result<Vec<u8>>) -> Result<Self, String> {
+        let gltf::Gltf { document, mut blob } =
+            gltf::Gltf::from_slice(bytes).map_err(|err| format!("Could not parse glTF: {}", err))?;
+
+        let buffers = document
+            .buffers()
+            .map(|buffer| {
+                let data = match buffer.source() {
+                    gltf::buffer::Source::Bin => blob.take().ok_or("Could not load glTF: missing binary chunk")?,
+                    gltf::buffer::Source::Uri(uri) => match uri.strip_prefix("data:") {
+                        Some(data_uri) => decode_data_uri(data_uri)?,
+                        None => load_uri(uri).map_err(|err| format!("Could not read glTF buffer {}: {}", uri, err))?,
+                    },
+                };
+                if data.len() < buffer.length() {
+                    return Err(format!("Could not load glTF: buffer {} is too short", buffer.index()));
+                }
+                Ok(data)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let meshes = document
+            .meshes()
+            .map(|mesh| {
+                let primitives = mesh
+                    .primitives()
+                    .filter(|primitive| primitive.mode() == gltf::mesh::Mode::Triangles)
+                    .map(|primitive| {
+                        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+                        let positions: Vec<Vec3> = reader
+                            .read_positions()
+                            .ok_or_else(|| format!("Could not load glTF: mesh {} has no positions", mesh.index()))?
+                            .map(|[x, y, z]| vec3(x, y, z))
+                            .collect();
+                        let normals = reader.read_normals().map(|normals| normals.map(|[x, y, z]| vec3(x, y, z)).collect());
+                        let uvs = reader.read_tex_coords(0).map(|uvs| uvs.into_f32().map(|[u, v]| vec2(u, v)).collect());
+                        let indices = reader.read_indices().map(|indices| indices.into_u32().collect());
+
+                        let pbr = primitive.material().pbr_metallic_roughness();
+                        let [r, g, b, a] = pbr.base_color_factor();
+                        Ok(MeshPrimitive {
+                            geometry: build_geometry(positions, normals, uvs, indices)?,
+                            color: vec4(r, g, b, a),
+                            material: Material::Pbr { metallic: pbr.metallic_factor(), roughness: pbr.roughness_factor() },
+                        })
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok(SceneMesh { name: mesh.name().map(str::to_string), primitives })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let nodes: Vec<SceneNode> = document
+            .nodes()
+            .map(|node| {
+                // Both are column-major.
+                let mut transform = Mat4::default();
+                for (column_index, column) in node.transform().matrix().iter().enumerate() {
+                    transform.v[column_index * 4..column_index * 4 + 4].copy_from_slice(column);
+                }
+                SceneNode {
+                    name: node.name().map(str::to_string),
+                    transform,
+                    mesh: node.mesh().map(|mesh| mesh.index()),
+                    children: node.children().map(|child| child.index()).collect(),
+                }
+            })
+            .collect();
+        if let Some(node_index) = find_node_cycle(&nodes) {
+            return Err(format!("Could not load glTF: node {} is its own descendant", node_index));
+        }
+
+        let root_nodes = match document.default_scene().or_else(|| document.scenes().next()) {
+            Some(scene) => scene.nodes().map(|node| node.index()).collect(),
+            None => (0..nodes.len()).filter(|&index| nodes.iter().all(|node| !node.children.contains(&index))).collect(),
+        };
+
+        Ok(Self { meshes, nodes, root_nodes })
+    }
+
+    /// Read and parse a glTF 2.0 file using [`UniversalFile`]; see [`Scene3d::from_gltf`]. External buffers are
+    /// read relative to `path`.
+    pub fn load_gltf(path: &str) -> Result<Self, String> {
+        let directory = path.rfind('/').map_or("", |index| &path[..=index]);
+        Self::from_gltf(&read_file(path)?, |uri| {
+            let mut bytes = vec![];
+            UniversalFile::open(&format!("{}{}", directory, uri))?.read_to_end(&mut bytes)?;
+            Ok(bytes)
+        })
+    }
+
+    /// Parse a Wavefront OBJ file. Every object (`o`) or group (`g`) becomes a separate [`SceneMesh`] with a
+    /// [`Material::Lambert`], each with a root [`SceneNode`].
+    ///
+    /// Polygons are triangulated as fans, so they should be convex. Missing normals are computed from the
+    /// triangles. Material libraries (`mtllib`) are ignored.
+    pub fn from_obj(text: &str) -> Result<Self, String> {
+        let mut positions = vec![];
+        let mut normals = vec![];
+        let mut uvs = vec![];
+        let mut objects = vec![ObjObject::default()];
+
+        for (line_index, line) in text.lines().enumerate() {
+            let error = |message: &str| format!("Could not parse OBJ on line {}: {}", line_index + 1, message);
+            let mut parts = line.split_whitespace();
+            let keyword = match parts.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+            let mut parse_floats = |count: usize| -> Result<Vec<f32>, String> {
+                let floats = parts.by_ref().take(count).map(str::parse).collect::<Result<Vec<f32>, _>>();
+                floats.ok().filter(|floats| floats.len() == count).ok_or_else(|| error("expected numbers"))
+            };
+
+            match keyword {
+                "v" => {
+                    let v = parse_floats(3)?;
+                    positions.push(vec3(v[0], v[1], v[2]));
+                }
+                "vn" => {
+                    let v = parse_floats(3)?;
+                    normals.push(vec3(v[0], v[1], v[2]));
+                }
+                "vt" => {
+                    let v = parse_floats(2)?;
+                    uvs.push(vec2(v[0], v[1]));
+                }
+                "f" => {
+                    let object = objects.last_mut().unwrap();
+                    let face = parts
+                        .map(|vertex| {
+                            let mut indices = vertex.split('/');
+                            let mut parse_index = |count: usize| -> Result<Option<usize>, String> {
+                                match indices.next() {
+                                    None | Some("") => Ok(None),
+                                    Some(index) => parse_obj_index(index, count).map(Some).ok_or_else(|| error("invalid index")),
+                                }
+                            };
+                            let position = parse_index(positions.len())?.ok_or_else(|| error("missing position index"))?;
+                            let uv = parse_index(uvs.len())?;
+                            let normal = parse_index(normals.len())?;
+                            Ok(object.add_vertex(position, uv, normal, &positions, &uvs, &normals))
+                        })
+                        .collect::<Result<Vec<u32>, String>>()?;
+                    if face.len() < 3 {
+                        return Err(error("faces need at least 3 vertices"));
+                    }
+                    object.indices.extend(face[1..].windows(2).flat_map(|edge| [face[0], edge[0], edge[1]]));
+                }
+                "o" | "g" => {
+                    let name = parts.collect::<Vec<_>>().join(" ");
+                    objects.push(ObjObject { name: Some(name).filter(|name| !name.is_empty()), ..ObjObject::default() });
+                }
+                _ => {}
+            }
+        }
+
+        let mut scene = Self::default();
+        for object in objects.into_iter().filter(|object| !object.indices.is_empty()) {
+            let normals = object.normals.into_iter().collect::<Option<Vec<Vec3>>>();
+            let uvs = object.uvs.into_iter().map(Option::unwrap_or_default).collect();
+            let geometry = build_geometry(object.positions, normals, Some(uvs), Some(object.indices))?;
+            scene.root_nodes.push(scene.nodes.len());
+            scene.nodes.push(SceneNode {
+                name: object.name.clone(),
+                transform: Mat4::identity(),
+                mesh: Some(scene.meshes.len()),
+                children: vec![],
+            });
+            scene.meshes.push(SceneMesh {
+                name: object.name,
+                primitives: vec![MeshPrimitive { geometry, color: COLOR_WHITE, material: Material::Lambert }],
+            });
+        }
+        Ok(scene)
+    }
+
+    /// Read and parse a Wavefront OBJ file using [`UniversalFile`]; see [`Scene3d::from_obj`].
+    pub fn load_obj(path: &str) -> Result<Self, String> {
+        let bytes = read_file(path)?;
+        Self::from_obj(std::str::from_utf8(&bytes).map_err(|err| format!("Could not parse OBJ: {}", err))?)
+    }
+
+    /// All the places where a mesh should be drawn, as pairs of an index into [`Scene3d::meshes`] and a
+    /// transform that combines all the node transforms from the root down.
+    pub fn mesh_instances(&self) -> Vec<(usize, Mat4)> {
+        let mut instances = vec![];
+        let mut stack: Vec<(usize, Mat4)> = self.root_nodes.iter().map(|&node_index| (node_index, Mat4::identity())).collect();
+        while let Some((node_index, parent_transform)) = stack.pop() {
+            let node = &self.nodes[node_index];
+            let transform = Mat4::mul(&node.transform, &parent_transform);
+            if let Some(mesh_index) = node.mesh {
+                instances.push((mesh_index, transform));
+            }
+            stack.extend(node.children.iter().map(|&child_index| (child_index, transform)));
+        }
+        instances
+    }
+}
+
+/// A [`Scene3d`] that has been uploaded to the GPU.
+pub struct GpuScene3d {
+    /// For every [`SceneMesh`], its primitives.
+    meshes: Vec<Vec<(GpuGeometry, Vec4, Material)>>,
+    /// For every [`SceneMesh`], the transforms of its instances.
+    mesh_transforms: Vec<Vec<Mat4>>,
+}
+
+impl GpuScene3d {
+    pub fn new(cx: &mut Cx, scene: &Scene3d) -> Self {
+        let meshes = scene
+            .meshes
+            .iter()
+            .map(|mesh| {
+                mesh.primitives
+                    .iter()
+                    .map(|primitive| (GpuGeometry::new(cx, primitive.geometry.clone()), primitive.color, primitive.material))
+                    .collect()
+            })
+            .collect();
+        let mut mesh_transforms = vec![vec![]; scene.meshes.len()];
+        for (mesh_index, transform) in scene.mesh_instances() {
+            mesh_transforms[mesh_index].push(transform);
+        }
+        Self { meshes, mesh_transforms }
+    }
+
+    /// Draw the whole scene, using one draw call per primitive. Call this between [`Viewport3D::begin_draw`]
+    /// and [`Viewport3D::end_draw`].
+    pub fn draw(&self, cx: &mut Cx, viewport_3d: &Viewport3D) {
+        for (primitives, transforms) in self.meshes.iter().zip(&self.mesh_transforms) {
+            if transforms.is_empty() {
+                continue;
+            }
+            for (gpu_geometry, color, material) in primitives {
+                let instances: Vec<MeshIns> = transforms.iter().map(|&transform| MeshIns::new(transform, *color)).collect();
+                viewport_3d.draw_mesh(cx, *material, gpu_geometry.clone(), &instances);
+            }
+        }
+    }
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    UniversalFile::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .map_err(|err| format!("Could not read {}: {}", path, err))?;
+    Ok(bytes)
+}
+
+/// Build a [`Geometry3d`] [`Geometry`], computing normals if they are missing.
+fn build_geometry(
+    positions: Vec<Vec3>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<Vec2>>,
+    indices: Option<Vec<u32>>,
+) -> Result<Geometry, String> {
+    let indices = indices.unwrap_or_else(|| (0..positions.len() as u32).collect());
+    if indices.len() % 3 != 0 || indices.iter().any(|&index| index as usize >= positions.len()) {
+        return Err("Could not load mesh: invalid triangle indices".to_string());
+    }
+    let triangles: Vec<[u32; 3]> = indices.chunks_exact(3).map(|triangle| [triangle[0], triangle[1], triangle[2]]).collect();
+
+    let normals = match normals {
+        Some(normals) if normals.len() == positions.len() => normals,
+        _ => {
+            // Sum the (area-weighted) normals of all triangles that share a vertex.
+            let mut normals = vec![Vec3::default(); positions.len()];
+            for &[a, b, c] in &triangles {
+                let (a, b, c) = (a as usize, b as usize, c as usize);
+                let normal = Vec3::cross(positions[b] - positions[a], positions[c] - positions[a]);
+                normals[a] += normal;
+                normals[b] += normal;
+                normals[c] += normal;
+            }
+            normals.iter().map(Vec3::normalize).collect()
+        }
+    };
+    let uvs = uvs.filter(|uvs| uvs.len() == positions.len()).unwrap_or_else(|| vec![Vec2::default(); positions.len()]);
+
+    let vertices: Vec<Geometry3d> = positions
+        .into_iter()
+        .zip(normals)
+        .zip(uvs)
+        .map(|((position, normal), uv)| Geometry3d::new(position, normal, uv))
+        .collect();
+    Ok(Geometry::new(vertices, triangles))
+}
+
+/// Find a node that is its own descendant, if any. glTF nodes should form trees, but if a file has a cycle,
+/// [`Scene3d::mesh_instances`] would never finish.
+fn find_node_cycle(nodes: &[SceneNode]) -> Option<usize> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Visit {
+        NotYet,
+        InProgress,
+        Done,
+    }
+    let mut visits = vec![Visit::NotYet; nodes.len()];
+    for start_index in 0..nodes.len() {
+        if visits[start_index] != Visit::NotYet {
+            continue;
+        }
+        visits[start_index] = Visit::InProgress;
+        // Depth-first, with for every node on the stack the position of the next child to visit.
+        let mut stack = vec![(start_index, 0)];
+        while let Some(&(node_index, child_position)) = stack.last() {
+            match nodes[node_index].children.get(child_position) {
+                Some(&child_index) => {
+                    stack.last_mut().unwrap().1 += 1;
+                    match visits[child_index] {
+                        Visit::NotYet => {
+                            visits[child_index] = Visit::InProgress;
+                            stack.push((child_index, 0));
+                        }
+                        Visit::InProgress => return Some(child_index),
+                        Visit::Done => {}
+                    }
+                }
+                None => {
+                    visits[node_index] = Visit::Done;
+                    stack.pop();
+                }
+            }
+        }
+    }
+    None
+}
+
+/// An object that is being parsed in [`Scene3d::from_obj`]. OBJ faces index positions, uvs, and normals
+/// separately, so we create a vertex for every unique combination.
+#[derive(Default)]
+struct ObjObject {
+    name: Option<String>,
+    vertex_indices: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+    positions: Vec<Vec3>,
+    uvs: Vec<Option<Vec2>>,
+    normals: Vec<Option<Vec3>>,
+    indices: Vec<u32>,
+}
+
+impl ObjObject {
+    fn add_vertex(
+        &mut self,
+        position: usize,
+        uv: Option<usize>,
+        normal: Option<usize>,
+        all_positions: &[Vec3],
+        all_uvs: &[Vec2],
+        all_normals: &[Vec3],
+    ) -> u32 {
+        *self.vertex_indices.entry((position, uv, normal)).or_insert_with(|| {
+            self.positions.push(all_positions[position]);
+            self.uvs.push(uv.map(|uv| all_uvs[uv]));
+            self.normals.push(normal.map(|normal| all_normals[normal]));
+            self.positions.len() as u32 - 1
+        })
+    }
+}
+
+/// Parse a 1-based OBJ index, where negative numbers are relative to the end, into a 0-based index.
+fn parse_obj_index(index: &str, count: usize) -> Option<usize> {
+    let index: isize = index.parse().ok()?;
+    let index = if index < 0 { count as isize + index } else { index - 1 };
+    (0..count as isize).contains(&index).then(|| index as usize)
+}
+
+/// Decode the part after `data:` of a base64 data URI.
+fn decode_data_uri(data_uri: &str) -> Result<Vec<u8>, String> {
+    match data_uri.split_once(',') {
+        Some((media_type, data)) if media_type.ends_with(";base64") => {
+            decode_base64(data).ok_or_else(|| "Could not load glTF: invalid base64 in data URI".to_string())
+        }
+        _ => Err("Could not load glTF: only base64 data URIs are supported".to_string()),
+    }
+}
+
+fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in data.bytes().take_while(|&byte| byte != b'=') {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_base64() {
+        assert_eq!(decode_base64("aGVsbG8="), Some(b"hello".to_vec()));
+        assert_eq!(decode_base64("aGk"), Some(b"hi".to_vec()));
+        assert_eq!(decode_base64("a?"), None);
+        assert!(decode_data_uri("text/plain,hello").is_err());
+    }
+
+    #[test]
+    fn parses_obj() {
+        let scene = Scene3d::from_obj(
+            "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\no first\nf 1 2 3 4\no second\nvn 0 0 1\nf -4//1 -3//1 -2//1\n",
+        )
+        .unwrap();
+        assert_eq!(scene.meshes.len(), 2);
+        assert_eq!(scene.meshes[0].name.as_deref(), Some("first"));
+        assert_eq!(scene.root_nodes, vec![0, 1]);
+        assert_eq!(scene.mesh_instances().len(), 2);
+
+        assert_eq!(parse_obj_index("1", 4), Some(0));
+        assert_eq!(parse_obj_index("-1", 4), Some(3));
+        assert_eq!(parse_obj_index("5", 4), None);
+        assert!(Scene3d::from_obj("v 0 0 0\nf 1 2 3\n").is_err());
+    }
+
+    #[test]
+    fn parses_gltf_hierarchy() {
+        let gltf = r#"{
+            "asset": { "version": "2.0" },
+            "buffers": [{
+                "byteLength": 36,
+                "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+            }],
+            "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+            "accessors": [{
+                "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0]
+            }],
+            "meshes": [{ "name": "triangle", "primitives": [{ "attributes": { "POSITION": 0 } }] }],
+            "nodes": [
+                { "name": "parent", "translation": [1, 0, 0], "scale": [2, 2, 2], "children": [1] },
+                { "name": "child", "translation": [0, 2, 0], "mesh": 0 }
+            ],
+            "scenes": [{ "nodes": [0] }],
+            "scene": 0
+        }"#;
+        let scene = Scene3d::from_gltf(gltf.as_bytes(), |_| panic!("No external buffers")).unwrap();
+        assert_eq!(scene.meshes[0].name.as_deref(), Some("triangle"));
+        assert_eq!(scene.meshes[0].primitives.len(), 1);
+        assert_eq!(scene.root_nodes, vec![0]);
+        assert_eq!(scene.nodes[0].children, vec![1]);
+
+        let instances = scene.mesh_instances();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].1.transform_vec4(vec4(0., 0., 0., 1.)), vec4(1., 4., 0., 1.));
+    }
+
+    #[test]
+    fn rejects_gltf_node_cycles() {
+        let gltf = r#"{
+            "asset": { "version": "2.0" },
+            "nodes": [
+                { "name": "root", "children": [1] },
+                { "name": "a", "children": [2] },
+                { "name": "b", "children": [1] }
+            ],
+            "scenes": [{ "nodes": [0] }]
+        }"#;
+        assert_eq!(
+            Scene3d::from_gltf(gltf.as_bytes(), |_| panic!("No external buffers")).err(),
+            Some("Could not load glTF: node 1 is its own descendant".to_string())
+        );
+
+        let node = |children: Vec<usize>| SceneNode { name: None, transform: Mat4::identity(), mesh: None, children };
+        // Shared children are fine.
+        assert_eq!(find_node_cycle(&[node(vec![1, 2]), node(vec![2]), node(vec![])]), None);
+        assert_eq!(find_node_cycle(&[node(vec![0])]), Some(0));
+    }
+}
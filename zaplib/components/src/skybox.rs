@@ -5,7 +5,7 @@ fn build_geom() -> Geometry {
     Geometry3d::cube(1.0, 1.0, 1.0, 1, 1, 1)
 }
 
-static SHADER: Shader = Shader {
+pub(crate) static SHADER: Shader = Shader {
     build_geom: Some(build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -47,6 +47,9 @@ static SHADER: Shader = Shader {
             fn pixel() -> vec4 {
                 let x = geom_uv.x;
                 let y = geom_uv.y;
+                // take derivatives before branching, since WGSL only allows them in uniform control flow
+                let coord = geom_uv * 150.0;
+                let coord_width = abs(dFdx(coord)) + abs(dFdy(coord));
                 // walls
                 let sky = sky_color;
                 let edge = edge_color;
@@ -55,10 +58,9 @@ static SHADER: Shader = Shader {
                 }
                 // floor
                 if geom_id>2.5 {
-                    let coord = geom_uv * 150.0;
                     let grid = abs(
                         fract(coord - 0.5) - 0.5
-                    ) / (abs(dFdx(coord)) + abs(dFdy(coord)));
+                    ) / coord_width;
                     let line = min(grid.x, grid.y);
                     let grid2 = floor_color + 0.4 * vec4(vec3(1.0 - min(line, 1.0)), 1.0);
                     let uv2 = abs(2.0 * geom_uv - 1.0);
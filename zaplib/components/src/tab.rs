@@ -10,7 +10,7 @@ struct TabIns {
     border_color: Vec4,
 }
 
-static SHADER: Shader = Shader {
+pub(crate) static SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -282,4 +282,21 @@ impl Tab {
     pub fn selected(&self) -> bool {
         self.is_selected
     }
+
+    /// Describe this tab in the accessibility tree; see [`Cx::add_accessibility_node`].
+    ///
+    /// This is not done in [`Tab::draw_tab`] since [`crate::TabControl`] also uses [`Tab`] for drawing a tab
+    /// that's being dragged, which we don't want to show up twice.
+    pub fn add_accessibility_node(&self, cx: &mut Cx) {
+        cx.add_accessibility_node(
+            self.component_id,
+            AccessibilityNodeProps {
+                role: AccessibilityRole::Tab,
+                label: self.label.clone(),
+                selected: Some(self.is_selected),
+                area: self.bg_area,
+                ..Default::default()
+            },
+        );
+    }
 }
@@ -6,6 +6,7 @@ use crate::tab::*;
 
 #[derive(Default)]
 pub struct TabControl {
+    component_id: ComponentId,
     pub tabs_view: ScrollView,
     pub tabs: Vec<Tab>,
     pub drag_tab_view: View,
@@ -35,6 +36,8 @@ const COLOR_BG_NORMAL: Vec4 = Vec4::all(0.);
 impl TabControl {
     pub fn new() -> Self {
         Self {
+            component_id: Default::default(),
+
             tabs_view: ScrollView::default().with_scroll_h(
                 ScrollBarConfig::default().with_bar_size(8.0).with_smoothing(0.15).with_use_vertical_pointer_scroll(true),
             ),
@@ -155,6 +158,10 @@ impl TabControl {
 
     pub fn begin_tabs(&mut self, cx: &mut Cx) {
         self.tabs_view.begin_view(cx, LayoutSize::new(Width::Fill, Height::Compute));
+        cx.begin_accessibility_node(
+            self.component_id,
+            AccessibilityNodeProps { role: AccessibilityRole::TabList, area: self.tabs_view.area(), ..Default::default() },
+        );
         cx.begin_row(Width::Fill, Height::Compute);
         self._tab_now_selected = None;
         self._tab_id_alloc = 0;
@@ -191,6 +198,11 @@ impl TabControl {
         self.tab_fill.end_draw(cx);
 
         self.tabs.truncate(self._tab_id_alloc);
+        for tab in &self.tabs {
+            tab.add_accessibility_node(cx);
+        }
+        cx.end_accessibility_node();
+
         if let Some((pe, id)) = &self._dragging_tab {
             cx.begin_absolute_box();
             self.drag_tab_view.begin_view(cx, LayoutSize::FILL);
@@ -6,7 +6,7 @@ use crate::textcursor::*;
 use crate::tokentype::*;
 use zaplib::*;
 
-static SHADER_INDENT_LINES: Shader = Shader {
+pub(crate) static SHADER_INDENT_LINES: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -35,7 +35,7 @@ static SHADER_INDENT_LINES: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
-static SHADER_CURSOR: Shader = Shader {
+pub(crate) static SHADER_CURSOR: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -56,7 +56,7 @@ static SHADER_CURSOR: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
-static SHADER_SELECTION: Shader = Shader {
+pub(crate) static SHADER_SELECTION: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -109,7 +109,7 @@ static SHADER_SELECTION: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
-static SHADER_PAREN_PAIR: Shader = Shader {
+pub(crate) static SHADER_PAREN_PAIR: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -126,7 +126,7 @@ static SHADER_PAREN_PAIR: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
-static SHADER_SEARCH_MARKER: Shader = Shader {
+pub(crate) static SHADER_SEARCH_MARKER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -138,14 +138,14 @@ static SHADER_SEARCH_MARKER: Shader = Shader {
                 let pos2 = vec2(pos.x, pos.y + 0.03 * sin(pos.x * rect_size.x));
                 let df = Df::viewport(pos2 * rect_size);
                 df.move_to(vec2(0., rect_size.y - 1.));
-                df.line_to(rect_size.x, rect_size.y - 1.);
+                df.line_to(vec2(rect_size.x, rect_size.y - 1.));
                 return df.stroke(vec4(171.0/255.0,99.0/255.0,99.0/255.0,1.0), 0.8);
             }"#
         ),
     ],
     ..Shader::DEFAULT
 };
-static SHADER_MESSAGE_MARKER: Shader = Shader {
+pub(crate) static SHADER_MESSAGE_MARKER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -157,7 +157,7 @@ static SHADER_MESSAGE_MARKER: Shader = Shader {
                 let pos2 = vec2(pos.x, pos.y + 0.03 * sin(pos.x * rect_size.x));
                 let df = Df::viewport(pos2 * rect_size);
                 df.move_to(vec2(0., rect_size.y - 1.));
-                df.line_to(rect_size.x, rect_size.y - 1.);
+                df.line_to(vec2(rect_size.x, rect_size.y - 1.));
                 return df.stroke(color, 0.8);
             }"#
         ),
@@ -870,7 +870,8 @@ impl TextEditor {
                 //return CodeEditorEvent::FoldStart
             }
             KeyCode::Tab => {
-                if !self.read_only {
+                // Single line inputs use Tab for moving focus; see `Cx::add_key_focusable`.
+                if !self.read_only && self.multiline {
                     if ke.modifiers.shift {
                         self.cursors.remove_tab(text_buffer, 4);
                     } else {
@@ -7,6 +7,8 @@ pub struct TextInput {
     pub text_editor: TextEditor,
     pub text_buffer: TextBuffer,
     pub empty_message: String,
+    /// See [`Cx::add_key_focusable`].
+    pub tab_index: Option<u32>,
 }
 
 #[derive(Default)]
@@ -14,6 +16,9 @@ pub struct TextInputOptions {
     pub multiline: bool,
     pub read_only: bool,
     pub empty_message: String,
+    /// Order in which the input gets focus when pressing Tab; see [`Cx::add_key_focusable`]. Multiline inputs
+    /// don't take part in this, since they use Tab for indentation.
+    pub tab_index: Option<u32>,
 }
 
 const COLOR_EMPTY_MESSAGE: Vec4 = vec4(102.0 / 255.0, 102.0 / 255.0, 102.0 / 255.0, 1.0);
@@ -34,6 +39,7 @@ impl TextInput {
                 ..TextEditor::default()
             },
             empty_message: opt.empty_message,
+            tab_index: opt.tab_index,
             text_buffer: TextBuffer::from_utf8(""),
         }
     }
@@ -110,6 +116,19 @@ impl TextInput {
         }
 
         self.text_editor.end_text_editor(cx, text_buffer);
+        if !self.text_editor.multiline {
+            cx.add_key_focusable(self.text_editor.component_id, self.tab_index);
+        }
+        cx.add_accessibility_node(
+            self.text_editor.component_id,
+            AccessibilityNodeProps {
+                role: AccessibilityRole::TextInput,
+                label: self.empty_message.clone(),
+                value: Some(text_buffer.get_as_string()),
+                area: self.text_editor.view.area(),
+                ..Default::default()
+            },
+        );
         cx.end_padding_box();
     }
 }
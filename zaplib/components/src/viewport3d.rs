@@ -1,11 +1,27 @@
 use std::f32::consts::PI;
 
+use crate::*;
 use zaplib::*;
 
 /// Carefully chosen so that at the poles (all the way up or down) you can still rotate
 /// nicely.
 const EPSILON: f32 = 0.0001;
 
+/// A little more than near.
+const MIN_DISTANCE: f32 = 1.0;
+/// A little less than far.
+const MAX_DISTANCE: f32 = 900.;
+
+/// Vertical field of view of the perspective projection, in degrees. Also used to determine how much is visible
+/// with [`CameraProjection::Orthographic`].
+const FOV_Y: f32 = 40.;
+
+/// Movement keys for [`CameraMode::Fly`].
+const FLY_KEYS: [KeyCode; 6] = [KeyCode::KeyW, KeyCode::KeyA, KeyCode::KeyS, KeyCode::KeyD, KeyCode::KeyQ, KeyCode::KeyE];
+
+/// Animates from 0 to 1, to interpolate between the viewpoints in [`Viewport3D::camera_transition`].
+const CAMERA_TRANSITION_TRACKS: &[Track] = &[Track::Float { ease: Ease::InOutQuad, key_frames: &[(0.0, 0.0), (1.0, 1.0)] }];
+
 /// A nice article about how a 3D camera's look_at function works:
 /// <https://www.scratchapixel.com/lessons/mathematics-physics-for-computer-graphics/lookat-function>
 fn look_at(eye: Vec3, at: Vec3, up: Vec3) -> Mat4 {
@@ -30,7 +46,7 @@ fn look_at(eye: Vec3, at: Vec3, up: Vec3) -> Mat4 {
 }
 
 /// Spherical coordinates follow the same conventions as <https://threejs.org/docs/#api/en/math/Spherical>
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct SphericalAngles {
     /// Polar angle from 0 to PI. A value of 0 looking down the Y axis, and PI looking up the Y axis.
     pub phi: f32,
@@ -54,6 +70,75 @@ fn cartesian_to_spherical(position: Vec3) -> SphericalAngles {
     SphericalAngles { phi: position.z.atan2(position.y), theta: (position.x / radius).asin(), radius }
 }
 
+fn spherical_to_cartesian(SphericalAngles { phi, theta, radius }: SphericalAngles) -> Vec3 {
+    radius * vec3(phi.sin() * theta.sin(), phi.cos(), phi.sin() * theta.cos())
+}
+
+/// How the 3d scene gets projected onto the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraProjection {
+    Perspective,
+    /// Parallel lines stay parallel, like in technical drawings; typically used with [`CameraViewpoint::top`],
+    /// [`CameraViewpoint::front`], and [`CameraViewpoint::side`]. Shows as much as the perspective projection
+    /// does at the camera target, so zooming works the same way.
+    Orthographic,
+}
+
+/// How the user controls the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    /// Rotate around the camera target using the right mouse button or one finger, and pan using the left mouse
+    /// button or two fingers.
+    Orbit,
+    /// Look around by dragging, and move using the W/A/S/D keys (and Q/E for down/up), with a speed of
+    /// [`Viewport3DProps::fly_speed`].
+    Fly,
+}
+
+/// A camera position and target. Save one using [`Viewport3D::get_camera_viewpoint`], and return to it using
+/// [`Viewport3D::set_camera_viewpoint`] or [`Viewport3D::animate_to_viewpoint`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraViewpoint {
+    /// Position of the camera, relative to [`CameraViewpoint::target`].
+    pub position: SphericalAngles,
+    pub target: Vec3,
+}
+
+impl CameraViewpoint {
+    /// Looking down from above, along the Y axis.
+    pub fn top(target: Vec3, distance: f32) -> Self {
+        Self { position: SphericalAngles { phi: EPSILON, theta: 0., radius: distance }, target }
+    }
+
+    /// Looking from the front, along the Z axis.
+    pub fn front(target: Vec3, distance: f32) -> Self {
+        Self { position: SphericalAngles { phi: PI / 2., theta: 0., radius: distance }, target }
+    }
+
+    /// Looking from the right side, along the X axis.
+    pub fn side(target: Vec3, distance: f32) -> Self {
+        Self { position: SphericalAngles { phi: PI / 2., theta: PI / 2., radius: distance }, target }
+    }
+
+    /// Interpolate between two viewpoints, rotating the shortest way around.
+    fn from_lerp(a: Self, b: Self, f: f32) -> Self {
+        let mut theta_delta = (b.position.theta - a.position.theta) % (PI * 2.);
+        if theta_delta > PI {
+            theta_delta -= PI * 2.;
+        } else if theta_delta < -PI {
+            theta_delta += PI * 2.;
+        }
+        Self {
+            position: SphericalAngles {
+                phi: a.position.phi + (b.position.phi - a.position.phi) * f,
+                theta: a.position.theta + theta_delta * f,
+                radius: a.position.radius + (b.position.radius - a.position.radius) * f,
+            },
+            target: Vec3::from_lerp(a.target, b.target, f),
+        }
+    }
+}
+
 pub struct Viewport3DProps {
     pub initial_camera_position: Coordinates,
     /// Represents if users can use the left mouse to pan the camera.
@@ -61,6 +146,10 @@ pub struct Viewport3DProps {
     pub camera_target: Vec3,
     /// Represents if panning should move camera vertically.
     pub vertical_panning_enabled: bool,
+    pub projection: CameraProjection,
+    pub camera_mode: CameraMode,
+    /// Units per second when moving with [`CameraMode::Fly`].
+    pub fly_speed: f32,
 }
 
 impl Viewport3DProps {
@@ -71,6 +160,9 @@ impl Viewport3DProps {
         camera_target: Vec3::all(0.),
         panning_enabled: true,
         vertical_panning_enabled: true,
+        projection: CameraProjection::Perspective,
+        camera_mode: CameraMode::Orbit,
+        fly_speed: 10.,
     };
 }
 
@@ -87,6 +179,11 @@ pub struct Viewport3D {
     clear_color: Vec4,
     pub color_texture: Texture,
     depth_texture: Texture,
+    /// Effects to apply to the rendered scene, like [`PostProcessEffect::fxaa`].
+    pub post_processing_effects: Vec<PostProcessEffect>,
+    post_process_chain: PostProcessChain,
+    /// Lights for meshes drawn with [`Viewport3D::draw_mesh`].
+    pub lighting: Lighting,
     view_2d: View,
     view_3d: View,
     pub measured_size: Vec2,
@@ -94,8 +191,16 @@ pub struct Viewport3D {
     camera_position_start: Option<SphericalAngles>,
     camera_target_offset: Vec3,
     camera_target_offset_start: Option<Vec3>,
+    /// The viewpoints to interpolate between using [`Viewport3D::animator`]; see [`Viewport3D::animate_to_viewpoint`].
+    camera_transition: Option<(CameraViewpoint, CameraViewpoint)>,
+    animator: Animator,
+    /// Keys from [`FLY_KEYS`] that are currently held down.
+    fly_keys_down: Vec<KeyCode>,
+    /// When we last moved because of [`Viewport3D::fly_keys_down`].
+    fly_last_time: f64,
     props: Viewport3DProps,
     has_read_props: bool,
+    gestures: GestureRecognizer,
 }
 
 impl Default for Viewport3D {
@@ -110,27 +215,108 @@ impl Default for Viewport3D {
             camera_position_start: Default::default(),
             camera_target_offset: Default::default(),
             camera_target_offset_start: Default::default(),
+            camera_transition: Default::default(),
+            animator: Default::default(),
+            fly_keys_down: Default::default(),
+            fly_last_time: Default::default(),
             pass: Default::default(),
             clear_color: Default::default(),
             color_texture: Default::default(),
             depth_texture: Default::default(),
+            post_processing_effects: Default::default(),
+            post_process_chain: Default::default(),
+            lighting: Default::default(),
             view_3d: Default::default(),
             view_2d: Default::default(),
             has_read_props: Default::default(),
             props: Default::default(),
+            gestures: Default::default(),
         }
     }
 }
 
 impl Viewport3D {
     pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> Option<PassMatrixMode> {
-        match event.hits_pointer(cx, self.component_id, self.area.get_rect_for_first_instance(cx)) {
+        if self.props.camera_mode == CameraMode::Fly {
+            match event.hits_keyboard(cx, self.component_id) {
+                Event::KeyDown(ke) if FLY_KEYS.contains(&ke.key_code) => {
+                    if self.fly_keys_down.is_empty() {
+                        self.fly_last_time = cx.last_event_time;
+                        cx.request_next_frame();
+                    }
+                    if !self.fly_keys_down.contains(&ke.key_code) {
+                        self.fly_keys_down.push(ke.key_code);
+                    }
+                }
+                Event::KeyUp(ke) => self.fly_keys_down.retain(|key_code| *key_code != ke.key_code),
+                Event::KeyFocusLost(_) => self.fly_keys_down.clear(),
+                _ => (),
+            }
+            if let Event::NextFrame = event {
+                if !self.fly_keys_down.is_empty() {
+                    let elapsed = (cx.last_event_time - self.fly_last_time) as f32;
+                    self.fly_last_time = cx.last_event_time;
+                    cx.request_next_frame();
+                    // While animating to a viewpoint, the animation below determines where the camera is.
+                    if self.camera_transition.is_none() {
+                        self.camera_target_offset += self.get_fly_direction() * self.props.fly_speed * elapsed;
+                        return Some(self.pass_set_matrix_mode(cx));
+                    }
+                }
+            }
+        }
+
+        if self.animator.handle(cx, event) {
+            if let Some((from, to)) = self.camera_transition {
+                let fraction = self.animator.get_float(0);
+                self.apply_camera_viewpoint(CameraViewpoint::from_lerp(from, to, fraction));
+                if fraction >= 1. {
+                    self.camera_transition = None;
+                }
+                return Some(self.pass_set_matrix_mode(cx));
+            }
+        }
+
+        let hit_event = event.hits_pointer_multi_touch(cx, self.component_id, self.area.get_rect_for_first_instance(cx));
+        match self.gestures.handle(cx, &hit_event) {
+            GestureEvent::TransformStart => {
+                self.camera_position_start = None;
+                self.camera_target_offset_start = None;
+            }
+            // pinch to zoom, rotate around the vertical axis, and pan with two fingers
+            GestureEvent::Transform(te) => {
+                self.camera_position.radius = (self.camera_position.radius / te.scale).clamp(MIN_DISTANCE, MAX_DISTANCE);
+                self.camera_position.theta = (self.camera_position.theta - te.rotation) % (PI * 2.);
+                if self.props.panning_enabled {
+                    self.camera_target_offset += self.get_camera_target_offset_for_pan(te.pan);
+                }
+                return Some(self.pass_set_matrix_mode(cx));
+            }
+            _ => (),
+        }
+
+        match hit_event {
             Event::PointerHover(_pe) => {
                 // cx.set_hover_mouse_cursor(MouseCursor::Move);
             }
+            // a second finger went down; handled by `self.gestures`
+            Event::PointerDown(_pe) if self.gestures.is_transforming() => {}
+            // in fly mode, any button or finger looks around
+            Event::PointerDown(_pe) if self.props.camera_mode == CameraMode::Fly => {
+                cx.set_key_focus(Some(self.component_id));
+                self.camera_transition = None;
+                self.camera_position_start = Some(self.camera_position);
+                self.camera_target_offset_start = Some(self.camera_target_offset);
+            }
+            // with a single finger we orbit, since panning is done with two fingers
+            Event::PointerDown(pe) if pe.input_type.is_touch() => {
+                self.camera_transition = None;
+                self.camera_position_start = Some(self.camera_position);
+            }
             // traditional mouse down
             Event::PointerDown(pe) => {
                 // cx.set_down_mouse_cursor(MouseCursor::Move);
+                self.camera_transition = None;
                 if self.props.panning_enabled && pe.button == MouseButton::Left {
                     self.camera_target_offset_start = Some(self.camera_target_offset);
                 } else if pe.button == MouseButton::Right {
@@ -143,11 +329,10 @@ impl Viewport3D {
                 self.camera_target_offset_start = None;
             }
             Event::PointerScroll(pe) => {
-                let min_distance = 1.0; // a little more than near
-                let max_distance = 900.; // a little less than far
-                let zoom_speed = (self.camera_position.radius * (PI / 4.) / max_distance).sin().abs() / 2.0;
+                self.camera_transition = None;
+                let zoom_speed = (self.camera_position.radius * (PI / 4.) / MAX_DISTANCE).sin().abs() / 2.0;
                 self.camera_position.radius =
-                    (self.camera_position.radius + pe.scroll.y * zoom_speed).max(min_distance).min(max_distance);
+                    (self.camera_position.radius + pe.scroll.y * zoom_speed).clamp(MIN_DISTANCE, MAX_DISTANCE);
                 return Some(self.pass_set_matrix_mode(cx));
             }
             Event::PointerMove(pe) => {
@@ -161,31 +346,21 @@ impl Viewport3D {
                         phi: (phi - (pe.abs.y - pe.abs_start.y) * rotate_speed).clamp(EPSILON, PI - EPSILON),
                         radius,
                     };
+                    // When flying we look around from where the camera is, instead of rotating around the target,
+                    // so we move the target.
+                    if let (CameraMode::Fly, Some(camera_target_offset_start)) =
+                        (self.props.camera_mode, self.camera_target_offset_start)
+                    {
+                        let camera_position_start = SphericalAngles { phi, theta, radius };
+                        self.camera_target_offset = camera_target_offset_start + spherical_to_cartesian(camera_position_start)
+                            - spherical_to_cartesian(self.camera_position);
+                    }
                     return Some(self.pass_set_matrix_mode(cx));
                 } else if let Some(camera_target_offset_start) = self.camera_target_offset_start {
-                    // TODO(Shobhit): Whenever we do Orthographic view properly, we need to adjust the panning accordingly
-                    // We would need to consider viewable area's width and height into consideration just like how
-                    // worldview does it:
-                    // https://git.io/J0wsP
-                    // Please refer some more discussion about this here:
-                    // https://github.robot.car/cruise/exviz/pull/107#discussion_r932946
-                    let pan_speed = 0.8;
-                    // Normalize using the height of the viewport and the camera distance, since those determine the field of view
-                    // intersecting with the camera target.
-                    let mouse_offset = (pe.rel - pe.rel_start) / self.measured_size.y * self.camera_position.radius * pan_speed;
-
-                    let vertical_offset =
-                        if self.props.vertical_panning_enabled { self.camera_position.phi.to_degrees() } else { 0. };
-
-                    // We need to calculate the value of camera target offset.
-                    // For that we create a rotation matrix from the camera_position (the rotation),
-                    // thereafter we translate it on x/y based on relative offset calculated mouse movements.
-                    // Finally, so that we don't forget about the previous camera target offsets,
-                    // we add camera_target_offset_start so that we don't start from beginning in every interaction.
-                    self.camera_target_offset = Mat4::rotation(vertical_offset, self.camera_position.theta.to_degrees(), 0.)
-                        .transform_vec4(vec4(-mouse_offset.x, 0., -mouse_offset.y, 1.0))
-                        .to_vec3()
-                        + camera_target_offset_start;
+                    // We add camera_target_offset_start so that we don't forget about the previous camera target offsets,
+                    // and don't start from beginning in every interaction.
+                    self.camera_target_offset =
+                        self.get_camera_target_offset_for_pan(pe.rel - pe.rel_start) + camera_target_offset_start;
 
                     return Some(self.pass_set_matrix_mode(cx));
                 }
@@ -196,19 +371,154 @@ impl Viewport3D {
         None
     }
 
+    /// Convert a pan on the screen (in logical pixels) to an offset for the camera target.
+    fn get_camera_target_offset_for_pan(&self, pan: Vec2) -> Vec3 {
+        // TODO(Shobhit): Whenever we do Orthographic view properly, we need to adjust the panning accordingly
+        // We would need to consider viewable area's width and height into consideration just like how
+        // worldview does it:
+        // https://git.io/J0wsP
+        // Please refer some more discussion about this here:
+        // https://github.robot.car/cruise/exviz/pull/107#discussion_r932946
+        let pan_speed = 0.8;
+        // Normalize using the height of the viewport and the camera distance, since those determine the field of view
+        // intersecting with the camera target.
+        let mouse_offset = pan / self.measured_size.y * self.camera_position.radius * pan_speed;
+
+        let vertical_offset = if self.props.vertical_panning_enabled { self.camera_position.phi.to_degrees() } else { 0. };
+
+        // We need to calculate the value of camera target offset.
+        // For that we create a rotation matrix from the camera_position (the rotation),
+        // thereafter we translate it on x/y based on relative offset calculated mouse movements.
+        Mat4::rotation(vertical_offset, self.camera_position.theta.to_degrees(), 0.)
+            .transform_vec4(vec4(-mouse_offset.x, 0., -mouse_offset.y, 1.0))
+            .to_vec3()
+    }
+
+    /// Unit vector for the direction to move in when flying, based on [`Viewport3D::fly_keys_down`].
+    fn get_fly_direction(&self) -> Vec3 {
+        let forward = -spherical_to_cartesian(SphericalAngles { radius: 1., ..self.camera_position });
+        let up = vec3(0., 1., 0.);
+        let right = Vec3::cross(forward, up).normalize();
+        let mut direction = Vec3::default();
+        for key_code in &self.fly_keys_down {
+            direction += match key_code {
+                KeyCode::KeyW => forward,
+                KeyCode::KeyS => -forward,
+                KeyCode::KeyD => right,
+                KeyCode::KeyA => -right,
+                KeyCode::KeyE => up,
+                KeyCode::KeyQ => -up,
+                _ => Vec3::default(),
+            };
+        }
+        direction.normalize()
+    }
+
     fn get_matrix_projection(&self) -> PassMatrixMode {
-        let SphericalAngles { phi, theta, radius } = self.camera_position;
-        let eye = radius * vec3(phi.sin() * theta.sin(), phi.cos(), phi.sin() * theta.cos());
-
-        PassMatrixMode::Projection {
-            fov_y: 40.0,
-            near: 0.1,
-            far: 1000.0,
-            cam: look_at(
-                eye + self.props.camera_target + self.camera_target_offset,
-                self.props.camera_target + self.camera_target_offset,
-                vec3(0., 1., 0.),
-            ),
+        let target = self.props.camera_target + self.camera_target_offset;
+        let cam = look_at(target + spherical_to_cartesian(self.camera_position), target, vec3(0., 1., 0.));
+
+        match self.props.projection {
+            CameraProjection::Perspective => PassMatrixMode::Projection { fov_y: FOV_Y, near: 0.1, far: 1000.0, cam },
+            CameraProjection::Orthographic => PassMatrixMode::Orthographic {
+                height: 2. * self.camera_position.radius * (FOV_Y.to_radians() / 2.).tan(),
+                // Without perspective we can show things behind the camera too, so that zooming in doesn't cut
+                // off parts of the scene.
+                near: -1000.0,
+                far: 1000.0,
+                cam,
+            },
+        }
+    }
+
+    /// The part of the scene that is visible with the current camera, for skipping instances that are out of view;
+    /// see [`Frustum3d`]. Uses the size of the last [`Viewport3D::begin_draw`].
+    pub fn get_frustum(&self) -> Option<Frustum3d> {
+        if self.measured_size.x <= 0. || self.measured_size.y <= 0. {
+            return None;
+        }
+        let aspect = self.measured_size.x / self.measured_size.y;
+        let (projection, cam) = match self.get_matrix_projection() {
+            PassMatrixMode::Projection { fov_y, near, far, cam } => (Mat4::perspective(fov_y, aspect, near, far), cam),
+            PassMatrixMode::Orthographic { height, near, far, cam } => (Mat4::orthographic(height, aspect, near, far), cam),
+            PassMatrixMode::Ortho => return None,
+        };
+        Some(Frustum3d::new(&cam, &projection, self.measured_size.y))
+    }
+
+    pub fn get_camera_viewpoint(&self) -> CameraViewpoint {
+        CameraViewpoint { position: self.camera_position, target: self.props.camera_target + self.camera_target_offset }
+    }
+
+    fn apply_camera_viewpoint(&mut self, viewpoint: CameraViewpoint) {
+        self.camera_position = viewpoint.position;
+        self.camera_target_offset = viewpoint.target - self.props.camera_target;
+    }
+
+    /// Move the camera to `viewpoint` right away, stopping any [`Viewport3D::animate_to_viewpoint`].
+    pub fn set_camera_viewpoint(&mut self, cx: &mut Cx, viewpoint: CameraViewpoint) -> PassMatrixMode {
+        self.camera_transition = None;
+        self.apply_camera_viewpoint(viewpoint);
+        self.pass_set_matrix_mode(cx)
+    }
+
+    /// Smoothly move the camera to `viewpoint`, taking `duration` seconds. Gets interrupted when the user
+    /// moves the camera.
+    pub fn animate_to_viewpoint(&mut self, cx: &mut Cx, viewpoint: CameraViewpoint, duration: f64) {
+        if duration <= 0. {
+            self.set_camera_viewpoint(cx, viewpoint);
+            return;
+        }
+        self.camera_transition = Some((self.get_camera_viewpoint(), viewpoint));
+        let anim = Anim { duration, tracks: CAMERA_TRANSITION_TRACKS, ..Anim::DEFAULT };
+        self.animator.play_anim(cx, anim.clone());
+        self.animator.draw(cx, anim);
+    }
+
+    /// A viewpoint from the current direction that fits everything between `min` and `max`, e.g. to pass into
+    /// [`Viewport3D::animate_to_viewpoint`].
+    pub fn get_viewpoint_for_bounds(&self, min: Vec3, max: Vec3) -> CameraViewpoint {
+        let bounding_radius = ((max - min) * 0.5).length();
+        let aspect = if self.measured_size.y > 0. { self.measured_size.x / self.measured_size.y } else { 1. };
+        // Use the horizontal field of view if it's smaller than the vertical one.
+        let half_fov = ((FOV_Y.to_radians() / 2.).tan() * aspect.min(1.)).atan();
+        CameraViewpoint {
+            position: SphericalAngles {
+                radius: (bounding_radius / half_fov.sin()).clamp(MIN_DISTANCE, MAX_DISTANCE),
+                ..self.camera_position
+            },
+            target: (min + max) * 0.5,
+        }
+    }
+
+    /// Get the ray from the camera through a position on the screen, like [`PointerHoverEvent::abs`] or
+    /// [`PointerDownEvent::abs`], for picking objects with a [`Picker3d`]. Returns [`None`] if the position is
+    /// outside of the viewport.
+    pub fn get_pointer_ray(&self, cx: &Cx, abs: Vec2) -> Option<Ray3d> {
+        let rect = self.area.get_rect_for_first_instance(cx)?;
+        if !rect.contains(abs) || rect.size.x <= 0. || rect.size.y <= 0. {
+            return None;
+        }
+        // Normalized device coordinates go from -1 to 1, with y pointing up.
+        let ndc = vec2((abs.x - rect.pos.x) / rect.size.x * 2. - 1., 1. - (abs.y - rect.pos.y) / rect.size.y * 2.);
+        let aspect = rect.size.x / rect.size.y;
+
+        match self.get_matrix_projection() {
+            PassMatrixMode::Projection { fov_y, cam, .. } => {
+                let tan_half_fov_y = (fov_y.to_radians() / 2.).tan();
+                let view_direction = vec4(ndc.x * tan_half_fov_y * aspect, ndc.y * tan_half_fov_y, -1., 0.);
+                let origin = cam.invert().transform_vec4(vec4(0., 0., 0., 1.)).to_vec3();
+                let direction = cam.as_rotation().transpose().transform_vec4(view_direction).to_vec3();
+                Some(Ray3d { pixel_size_per_distance: 2. * tan_half_fov_y / rect.size.y, ..Ray3d::new(origin, direction) })
+            }
+            PassMatrixMode::Orthographic { height, near, cam, .. } => {
+                // Start at the near plane, which is behind the camera.
+                let view_origin = vec4(ndc.x * height / 2. * aspect, ndc.y * height / 2., -near, 1.);
+                let origin = cam.invert().transform_vec4(view_origin).to_vec3();
+                let direction = cam.as_rotation().transpose().transform_vec4(vec4(0., 0., -1., 0.)).to_vec3();
+                Some(Ray3d { pixel_size: height / rect.size.y, ..Ray3d::new(origin, direction) })
+            }
+            PassMatrixMode::Ortho => None,
         }
     }
 
@@ -218,8 +528,8 @@ impl Viewport3D {
         matrix_mode
     }
 
-    /// TODO(JP): This is kind of exploiting a potential bug in the framework.. We don't clean up [`Pass`]es,
-    /// so if we just don't call [`Pass::begin_pass`] then it will happily keep on rendering. Is this a bug
+    /// TODO(JP): This is kind of exploiting a potential bug in the framework.. [`Pass`]es stick around until they
+    /// are freed, so if we just don't call [`Pass::begin_pass`] then it will happily keep on rendering. Is this a bug
     /// or a feature? I'm not sure.. See [`Pass::begin_pass`] for more thoughts.
     #[must_use]
     pub fn skip_draw(&mut self, cx: &mut Cx) -> bool {
@@ -260,16 +570,77 @@ impl Viewport3D {
         self.view_3d.end_view(cx);
         self.pass.end_pass(cx);
 
+        let texture_handle = self.post_process_chain.draw(cx, &self.pass, &self.post_processing_effects);
+        self.area.write_texture_2d(cx, "texture", texture_handle);
+
         matrix_mode
     }
 
+    /// Draw `instances` of `gpu_geometry` with `material`, lit by [`Viewport3D::lighting`]. Call this between
+    /// [`Viewport3D::begin_draw`] and [`Viewport3D::end_draw`].
+    pub fn draw_mesh(&self, cx: &mut Cx, material: Material, gpu_geometry: GpuGeometry, instances: &[MeshIns]) -> Area {
+        material.draw(cx, &self.lighting, gpu_geometry, instances)
+    }
+
     fn draw_viewport_2d(&mut self, cx: &mut Cx) {
         self.view_2d.begin_view(cx, LayoutSize::FILL);
         // blit the texture to a view rect
         self.measured_size = vec2(cx.get_width_total(), cx.get_height_total());
-        let color_texture_handle = self.color_texture.get_color(cx);
-        self.area = ImageIns::draw(cx, Rect { pos: cx.get_box_origin(), size: self.measured_size }, color_texture_handle);
+        let texture_handle = self.post_process_chain.output_texture().unwrap_or_else(|| self.color_texture.get_color(cx));
+        self.area = ImageIns::draw(cx, Rect { pos: cx.get_box_origin(), size: self.measured_size }, texture_handle);
 
         self.view_2d.end_view(cx);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} is not close to {}", a, b);
+    }
+
+    #[test]
+    fn lerps_viewpoints_the_shortest_way_around() {
+        let a = CameraViewpoint { position: SphericalAngles { phi: 1., theta: 3., radius: 10. }, target: vec3(0., 0., 0.) };
+        let b = CameraViewpoint { position: SphericalAngles { phi: 2., theta: -3., radius: 20. }, target: vec3(2., 4., 6.) };
+
+        assert_eq!(CameraViewpoint::from_lerp(a, b, 0.), a);
+        let end = CameraViewpoint::from_lerp(a, b, 1.);
+        assert_close(end.position.phi, 2.);
+        // Going from 3 to -3 the short way passes PI, and ends up at the same angle as -3.
+        assert_close(end.position.theta, 2. * PI - 3.);
+        assert_close(end.position.radius, 20.);
+        assert_eq!(end.target, b.target);
+
+        let half = CameraViewpoint::from_lerp(a, b, 0.5);
+        assert_close(half.position.phi, 1.5);
+        assert_close(half.position.theta, PI);
+        assert_close(half.position.radius, 15.);
+        assert_eq!(half.target, vec3(1., 2., 3.));
+
+        // And the other way around.
+        assert_close(CameraViewpoint::from_lerp(b, a, 0.5).position.theta, -PI);
+    }
+
+    #[test]
+    fn fits_viewpoint_to_bounds() {
+        let mut viewport = Viewport3D::default();
+        let (min, max) = (vec3(-1., -1., -1.), vec3(3., 1., 1.));
+        // Half the diagonal of the box.
+        let bounding_radius = 6f32.sqrt();
+
+        // Wide viewport, so the vertical field of view is the limiting one.
+        viewport.measured_size = vec2(200., 100.);
+        let viewpoint = viewport.get_viewpoint_for_bounds(min, max);
+        assert_eq!(viewpoint.target, vec3(1., 0., 0.));
+        assert_close(viewpoint.position.radius, bounding_radius / (FOV_Y / 2.).to_radians().sin());
+        assert_eq!(viewpoint.position.theta, viewport.camera_position.theta);
+
+        // Tall viewport, so the horizontal field of view (half as wide) is the limiting one.
+        viewport.measured_size = vec2(100., 200.);
+        let half_fov = ((FOV_Y / 2.).to_radians().tan() * 0.5).atan();
+        assert_close(viewport.get_viewpoint_for_bounds(min, max).position.radius, bounding_radius / half_fov.sin());
+    }
+}
@@ -5,7 +5,7 @@ pub struct HomePage {
     view: ScrollView,
     email_input: TextInput,
     email_state: EmailState,
-    email_signal: Signal,
+    email_request: Option<HttpRequestId>,
     send_mail_button: Button,
 }
 
@@ -39,31 +39,30 @@ const TEXT_BODY: TextInsProps = TextInsProps {
 };
 
 impl HomePage {
-    pub fn new(cx: &mut Cx) -> Self {
+    pub fn new(_cx: &mut Cx) -> Self {
         Self {
             view: ScrollView::new_standard_vh(),
             send_mail_button: Button::default(),
-            email_signal: cx.new_signal(),
+            email_request: None,
             email_input: TextInput::new(TextInputOptions {
                 multiline: false,
                 read_only: false,
                 empty_message: "Enter email".to_string(),
+                tab_index: None,
             }),
             email_state: EmailState::Empty,
         }
     }
 
     pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
-        if let Event::Signal(sig) = event {
-            if let Some(statusses) = sig.signals.get(&self.email_signal) {
-                for status in statusses {
-                    if *status == Cx::STATUS_HTTP_SEND_OK {
-                        self.email_state = EmailState::OkSending;
-                    } else if *status == Cx::STATUS_HTTP_SEND_FAIL {
-                        self.email_state = EmailState::ErrorSending;
-                    }
-                    cx.request_draw();
-                }
+        if let Event::HttpResponse(HttpResponseEvent { request_id, result }) = event {
+            if Some(*request_id) == self.email_request {
+                self.email_request = None;
+                self.email_state = match result {
+                    Ok(response) if response.is_success() => EmailState::OkSending,
+                    _ => EmailState::ErrorSending,
+                };
+                cx.request_draw();
             }
         }
         if let TextEditorEvent::Change = self.email_input.handle(cx, event) {
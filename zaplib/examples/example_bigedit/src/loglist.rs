@@ -309,6 +309,11 @@ impl LogItemDraw {
         }
         self.item_bg.end_draw(cx);
         list_item.set_area(self.item_bg.area());
+        if let Some(loc_msg) = log_item.get_loc_message() {
+            list_item.set_label(&format!("{}:{} - {}", loc_msg.path, loc_msg.line, loc_msg.body));
+        } else {
+            list_item.set_label(log_item.get_body());
+        }
     }
 
     fn draw_status_line(&mut self, cx: &mut Cx, counter: usize, bm: &BuildManager) {
@@ -57,6 +57,7 @@ impl SearchResults {
                 multiline: false,
                 read_only: false,
                 empty_message: "search".to_string(),
+                tab_index: None,
             }),
             result_draw: SearchResultDraw::new(),
             list: List::default(),
@@ -330,11 +331,13 @@ impl SearchResultDraw {
         let pos = text_buffer.offset_to_text_pos(tok.offset);
 
         let split = path.split('/').collect::<Vec<&str>>();
+        let location = format!("{}:{} - {}", split.last().unwrap(), pos.row, split[0..split.len() - 1].join("/"));
         TextIns::draw_walk(
             cx,
-            &format!("{}:{} - {}", split.last().unwrap(), pos.row, split[0..split.len() - 1].join("/")),
+            &location,
             &TextInsProps { wrapping: Wrapping::Word, color: vec4(0.6, 0.6, 0.6, 1.0), ..TextInsProps::DEFAULT },
         );
+        list_item.set_label(&location);
         cx.draw_new_line();
         cx.move_draw_pos(0., 5.);
 
@@ -1,9 +1,5 @@
-use std::io::{Cursor, Read};
-
 use zaplib::*;
 
-use image::{codecs::jpeg::JpegDecoder, ImageDecoder};
-
 static DUMMY_SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
@@ -40,42 +36,14 @@ static IMAGE_SHADER: Shader = Shader {
     ..Shader::DEFAULT
 };
 
-struct JpegImage {
-    texture: Texture,
+struct Image {
+    texture_handle: TextureHandle,
 }
 
-impl JpegImage {
-    fn new(bytes: Vec<u8>, cx: &mut Cx) -> JpegImage {
-        // TODO(JP): replace with Zaplib API; see https://github.com/Zaplib/zaplib/issues/161
-        let decoder = JpegDecoder::new(Cursor::new(bytes)).expect("Could not decode image");
-        let mut img_bytes = vec![0; decoder.total_bytes() as usize];
-
-        let (width, height) = decoder.dimensions();
-        decoder.read_image(&mut img_bytes).expect("Could not decode image");
-
-        let mut texture = Texture::default();
-        let texture_handle = texture.get_with_dimensions(cx, width as usize, height as usize);
-        let texture_img = texture_handle.get_image_mut(cx);
-
-        let img_buffer = image::RgbImage::from_raw(width, height, img_bytes).expect("Could not create image");
-
-        let mut index = 0;
-        for h in 0..height {
-            for w in 0..width {
-                let pixel = img_buffer.get_pixel(w, h);
-                let image::Rgb(data) = *pixel;
-                *(texture_img.get_mut(index)).unwrap() = u32::from_le_bytes([data[0], data[1], data[2], 0xff]);
-                index += 1;
-            }
-        }
-
-        JpegImage { texture }
-    }
-
+impl Image {
     fn draw(&mut self, cx: &mut Cx) {
-        let texture_handle = self.texture.unwrap_texture_handle();
         let area = cx.add_instances(&IMAGE_SHADER, &[QuadIns::from_rect(cx.get_box_rect())]);
-        area.write_texture_2d(cx, "texture", texture_handle);
+        area.write_texture_2d(cx, "texture", self.texture_handle);
 
         // Dummy shader call to prevent texture batching
         // TODO(JP): Fix https://github.com/Zaplib/zaplib/issues/156
@@ -89,7 +57,7 @@ struct ImageExampleApp {
     pass: Pass,
     main_view: View,
 
-    images: Vec<JpegImage>,
+    images: Vec<Image>,
 }
 
 impl ImageExampleApp {
@@ -97,12 +65,10 @@ impl ImageExampleApp {
         Self::default()
     }
 
-    fn read_image(&mut self, cx: &mut Cx, path: &str) {
+    fn load_image(&mut self, cx: &mut Cx, path: &str) {
         match UniversalFile::open(path) {
-            Ok(mut file) => {
-                let mut buf = Vec::new();
-                file.read_to_end(&mut buf).expect("Could not read file");
-                self.images.push(JpegImage::new(buf, cx));
+            Ok(file) => {
+                Texture::load(cx, file);
             }
             Err(msg) => {
                 log!("Error: {:?}", msg);
@@ -113,9 +79,16 @@ impl ImageExampleApp {
     fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
         match event {
             Event::Construct => {
-                self.read_image(cx, "zaplib/examples/example_image/data/img1.jpg");
-                self.read_image(cx, "zaplib/examples/example_image/data/img2.jpg");
+                self.load_image(cx, "zaplib/examples/example_image/data/img1.jpg");
+                self.load_image(cx, "zaplib/examples/example_image/data/img2.jpg");
             }
+            Event::TextureLoaded(TextureLoadedEvent { result, .. }) => match result {
+                Ok(texture_handle) => {
+                    self.images.push(Image { texture_handle: *texture_handle });
+                    cx.request_draw();
+                }
+                Err(err) => log!("Error: {}", err),
+            },
             _ => {}
         }
     }
@@ -1,37 +1,6 @@
 use zaplib::*;
 use zaplib_components::*;
 
-#[repr(C)]
-#[derive(Debug, Clone)]
-struct Instance {
-    normal: Vec3,
-}
-
-static SHADER: Shader = Shader {
-    code_to_concatenate: &[
-        Cx::STD_SHADER,
-        Geometry3d::SHADER,
-        code_fragment!(
-            r#"
-            // TODO(JP): Make it so you can just render a single geometry without having to use instancing.
-            // Since we still need instancing we need a dummy value here.
-            instance _dummy: float;
-
-            fn vertex() -> vec4 {
-                return camera_projection * camera_view * vec4(geom_pos, 1.);
-            }
-
-            fn pixel() -> vec4 {
-                let lightPosition = vec3(20.,0.,30.);
-                let lightDirection = normalize(geom_pos - lightPosition);
-                return vec4(vec3(clamp(dot(-lightDirection, geom_normal), 0.2, 1.0)),1.0);
-            }
-            "#
-        ),
-    ],
-    ..Shader::DEFAULT
-};
-
 #[derive(Default)]
 struct GeometryExampleApp {
     window: Window,
@@ -77,7 +46,12 @@ impl GeometryExampleApp {
         // causes us to oscillate between two GPU buffers. Not the end of the world but not great.
         let gpu_geometry = GpuGeometry::new(cx, Geometry3d::sphere(self.num_sides + 3, self.num_sides + 3, 0.5));
         self.viewport_3d.begin_draw(cx, VIEWPORT_PROPS);
-        cx.add_mesh_instances(&SHADER, &[0], gpu_geometry);
+        self.viewport_3d.draw_mesh(
+            cx,
+            Material::BlinnPhong { shininess: 32., specular: 0.5 },
+            gpu_geometry,
+            &[MeshIns::default()],
+        );
         self.viewport_3d.end_draw(cx);
 
         self.main_view.end_view(cx);
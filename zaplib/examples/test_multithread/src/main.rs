@@ -83,7 +83,7 @@ impl MultithreadExampleApp {
                         universal_thread::spawn(move || {
                             universal_thread::sleep(Duration::from_millis(100));
                             Cx::post_signal(signal, FADE);
-                        })
+                        });
                     }
                 }
             }
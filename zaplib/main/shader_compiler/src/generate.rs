@@ -19,7 +19,14 @@ use {
 pub(crate) trait BackendWriter {
     fn write_var_decl(&self, string: &mut String, is_inout: bool, is_packed: bool, ident: Ident, ty: &Ty);
 
-    fn write_call_expr_hidden_args(&self, string: &mut String, ident_path: IdentPath, shader: &ShaderAst, sep: &str);
+    fn write_call_expr_hidden_args(
+        &self,
+        string: &mut String,
+        ident_path: IdentPath,
+        arg_exprs: &[Expr],
+        shader: &ShaderAst,
+        sep: &str,
+    );
 
     fn generate_var_expr(
         &self,
@@ -45,6 +52,20 @@ pub(crate) trait BackendWriter {
     fn use_cons_fn(&self, what: &str) -> bool;
 }
 
+/// If this is a call to `sample2d`, get the identifier of the texture that is being sampled, so backends that
+/// have separate sampler objects can pass in the sampler for that texture.
+///
+/// The type checker makes sure that the first argument of `sample2d` is always a texture variable.
+pub(crate) fn sample2d_texture_ident(ident_path: IdentPath, arg_exprs: &[Expr]) -> Option<Ident> {
+    if ident_path != IdentPath::from_str("sample2d") {
+        return None;
+    }
+    match &arg_exprs.first()?.kind {
+        ExprKind::Var { ident_path, .. } => ident_path.get_single(),
+        _ => None,
+    }
+}
+
 pub(crate) struct BlockGenerator<'a> {
     pub(crate) shader: &'a ShaderAst,
     pub(crate) decl: &'a FnDecl,
@@ -370,7 +391,7 @@ impl<'a> ExprGenerator<'a> {
             sep = ", ";
         }
 
-        self.backend_writer.write_call_expr_hidden_args(self.string, ident_path, self.shader, sep);
+        self.backend_writer.write_call_expr_hidden_args(self.string, ident_path, arg_exprs, self.shader, sep);
 
         write!(self.string, ")").unwrap();
     }
@@ -625,7 +625,15 @@ impl<'a> VarUnpacker<'a> {
 struct GlslBackendWriter();
 
 impl BackendWriter for GlslBackendWriter {
-    fn write_call_expr_hidden_args(&self, _string: &mut String, _ident_path: IdentPath, _shader: &ShaderAst, _sep: &str) {}
+    fn write_call_expr_hidden_args(
+        &self,
+        _string: &mut String,
+        _ident_path: IdentPath,
+        _arg_exprs: &[Expr],
+        _shader: &ShaderAst,
+        _sep: &str,
+    ) {
+    }
 
     fn generate_var_expr(
         &self,
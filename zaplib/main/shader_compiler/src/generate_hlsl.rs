@@ -1,7 +1,7 @@
 use {
     crate::{
         env::VarKind,
-        generate::{BackendWriter, BlockGenerator, ExprGenerator},
+        generate::{sample2d_texture_ident, BackendWriter, BlockGenerator, ExprGenerator},
         ident::{Ident, IdentPath},
         shaderast::*,
         span::Span,
@@ -32,16 +32,8 @@ struct ShaderGenerator<'a> {
 
 impl<'a> ShaderGenerator<'a> {
     fn generate_shader(&mut self) {
-        writeln!(
-            self.string,
-            "SamplerState mpsc_default_texture_sampler{{Filter=MIN_MAX_MIP_LINEAR;AddressU = Wrap;AddressV=Wrap;}};"
-        )
-        .unwrap();
-        writeln!(
-            self.string,
-            "float4 sample2d(Texture2D tex, float2 pos){{return tex.Sample(mpsc_default_texture_sampler,pos);}}"
-        )
-        .unwrap();
+        writeln!(self.string, "float4 sample2d(Texture2D tex, float2 pos, SamplerState smp){{return tex.Sample(smp,pos);}}")
+            .unwrap();
         self.generate_struct_decls();
         self.generate_uniform_structs();
         self.generate_texture_defs();
@@ -134,6 +126,9 @@ impl<'a> ShaderGenerator<'a> {
                     write!(self.string, "Texture2D ").unwrap();
                     self.backend_writer.write_ident(self.string, decl.ident);
                     writeln!(self.string, ": register(t{});", index).unwrap();
+                    write!(self.string, "SamplerState ").unwrap();
+                    self.backend_writer.write_ident(self.string, decl.ident);
+                    writeln!(self.string, "_sampler: register(s{});", index).unwrap();
                     index += 1;
                 }
                 _ => {}
@@ -548,7 +543,20 @@ impl<'a> FnDeclGenerator<'a> {
 struct HlslBackendWriter();
 
 impl BackendWriter for HlslBackendWriter {
-    fn write_call_expr_hidden_args(&self, string: &mut String, ident_path: IdentPath, shader: &ShaderAst, sep: &str) {
+    fn write_call_expr_hidden_args(
+        &self,
+        string: &mut String,
+        ident_path: IdentPath,
+        arg_exprs: &[Expr],
+        shader: &ShaderAst,
+        sep: &str,
+    ) {
+        if let Some(texture_ident) = sample2d_texture_ident(ident_path, arg_exprs) {
+            // Every texture has its own sampler; see `generate_texture_defs`.
+            write!(string, "{}", sep).unwrap();
+            self.write_ident(string, texture_ident);
+            write!(string, "_sampler").unwrap();
+        }
         if let Some(decl) = shader.find_fn_decl(ident_path) {
             let mut sep = sep;
             if decl.is_used_in_vertex_shader.get().unwrap() {
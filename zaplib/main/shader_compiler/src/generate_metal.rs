@@ -1,7 +1,7 @@
 use {
     crate::{
         env::VarKind,
-        generate::{BackendWriter, BlockGenerator, ExprGenerator},
+        generate::{sample2d_texture_ident, BackendWriter, BlockGenerator, ExprGenerator},
         ident::{Ident, IdentPath},
         shaderast::*,
         span::Span,
@@ -30,12 +30,8 @@ impl<'a> ShaderGenerator<'a> {
     fn generate_shader(&mut self) {
         writeln!(self.string, "#include <metal_stdlib>").unwrap();
         writeln!(self.string, "using namespace metal;").unwrap();
-        writeln!(
-            self.string,
-            "float4 sample2d(texture2d<float> tex, float2 pos){{return \
-             tex.sample(sampler(mag_filter::linear,min_filter::linear),pos);}}"
-        )
-        .unwrap();
+        writeln!(self.string, "float4 sample2d(texture2d<float> tex, float2 pos, sampler smp){{return tex.sample(smp,pos);}}")
+            .unwrap();
         self.generate_struct_decls();
         self.generate_uniform_structs();
         self.generate_texture_struct();
@@ -117,6 +113,9 @@ impl<'a> ShaderGenerator<'a> {
                     write!(self.string, "    texture2d<float> ").unwrap();
                     self.backend_writer.write_ident(self.string, decl.ident);
                     write!(self.string, " [[texture({})]];", index).unwrap();
+                    write!(self.string, " sampler ").unwrap();
+                    self.backend_writer.write_ident(self.string, decl.ident);
+                    writeln!(self.string, "_sampler [[sampler({})]];", index).unwrap();
                     index += 1;
                 }
                 _ => {}
@@ -489,8 +488,21 @@ impl<'a> FnDeclGenerator<'a> {
 struct MetalBackendWriter();
 
 impl BackendWriter for MetalBackendWriter {
-    fn write_call_expr_hidden_args(&self, string: &mut String, ident_path: IdentPath, shader: &ShaderAst, sep: &str) {
+    fn write_call_expr_hidden_args(
+        &self,
+        string: &mut String,
+        ident_path: IdentPath,
+        arg_exprs: &[Expr],
+        shader: &ShaderAst,
+        sep: &str,
+    ) {
         let mut sep = sep;
+        if let Some(texture_ident) = sample2d_texture_ident(ident_path, arg_exprs) {
+            // Every texture has its own sampler; see `generate_texture_struct`.
+            write!(string, "{}mpsc_textures.", sep).unwrap();
+            self.write_ident(string, texture_ident);
+            write!(string, "_sampler").unwrap();
+        }
         if let Some(decl) = shader.find_fn_decl(ident_path) {
             for &ident in decl.uniform_block_deps.borrow().as_ref().unwrap() {
                 write!(string, "{}mpsc_{}_uniforms", sep, ident).unwrap();
@@ -0,0 +1,1227 @@
+use {
+    crate::{
+        generate::sample2d_texture_ident,
+        ident::{Ident, IdentPath},
+        lit::Lit,
+        shaderast::*,
+        swizzle::Swizzle,
+        ty::{Ty, TyLit},
+        util::PrettyPrintedFloat,
+        val::Val,
+    },
+    std::{
+        collections::{BTreeMap, HashSet},
+        fmt::Write,
+    },
+};
+
+/// Generate a WGSL module with a `mpsc_vertex_main` and a `mpsc_fragment_main` entry point.
+///
+/// Bindings are laid out as follows:
+/// * Uniform blocks are in group 0, as arrays of `vec4<f32>` that hold the uniforms tightly packed in the order
+///   in which they are declared, just like the other backends. `in pass` uniforms are at binding 0, `in view`
+///   at 1, `in draw` at 2, uniforms without a block at 3, and any other blocks after that in alphabetical order.
+/// * Textures are in group 1, with texture `i` at binding `2 * i` and its sampler at binding `2 * i + 1`.
+/// * Geometries and instances are packed into `vec4<f32>` vertex attributes, like in the GLSL backend, with the
+///   instance attributes following the geometry ones.
+///
+/// Note that WGSL only allows derivatives (`dFdx`, `dFdy`) in uniform control flow, e.g. not after returning early
+/// in an `if`, or inside an `if` on a geometry or instance value. The other backends don't have this restriction,
+/// and we don't turn the check off using a `diagnostic` directive since not all WGSL implementations support those
+/// yet, so such shaders produce WGSL that fails validation. Take the derivatives at the top of `pixel()` instead.
+pub fn generate_shader(shader: &ShaderAst) -> String {
+    let mut string = String::new();
+    ShaderGenerator { shader, string: &mut string }.generate_shader();
+    string
+}
+
+struct UniformBlock<'a> {
+    ident: Ident,
+    binding: usize,
+    decls: Vec<&'a UniformDecl>,
+}
+
+struct ShaderGenerator<'a> {
+    shader: &'a ShaderAst,
+    string: &'a mut String,
+}
+
+impl<'a> ShaderGenerator<'a> {
+    fn generate_shader(&mut self) {
+        let uniform_blocks = self.get_uniform_blocks();
+        self.generate_struct_decls();
+        self.generate_uniform_bindings(&uniform_blocks);
+        self.generate_texture_bindings();
+        self.generate_geometry_struct();
+        self.generate_instance_struct();
+        self.generate_varying_struct();
+        self.generate_private_vars();
+        self.generate_const_decls();
+        let vertex_decl = self.shader.find_fn_decl(IdentPath::from_str("vertex")).unwrap();
+        let fragment_decl = self.shader.find_fn_decl(IdentPath::from_str("pixel")).unwrap();
+        for &(ty_lit, ref param_tys) in
+            vertex_decl.cons_fn_deps.borrow().as_ref().unwrap().union(fragment_decl.cons_fn_deps.borrow().as_ref().unwrap())
+        {
+            self.generate_cons_fn(ty_lit, param_tys);
+        }
+        let mut visited = HashSet::new();
+        self.generate_fn_decl(vertex_decl, &mut visited);
+        self.generate_fn_decl(fragment_decl, &mut visited);
+        self.generate_vertex_main(&uniform_blocks);
+        self.generate_fragment_main(&uniform_blocks);
+    }
+
+    fn get_uniform_blocks(&self) -> Vec<UniformBlock<'a>> {
+        let mut uniform_blocks = BTreeMap::new();
+        for decl in &self.shader.decls {
+            match decl {
+                Decl::Uniform(decl) => {
+                    let uniform_block =
+                        uniform_blocks.entry(decl.block_ident.unwrap_or(Ident::new("default"))).or_insert(Vec::new());
+                    uniform_block.push(decl);
+                }
+                _ => {}
+            }
+        }
+        let mut next_binding = 4;
+        uniform_blocks
+            .into_iter()
+            .map(|(ident, decls)| {
+                let binding = ident.with(|ident_string| match ident_string {
+                    "pass" => 0,
+                    "view" => 1,
+                    "draw" => 2,
+                    "default" => 3,
+                    _ => {
+                        next_binding += 1;
+                        next_binding - 1
+                    }
+                });
+                UniformBlock { ident, binding, decls }
+            })
+            .collect()
+    }
+
+    fn generate_struct_decls(&mut self) {
+        for decl in &self.shader.decls {
+            match decl {
+                Decl::Struct(decl) => {
+                    write!(self.string, "struct ").unwrap();
+                    write_ident(self.string, decl.ident);
+                    writeln!(self.string, " {{").unwrap();
+                    for field in &decl.fields {
+                        write!(self.string, "    ").unwrap();
+                        write_ident(self.string, field.ident);
+                        write!(self.string, ": ").unwrap();
+                        write_ty(self.string, field.ty_expr.ty.borrow().as_ref().unwrap());
+                        writeln!(self.string, ",").unwrap();
+                    }
+                    writeln!(self.string, "}}").unwrap();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn generate_uniform_bindings(&mut self, uniform_blocks: &[UniformBlock]) {
+        for uniform_block in uniform_blocks {
+            let size: usize = uniform_block.decls.iter().map(|decl| decl.ty_expr.ty.borrow().as_ref().unwrap().size()).sum();
+            writeln!(
+                self.string,
+                "@group(0) @binding({}) var<uniform> mpsc_{}_uniforms: array<vec4<f32>, {}>;",
+                uniform_block.binding,
+                uniform_block.ident,
+                (size + 3) / 4
+            )
+            .unwrap();
+        }
+    }
+
+    fn generate_texture_bindings(&mut self) {
+        let mut index = 0;
+        for decl in &self.shader.decls {
+            match decl {
+                Decl::Texture(decl) => {
+                    assert_eq!(*decl.ty_expr.ty.borrow().as_ref().unwrap(), Ty::Texture2D);
+                    write!(self.string, "@group(1) @binding({}) var ", 2 * index).unwrap();
+                    write_ident(self.string, decl.ident);
+                    writeln!(self.string, ": texture_2d<f32>;").unwrap();
+                    write!(self.string, "@group(1) @binding({}) var ", 2 * index + 1).unwrap();
+                    write_ident(self.string, decl.ident);
+                    writeln!(self.string, "_sampler: sampler;").unwrap();
+                    index += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn generate_geometry_struct(&mut self) {
+        let packed_geometries_size = self.compute_packed_geometries_size();
+        if packed_geometries_size > 0 {
+            writeln!(self.string, "struct mpsc_Geometries {{").unwrap();
+            self.generate_packed_var_fields("mpsc_packed_geometry", packed_geometries_size, 0);
+            writeln!(self.string, "}}").unwrap();
+        }
+    }
+
+    fn generate_instance_struct(&mut self) {
+        let packed_instances_size = self.compute_packed_instances_size();
+        if packed_instances_size > 0 {
+            writeln!(self.string, "struct mpsc_Instances {{").unwrap();
+            let first_location = (self.compute_packed_geometries_size() + 3) / 4;
+            self.generate_packed_var_fields("mpsc_packed_instance", packed_instances_size, first_location);
+            writeln!(self.string, "}}").unwrap();
+        }
+    }
+
+    fn generate_varying_struct(&mut self) {
+        writeln!(self.string, "struct mpsc_Varyings {{").unwrap();
+        writeln!(self.string, "    @builtin(position) mpsc_position: vec4<f32>,").unwrap();
+        self.generate_packed_var_fields("mpsc_packed_varying", self.compute_packed_varyings_size(), 0);
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    /// Geometries, instances, varyings, and uniforms are all module-scope variables, so functions can just refer to
+    /// them. The entry points unpack them from their attributes or buffers.
+    fn generate_private_vars(&mut self) {
+        for decl in &self.shader.decls {
+            let (ident, ty_expr) = match decl {
+                Decl::Geometry(decl) => (decl.ident, &decl.ty_expr),
+                Decl::Instance(decl) => (decl.ident, &decl.ty_expr),
+                Decl::Varying(decl) => (decl.ident, &decl.ty_expr),
+                Decl::Uniform(decl) => (decl.ident, &decl.ty_expr),
+                _ => continue,
+            };
+            write!(self.string, "var<private> ").unwrap();
+            write_ident(self.string, ident);
+            write!(self.string, ": ").unwrap();
+            write_ty(self.string, ty_expr.ty.borrow().as_ref().unwrap());
+            writeln!(self.string, ";").unwrap();
+        }
+    }
+
+    fn generate_const_decls(&mut self) {
+        for decl in &self.shader.decls {
+            match decl {
+                Decl::Const(decl) => {
+                    // Module-scope constants are spelled differently across WGSL versions, but a private variable
+                    // with a constant initializer works everywhere.
+                    write!(self.string, "var<private> ").unwrap();
+                    write_ident(self.string, decl.ident);
+                    write!(self.string, ": ").unwrap();
+                    write_ty(self.string, decl.ty_expr.ty.borrow().as_ref().unwrap());
+                    write!(self.string, " = ").unwrap();
+                    ExprGenerator { shader: self.shader, decl: None, string: self.string }.generate_expr(&decl.expr);
+                    writeln!(self.string, ";").unwrap();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn compute_packed_geometries_size(&self) -> usize {
+        let mut packed_geometries_size = 0;
+        for decl in &self.shader.decls {
+            packed_geometries_size += match decl {
+                Decl::Geometry(decl) => decl.ty_expr.ty.borrow().as_ref().unwrap().size(),
+                _ => 0,
+            }
+        }
+        packed_geometries_size
+    }
+
+    fn compute_packed_instances_size(&self) -> usize {
+        let mut packed_instances_size = 0;
+        for decl in &self.shader.decls {
+            packed_instances_size += match decl {
+                Decl::Instance(decl) => decl.ty_expr.ty.borrow().as_ref().unwrap().size(),
+                _ => 0,
+            }
+        }
+        packed_instances_size
+    }
+
+    fn compute_packed_varyings_size(&self) -> usize {
+        self.get_varyings().iter().map(|(_, ty)| ty.size()).sum()
+    }
+
+    /// Everything that gets passed from the vertex shader to the fragment shader, in the order in which it's packed.
+    fn get_varyings(&self) -> Vec<(Ident, Ty)> {
+        let mut varyings = Vec::new();
+        for decl in &self.shader.decls {
+            match decl {
+                Decl::Geometry(decl) if decl.is_used_in_fragment_shader.get().unwrap() => {
+                    varyings.push((decl.ident, decl.ty_expr.ty.borrow().clone().unwrap()));
+                }
+                Decl::Instance(decl) if decl.is_used_in_fragment_shader.get().unwrap() => {
+                    varyings.push((decl.ident, decl.ty_expr.ty.borrow().clone().unwrap()));
+                }
+                Decl::Varying(decl) => {
+                    varyings.push((decl.ident, decl.ty_expr.ty.borrow().clone().unwrap()));
+                }
+                _ => {}
+            }
+        }
+        varyings
+    }
+
+    fn generate_packed_var_fields(&mut self, packed_var_name: &str, packed_vars_size: usize, first_location: usize) {
+        for packed_var_index in 0..(packed_vars_size + 3) / 4 {
+            writeln!(
+                self.string,
+                "    @location({}) {}_{}: {},",
+                first_location + packed_var_index,
+                packed_var_name,
+                packed_var_index,
+                match (packed_vars_size - 4 * packed_var_index).min(4) {
+                    1 => "f32",
+                    2 => "vec2<f32>",
+                    3 => "vec3<f32>",
+                    _ => "vec4<f32>",
+                }
+            )
+            .unwrap();
+        }
+    }
+
+    fn generate_cons_fn(&mut self, ty_lit: TyLit, param_tys: &[Ty]) {
+        if !use_cons_fn(ty_lit, param_tys) {
+            return;
+        }
+        let mut cons_name = format!("mpsc_{}", ty_lit);
+        for param_ty in param_tys {
+            write!(cons_name, "_{}", param_ty).unwrap();
+        }
+
+        write!(self.string, "fn {}(", cons_name).unwrap();
+        let mut sep = "";
+        for (index, param_ty) in param_tys.iter().enumerate() {
+            write!(self.string, "{}x{}: ", sep, index).unwrap();
+            write_ty(self.string, param_ty);
+            sep = ", ";
+        }
+        write!(self.string, ") -> ").unwrap();
+        write_ty_lit(self.string, ty_lit);
+        writeln!(self.string, " {{").unwrap();
+        write!(self.string, "    return ").unwrap();
+        write_ty_lit(self.string, ty_lit);
+        write!(self.string, "(").unwrap();
+        let ty = ty_lit.to_ty();
+        let mut sep = "";
+        match (param_tys, matrix_dim(&ty)) {
+            // Like in GLSL, a matrix made from a single scalar has that scalar on its diagonal.
+            ([param_ty], Some(dst_dim)) if param_ty.is_scalar() => {
+                let diagonal = float_scalar("x0", param_ty);
+                for col_index in 0..dst_dim {
+                    for row_index in 0..dst_dim {
+                        write!(self.string, "{}{}", sep, if col_index == row_index { &diagonal } else { "0.0" }).unwrap();
+                        sep = ", ";
+                    }
+                }
+            }
+            ([param_ty], Some(dst_dim)) if matrix_dim(param_ty).is_some() => {
+                let src_dim = matrix_dim(param_ty).unwrap();
+                for col_index in 0..dst_dim {
+                    for row_index in 0..dst_dim {
+                        if row_index < src_dim && col_index < src_dim {
+                            write!(self.string, "{}x0[{}][{}]", sep, col_index, row_index).unwrap();
+                        } else {
+                            write!(self.string, "{}{}", sep, if col_index == row_index { "1.0" } else { "0.0" }).unwrap();
+                        }
+                        sep = ", ";
+                    }
+                }
+            }
+            _ => {
+                // Flatten all parameters into scalars, dropping any that don't fit.
+                let mut count = 0;
+                for (index_0, param_ty) in param_tys.iter().enumerate() {
+                    for index_1 in 0..param_ty.size() {
+                        if count == ty.size() {
+                            break;
+                        }
+                        let scalar =
+                            if param_ty.size() == 1 { format!("x{}", index_0) } else { format!("x{}[{}]", index_0, index_1) };
+                        if is_float_ty(&ty) {
+                            write!(self.string, "{}{}", sep, float_scalar(&scalar, param_ty)).unwrap();
+                        } else {
+                            write!(self.string, "{}{}", sep, scalar).unwrap();
+                        }
+                        sep = ", ";
+                        count += 1;
+                    }
+                }
+            }
+        }
+        writeln!(self.string, ");").unwrap();
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    fn generate_fn_decl(&mut self, decl: &FnDecl, visited: &mut HashSet<IdentPath>) {
+        FnDeclGenerator { shader: self.shader, decl, visited, string: self.string }.generate_fn_decl()
+    }
+
+    fn generate_vertex_main(&mut self, uniform_blocks: &[UniformBlock]) {
+        let decl = self.shader.find_fn_decl(IdentPath::from_str("vertex")).unwrap();
+        let packed_geometries_size = self.compute_packed_geometries_size();
+        let packed_instances_size = self.compute_packed_instances_size();
+        writeln!(self.string, "@vertex").unwrap();
+        write!(self.string, "fn mpsc_vertex_main(").unwrap();
+        let mut sep = "";
+        if packed_geometries_size > 0 {
+            write!(self.string, "mpsc_geometries: mpsc_Geometries").unwrap();
+            sep = ", ";
+        }
+        if packed_instances_size > 0 {
+            write!(self.string, "{}mpsc_instances: mpsc_Instances", sep).unwrap();
+        }
+        writeln!(self.string, ") -> mpsc_Varyings {{").unwrap();
+        self.generate_uniform_unpacking(decl, uniform_blocks);
+        let mut offset = 0;
+        for decl in &self.shader.decls {
+            match decl {
+                Decl::Geometry(decl) => {
+                    let ty = decl.ty_expr.ty.borrow();
+                    self.generate_unpack_var(decl.ident, ty.as_ref().unwrap(), |slot| {
+                        packed_scalar("mpsc_geometries.mpsc_packed_geometry", packed_geometries_size, offset + slot)
+                    });
+                    offset += ty.as_ref().unwrap().size();
+                }
+                _ => {}
+            }
+        }
+        let mut offset = 0;
+        for decl in &self.shader.decls {
+            match decl {
+                Decl::Instance(decl) => {
+                    let ty = decl.ty_expr.ty.borrow();
+                    self.generate_unpack_var(decl.ident, ty.as_ref().unwrap(), |slot| {
+                        packed_scalar("mpsc_instances.mpsc_packed_instance", packed_instances_size, offset + slot)
+                    });
+                    offset += ty.as_ref().unwrap().size();
+                }
+                _ => {}
+            }
+        }
+        writeln!(self.string, "    var mpsc_varyings: mpsc_Varyings;").unwrap();
+        write!(self.string, "    mpsc_varyings.mpsc_position = ").unwrap();
+        write_ident(self.string, decl.ident_path.get_single().expect("unexpected"));
+        writeln!(self.string, "();").unwrap();
+
+        let mut scalars = Vec::new();
+        for (ident, ty) in self.get_varyings() {
+            let mut name = String::new();
+            write_ident(&mut name, ident);
+            for slot in 0..ty.size() {
+                let scalar = scalar_of_var(&name, &ty, slot);
+                scalars.push(float_scalar(&scalar, &ty));
+            }
+        }
+        for (packed_var_index, chunk) in scalars.chunks(4).enumerate() {
+            write!(self.string, "    mpsc_varyings.mpsc_packed_varying_{} = ", packed_var_index).unwrap();
+            if chunk.len() == 1 {
+                writeln!(self.string, "{};", chunk[0]).unwrap();
+            } else {
+                writeln!(self.string, "vec{}<f32>({});", chunk.len(), chunk.join(", ")).unwrap();
+            }
+        }
+        writeln!(self.string, "    return mpsc_varyings;").unwrap();
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    fn generate_fragment_main(&mut self, uniform_blocks: &[UniformBlock]) {
+        let decl = self.shader.find_fn_decl(IdentPath::from_str("pixel")).unwrap();
+        writeln!(self.string, "@fragment").unwrap();
+        writeln!(self.string, "fn mpsc_fragment_main(mpsc_varyings: mpsc_Varyings) -> @location(0) vec4<f32> {{").unwrap();
+        self.generate_uniform_unpacking(decl, uniform_blocks);
+        let packed_varyings_size = self.compute_packed_varyings_size();
+        let mut offset = 0;
+        for (ident, ty) in self.get_varyings() {
+            self.generate_unpack_var(ident, &ty, |slot| {
+                packed_scalar("mpsc_varyings.mpsc_packed_varying", packed_varyings_size, offset + slot)
+            });
+            offset += ty.size();
+        }
+        write!(self.string, "    return ").unwrap();
+        write_ident(self.string, decl.ident_path.get_single().expect("unexpected"));
+        writeln!(self.string, "();").unwrap();
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    /// Unpack the uniforms in the blocks that `decl` depends on.
+    fn generate_uniform_unpacking(&mut self, decl: &FnDecl, uniform_blocks: &[UniformBlock]) {
+        let uniform_block_deps = decl.uniform_block_deps.borrow();
+        for uniform_block in uniform_blocks {
+            if !uniform_block_deps.as_ref().unwrap().contains(&uniform_block.ident) {
+                continue;
+            }
+            let mut offset = 0;
+            for decl in &uniform_block.decls {
+                let ty = decl.ty_expr.ty.borrow();
+                self.generate_unpack_var(decl.ident, ty.as_ref().unwrap(), |slot| {
+                    format!(
+                        "mpsc_{}_uniforms[{}].{}",
+                        uniform_block.ident,
+                        (offset + slot) / 4,
+                        Swizzle::from_range((offset + slot) % 4, (offset + slot) % 4 + 1)
+                    )
+                });
+                offset += ty.as_ref().unwrap().size();
+            }
+        }
+    }
+
+    /// Assign the scalars returned by `packed_scalar` for every slot to the private variable `ident`.
+    fn generate_unpack_var(&mut self, ident: Ident, ty: &Ty, packed_scalar: impl Fn(usize) -> String) {
+        write!(self.string, "    ").unwrap();
+        write_ident(self.string, ident);
+        write!(self.string, " = ").unwrap();
+        let scalars: Vec<String> = (0..ty.size()).map(packed_scalar).collect();
+        if *ty == Ty::Float {
+            write!(self.string, "{}", scalars[0]).unwrap();
+        } else {
+            write_ty(self.string, ty);
+            write!(self.string, "({})", scalars.join(", ")).unwrap();
+        }
+        writeln!(self.string, ";").unwrap();
+    }
+}
+
+struct FnDeclGenerator<'a> {
+    shader: &'a ShaderAst,
+    decl: &'a FnDecl,
+    visited: &'a mut HashSet<IdentPath>,
+    string: &'a mut String,
+}
+
+impl<'a> FnDeclGenerator<'a> {
+    fn generate_fn_decl(&mut self) {
+        if self.visited.contains(&self.decl.ident_path) {
+            return;
+        }
+        for &callee in self.decl.callees.borrow().as_ref().unwrap().iter() {
+            FnDeclGenerator {
+                shader: self.shader,
+                decl: self.shader.find_fn_decl(callee).unwrap(),
+                visited: self.visited,
+                string: self.string,
+            }
+            .generate_fn_decl()
+        }
+        write!(self.string, "fn ").unwrap();
+        write_ident(self.string, self.decl.ident_path.to_struct_fn_ident());
+        write!(self.string, "(").unwrap();
+        let mut sep = "";
+        for param in &self.decl.params {
+            write!(self.string, "{}", sep).unwrap();
+            if param.is_inout {
+                write_ident(self.string, param.ident);
+                write!(self.string, ": ptr<function, ").unwrap();
+                write_ty(self.string, param.ty_expr.ty.borrow().as_ref().unwrap());
+                write!(self.string, ">").unwrap();
+            } else {
+                write!(self.string, "mpsc_param_{}: ", param.ident).unwrap();
+                write_ty(self.string, param.ty_expr.ty.borrow().as_ref().unwrap());
+            }
+            sep = ", ";
+        }
+        write!(self.string, ")").unwrap();
+        let return_ty = self.decl.return_ty.borrow();
+        if *return_ty.as_ref().unwrap() != Ty::Void {
+            write!(self.string, " -> ").unwrap();
+            write_ty(self.string, return_ty.as_ref().unwrap());
+        }
+        writeln!(self.string, " {{").unwrap();
+        // Parameters are immutable in WGSL, so copy them into variables.
+        for param in &self.decl.params {
+            if !param.is_inout {
+                write!(self.string, "    var ").unwrap();
+                write_ident(self.string, param.ident);
+                write!(self.string, ": ").unwrap();
+                write_ty(self.string, param.ty_expr.ty.borrow().as_ref().unwrap());
+                writeln!(self.string, " = mpsc_param_{};", param.ident).unwrap();
+            }
+        }
+        BlockGenerator { shader: self.shader, decl: self.decl, indent_level: 1, string: self.string }
+            .generate_stmts(&self.decl.block);
+        writeln!(self.string, "}}").unwrap();
+        self.visited.insert(self.decl.ident_path);
+    }
+}
+
+struct BlockGenerator<'a> {
+    shader: &'a ShaderAst,
+    decl: &'a FnDecl,
+    indent_level: usize,
+    string: &'a mut String,
+}
+
+impl<'a> BlockGenerator<'a> {
+    fn generate_block(&mut self, block: &Block) {
+        writeln!(self.string, "{{").unwrap();
+        self.indent_level += 1;
+        self.generate_stmts(block);
+        self.indent_level -= 1;
+        self.write_indent();
+        write!(self.string, "}}").unwrap()
+    }
+
+    fn generate_stmts(&mut self, block: &Block) {
+        for stmt in &block.stmts {
+            self.generate_stmt(stmt);
+        }
+    }
+
+    fn generate_stmt(&mut self, stmt: &Stmt) {
+        self.write_indent();
+        match *stmt {
+            Stmt::Break { .. } => writeln!(self.string, "break;").unwrap(),
+            Stmt::Continue { .. } => writeln!(self.string, "continue;").unwrap(),
+            Stmt::For { ident, ref from_expr, ref to_expr, ref step_expr, ref block, .. } => {
+                self.generate_for_stmt(ident, from_expr, to_expr, step_expr, block)
+            }
+            Stmt::If { ref expr, ref block_if_true, ref block_if_false, .. } => {
+                self.generate_if_stmt(expr, block_if_true, block_if_false)
+            }
+            Stmt::Let { ref ty, ident, ref expr, .. } => {
+                write!(self.string, "var ").unwrap();
+                write_ident(self.string, ident);
+                write!(self.string, ": ").unwrap();
+                write_ty(self.string, ty.borrow().as_ref().unwrap());
+                if let Some(expr) = expr {
+                    write!(self.string, " = ").unwrap();
+                    self.generate_expr(expr);
+                }
+                writeln!(self.string, ";").unwrap();
+            }
+            Stmt::Return { ref expr, .. } => {
+                write!(self.string, "return").unwrap();
+                if let Some(expr) = expr {
+                    write!(self.string, " ").unwrap();
+                    self.generate_expr(expr);
+                }
+                writeln!(self.string, ";").unwrap();
+            }
+            Stmt::Expr { ref expr, .. } => self.generate_expr_stmt(expr),
+        }
+    }
+
+    fn generate_for_stmt(&mut self, ident: Ident, from_expr: &Expr, to_expr: &Expr, step_expr: &Option<Expr>, block: &Block) {
+        let from = from_expr.const_val.borrow().as_ref().unwrap().as_ref().unwrap().to_int().unwrap();
+        let to = to_expr.const_val.borrow().as_ref().unwrap().as_ref().unwrap().to_int().unwrap();
+        let step = if let Some(step_expr) = step_expr {
+            step_expr.const_val.borrow().as_ref().unwrap().as_ref().unwrap().to_int().unwrap()
+        } else if from < to {
+            1
+        } else {
+            -1
+        };
+        let mut ident_string = String::new();
+        write_ident(&mut ident_string, ident);
+        write!(
+            self.string,
+            "for (var {0}: i32 = {1}; {0} {2} {3}; {0} = {0} {4} {5}) ",
+            ident_string,
+            if from <= to { from } else { from - 1 },
+            if from <= to { "<" } else { ">=" },
+            to,
+            if step > 0 { "+" } else { "-" },
+            step.abs()
+        )
+        .unwrap();
+        self.generate_block(block);
+        writeln!(self.string).unwrap();
+    }
+
+    fn generate_if_stmt(&mut self, expr: &Expr, block_if_true: &Block, block_if_false: &Option<Box<Block>>) {
+        write!(self.string, "if (").unwrap();
+        self.generate_expr(expr);
+        write!(self.string, ") ").unwrap();
+        self.generate_block(block_if_true);
+        if let Some(block_if_false) = block_if_false {
+            write!(self.string, " else ").unwrap();
+            self.generate_block(block_if_false);
+        }
+        writeln!(self.string).unwrap();
+    }
+
+    fn generate_expr_stmt(&mut self, expr: &Expr) {
+        match expr.kind {
+            ExprKind::Bin { op, ref left_expr, ref right_expr, .. } if is_assign_op(op) => {
+                self.generate_assign_stmt(op, left_expr, right_expr);
+            }
+            ExprKind::Call { .. } | ExprKind::MethodCall { .. } => {
+                self.generate_expr(expr);
+                writeln!(self.string, ";").unwrap();
+            }
+            _ => {
+                // WGSL doesn't allow other expressions as statements, so explicitly ignore their value.
+                write!(self.string, "_ = ").unwrap();
+                self.generate_expr(expr);
+                writeln!(self.string, ";").unwrap();
+            }
+        }
+    }
+
+    /// WGSL assignments are statements, so we need to unroll chained assignments (`a = b = c`), and assignments
+    /// to multiple components of a vector (`v.xy = c`).
+    fn generate_assign_stmt(&mut self, op: BinOp, left_expr: &Expr, right_expr: &Expr) {
+        let right_expr = match right_expr.kind {
+            ExprKind::Bin { op: inner_op, left_expr: ref inner_left_expr, right_expr: ref inner_right_expr, .. }
+                if is_assign_op(inner_op) =>
+            {
+                self.generate_assign_stmt(inner_op, inner_left_expr, inner_right_expr);
+                self.write_indent();
+                inner_left_expr
+            }
+            _ => right_expr,
+        };
+        if let ExprKind::Field { expr: ref vector_expr, field_ident, .. } = left_expr.kind {
+            let is_vector = vector_expr.ty.borrow().as_ref().unwrap().is_vector();
+            match Swizzle::parse(field_ident) {
+                Some(swizzle) if is_vector && swizzle.len() > 1 => {
+                    let is_scalar = right_expr.ty.borrow().as_ref().unwrap().is_scalar();
+                    writeln!(self.string, "{{").unwrap();
+                    self.indent_level += 1;
+                    self.write_indent();
+                    write!(self.string, "let mpsc_value = ").unwrap();
+                    self.generate_expr(right_expr);
+                    writeln!(self.string, ";").unwrap();
+                    for (right_index, &left_index) in swizzle.iter().enumerate() {
+                        self.write_indent();
+                        self.generate_expr(vector_expr);
+                        write!(self.string, ".{} {} mpsc_value", Swizzle::from_range(left_index, left_index + 1), op).unwrap();
+                        if !is_scalar {
+                            write!(self.string, ".{}", Swizzle::from_range(right_index, right_index + 1)).unwrap();
+                        }
+                        writeln!(self.string, ";").unwrap();
+                    }
+                    self.indent_level -= 1;
+                    self.write_indent();
+                    writeln!(self.string, "}}").unwrap();
+                    return;
+                }
+                _ => {}
+            }
+        }
+        self.generate_expr(left_expr);
+        write!(self.string, " {} ", op).unwrap();
+        self.generate_expr(right_expr);
+        writeln!(self.string, ";").unwrap();
+    }
+
+    fn generate_expr(&mut self, expr: &Expr) {
+        ExprGenerator { shader: self.shader, decl: Some(self.decl), string: self.string }.generate_expr(expr)
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.indent_level {
+            write!(self.string, "    ").unwrap();
+        }
+    }
+}
+
+struct ExprGenerator<'a> {
+    shader: &'a ShaderAst,
+    decl: Option<&'a FnDecl>,
+    string: &'a mut String,
+}
+
+impl<'a> ExprGenerator<'a> {
+    fn generate_expr(&mut self, expr: &Expr) {
+        match expr.const_val.borrow().as_ref() {
+            Some(Some(Val::Vec4(val))) => {
+                write!(
+                    self.string,
+                    "vec4<f32>({}, {}, {}, {})",
+                    PrettyPrintedFloat(val.x),
+                    PrettyPrintedFloat(val.y),
+                    PrettyPrintedFloat(val.z),
+                    PrettyPrintedFloat(val.w),
+                )
+                .unwrap();
+            }
+            Some(Some(val)) => {
+                write!(self.string, "{}", val).unwrap();
+            }
+            _ => match expr.kind {
+                ExprKind::Cond { ref expr, ref expr_if_true, ref expr_if_false, .. } => {
+                    // `select` evaluates both sides, which is fine since expressions don't have side effects.
+                    write!(self.string, "select(").unwrap();
+                    self.generate_expr(expr_if_false);
+                    write!(self.string, ", ").unwrap();
+                    self.generate_expr(expr_if_true);
+                    write!(self.string, ", ").unwrap();
+                    self.generate_expr(expr);
+                    write!(self.string, ")").unwrap();
+                }
+                ExprKind::Bin { op, ref left_expr, ref right_expr, .. } => self.generate_bin_expr(op, left_expr, right_expr),
+                ExprKind::Un { op, ref expr, .. } => {
+                    write!(self.string, "({}", op).unwrap();
+                    self.generate_expr(expr);
+                    write!(self.string, ")").unwrap();
+                }
+                ExprKind::MethodCall { ident, ref arg_exprs, .. } => match arg_exprs[0].ty.borrow().as_ref().unwrap() {
+                    Ty::Struct { ident: struct_ident } => {
+                        self.generate_call_expr(expr, IdentPath::from_two(*struct_ident, ident), arg_exprs)
+                    }
+                    _ => panic!(),
+                },
+                ExprKind::Field { ref expr, field_ident, .. } => {
+                    self.generate_expr(expr);
+                    write!(self.string, ".").unwrap();
+                    if expr.ty.borrow().as_ref().unwrap().is_vector() {
+                        write!(self.string, "{}", field_ident).unwrap();
+                    } else {
+                        write_ident(self.string, field_ident);
+                    }
+                }
+                ExprKind::Index { ref expr, ref index_expr, .. } => {
+                    self.generate_expr(expr);
+                    write!(self.string, "[").unwrap();
+                    self.generate_expr(index_expr);
+                    write!(self.string, "]").unwrap();
+                }
+                ExprKind::Call { ident_path, ref arg_exprs, .. } => self.generate_call_expr(expr, ident_path, arg_exprs),
+                ExprKind::ConsCall { ty_lit, ref arg_exprs, .. } => self.generate_cons_call_expr(ty_lit, arg_exprs),
+                ExprKind::Var { ident_path, .. } => {
+                    let ident = ident_path.get_single().expect("unexpected");
+                    if self.is_inout_param(ident) {
+                        write!(self.string, "(*").unwrap();
+                        write_ident(self.string, ident);
+                        write!(self.string, ")").unwrap();
+                    } else {
+                        write_ident(self.string, ident);
+                    }
+                }
+                ExprKind::Lit { lit, .. } => match lit {
+                    Lit::Vec4(val) => write!(
+                        self.string,
+                        "vec4<f32>({}, {}, {}, {})",
+                        PrettyPrintedFloat(val.x),
+                        PrettyPrintedFloat(val.y),
+                        PrettyPrintedFloat(val.z),
+                        PrettyPrintedFloat(val.w),
+                    )
+                    .unwrap(),
+                    _ => write!(self.string, "{}", lit).unwrap(),
+                },
+            },
+        }
+    }
+
+    fn generate_bin_expr(&mut self, op: BinOp, left_expr: &Expr, right_expr: &Expr) {
+        // Comparing vectors gives a vector of bools in WGSL, but a single bool in our language.
+        let is_vector = left_expr.ty.borrow().as_ref().unwrap().is_vector();
+        match op {
+            BinOp::Eq if is_vector => write!(self.string, "all").unwrap(),
+            BinOp::Ne if is_vector => write!(self.string, "any").unwrap(),
+            _ => {}
+        }
+        write!(self.string, "(").unwrap();
+        self.generate_expr(left_expr);
+        write!(self.string, " {} ", op).unwrap();
+        self.generate_expr(right_expr);
+        write!(self.string, ")").unwrap();
+    }
+
+    fn generate_call_expr(&mut self, expr: &Expr, ident_path: IdentPath, arg_exprs: &[Expr]) {
+        if let Some(decl) = self.shader.find_fn_decl(ident_path) {
+            write_ident(self.string, ident_path.to_struct_fn_ident());
+            write!(self.string, "(").unwrap();
+            let mut sep = "";
+            for (arg_expr, param) in arg_exprs.iter().zip(&decl.params) {
+                write!(self.string, "{}", sep).unwrap();
+                if param.is_inout {
+                    self.generate_pointer_expr(arg_expr);
+                } else {
+                    self.generate_expr(arg_expr);
+                }
+                sep = ", ";
+            }
+            write!(self.string, ")").unwrap();
+            return;
+        }
+
+        if let Some(texture_ident) = sample2d_texture_ident(ident_path, arg_exprs) {
+            // Use an explicit level of detail, since implicit derivatives only work in uniform control flow in
+            // fragment shaders.
+            write!(self.string, "textureSampleLevel(").unwrap();
+            write_ident(self.string, texture_ident);
+            write!(self.string, ", ").unwrap();
+            write_ident(self.string, texture_ident);
+            write!(self.string, "_sampler, ").unwrap();
+            self.generate_expr(&arg_exprs[1]);
+            write!(self.string, ", 0.0)").unwrap();
+            return;
+        }
+
+        let ident = ident_path.get_single().expect("unexpected");
+        let infix_op = ident.with(|ident_string| match ident_string {
+            "equal" => Some("=="),
+            "notEqual" => Some("!="),
+            "lessThan" => Some("<"),
+            "lessThanEqual" => Some("<="),
+            "greaterThan" => Some(">"),
+            "greaterThanEqual" => Some(">="),
+            _ => None,
+        });
+        if let Some(infix_op) = infix_op {
+            write!(self.string, "(").unwrap();
+            self.generate_expr(&arg_exprs[0]);
+            write!(self.string, " {} ", infix_op).unwrap();
+            self.generate_expr(&arg_exprs[1]);
+            write!(self.string, ")").unwrap();
+            return;
+        }
+        if ident == Ident::new("not") {
+            write!(self.string, "(!").unwrap();
+            self.generate_expr(&arg_exprs[0]);
+            write!(self.string, ")").unwrap();
+            return;
+        }
+        if ident == Ident::new("mod") {
+            // GLSL's `mod`, which is different from `%` for negative numbers.
+            write!(self.string, "(").unwrap();
+            self.generate_expr(&arg_exprs[0]);
+            write!(self.string, " - ").unwrap();
+            self.generate_expr(&arg_exprs[1]);
+            write!(self.string, " * floor(").unwrap();
+            self.generate_expr(&arg_exprs[0]);
+            write!(self.string, " / ").unwrap();
+            self.generate_expr(&arg_exprs[1]);
+            write!(self.string, "))").unwrap();
+            return;
+        }
+
+        if ident == Ident::new("atan") && arg_exprs.len() == 2 {
+            write!(self.string, "atan2").unwrap();
+        } else if ident == Ident::new("dFdx") {
+            write!(self.string, "dpdx").unwrap();
+        } else if ident == Ident::new("dFdy") {
+            write!(self.string, "dpdy").unwrap();
+        } else if ident == Ident::new("inversesqrt") {
+            write!(self.string, "inverseSqrt").unwrap();
+        } else {
+            write!(self.string, "{}", ident).unwrap();
+        }
+        // Builtins like `mix` and `clamp` take scalars for vector arguments in our language, but not in WGSL
+        // (except for the ratio in `refract`).
+        let return_ty = expr.ty.borrow();
+        let splat_ty_lit = match return_ty.as_ref().unwrap() {
+            Ty::Vec2 | Ty::Vec3 | Ty::Vec4 if ident != Ident::new("refract") => return_ty.as_ref().unwrap().maybe_ty_lit(),
+            _ => None,
+        };
+        write!(self.string, "(").unwrap();
+        let mut sep = "";
+        for arg_expr in arg_exprs {
+            write!(self.string, "{}", sep).unwrap();
+            match splat_ty_lit {
+                Some(ty_lit) if *arg_expr.ty.borrow().as_ref().unwrap() == Ty::Float => {
+                    write_ty_lit(self.string, ty_lit);
+                    write!(self.string, "(").unwrap();
+                    self.generate_expr(arg_expr);
+                    write!(self.string, ")").unwrap();
+                }
+                _ => self.generate_expr(arg_expr),
+            }
+            sep = ", ";
+        }
+        write!(self.string, ")").unwrap();
+    }
+
+    fn generate_cons_call_expr(&mut self, ty_lit: TyLit, arg_exprs: &[Expr]) {
+        let param_tys: Vec<Ty> = arg_exprs.iter().map(|arg_expr| arg_expr.ty.borrow().clone().unwrap()).collect();
+        if use_cons_fn(ty_lit, &param_tys) {
+            write!(self.string, "mpsc_{}", ty_lit).unwrap();
+            for param_ty in &param_tys {
+                write!(self.string, "_{}", param_ty).unwrap();
+            }
+        } else {
+            write_ty_lit(self.string, ty_lit);
+        }
+        write!(self.string, "(").unwrap();
+        let mut sep = "";
+        for (arg_expr, param_ty) in arg_exprs.iter().zip(&param_tys) {
+            write!(self.string, "{}", sep).unwrap();
+            // Unlike GLSL, WGSL doesn't implicitly convert e.g. the `0` in `vec4(v, 0, 0)` to a float.
+            if is_float_ty(&ty_lit.to_ty()) && !is_float_ty(param_ty) && !use_cons_fn(ty_lit, &param_tys) {
+                write_ty(self.string, &float_ty_of_size(param_ty.size()));
+                write!(self.string, "(").unwrap();
+                self.generate_expr(arg_expr);
+                write!(self.string, ")").unwrap();
+            } else {
+                self.generate_expr(arg_expr);
+            }
+            sep = ", ";
+        }
+        write!(self.string, ")").unwrap();
+    }
+
+    /// Pass a pointer to an `inout` parameter. If the argument is itself an `inout` parameter of the current
+    /// function, then it is already a pointer.
+    fn generate_pointer_expr(&mut self, expr: &Expr) {
+        match expr.kind {
+            ExprKind::Var { ident_path, .. } if self.is_inout_param(ident_path.get_single().expect("unexpected")) => {
+                write_ident(self.string, ident_path.get_single().unwrap());
+            }
+            _ => {
+                write!(self.string, "&").unwrap();
+                self.generate_expr(expr);
+            }
+        }
+    }
+
+    fn is_inout_param(&self, ident: Ident) -> bool {
+        match self.decl {
+            Some(decl) => decl.params.iter().any(|param| param.is_inout && param.ident == ident),
+            None => false,
+        }
+    }
+}
+
+fn is_assign_op(op: BinOp) -> bool {
+    matches!(op, BinOp::Assign | BinOp::AddAssign | BinOp::SubAssign | BinOp::MulAssign | BinOp::DivAssign)
+}
+
+fn is_float_ty(ty: &Ty) -> bool {
+    matches!(ty, Ty::Float | Ty::Vec2 | Ty::Vec3 | Ty::Vec4 | Ty::Mat2 | Ty::Mat3 | Ty::Mat4)
+}
+
+fn float_ty_of_size(size: usize) -> Ty {
+    match size {
+        1 => Ty::Float,
+        2 => Ty::Vec2,
+        3 => Ty::Vec3,
+        4 => Ty::Vec4,
+        _ => panic!("unexpected"),
+    }
+}
+
+/// Scalar `name` of type `ty`, converted to an `f32` if it isn't one already.
+fn float_scalar(name: &str, ty: &Ty) -> String {
+    if is_float_ty(ty) {
+        name.to_string()
+    } else {
+        format!("f32({})", name)
+    }
+}
+
+fn matrix_dim(ty: &Ty) -> Option<usize> {
+    match ty {
+        Ty::Mat2 => Some(2),
+        Ty::Mat3 => Some(3),
+        Ty::Mat4 => Some(4),
+        _ => None,
+    }
+}
+
+/// If a constructor call needs a generated function; see [`ShaderGenerator::generate_cons_fn`]. WGSL doesn't allow
+/// making matrices out of other matrices or a mix of vectors and scalars, or making a vector out of a bigger one.
+fn use_cons_fn(ty_lit: TyLit, param_tys: &[Ty]) -> bool {
+    let ty = ty_lit.to_ty();
+    if matrix_dim(&ty).is_some() {
+        !(param_tys.len() == ty.size() && param_tys.iter().all(|param_ty| *param_ty == Ty::Float))
+    } else {
+        match param_tys {
+            [param_ty] => param_ty.is_vector() && param_ty.size() > ty.size(),
+            _ => false,
+        }
+    }
+}
+
+/// Scalar `slot` of `name`, where `ty` is laid out as a flat list of scalars, like in our packed buffers.
+fn scalar_of_var(name: &str, ty: &Ty, slot: usize) -> String {
+    match ty {
+        Ty::Bool | Ty::Int | Ty::Float => name.to_string(),
+        Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => {
+            let dim = matrix_dim(ty).unwrap();
+            format!("{}[{}][{}]", name, slot / dim, slot % dim)
+        }
+        _ => format!("{}.{}", name, Swizzle::from_range(slot, slot + 1)),
+    }
+}
+
+/// Scalar `slot` of packed variables `{packed_var_name}_0`, `{packed_var_name}_1`, etc, which are `vec4<f32>`, except
+/// for the last one which holds what remains of `packed_vars_size`; see
+/// [`ShaderGenerator::generate_packed_var_fields`].
+fn packed_scalar(packed_var_name: &str, packed_vars_size: usize, slot: usize) -> String {
+    let packed_var_index = slot / 4;
+    if packed_vars_size - 4 * packed_var_index == 1 {
+        format!("{}_{}", packed_var_name, packed_var_index)
+    } else {
+        format!("{}_{}.{}", packed_var_name, packed_var_index, Swizzle::from_range(slot % 4, slot % 4 + 1))
+    }
+}
+
+fn write_ty(string: &mut String, ty: &Ty) {
+    match ty {
+        Ty::Void => panic!("unexpected"),
+        Ty::Texture2D => write!(string, "texture_2d<f32>").unwrap(),
+        Ty::Array { elem_ty, len } => {
+            write!(string, "array<").unwrap();
+            write_ty(string, elem_ty);
+            write!(string, ", {}>", len).unwrap();
+        }
+        Ty::Struct { ident } => write_ident(string, *ident),
+        _ => write_ty_lit(string, ty.maybe_ty_lit().unwrap()),
+    }
+}
+
+fn write_ty_lit(string: &mut String, ty_lit: TyLit) {
+    write!(
+        string,
+        "{}",
+        match ty_lit {
+            TyLit::Bool => "bool",
+            TyLit::Int => "i32",
+            TyLit::Float => "f32",
+            TyLit::Bvec2 => "vec2<bool>",
+            TyLit::Bvec3 => "vec3<bool>",
+            TyLit::Bvec4 => "vec4<bool>",
+            TyLit::Ivec2 => "vec2<i32>",
+            TyLit::Ivec3 => "vec3<i32>",
+            TyLit::Ivec4 => "vec4<i32>",
+            TyLit::Vec2 => "vec2<f32>",
+            TyLit::Vec3 => "vec3<f32>",
+            TyLit::Vec4 => "vec4<f32>",
+            TyLit::Mat2 => "mat2x2<f32>",
+            TyLit::Mat3 => "mat3x3<f32>",
+            TyLit::Mat4 => "mat4x4<f32>",
+            TyLit::Texture2D => "texture_2d<f32>",
+        }
+    )
+    .unwrap();
+}
+
+/// Keywords and reserved words in WGSL (including ones from earlier drafts of the spec that some implementations still
+/// reject) that are valid identifiers in our language.
+#[rustfmt::skip]
+const RESERVED_IDENTS: &[&str] = &[
+    "NULL", "Self", "abstract", "active", "alias", "alignas", "alignof", "array", "as", "asm", "asm_fragment", "async",
+    "atomic", "atomic_uint", "attribute", "auto", "await", "become", "bf16", "binding_array", "bitcast", "case", "cast",
+    "catch", "cbuffer", "char", "class", "co_await", "co_return", "co_yield", "coherent", "column_major", "common", "compile",
+    "compile_fragment", "concept", "const", "const_cast", "consteval", "constexpr", "constinit", "continuing", "crate",
+    "debugger", "decltype", "default", "delete", "demote", "demote_to_helper", "discard", "do", "dword", "dynamic_cast",
+    "enable", "enum", "explicit", "export", "extends", "extern", "external", "f16", "f32", "f64", "fallthrough", "filter",
+    "final", "finally", "fixed", "friend", "from", "function", "fvec2", "fvec3", "fvec4", "fxgroup", "get", "goto",
+    "groupshared", "handle", "highp", "hvec2", "hvec3", "hvec4", "i16", "i32", "i64", "i8", "impl", "implements", "import",
+    "inline", "inout", "instanceof", "interface", "invariant", "isubpassInput", "layout", "line", "lineadj", "loop", "lowp",
+    "macro", "macro_rules", "mat", "match", "matrix", "mediump", "meta", "mod", "module", "move", "mut", "mutable",
+    "namespace", "new", "nil", "noexcept", "noinline", "nointerpolation", "noperspective", "null", "nullptr", "of",
+    "operator", "override", "package", "packoffset", "partition", "pass", "patch", "pixelfragment", "point", "precise",
+    "precision", "premerge", "priv", "private", "protected", "ptr", "pub", "public", "readonly", "ref", "regardless",
+    "register", "reinterpret_cast", "requires", "resource", "restrict", "row_major", "samper", "sampler", "self", "set",
+    "shared", "signed", "sizeof", "smooth", "snorm", "stateblock", "stateblock_state", "static", "static_assert",
+    "static_cast", "std", "storage", "string", "subpassInput", "subroutine", "super", "superp", "switch", "target", "tbuffer",
+    "technique", "technique10", "technique11", "template", "texture", "this", "thread_local", "throw", "trait", "triangle",
+    "triangleadj", "try", "type", "typedef", "typeid", "typename", "typeof", "u16", "u32", "u64", "u8", "uniform", "union",
+    "unless", "unorm", "unsafe", "unsigned", "unsized", "use", "using", "usubpassInput", "var", "varying", "vec", "vector",
+    "vertexfragment", "virtual", "void", "volatile", "wchar_t", "wgsl", "where", "while", "with", "workgroup", "writeonly",
+    "yield",
+];
+
+fn write_ident(string: &mut String, ident: Ident) {
+    ident.with(|ident_string| {
+        if RESERVED_IDENTS.contains(&ident_string) {
+            write!(string, "mpsc_{}", ident_string).unwrap()
+        } else {
+            write!(string, "{}", ident_string).unwrap()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{code_fragment::CodeFragment, generate_shader_ast::ShaderAstGenerator};
+
+    #[test]
+    fn test_generate_shader_is_valid_wgsl() {
+        let code = r#"
+            uniform camera_projection: mat4 in pass;
+            uniform draw_zbias: float in draw;
+            uniform tint: vec3;
+            uniform transform: mat4;
+            texture texture: texture2D;
+            geometry geom: vec2;
+            instance rect_pos: vec2;
+            instance rect_size: vec2;
+            instance color: vec4;
+            varying pos: vec2;
+            varying corner: vec4;
+            const SCALE: float = 2.;
+
+            struct Shape {
+                pos: vec2,
+                result: vec4,
+                shape: float,
+                old_shape: float
+            }
+
+            impl Shape {
+                fn new(pos: vec2) -> Shape {
+                    let shape: Shape;
+                    shape.pos = pos;
+                    shape.result = vec4(0.);
+                    shape.old_shape = shape.shape = 1e+20;
+                    return shape;
+                }
+
+                fn circle(inout self, radius: float) {
+                    radius = radius * SCALE;
+                    self.shape = min(self.shape, length(self.pos) - radius);
+                }
+
+                fn fill(inout self, color: vec4) -> vec4 {
+                    self.circle(0.5);
+                    self.result.rgb += color.rgb * clamp(-self.shape, 0., 1.);
+                    return self.result;
+                }
+            }
+
+            fn swap(inout a: vec2, inout b: vec2) {
+                let c = a;
+                a = b;
+                b = c;
+            }
+
+            fn vertex() -> vec4 {
+                let a = geom;
+                let b = rect_size;
+                swap(a, b);
+                pos = geom;
+                let m = mat2(transform);
+                corner = vec4(m[0], m[1]);
+                for i from 0 to 4 {
+                    pos.xy += vec2(float(i)) * 0.1;
+                }
+                let clipped = clamp(a * b + rect_pos, vec2(0.), vec2(100.));
+                return camera_projection * transform * vec4(clipped, draw_zbias, 1);
+            }
+
+            fn pixel() -> vec4 {
+                let shape = Shape::new(pos);
+                shape.fill(color);
+                let sampled = sample2d(texture, corner.xy);
+                let ratio = dFdx(pos.x) > 0.5 ? 0.5 : 1.0;
+                if pos == vec2(0.) {
+                    return vec4(mod(pos.x, 1.), atan(pos.y, pos.x), 0., 1.);
+                }
+                return mix(sampled, vec4(tint, 1.) * shape.result, ratio);
+            }
+        "#;
+        let shader_ast = ShaderAstGenerator::new()
+            .generate_shader_ast(&[CodeFragment::Dynamic { name: "test".to_string(), code: code.to_string() }])
+            .unwrap();
+        let wgsl = generate_shader(&shader_ast);
+
+        let module = naga::front::wgsl::parse_str(&wgsl).unwrap_or_else(|err| panic!("{:?}\n{}", err, wgsl));
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::empty())
+            .validate(&module)
+            .unwrap_or_else(|err| panic!("{:?}\n{}", err, wgsl));
+        assert!(module.entry_points.iter().any(|entry_point| entry_point.name == "mpsc_vertex_main"));
+        assert!(module.entry_points.iter().any(|entry_point| entry_point.name == "mpsc_fragment_main"));
+    }
+}
@@ -21,6 +21,7 @@ pub mod generate_glsl;
 pub mod generate_hlsl;
 pub mod generate_metal;
 pub mod generate_shader_ast;
+pub mod generate_wgsl;
 mod ident;
 mod lex;
 mod lhs_check;
@@ -778,6 +778,32 @@ impl Mat4 {
         }
     }
 
+    /// Orthographic projection for 3d scenes, showing `height` units vertically. Maps depth the same way as
+    /// [`Mat4::perspective`], so they can be swapped out for each other.
+    pub fn orthographic(height: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let nf = 1.0 / (near - far);
+        Mat4 {
+            v: [
+                2.0 / (height * aspect),
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                2.0 / height,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                2.0 * nf,
+                0.0,
+                0.0,
+                0.0,
+                (far + near) * nf,
+                1.0,
+            ],
+        }
+    }
+
     pub fn translation(x: f32, y: f32, z: f32) -> Mat4 {
         Mat4 { v: [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, x, y, z, 1.0] }
     }
@@ -384,6 +384,20 @@ impl<'a> TyChecker<'a> {
             .ok_or_else(|| ParseError { span, message: format!("`{}` is not defined", ident_path) })?
         {
             Sym::Builtin => {
+                // Backends with separate sampler objects need to know which texture is being sampled; see
+                // `sample2d_texture_ident`.
+                if ident_path == IdentPath::from_str("sample2d") {
+                    match arg_exprs.first().map(|arg_expr| &arg_expr.kind) {
+                        Some(ExprKind::Var { kind, .. }) if matches!(kind.get(), Some(VarKind::Texture)) => {}
+                        Some(_) => {
+                            return Err(ParseError {
+                                span,
+                                message: "the first argument of `sample2d` must be the name of a texture".into(),
+                            })
+                        }
+                        None => {}
+                    }
+                }
                 let builtin = self.builtins.get(&ident_path.get_single().expect("unexpected")).unwrap();
                 let arg_tys = arg_exprs.iter().map(|arg_expr| arg_expr.ty.borrow().as_ref().unwrap().clone()).collect::<Vec<_>>();
                 Ok(builtin
@@ -513,3 +527,35 @@ impl<'a> TyChecker<'a> {
         Ok(lit.to_ty())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{code_fragment::CodeFragment, generate_shader_ast::ShaderAstGenerator};
+
+    #[test]
+    fn test_sample2d_requires_texture_variable() {
+        let generate = |pixel: &str| {
+            let code = format!(
+                r#"
+                texture texture: texture2D;
+                geometry geom: vec2;
+                fn pick(a: texture2D) -> texture2D {{
+                    return a;
+                }}
+                fn vertex() -> vec4 {{
+                    return vec4(geom, 0., 1.);
+                }}
+                fn pixel() -> vec4 {{
+                    {}
+                }}
+                "#,
+                pixel
+            );
+            ShaderAstGenerator::new().generate_shader_ast(&[CodeFragment::Dynamic { name: "test".to_string(), code }])
+        };
+
+        assert!(generate("return sample2d(texture, vec2(0.));").is_ok());
+        let err = generate("return sample2d(pick(texture), vec2(0.));").err().unwrap();
+        assert_eq!(err.message, "the first argument of `sample2d` must be the name of a texture");
+    }
+}
@@ -0,0 +1,268 @@
+//! Semantic tree of what's on the screen, for screen readers and other assistive technology.
+//!
+//! Since we draw everything on the GPU, the operating system (or browser) has no idea what is
+//! actually on the screen. So while drawing, components describe themselves using
+//! [`Cx::begin_accessibility_node`] and [`Cx::end_accessibility_node`] (or [`Cx::add_accessibility_node`]
+//! for leaf nodes), which builds up a tree alongside the draw tree. After drawing you can get the
+//! resulting [`AccessibilityTree`] using [`Cx::get_accessibility_tree`], and hand it to a platform
+//! bridge (e.g. AT-SPI on Linux, or an ARIA shadow DOM on the web), typically using
+//! [`AccessibilityTree::to_json`].
+
+use crate::*;
+use std::fmt::Write;
+
+/// The kind of thing an [`AccessibilityNode`] represents. Loosely modeled after ARIA roles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AccessibilityRole {
+    /// Generic container for other nodes.
+    Group,
+    Button,
+    Checkbox,
+    TextInput,
+    /// Container for [`AccessibilityRole::Tab`]s.
+    TabList,
+    Tab,
+    /// Container for [`AccessibilityRole::ListItem`]s.
+    List,
+    ListItem,
+}
+
+impl Default for AccessibilityRole {
+    fn default() -> Self {
+        Self::Group
+    }
+}
+
+impl AccessibilityRole {
+    /// The corresponding ARIA role name.
+    pub fn aria_role(&self) -> &'static str {
+        match self {
+            AccessibilityRole::Group => "group",
+            AccessibilityRole::Button => "button",
+            AccessibilityRole::Checkbox => "checkbox",
+            AccessibilityRole::TextInput => "textbox",
+            AccessibilityRole::TabList => "tablist",
+            AccessibilityRole::Tab => "tab",
+            AccessibilityRole::List => "list",
+            AccessibilityRole::ListItem => "listitem",
+        }
+    }
+}
+
+/// What a component tells us about itself while drawing; see [`Cx::begin_accessibility_node`].
+#[derive(Clone, Debug, Default)]
+pub struct AccessibilityNodeProps {
+    pub role: AccessibilityRole,
+    /// Human-readable name of the node, e.g. the text on a button.
+    pub label: String,
+    /// Current value, e.g. the text in a text input.
+    pub value: Option<String>,
+    /// For nodes that can be checked, like [`AccessibilityRole::Checkbox`].
+    pub checked: Option<bool>,
+    /// For nodes that can be selected, like [`AccessibilityRole::Tab`] and [`AccessibilityRole::ListItem`].
+    pub selected: Option<bool>,
+    /// The [`Area`] that covers the node on the screen. Gets resolved to a [`Rect`] in [`Cx::get_accessibility_tree`],
+    /// so it's fine to pass in an [`Area`] of which the layout isn't final yet.
+    pub area: Area,
+}
+
+/// Node in the tree as we collect it during drawing.
+#[derive(Clone, Debug)]
+pub(crate) struct CxAccessibilityNode {
+    component_id: ComponentId,
+    props: AccessibilityNodeProps,
+    children: Vec<usize>,
+}
+
+/// A single node in an [`AccessibilityTree`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccessibilityNode {
+    pub component_id: ComponentId,
+    pub role: AccessibilityRole,
+    pub label: String,
+    pub value: Option<String>,
+    pub checked: Option<bool>,
+    pub selected: Option<bool>,
+    /// Whether this node currently has keyboard focus; see [`Cx::set_key_focus`].
+    pub focused: bool,
+    /// Position on the screen, if the node is visible.
+    pub rect: Option<Rect>,
+    /// Indices into [`AccessibilityTree::nodes`].
+    pub children: Vec<usize>,
+}
+
+/// Platform-neutral semantic tree of the components on the screen, as of the last draw.
+///
+/// Components add nodes to it while drawing, using [`Cx::begin_accessibility_node`] and friends.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccessibilityTree {
+    /// All nodes, in the order in which they were drawn.
+    pub nodes: Vec<AccessibilityNode>,
+    /// Indices into [`AccessibilityTree::nodes`] for the nodes that have no parent.
+    pub roots: Vec<usize>,
+}
+
+impl AccessibilityTree {
+    /// Serialize the tree to JSON, e.g. for passing it to JavaScript.
+    ///
+    /// Has the same structure as [`AccessibilityTree`], with `role` as [`AccessibilityRole::aria_role`],
+    /// `component_id` as a number, `rect` as `{ x, y, width, height }`, and [`None`] values omitted.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        json.push_str("{\"roots\":");
+        write_json_indices(&mut json, &self.roots);
+        json.push_str(",\"nodes\":[");
+        for (index, node) in self.nodes.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            write!(json, "{{\"component_id\":{},\"role\":\"{}\",\"label\":", node.component_id.0, node.role.aria_role()).unwrap();
+            write_json_string(&mut json, &node.label);
+            if let Some(value) = &node.value {
+                json.push_str(",\"value\":");
+                write_json_string(&mut json, value);
+            }
+            if let Some(checked) = node.checked {
+                write!(json, ",\"checked\":{}", checked).unwrap();
+            }
+            if let Some(selected) = node.selected {
+                write!(json, ",\"selected\":{}", selected).unwrap();
+            }
+            write!(json, ",\"focused\":{}", node.focused).unwrap();
+            if let Some(rect) = node.rect {
+                write!(
+                    json,
+                    ",\"rect\":{{\"x\":{},\"y\":{},\"width\":{},\"height\":{}}}",
+                    rect.pos.x, rect.pos.y, rect.size.x, rect.size.y
+                )
+                .unwrap();
+            }
+            json.push_str(",\"children\":");
+            write_json_indices(&mut json, &node.children);
+            json.push('}');
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+fn write_json_indices(json: &mut String, indices: &[usize]) {
+    json.push('[');
+    for (i, index) in indices.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        write!(json, "{}", index).unwrap();
+    }
+    json.push(']');
+}
+
+pub(crate) fn write_json_string(json: &mut String, string: &str) {
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+impl Cx {
+    /// Add a node to the accessibility tree, and make it the parent of subsequent nodes until
+    /// [`Cx::end_accessibility_node`] is called. Should be called while drawing.
+    pub fn begin_accessibility_node(&mut self, component_id: ComponentId, props: AccessibilityNodeProps) {
+        let index = self.accessibility_nodes.len();
+        self.accessibility_nodes.push(CxAccessibilityNode { component_id, props, children: vec![] });
+        if let Some(&parent) = self.accessibility_node_stack.last() {
+            self.accessibility_nodes[parent].children.push(index);
+        }
+        self.accessibility_node_stack.push(index);
+    }
+
+    /// Ends a node started with [`Cx::begin_accessibility_node`].
+    pub fn end_accessibility_node(&mut self) {
+        self.accessibility_node_stack.pop().expect("end_accessibility_node called without begin_accessibility_node");
+    }
+
+    /// Add a node without children to the accessibility tree. Should be called while drawing.
+    pub fn add_accessibility_node(&mut self, component_id: ComponentId, props: AccessibilityNodeProps) {
+        self.begin_accessibility_node(component_id, props);
+        self.end_accessibility_node();
+    }
+
+    /// Get the [`AccessibilityTree`] as of the last draw, with the current keyboard focus.
+    pub fn get_accessibility_tree(&self) -> AccessibilityTree {
+        let mut is_child = vec![false; self.accessibility_nodes.len()];
+        let nodes = self
+            .accessibility_nodes
+            .iter()
+            .map(|node| {
+                for &child in &node.children {
+                    is_child[child] = true;
+                }
+                AccessibilityNode {
+                    component_id: node.component_id,
+                    role: node.props.role,
+                    label: node.props.label.clone(),
+                    value: node.props.value.clone(),
+                    checked: node.props.checked,
+                    selected: node.props.selected,
+                    focused: self.has_key_focus(Some(node.component_id)),
+                    rect: node.props.area.get_rect_for_first_instance(self),
+                    children: node.children.clone(),
+                }
+            })
+            .collect::<Vec<_>>();
+        let roots = (0..nodes.len()).filter(|&index| !is_child[index]).collect();
+        AccessibilityTree { nodes, roots }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_and_serializes_tree() {
+        let mut cx = Cx::new_test();
+        let list_id = ComponentId::default();
+        let item_id = ComponentId::default();
+        let button_id = ComponentId::default();
+
+        cx.begin_accessibility_node(list_id, AccessibilityNodeProps { role: AccessibilityRole::List, ..Default::default() });
+        cx.add_accessibility_node(
+            item_id,
+            AccessibilityNodeProps { role: AccessibilityRole::ListItem, selected: Some(true), ..Default::default() },
+        );
+        cx.end_accessibility_node();
+        cx.add_accessibility_node(
+            button_id,
+            AccessibilityNodeProps { role: AccessibilityRole::Button, label: "Say \"hi\"".to_string(), ..Default::default() },
+        );
+        cx.key_focus = Some(button_id);
+
+        let tree = cx.get_accessibility_tree();
+        assert_eq!(tree.roots, vec![0, 2]);
+        assert_eq!(tree.nodes[0].children, vec![1]);
+        assert_eq!(tree.nodes[1].selected, Some(true));
+        assert!(tree.nodes[2].focused);
+        assert!(!tree.nodes[0].focused);
+
+        assert_eq!(
+            tree.to_json(),
+            format!(
+                "{{\"roots\":[0,2],\"nodes\":[{{\"component_id\":{},\"role\":\"list\",\"label\":\"\",\"focused\":false,\"\
+                 children\":[1]}},{{\"component_id\":{},\"role\":\"listitem\",\"label\":\"\",\"selected\":true,\"focused\":\
+                 false,\"children\":[]}},{{\"component_id\":{},\"role\":\"button\",\"label\":\"Say \
+                 \\\"hi\\\"\",\"focused\":true,\"children\":[]}}]}}",
+                list_id.0, item_id.0, button_id.0
+            )
+        );
+    }
+}
@@ -360,6 +360,7 @@ impl Area {
     ///
     /// TODO(JP): Fix bug: <https://github.com/Zaplib/zaplib/issues/156>
     pub fn write_texture_2d(&self, cx: &mut Cx, name: &str, texture_handle: TextureHandle) {
+        texture_handle.debug_assert_not_freed(cx);
         if self.is_valid(cx) {
             if let Area::InstanceRange(inst) = self {
                 let cxview = &mut cx.views[inst.view_id];
@@ -13,7 +13,7 @@ use crate::*;
 /// either directly or when by using [`Area::get_slice`] or [`Area::get_first`].
 #[repr(C)]
 #[derive(Copy, Clone, Debug, PartialEq)]
-pub struct ComponentId(u32);
+pub struct ComponentId(pub(crate) u32);
 
 /// The next number to use for [`ComponentId`].
 ///
@@ -51,18 +51,18 @@ pub struct Cx {
     pub(crate) windows_free: Vec<usize>,
 
     /// List of actual [`CxPass`] objects. [`Pass::pass_id`] represents an index in this list.
-    ///
-    /// TODO(JP): We currently never remove old [`CxPass`]es.
     pub(crate) passes: Vec<CxPass>,
+    /// Indices in [`Cx::passes`] that have been freed using [`Pass::free`] and can be reused.
+    pub(crate) passes_free: Vec<usize>,
 
     /// The [`CxView`] objects that make up the draw tree. [`View::view_id`] represents an index in this list.
     ///
     /// TODO(JP): The first element is a dummy element, since we use `view_id == 0`
     /// as a sort of null pointer, which is pretty gross and can be confusing. Might
     /// be better to use `Option` wherever we need that instead..
-    ///
-    /// TODO(JP): We currently never remove old [`CxView`]s.
     pub(crate) views: Vec<CxView>,
+    /// Indices in [`Cx::views`] that have been freed using [`View::free`] and can be reused.
+    pub(crate) views_free: Vec<usize>,
 
     /// The compiled [`CxShader`]s. [`Shader::shader_id`] and [`Shader::shader_id`] represent an index in this list.
     pub(crate) shaders: Vec<CxShader>,
@@ -70,8 +70,12 @@ pub struct Cx {
     pub(crate) shader_recompile_ids: Vec<usize>,
     /// List of actual [`CxTexture`] objects. [`TextureHandle::texture_id`] represents an index in this list.
     pub(crate) textures: Vec<CxTexture>,
+    /// Indices in [`Cx::textures`] that have been freed using [`TextureHandle::free`] and can be reused.
+    pub(crate) textures_free: Vec<u32>,
     /// List of actual [`CxGpuGeometry`] objects. [`GpuGeometry::gpu_geometry_id`] represents an index in this list.
     pub(crate) gpu_geometries: Vec<CxGpuGeometry>,
+    /// Platform-specific resources that have been freed, but that still need to be released by the platform.
+    pub(crate) released_resources: CxReleasedResources,
 
     /// Whether we are currently (re)drawing, ie. we called the app's `draw` function.
     pub(crate) in_redraw_cycle: bool,
@@ -146,6 +150,9 @@ pub struct Cx {
     /// event handler, and then it won't update [`Cx::key_focus`] until the next event
     /// is handled. We should probably guard against that.
     next_key_focus: Option<Option<ComponentId>>,
+    /// The components that can get keyboard focus using Tab and Shift+Tab, as registered during
+    /// the last draw using [`Cx::add_key_focusable`].
+    key_focusables: Vec<CxKeyFocusable>,
     pub(crate) keys_down: Vec<KeyEvent>,
 
     /// The cursor type that the user sees while holding the mouse down. Gets reset to [`None`] when
@@ -173,9 +180,26 @@ pub struct Cx {
     /// See also [`Signal`] and [`SignalEvent`].
     pub(crate) signals: HashMap<Signal, BTreeSet<StatusId>>,
 
+    /// See [`Cx::http_request`].
+    pub(crate) http_requests: universal_http_stream::CxHttpRequests,
+
+    /// See [`Texture::load`].
+    pub(crate) texture_loads: image_decoding::CxTextureLoads,
+
+    /// See [`Pass::request_capture`].
+    pub(crate) pass_captures: pass::CxPassCaptures,
+
     /// A map from profile IDs to [`UniversalInstant`], for keeping track of how long things
     /// take.
     pub(crate) profiles: HashMap<u64, UniversalInstant>,
+    /// See [`Cx::set_profiler_enabled`].
+    pub(crate) profiler: CxProfiler,
+    /// See [`Cx::get_render_stats`].
+    pub(crate) render_stats: RenderStats,
+    /// See [`RenderStats::batched_draw_calls`].
+    pub(crate) batched_draw_calls_since_paint: usize,
+    /// See [`RenderStats::culled_instances`].
+    pub(crate) culled_instances_since_paint: usize,
 
     /// For compiling [`Shader`]s.
     pub(crate) shader_ast_generator: ShaderAstGenerator,
@@ -194,6 +218,12 @@ pub struct Cx {
     /// The user's event handler. Storing it like this cuts the compile time of an end-user application in half.
     pub(crate) event_handler: Option<*mut dyn FnMut(&mut Cx, &mut Event)>,
 
+    /// Set when recording events; see [`Cx::start_event_recording`].
+    pub(crate) event_recorder: Option<EventRecorder>,
+
+    /// Whether we're currently replaying events; see [`Cx::replay_event_recording`].
+    pub(crate) is_replaying_events: bool,
+
     /// Fonts specific data
     /// It might be possible for fonts data to be shared between different threads, so
     /// we need to make use of locks.
@@ -218,6 +248,11 @@ pub struct Cx {
     /// See [`DebugLog`] for more information on supported types
     pub(crate) debug_logs: Vec<DebugLog>,
 
+    /// Nodes of the accessibility tree, added while drawing; see [`Cx::get_accessibility_tree`].
+    pub(crate) accessibility_nodes: Vec<CxAccessibilityNode>,
+    /// The nodes that we're currently drawing into; see [`Cx::begin_accessibility_node`].
+    pub(crate) accessibility_node_stack: Vec<usize>,
+
     /// Function registered through [`Cx::on_call_rust_async`]
     pub call_rust_async_fn: Option<usize>,
 
@@ -229,6 +264,30 @@ pub struct Cx {
     pub(crate) finished_app_new: bool,
 }
 
+/// Platform-specific resources of freed [`Texture`]s, [`Pass`]es, [`View`]s (and their [`DrawCall`]s), and
+/// unused [`GpuGeometry`]s. Platforms release these before painting, using [`Cx::take_released_resources`].
+///
+/// Some platforms release their resources when these simply get dropped, but others (like OpenGL) need
+/// to do so explicitly.
+#[derive(Default)]
+pub(crate) struct CxReleasedResources {
+    /// Pairs of [`TextureHandle::texture_id`] and the corresponding platform resources.
+    pub(crate) textures: Vec<(u32, CxPlatformTexture)>,
+    /// Pairs of [`Pass::pass_id`] and the corresponding platform resources.
+    pub(crate) passes: Vec<(usize, CxPlatformPass)>,
+    pub(crate) views: Vec<CxPlatformView>,
+    pub(crate) draw_calls: Vec<CxPlatformDrawCall>,
+    pub(crate) gpu_geometries: Vec<CxPlatformGpuGeometry>,
+}
+
+impl Cx {
+    /// Take all the resources that need to be released by the platform. Call this before painting.
+    pub(crate) fn take_released_resources(&mut self) -> CxReleasedResources {
+        self.release_unused_gpu_geometries();
+        std::mem::take(&mut self.released_resources)
+    }
+}
+
 /// Flags that can be set that enable debug functionality. See [`Cx::debug_flags_mut`] for an example.
 #[derive(Copy, Clone, Default)]
 pub struct CxDebugFlags {
@@ -272,6 +331,13 @@ pub(crate) struct CxCommandSetting {
     pub(crate) enabled: bool,
 }
 
+/// See [`Cx::add_key_focusable`].
+#[derive(Clone, Copy)]
+struct CxKeyFocusable {
+    component_id: ComponentId,
+    tab_index: Option<u32>,
+}
+
 #[derive(Default, Clone)]
 pub(crate) struct CxPerPointer {
     pub(crate) captured: Option<ComponentId>,
@@ -293,7 +359,7 @@ impl Cx {
             desc: TextureDesc { format: TextureFormat::ImageRGBA, width: Some(4), height: Some(4), multisample: None },
             image_u32: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
             update_image: true,
-            platform: CxPlatformTexture::default(),
+            ..CxTexture::default()
         }];
 
         // We try to initialize Vecs with some reasonable capacity, to prevent reallocations.
@@ -303,13 +369,17 @@ impl Cx {
             windows: Vec::new(),
             windows_free: Vec::new(),
             passes: Vec::with_capacity(10),
+            passes_free: Vec::new(),
             // TODO(JP): See my note up at [`Cx::views`].
             views: vec![CxView::default()],
+            views_free: Vec::new(),
             fonts_data: Arc::new(RwLock::new(CxFontsData::default())),
             textures,
+            textures_free: Vec::new(),
             shaders: Vec::with_capacity(50),
             shader_recompile_ids: Vec::with_capacity(50),
             gpu_geometries: Vec::new(),
+            released_resources: CxReleasedResources::default(),
 
             default_dpi_factor: 1.0,
             current_dpi_factor: 1.0,
@@ -328,6 +398,7 @@ impl Cx {
             last_signal_id: 1,
 
             next_key_focus: None,
+            key_focusables: Vec::new(),
             prev_key_focus: None,
             key_focus: None,
             keys_down: Vec::new(),
@@ -344,15 +415,27 @@ impl Cx {
             requested_draw: false,
 
             profiles: HashMap::new(),
+            profiler: CxProfiler::default(),
+            render_stats: RenderStats::default(),
+            batched_draw_calls_since_paint: 0,
+            culled_instances_since_paint: 0,
 
             signals: HashMap::new(),
 
+            http_requests: Default::default(),
+            texture_loads: Default::default(),
+            pass_captures: Default::default(),
+
             panic_redraw: false,
 
             platform: CxPlatform::default(),
 
             event_handler: None,
 
+            event_recorder: None,
+
+            is_replaying_events: false,
+
             temp_default_data: Vec::with_capacity(1000),
 
             debug_flags: Default::default(),
@@ -362,6 +445,9 @@ impl Cx {
 
             debug_logs: Vec::new(),
 
+            accessibility_nodes: Vec::new(),
+            accessibility_node_stack: Vec::new(),
+
             call_rust_async_fn: None,
             app_type_id,
             finished_app_new: false,
@@ -376,6 +462,8 @@ impl Cx {
     }
 
     pub(crate) fn process_pre_event(&mut self, event: &mut Event) {
+        self.record_event(event);
+
         match event {
             Event::PointerHover(pe) => {
                 self.pointers[pe.digit].over_last = None;
@@ -522,7 +610,8 @@ impl Cx {
                         _ => None,
                     };
                     if let Some(other) = other {
-                        if !self.passes[other].paint_dirty {
+                        // Don't paint parents that have been freed (or that have nothing to paint anyway).
+                        if !self.passes[other].paint_dirty && self.passes[other].main_view_id.is_some() {
                             self.passes[other].paint_dirty = true;
                             altered = true;
                         }
@@ -536,32 +625,32 @@ impl Cx {
 
         for (pass_id, cxpass) in self.passes.iter().enumerate() {
             if cxpass.paint_dirty {
-                let mut inserted = false;
-                match cxpass.dep_of {
-                    CxPassDepOf::Window(_) => *windows_need_repaint += 1,
-                    CxPassDepOf::Pass(dep_of_pass_id) => {
-                        if pass_id == dep_of_pass_id {
-                            eprintln!("WHAAAT");
-                        }
-                        for insert_before in 0..passes_todo.len() {
-                            if passes_todo[insert_before] == dep_of_pass_id {
-                                passes_todo.insert(insert_before, pass_id);
-                                inserted = true;
-                                break;
-                            }
-                        }
-                    }
-                    CxPassDepOf::None => {
-                        // we need to be first
-                        passes_todo.insert(0, pass_id);
-                        inserted = true;
-                    }
-                }
-                if !inserted {
-                    passes_todo.push(pass_id);
+                if let CxPassDepOf::Window(_) = cxpass.dep_of {
+                    *windows_need_repaint += 1;
                 }
+                passes_todo.push(pass_id);
             }
         }
+        // Passes that are drawn as part of another pass need to be painted before that pass, so paint
+        // the most deeply nested passes first. We can't rely on the order of [`Cx::passes`], since freed
+        // passes get reused. Passes that are not part of a window (like the fonts atlas) go first, since
+        // they might be used anywhere.
+        passes_todo.sort_by_key(|&pass_id| {
+            let (root_pass_id, depth) = self.get_pass_root_and_depth(pass_id);
+            (matches!(self.passes[root_pass_id].dep_of, CxPassDepOf::Window(_)), std::cmp::Reverse(depth))
+        });
+    }
+
+    /// The outermost [`Pass`] that `pass_id` is (indirectly) drawn in, and the number of [`Pass`]es in between.
+    fn get_pass_root_and_depth(&self, pass_id: usize) -> (usize, usize) {
+        let mut depth = 0;
+        let mut pass_id = pass_id;
+        while let CxPassDepOf::Pass(dep_of_pass_id) = self.passes[pass_id].dep_of {
+            depth += 1;
+            pass_id = dep_of_pass_id;
+            assert!(depth <= self.passes.len(), "Pass dependencies contain a cycle");
+        }
+        (pass_id, depth)
     }
 
     /// Request a new redraw of the application.
@@ -602,6 +691,41 @@ impl Cx {
         self.key_focus == component_id
     }
 
+    /// Register a [`ComponentId`] that can get keyboard focus by pressing Tab or Shift+Tab. Should be
+    /// called while drawing.
+    ///
+    /// Like `tabindex` in HTML, components with a `tab_index` come first (in ascending order), followed by
+    /// components without one, in the order in which they were drawn.
+    ///
+    /// Focus only moves when the component that currently has focus is registered here (or when nothing has
+    /// focus), so components that want to handle Tab themselves (like a code editor) can just not register.
+    pub fn add_key_focusable(&mut self, component_id: ComponentId, tab_index: Option<u32>) {
+        self.key_focusables.push(CxKeyFocusable { component_id, tab_index });
+    }
+
+    /// Find the [`ComponentId`] that should get focus when pressing Tab (or Shift+Tab when `backwards` is set).
+    fn get_next_key_focusable(&self, backwards: bool) -> Option<ComponentId> {
+        let mut focusables = self.key_focusables.clone();
+        // Stable sort, so components with the same `tab_index` stay in draw order.
+        focusables.sort_by_key(|focusable| (focusable.tab_index.is_none(), focusable.tab_index));
+
+        let len = focusables.len();
+        let index = match self.key_focus {
+            Some(key_focus) => {
+                let current = focusables.iter().position(|focusable| focusable.component_id == key_focus)?;
+                if backwards {
+                    (current + len - 1) % len
+                } else {
+                    (current + 1) % len
+                }
+            }
+            None if len == 0 => return None,
+            None if backwards => len - 1,
+            None => 0,
+        };
+        Some(focusables[index].component_id)
+    }
+
     pub(crate) fn process_key_down(&mut self, key_event: KeyEvent) {
         if self.keys_down.iter().any(|k| k.key_code == key_event.key_code) {
             return;
@@ -629,9 +753,25 @@ impl Cx {
     pub(crate) fn call_event_handler(&mut self, event: &mut Event) {
         let event_handler = self.event_handler.unwrap();
 
+        self.profiler.begin_zone("handle_event");
         unsafe {
             (*event_handler)(self, event);
         }
+        self.profiler.end_zone();
+
+        // Move key focus with Tab and Shift+Tab, unless the event handler already changed it.
+        if let Event::KeyDown(ke) = event {
+            if ke.key_code == KeyCode::Tab
+                && !ke.modifiers.control
+                && !ke.modifiers.alt
+                && !ke.modifiers.logo
+                && self.next_key_focus.is_none()
+            {
+                if let Some(next_key_focus) = self.get_next_key_focusable(ke.modifiers.shift) {
+                    self.next_key_focus = Some(Some(next_key_focus));
+                }
+            }
+        }
 
         // Someone has to call `set_key_focus` or `keep_key_focus` when handling `PointerDown`, otherwise
         // the key focus will be reset.
@@ -664,6 +804,8 @@ impl Cx {
         self.redraw_id += 1;
         self.layout_box_align_list.clear();
         self.debug_logs.clear();
+        self.accessibility_nodes.clear();
+        self.key_focusables.clear();
 
         // TODO(Paras): Terrible hack.
         //
@@ -676,7 +818,10 @@ impl Cx {
             self.requested_draw = false;
         }
 
+        self.record_event(&Event::System(SystemEvent::Draw));
+        self.profiler.begin_zone("draw");
         self.call_event_handler(&mut Event::System(SystemEvent::Draw));
+        self.profiler.end_zone();
         self.in_redraw_cycle = false;
         if !self.view_stack.is_empty() {
             panic!("View stack disaligned, forgot an end_view(cx)");
@@ -693,11 +838,15 @@ impl Cx {
         if !self.shader_group_instance_offsets.is_empty() {
             panic!("Shader group stack disaligned, forgot an end_shader_group()");
         }
+        if !self.accessibility_node_stack.is_empty() {
+            panic!("Accessibility node stack disaligned, forgot an end_accessibility_node()");
+        }
         //self.profile();
     }
 
     pub(crate) fn call_next_frame_event(&mut self) {
         self.requested_next_frame = false;
+        self.record_event(&Event::NextFrame);
         self.call_event_handler(&mut Event::NextFrame);
     }
 
@@ -744,7 +893,14 @@ impl Cx {
             let mut signals = HashMap::new();
             std::mem::swap(&mut self.signals, &mut signals);
 
-            self.call_event_handler(&mut Event::Signal(SignalEvent { signals }));
+            signals.retain(|signal, _| {
+                !self.call_http_response_events(signal)
+                    && !self.call_texture_loaded_events(signal)
+                    && !self.call_pass_captured_events(signal)
+            });
+            if !signals.is_empty() {
+                self.call_event_handler(&mut Event::Signal(SignalEvent { signals }));
+            }
 
             if counter > 100 {
                 println!("Signal feedback loop detected");
@@ -753,8 +909,29 @@ impl Cx {
         }
     }
 
-    pub const STATUS_HTTP_SEND_OK: StatusId = location_hash!();
-    pub const STATUS_HTTP_SEND_FAIL: StatusId = location_hash!();
+    /// Handle an [`Event::Signal`] that was posted using [`Cx::post_signal`], by queueing up its signals and
+    /// calling [`Cx::call_signals`]. That way internal signals (e.g. for [`Cx::http_request`]) get turned into
+    /// their own events, instead of being passed on to the app as a raw [`SignalEvent`].
+    pub(crate) fn call_posted_signals(&mut self, signal_event: &SignalEvent) {
+        for (signal, statuses) in &signal_event.signals {
+            for status in statuses {
+                self.send_signal(*signal, *status);
+            }
+        }
+        self.call_signals();
+    }
+
+    /// Run `f` with an event handler that collects all the events that get fired, and return those.
+    #[cfg(test)]
+    pub(crate) fn collect_events(&mut self, f: impl FnOnce(&mut Cx)) -> Vec<Event> {
+        let mut events = vec![];
+        let mut event_handler = |_: &mut Cx, event: &mut Event| events.push(event.clone());
+        self.event_handler =
+            Some(&mut event_handler as *const dyn FnMut(&mut Cx, &mut Event) as *mut dyn FnMut(&mut Cx, &mut Event));
+        f(self);
+        self.event_handler = None;
+        events
+    }
 
     /// Change the debug flags, which control various debug functionality.
     /// See [`CxDebugFlags`] for more information on the individual flags.
@@ -942,26 +1119,13 @@ pub trait CxDesktopVsWasmCommon {
     /// TODO(JP): This doesn't make too much sense for Wasm; maybe just omit this method there?
     fn get_default_window_size(&self) -> Vec2;
 
-    /// Write `data` to `path`.
-    fn file_write(&mut self, path: &str, data: &[u8]);
+    /// Write `data` to `path`, creating or truncating the file. In WebAssembly this lets the user save
+    /// the file instead; see [`UniversalOpenOptions`].
+    fn file_write(&mut self, path: &str, data: &[u8]) -> std::io::Result<()>;
 
     /// Send data over a Websocket.
     fn websocket_send(&mut self, url: &str, data: &[u8]);
 
-    /// Make an HTTP request. When done, you get a [`SignalEvent`] corresponding to the provided
-    /// [`Signal`] with [`Cx::STATUS_HTTP_SEND_OK`] or [`Cx::STATUS_HTTP_SEND_FAIL`] as the status.
-    fn http_send(
-        &mut self,
-        verb: &str,
-        path: &str,
-        _proto: &str,
-        domain: &str,
-        port: u16,
-        content_type: &str,
-        body: &[u8],
-        signal: Signal,
-    );
-
     /// Call JS function from Rust. Must be called on main thread and call a function already
     /// registered using `register_call_js_callbacks`. `params` is an arbitrary string.
     /// `buffers` is an array of reference-counted byte buffers.
@@ -994,3 +1158,121 @@ pub trait CxPlatformCommon {
     /// Send zaplib Event for processing from any thread
     fn send_event_from_any_thread(event: Event);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press_tab(cx: &mut Cx, shift: bool) {
+        let mut event_handler = |_: &mut Cx, _: &mut Event| {};
+        cx.event_handler =
+            Some(&mut event_handler as *const dyn FnMut(&mut Cx, &mut Event) as *mut dyn FnMut(&mut Cx, &mut Event));
+        cx.call_event_handler(&mut Event::KeyDown(KeyEvent {
+            key_code: KeyCode::Tab,
+            is_repeat: false,
+            modifiers: KeyModifiers { shift, ..KeyModifiers::default() },
+            time: 0.,
+        }));
+        cx.event_handler = None;
+    }
+
+    #[test]
+    fn tab_moves_key_focus_in_tab_index_order() {
+        let mut cx = Cx::new_test();
+        let first = ComponentId::default();
+        let second = ComponentId::default();
+        let third = ComponentId::default();
+        // Components with a `tab_index` come first, the rest in draw order.
+        cx.add_key_focusable(third, None);
+        cx.add_key_focusable(second, Some(2));
+        cx.add_key_focusable(first, Some(1));
+
+        press_tab(&mut cx, false);
+        assert_eq!(cx.key_focus, Some(first));
+        press_tab(&mut cx, false);
+        assert_eq!(cx.key_focus, Some(second));
+        press_tab(&mut cx, false);
+        assert_eq!(cx.key_focus, Some(third));
+        // Wraps around at the end.
+        press_tab(&mut cx, false);
+        assert_eq!(cx.key_focus, Some(first));
+    }
+
+    #[test]
+    fn shift_tab_moves_key_focus_backwards() {
+        let mut cx = Cx::new_test();
+        let first = ComponentId::default();
+        let second = ComponentId::default();
+        cx.add_key_focusable(first, None);
+        cx.add_key_focusable(second, None);
+
+        // Without focus, Shift+Tab starts at the last component.
+        press_tab(&mut cx, true);
+        assert_eq!(cx.key_focus, Some(second));
+        press_tab(&mut cx, true);
+        assert_eq!(cx.key_focus, Some(first));
+        // Wraps around at the start.
+        press_tab(&mut cx, true);
+        assert_eq!(cx.key_focus, Some(second));
+    }
+
+    #[test]
+    fn tab_keeps_focus_on_non_focusable_components() {
+        let mut cx = Cx::new_test();
+        let focusable = ComponentId::default();
+        let editor = ComponentId::default();
+        cx.add_key_focusable(focusable, None);
+        cx.key_focus = Some(editor);
+
+        press_tab(&mut cx, false);
+        assert_eq!(cx.key_focus, Some(editor));
+    }
+
+    #[test]
+    fn tab_without_focusables_does_nothing() {
+        let mut cx = Cx::new_test();
+        press_tab(&mut cx, false);
+        assert_eq!(cx.key_focus, None);
+        press_tab(&mut cx, true);
+        assert_eq!(cx.key_focus, None);
+    }
+
+    #[test]
+    fn paints_nested_passes_before_their_parents() {
+        let mut cx = Cx::new_test();
+        for cxpass in &mut cx.passes {
+            cxpass.paint_dirty = false;
+        }
+        // Parents don't necessarily come before their children in `Cx::passes`, since freed slots get reused.
+        let first = cx.passes.len();
+        let (window_pass, blur, scene, atlas, orphan, freed) = (first, first + 1, first + 2, first + 3, first + 4, first + 5);
+        cx.passes.resize_with(first + 6, CxPass::default);
+        for (pass_id, dep_of) in [
+            (window_pass, CxPassDepOf::Window(0)),
+            (blur, CxPassDepOf::Pass(scene)),
+            (scene, CxPassDepOf::Pass(window_pass)),
+            (atlas, CxPassDepOf::None),
+            (orphan, CxPassDepOf::Pass(freed)),
+        ] {
+            cx.passes[pass_id].dep_of = dep_of;
+            cx.passes[pass_id].main_view_id = Some(0);
+        }
+        cx.passes[blur].paint_dirty = true;
+        cx.passes[atlas].paint_dirty = true;
+        cx.passes[orphan].paint_dirty = true;
+
+        let mut passes_todo = Vec::new();
+        let mut windows_need_repaint = 0;
+        cx.compute_passes_to_repaint(&mut passes_todo, &mut windows_need_repaint);
+
+        // Parents of dirty passes get painted too, except for ones that were freed.
+        assert!(!cx.passes[freed].paint_dirty);
+        assert_eq!(windows_need_repaint, 1);
+        let position = |pass_id| passes_todo.iter().position(|&todo_pass_id| todo_pass_id == pass_id).unwrap();
+        assert_eq!(passes_todo.len(), 5);
+        assert!(position(atlas) < position(blur));
+        assert!(position(orphan) < position(blur));
+        assert!(position(blur) < position(scene));
+        assert!(position(scene) < position(window_pass));
+    }
+}
@@ -463,8 +463,13 @@ pub(crate) struct MTLClearColor {
 #[allow(non_camel_case_types)]
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 pub(crate) enum MTLPixelFormat {
+    R8Unorm = 10,
+    RG8Unorm = 30,
+    R32Float = 55,
     RGBA8Unorm = 70,
     BGRA8Unorm = 80,
+    RGBA16Float = 115,
+    RGBA32Float = 125,
     Depth32Float = 252,
     Stencil8 = 253,
     Depth24Unorm_Stencil8 = 255,
@@ -570,6 +575,33 @@ pub(crate) enum MTLStorageMode {
     Memoryless = 3,
 }
 
+#[repr(u64)]
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum MTLSamplerMinMagFilter {
+    Nearest = 0,
+    Linear = 1,
+}
+
+#[repr(u64)]
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum MTLSamplerMipFilter {
+    NotMipmapped = 0,
+    Nearest = 1,
+    Linear = 2,
+}
+
+#[repr(u64)]
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum MTLSamplerAddressMode {
+    ClampToEdge = 0,
+    MirrorClampToEdge = 1,
+    Repeat = 2,
+    MirrorRepeat = 3,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub(crate) struct MTLOrigin {
@@ -1,9 +1,7 @@
 //! Common code between all native desktop platforms. The counterpart is `cx_wasm32`.
 
 use crate::*;
-use std::fs::File;
 use std::io::prelude::*;
-use std::net::TcpStream;
 
 #[derive(Clone, Default)]
 pub(crate) struct CxDesktop {
@@ -17,74 +15,15 @@ impl CxDesktopVsWasmCommon for Cx {
     }
 
     /// See [`CxDesktopVsWasmCommon::file_write`] for documentation.
-    fn file_write(&mut self, path: &str, data: &[u8]) {
-        // just write it right now
-        if let Ok(mut file) = File::create(path) {
-            if file.write_all(data).is_ok() {
-            } else {
-                println!("ERROR WRITING FILE {}", path);
-            }
-        } else {
-            println!("ERROR WRITING FILE {}", path);
-        }
+    fn file_write(&mut self, path: &str, data: &[u8]) -> std::io::Result<()> {
+        let mut file = UniversalFile::create(path)?;
+        file.write_all(data)?;
+        file.flush()
     }
 
     /// See [`CxDesktopVsWasmCommon::websocket_send`] for documentation.
     fn websocket_send(&mut self, _url: &str, _data: &[u8]) {}
 
-    /// See [`CxDesktopVsWasmCommon::http_send`] for documentation.
-    fn http_send(
-        &mut self,
-        verb: &str,
-        path: &str,
-        _proto: &str,
-        domain: &str,
-        port: u16,
-        content_type: &str,
-        body: &[u8],
-        signal: Signal,
-    ) {
-        fn write_bytes_to_tcp_stream(tcp_stream: &mut TcpStream, bytes: &[u8]) -> bool {
-            let bytes_total = bytes.len();
-            let mut bytes_left = bytes_total;
-            while bytes_left > 0 {
-                let buf = &bytes[(bytes_total - bytes_left)..bytes_total];
-                if let Ok(bytes_written) = tcp_stream.write(buf) {
-                    if bytes_written == 0 {
-                        return false;
-                    }
-                    bytes_left -= bytes_written;
-                } else {
-                    return true;
-                }
-            }
-            false
-        }
-
-        // start a thread, connect, and report back.
-        let data = body.to_vec();
-        let byte_len = data.len();
-        let header = format!(
-            "{} {} HTTP/1.1\r\nHost: {}\r\nConnect: close\r\nContent-Type:{}\r\nContent-Length:{}\r\n\r\n",
-            verb, path, domain, content_type, byte_len
-        );
-        let host = format!("{}:{}", domain, port);
-        let _connect_thread = {
-            std::thread::spawn(move || {
-                let stream = TcpStream::connect(&host);
-                if let Ok(mut stream) = stream {
-                    if !write_bytes_to_tcp_stream(&mut stream, header.as_bytes())
-                        && !write_bytes_to_tcp_stream(&mut stream, &data)
-                    {
-                        Cx::post_signal(signal, Cx::STATUS_HTTP_SEND_OK);
-                        return;
-                    }
-                }
-                Cx::post_signal(signal, Cx::STATUS_HTTP_SEND_FAIL);
-            })
-        };
-    }
-
     /// See [`CxDesktopVsWasmCommon::call_js`] for documentation.
     #[allow(unused_variables)]
     #[cfg(feature = "cef")]
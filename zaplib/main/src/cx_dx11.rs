@@ -4,6 +4,8 @@
 
 use crate::cx_win32::*;
 use crate::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi;
 use std::mem;
 use std::ptr;
@@ -64,7 +66,9 @@ impl Cx {
                         continue;
                     }
                     // update the instance buffer data
+                    self.profiler.begin_zone("upload_buffers");
                     draw_call.platform.inst_vbuf.update_with_f32_vertex_data(d3d11_cx, &draw_call.instances);
+                    self.profiler.end_zone();
                 }
 
                 // update the zbias uniform if we have it.
@@ -91,8 +95,10 @@ impl Cx {
                 let geometry = &mut self.gpu_geometries[gpu_geometry_id];
 
                 if geometry.dirty {
+                    self.profiler.begin_zone("upload_buffers");
                     geometry.platform.geom_vbuf.update_with_f32_vertex_data(d3d11_cx, geometry.geometry.vertices_f32_slice());
                     geometry.platform.geom_ibuf.update_with_u32_index_data(d3d11_cx, geometry.geometry.indices_u32_slice());
+                    self.profiler.end_zone();
                     geometry.dirty = false;
                 }
 
@@ -121,20 +127,16 @@ impl Cx {
                 for (i, texture_id) in draw_call.textures_2d.iter().enumerate() {
                     let cxtexture = &mut self.textures[*texture_id as usize];
                     match cxtexture.desc.format {
-                        // we only allocate Image and Mapped textures.
-                        TextureFormat::ImageRGBA => {
+                        // we only allocate image textures here.
+                        TextureFormat::Depth32Stencil8 => (),
+                        _ => {
                             if cxtexture.update_image {
                                 cxtexture.update_image = false;
-                                d3d11_cx.update_platform_texture_image_rgba(
-                                    &mut cxtexture.platform,
-                                    cxtexture.desc.width.unwrap(),
-                                    cxtexture.desc.height.unwrap(),
-                                    &cxtexture.image_u32,
-                                );
+                                d3d11_cx.update_platform_texture_image2d(cxtexture);
                             }
                             d3d11_cx.set_shader_resource(i, &cxtexture.platform.shader_resource);
+                            d3d11_cx.set_sampler(i, &cxtexture.sampler);
                         }
-                        _ => (),
                     }
                 }
 
@@ -175,7 +177,7 @@ impl Cx {
                 render_target = first_target.unwrap();
                 is_initial = true;
             } else {
-                let cxtexture = &mut self.textures[color_texture.texture_id as usize];
+                let cxtexture = &mut self.textures[color_texture.texture_handle.texture_id as usize];
                 is_initial = d3d11_cx.update_render_target(cxtexture, dpi_factor, pass_size);
                 render_target = cxtexture.platform.render_target_view.as_ref().unwrap();
             }
@@ -261,6 +263,7 @@ impl Cx {
             &mut zbias,
             zbias_step,
         );
+        self.d3d11_read_pass_captures(pass_id, d3d11_window.swap_texture.clone(), true, d3d11_cx);
         d3d11_window.present(vsync);
         //println!("{}", (Cx::profile_time_ns() - time1)as f64 / 1000.0);
     }
@@ -280,14 +283,36 @@ impl Cx {
             &mut zbias,
             zbias_step,
         );
+        let first_texture = self.passes[pass_id]
+            .color_textures
+            .first()
+            .and_then(|color_texture| self.textures[color_texture.texture_handle.texture_id as usize].platform.texture.clone());
+        self.d3d11_read_pass_captures(pass_id, first_texture, false, d3d11_cx);
+    }
+
+    /// Read back the pixels of `texture`, if any captures were requested for `pass_id`.
+    fn d3d11_read_pass_captures(
+        &mut self,
+        pass_id: usize,
+        texture: Option<ComPtr<d3d11::ID3D11Texture2D>>,
+        is_bgra: bool,
+        d3d11_cx: &D3d11Cx,
+    ) {
+        let capture_ids = self.take_pass_capture_ids(pass_id);
+        if capture_ids.is_empty() {
+            return;
+        }
+        let (width, height, data) = match texture {
+            Some(texture) => d3d11_cx.read_texture_pixels(&texture, is_bgra),
+            None => (0, 0, vec![]),
+        };
+        self.finish_pass_captures(capture_ids, width, height, data);
     }
 
     pub(crate) fn hlsl_compile_shaders(&mut self, d3d11_cx: &D3d11Cx) {
         for shader_id in self.shader_recompile_ids.drain(..) {
             let shader = unsafe { self.shaders.get_unchecked_mut(shader_id) };
-            let shader_ast = shader.shader_ast.as_ref().unwrap();
-            let hlsl = generate_hlsl::generate_shader(shader_ast);
-            let debug = shader_ast.debug;
+            let (hlsl, debug) = shader.get_hlsl();
             if debug {
                 println!("--------------- Shader {} --------------- \n{}\n", &shader.name, hlsl);
             }
@@ -515,6 +540,8 @@ pub(crate) struct D3d11Cx {
     pub(crate) context: ComPtr<d3d11::ID3D11DeviceContext>,
     pub(crate) factory: ComPtr<dxgi1_2::IDXGIFactory2>,
     //    pub(crate) d2d1_factory: ComPtr<d2d1::ID2D1Factory>
+    /// Cache of sampler states, since there are typically only a few different ones.
+    samplers: RefCell<HashMap<SamplerDesc, ComPtr<d3d11::ID3D11SamplerState>>>,
 }
 
 impl D3d11Cx {
@@ -528,7 +555,57 @@ impl D3d11Cx {
             context,
             factory,
             //    d2d1_factory: d2d1_factory
+            samplers: Default::default(),
+        }
+    }
+
+    /// Copy `texture` into a staging texture and read back its pixels as RGBA, starting with the top row.
+    pub(crate) fn read_texture_pixels(
+        &self,
+        texture: &ComPtr<d3d11::ID3D11Texture2D>,
+        is_bgra: bool,
+    ) -> (usize, usize, Vec<u32>) {
+        let mut texture_desc: d3d11::D3D11_TEXTURE2D_DESC = unsafe { mem::zeroed() };
+        unsafe { texture.GetDesc(&mut texture_desc) };
+        let width = texture_desc.Width as usize;
+        let height = texture_desc.Height as usize;
+        texture_desc.MipLevels = 1;
+        texture_desc.ArraySize = 1;
+        texture_desc.SampleDesc = dxgitype::DXGI_SAMPLE_DESC { Count: 1, Quality: 0 };
+        texture_desc.Usage = d3d11::D3D11_USAGE_STAGING;
+        texture_desc.BindFlags = 0;
+        texture_desc.CPUAccessFlags = d3d11::D3D11_CPU_ACCESS_READ;
+        texture_desc.MiscFlags = 0;
+
+        let mut staging_texture = ptr::null_mut();
+        let hr = unsafe { self.device.CreateTexture2D(&texture_desc, ptr::null(), &mut staging_texture as *mut *mut _) };
+        if !winerror::SUCCEEDED(hr) {
+            panic!("read_texture_pixels failed to create staging texture");
+        }
+        let staging_texture: ComPtr<d3d11::ID3D11Texture2D> = unsafe { ComPtr::from_raw(staging_texture) };
+
+        let mut data = Vec::with_capacity(width * height);
+        unsafe {
+            self.context.CopyResource(staging_texture.as_raw() as *mut _, texture.as_raw() as *mut _);
+            let mut mapped: d3d11::D3D11_MAPPED_SUBRESOURCE = mem::zeroed();
+            let hr = self.context.Map(staging_texture.as_raw() as *mut _, 0, d3d11::D3D11_MAP_READ, 0, &mut mapped);
+            if !winerror::SUCCEEDED(hr) {
+                panic!("read_texture_pixels failed to map staging texture");
+            }
+            for y in 0..height {
+                let row = (mapped.pData as *const u8).add(y * mapped.RowPitch as usize) as *const u32;
+                data.extend(std::slice::from_raw_parts(row, width).iter().map(|&pixel| {
+                    if is_bgra {
+                        let [b, g, r, a] = pixel.to_le_bytes();
+                        u32::from_le_bytes([r, g, b, a])
+                    } else {
+                        pixel
+                    }
+                }));
+            }
+            self.context.Unmap(staging_texture.as_raw() as *mut _, 0);
         }
+        (width, height, data)
     }
 
     pub(crate) fn disconnect_rendertargets(&self) {
@@ -593,6 +670,47 @@ impl D3d11Cx {
         }
     }
 
+    pub(crate) fn set_sampler(&self, index: usize, sampler: &SamplerDesc) {
+        let mut samplers = self.samplers.borrow_mut();
+        let sampler_state = samplers.entry(*sampler).or_insert_with(|| {
+            let filter = match (sampler.min_filter, sampler.mag_filter, sampler.mipmaps) {
+                (TextureFilter::Nearest, TextureFilter::Nearest, _) => d3d11::D3D11_FILTER_MIN_MAG_MIP_POINT,
+                (TextureFilter::Nearest, TextureFilter::Linear, _) => d3d11::D3D11_FILTER_MIN_POINT_MAG_LINEAR_MIP_POINT,
+                (TextureFilter::Linear, TextureFilter::Nearest, false) => d3d11::D3D11_FILTER_MIN_LINEAR_MAG_MIP_POINT,
+                (TextureFilter::Linear, TextureFilter::Nearest, true) => d3d11::D3D11_FILTER_MIN_LINEAR_MAG_POINT_MIP_LINEAR,
+                (TextureFilter::Linear, TextureFilter::Linear, false) => d3d11::D3D11_FILTER_MIN_MAG_LINEAR_MIP_POINT,
+                (TextureFilter::Linear, TextureFilter::Linear, true) => d3d11::D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+            };
+            let address_mode = |wrap: TextureWrap| match wrap {
+                TextureWrap::ClampToEdge => d3d11::D3D11_TEXTURE_ADDRESS_CLAMP,
+                TextureWrap::Repeat => d3d11::D3D11_TEXTURE_ADDRESS_WRAP,
+                TextureWrap::MirroredRepeat => d3d11::D3D11_TEXTURE_ADDRESS_MIRROR,
+            };
+            let sampler_desc = d3d11::D3D11_SAMPLER_DESC {
+                Filter: filter,
+                AddressU: address_mode(sampler.wrap_u),
+                AddressV: address_mode(sampler.wrap_v),
+                AddressW: d3d11::D3D11_TEXTURE_ADDRESS_CLAMP,
+                MipLODBias: 0.,
+                MaxAnisotropy: 1,
+                ComparisonFunc: d3d11::D3D11_COMPARISON_NEVER,
+                BorderColor: [0.; 4],
+                MinLOD: 0.,
+                MaxLOD: if sampler.mipmaps { d3d11::D3D11_FLOAT32_MAX } else { 0. },
+            };
+            let mut sampler_state = ptr::null_mut();
+            let hr = unsafe { self.device.CreateSamplerState(&sampler_desc, &mut sampler_state as *mut *mut _) };
+            if winerror::SUCCEEDED(hr) {
+                unsafe { ComPtr::from_raw(sampler_state as *mut _) }
+            } else {
+                panic!("CreateSamplerState failed");
+            }
+        });
+        let raw = [sampler_state.as_raw() as *const std::ffi::c_void];
+        unsafe { self.context.PSSetSamplers(index as u32, 1, raw.as_ptr() as *const *mut _) }
+        unsafe { self.context.VSSetSamplers(index as u32, 1, raw.as_ptr() as *const *mut _) }
+    }
+
     //fn set_raster_state(&self, d3d11_window: &D3d11Window) {
     //    unsafe {self.context.RSSetState(d3d11_window.raster_state.as_raw() as *mut _)};
     // }
@@ -1079,47 +1197,72 @@ impl D3d11Cx {
         true
     }
 
-    pub(crate) fn update_platform_texture_image_rgba(
-        &self,
-        res: &mut CxPlatformTexture,
-        width: usize,
-        height: usize,
-        image_u32: &Vec<u32>,
-    ) {
-        if image_u32.len() != width * height {
-            println!("update_platform_texture_image_rgba with wrong buffer_u32 size!");
+    pub(crate) fn update_platform_texture_image2d(&self, cxtexture: &mut CxTexture) {
+        let width = cxtexture.desc.width.unwrap();
+        let height = cxtexture.desc.height.unwrap();
+        if !cxtexture.image_has_valid_len(width, height) {
+            println!("update_platform_texture_image2d with wrong buffer size!");
             return;
         }
 
+        let format = match cxtexture.desc.format {
+            TextureFormat::ImageRGBA => dxgiformat::DXGI_FORMAT_R8G8B8A8_UNORM,
+            TextureFormat::R8 => dxgiformat::DXGI_FORMAT_R8_UNORM,
+            TextureFormat::RG8 => dxgiformat::DXGI_FORMAT_R8G8_UNORM,
+            TextureFormat::R32F => dxgiformat::DXGI_FORMAT_R32_FLOAT,
+            TextureFormat::RGBA16F => dxgiformat::DXGI_FORMAT_R16G16B16A16_FLOAT,
+            TextureFormat::RGBA32F => dxgiformat::DXGI_FORMAT_R32G32B32A32_FLOAT,
+            TextureFormat::Depth32Stencil8 => panic!("update_platform_texture_image2d with unsupported format"),
+        };
+
+        let image_bytes = cxtexture.image_bytes();
+        let row_pitch = (width * cxtexture.desc.format.bytes_per_pixel()) as u32;
         let sub_data = d3d11::D3D11_SUBRESOURCE_DATA {
-            pSysMem: image_u32.as_ptr() as *const _,
-            SysMemPitch: (width * 4) as u32,
+            pSysMem: image_bytes.as_ptr() as *const _,
+            SysMemPitch: row_pitch,
             SysMemSlicePitch: 0,
         };
+        let mipmaps = cxtexture.sampler.mipmaps;
 
         let texture_desc = d3d11::D3D11_TEXTURE2D_DESC {
             Width: width as u32,
             Height: height as u32,
-            MipLevels: 1,
+            // 0 means a full mipmap chain.
+            MipLevels: if mipmaps { 0 } else { 1 },
             ArraySize: 1,
-            Format: dxgiformat::DXGI_FORMAT_R8G8B8A8_UNORM,
+            Format: format,
             SampleDesc: dxgitype::DXGI_SAMPLE_DESC { Count: 1, Quality: 0 },
             Usage: d3d11::D3D11_USAGE_DEFAULT,
-            BindFlags: d3d11::D3D11_BIND_SHADER_RESOURCE,
+            // Generating mipmaps requires the texture to be a render target.
+            BindFlags: if mipmaps {
+                d3d11::D3D11_BIND_SHADER_RESOURCE | d3d11::D3D11_BIND_RENDER_TARGET
+            } else {
+                d3d11::D3D11_BIND_SHADER_RESOURCE
+            },
             CPUAccessFlags: 0,
-            MiscFlags: 0,
+            MiscFlags: if mipmaps { d3d11::D3D11_RESOURCE_MISC_GENERATE_MIPS } else { 0 },
         };
         let mut texture = ptr::null_mut();
-        let hr = unsafe { self.device.CreateTexture2D(&texture_desc, &sub_data, &mut texture as *mut *mut _) };
+        // With mipmaps we'd have to pass in data for every level, so instead we upload the first level below.
+        let initial_data = if mipmaps { ptr::null() } else { &sub_data as *const _ };
+        let hr = unsafe { self.device.CreateTexture2D(&texture_desc, initial_data, &mut texture as *mut *mut _) };
         if winerror::SUCCEEDED(hr) {
             let mut shader_resource = ptr::null_mut();
             unsafe { self.device.CreateShaderResourceView(texture as *mut _, ptr::null(), &mut shader_resource as *mut *mut _) };
+            if mipmaps {
+                unsafe {
+                    self.context.UpdateSubresource(texture as *mut _, 0, ptr::null(), sub_data.pSysMem, row_pitch, 0);
+                    self.context.GenerateMips(shader_resource);
+                }
+            }
+            drop(image_bytes);
+            let res = &mut cxtexture.platform;
             res.width = width;
             res.height = height;
             res.texture = Some(unsafe { ComPtr::from_raw(texture as *mut _) });
             res.shader_resource = Some(unsafe { ComPtr::from_raw(shader_resource as *mut _) });
         } else {
-            panic!("update_platform_texture_image_rgba failed");
+            panic!("update_platform_texture_image2d failed");
         }
     }
 }
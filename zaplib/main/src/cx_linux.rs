@@ -178,7 +178,12 @@ impl Cx {
                                 self.compute_passes_to_repaint(&mut passes_todo, &mut windows_need_repaint);
 
                                 if !passes_todo.is_empty() {
+                                    self.collect_render_stats(&passes_todo);
+                                    self.profiler.begin_zone("paint");
+                                    self.opengl_release_resources();
+                                    self.profiler.begin_zone("compile_shaders");
                                     self.opengl_compile_shaders(&opengl_cx);
+                                    self.profiler.end_zone();
                                     for pass_id in &passes_todo {
                                         match self.passes[*pass_id].dep_of.clone() {
                                             CxPassDepOf::Window(window_id) => {
@@ -224,7 +229,9 @@ impl Cx {
                                             }
                                         }
                                     }
+                                    self.profiler.end_zone();
                                 }
+                                self.profile_end_frame();
                             }
                             _ => {
                                 self.call_event_handler(event);
@@ -232,9 +239,8 @@ impl Cx {
                         }
                     }
                     Event::None => {}
-                    Event::Signal { .. } => {
-                        self.call_event_handler(event);
-                        self.call_signals();
+                    Event::Signal(signal_event) => {
+                        self.call_posted_signals(signal_event);
                     }
                     _ => {
                         self.call_event_handler(event);
@@ -243,7 +249,7 @@ impl Cx {
                 self.process_post_event(event);
             }
 
-            !(paint_dirty || self.requested_draw || self.requested_next_frame)
+            !(paint_dirty || self.requested_draw || self.requested_next_frame || !self.signals.is_empty())
         })
     }
 
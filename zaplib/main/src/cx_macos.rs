@@ -253,7 +253,13 @@ impl Cx {
                                 self.compute_passes_to_repaint(&mut passes_todo, &mut windows_need_repaint);
 
                                 if !passes_todo.is_empty() {
+                                    self.collect_render_stats(&passes_todo);
+                                    self.profiler.begin_zone("paint");
+                                    // Metal resources are reference counted, so they get released when dropped.
+                                    drop(self.take_released_resources());
+                                    self.profiler.begin_zone("compile_shaders");
                                     self.mtl_compile_shaders(&metal_cx);
+                                    self.profiler.end_zone();
 
                                     for pass_id in &passes_todo {
                                         match self.passes[*pass_id].dep_of.clone() {
@@ -293,7 +299,9 @@ impl Cx {
                                             }
                                         }
                                     }
+                                    self.profiler.end_zone();
                                 }
+                                self.profile_end_frame();
                             }
                             #[cfg(feature = "cef")]
                             SystemEvent::CefDoMessageLoopWork => {
@@ -305,9 +313,8 @@ impl Cx {
                         }
                     }
                     Event::None => {}
-                    Event::Signal { .. } => {
-                        self.call_event_handler(event);
-                        self.call_signals();
+                    Event::Signal(signal_event) => {
+                        self.call_posted_signals(signal_event);
                     }
                     _ => {
                         self.call_event_handler(event);
@@ -316,7 +323,7 @@ impl Cx {
                 self.process_post_event(event);
             }
 
-            !(self.requested_draw || self.requested_next_frame)
+            !(self.requested_draw || self.requested_next_frame || !self.signals.is_empty())
         })
     }
     /// Schedule another timer in addition to our regular timer, in case we need to do something
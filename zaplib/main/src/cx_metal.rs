@@ -1,5 +1,7 @@
 //! Mac OS X Metal bindings.
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::c_void;
 use std::mem;
 use std::os::raw::c_int;
@@ -15,7 +17,6 @@ use crate::cx_cocoa::*;
 use crate::*;
 use zaplib_objc_sys::msg_send;
 use zaplib_objc_sys::runtime::YES;
-use zaplib_shader_compiler::generate_metal;
 
 impl Cx {
     fn render_view(
@@ -63,8 +64,10 @@ impl Cx {
                 if draw_call.instance_dirty {
                     draw_call.instance_dirty = false;
                     // update the instance buffer data
+                    self.profiler.begin_zone("upload_buffers");
                     self.platform.bytes_written += draw_call.instances.len() * 4;
                     draw_call.platform.instance_buffer.cpu_write().update(metal_cx, &draw_call.instances);
+                    self.profiler.end_zone();
                 }
 
                 // update the zbias uniform if we have it.
@@ -90,8 +93,10 @@ impl Cx {
                 let geometry = &mut self.gpu_geometries[gpu_geometry_id];
 
                 if geometry.dirty {
+                    self.profiler.begin_zone("upload_buffers");
                     geometry.platform.vertex_buffer.cpu_write().update(metal_cx, geometry.geometry.vertices_f32_slice());
                     geometry.platform.index_buffer.cpu_write().update(metal_cx, geometry.geometry.indices_u32_slice());
+                    self.profiler.end_zone();
                     geometry.dirty = false;
                 }
 
@@ -166,6 +171,21 @@ impl Cx {
                                 atIndex: i as u64
                             ]
                         };
+                        let sampler_state = metal_cx.get_sampler_state(&cxtexture.sampler);
+                        let () = unsafe {
+                            msg_send![
+                                encoder,
+                                setFragmentSamplerState: sampler_state
+                                atIndex: i as u64
+                            ]
+                        };
+                        let () = unsafe {
+                            msg_send![
+                                encoder,
+                                setVertexSamplerState: sampler_state
+                                atIndex: i as u64
+                            ]
+                        };
                     }
                 }
                 self.platform.draw_calls_done += 1;
@@ -227,7 +247,7 @@ impl Cx {
                 };
                 is_initial = true;
             } else {
-                let cxtexture = &mut self.textures[color_texture.texture_id as usize];
+                let cxtexture = &mut self.textures[color_texture.texture_handle.texture_id as usize];
                 cxtexture.platform.update(metal_cx, AttachmentKind::Color, &cxtexture.desc, dpi_factor * pass_size);
                 is_initial = !cxtexture.platform.inner.as_ref().unwrap().is_inited;
 
@@ -374,6 +394,7 @@ impl Cx {
             );
 
             let () = unsafe { msg_send![encoder, endEncoding] };
+            let capture = self.metal_encode_pass_capture(pass_id, command_buffer, Some(texture), true, metal_cx);
             if is_resizing {
                 self.commit_command_buffer(command_buffer, gpu_read_guards);
                 let () = unsafe { msg_send![command_buffer, waitUntilScheduled] };
@@ -382,6 +403,9 @@ impl Cx {
                 let () = unsafe { msg_send![command_buffer, presentDrawable: drawable] };
                 self.commit_command_buffer(command_buffer, gpu_read_guards);
             }
+            if let Some(capture) = capture {
+                self.metal_finish_pass_capture(command_buffer, capture);
+            }
         }
         let () = unsafe { msg_send![pool, release] };
     }
@@ -417,10 +441,89 @@ impl Cx {
         );
         let () = unsafe { msg_send![encoder, textureBarrier] };
         let () = unsafe { msg_send![encoder, endEncoding] };
+        let first_texture = self.passes[pass_id].color_textures.first().and_then(|color_texture| {
+            self.textures[color_texture.texture_handle.texture_id as usize].platform.inner.as_ref().map(|inner| inner.texture.as_id())
+        });
+        let capture = self.metal_encode_pass_capture(pass_id, command_buffer, first_texture, false, metal_cx);
         self.commit_command_buffer(command_buffer, gpu_read_guards);
+        if let Some(capture) = capture {
+            self.metal_finish_pass_capture(command_buffer, capture);
+        }
         let () = unsafe { msg_send![pool, release] };
     }
 
+    /// If any captures were requested for `pass_id`, encode a copy of `texture` into a buffer that we can
+    /// read back once `command_buffer` has completed, using [`Cx::metal_finish_pass_capture`].
+    fn metal_encode_pass_capture(
+        &mut self,
+        pass_id: usize,
+        command_buffer: id,
+        texture: Option<id>,
+        is_bgra: bool,
+        metal_cx: &MetalCx,
+    ) -> Option<MetalPassCapture> {
+        let capture_ids = self.take_pass_capture_ids(pass_id);
+        if capture_ids.is_empty() {
+            return None;
+        }
+        let texture = match texture {
+            Some(texture) => texture,
+            None => {
+                self.finish_pass_captures(capture_ids, 0, 0, vec![]);
+                return None;
+            }
+        };
+        let width: u64 = unsafe { msg_send![texture, width] };
+        let height: u64 = unsafe { msg_send![texture, height] };
+        let bytes_per_row = width * 4;
+        let buffer = RcObjcId::from_owned(
+            NonNull::new(unsafe {
+                msg_send![
+                    metal_cx.device,
+                    newBufferWithLength: bytes_per_row * height
+                    options: nil
+                ]
+            })
+            .unwrap(),
+        );
+        unsafe {
+            let blit_encoder: id = msg_send![command_buffer, blitCommandEncoder];
+            let () = msg_send![
+                blit_encoder,
+                copyFromTexture: texture
+                sourceSlice: 0u64
+                sourceLevel: 0u64
+                sourceOrigin: MTLOrigin { x: 0, y: 0, z: 0 }
+                sourceSize: MTLSize { width, height, depth: 1 }
+                toBuffer: buffer.as_id()
+                destinationOffset: 0u64
+                destinationBytesPerRow: bytes_per_row
+                destinationBytesPerImage: bytes_per_row * height
+            ];
+            let () = msg_send![blit_encoder, endEncoding];
+        }
+        Some(MetalPassCapture { capture_ids, width: width as usize, height: height as usize, is_bgra, buffer })
+    }
+
+    /// Wait for the copy from [`Cx::metal_encode_pass_capture`] to complete, and read back the pixels.
+    fn metal_finish_pass_capture(&mut self, command_buffer: id, capture: MetalPassCapture) {
+        let () = unsafe { msg_send![command_buffer, waitUntilCompleted] };
+        let contents: *const u32 = unsafe { msg_send![capture.buffer.as_id(), contents] };
+        let pixels = unsafe { std::slice::from_raw_parts(contents, capture.width * capture.height) };
+        let data = if capture.is_bgra {
+            pixels
+                .iter()
+                .map(|pixel| {
+                    let [b, g, r, a] = pixel.to_le_bytes();
+                    u32::from_le_bytes([r, g, b, a])
+                })
+                .collect()
+        } else {
+            pixels.to_vec()
+        };
+        self.finish_pass_captures(capture.capture_ids, capture.width, capture.height, data);
+    }
+
     fn commit_command_buffer(&mut self, command_buffer: id, gpu_read_guards: Vec<MetalRwLockGpuReadGuard>) {
         #[repr(C)]
         struct BlockDescriptor {
@@ -482,6 +585,8 @@ impl Cx {
 pub(crate) struct MetalCx {
     pub(crate) device: id,
     pub(crate) command_queue: id,
+    /// Cache of sampler states, since there are typically only a few different ones.
+    samplers: RefCell<HashMap<SamplerDesc, RcObjcId>>,
 }
 
 #[derive(Clone)]
@@ -514,6 +619,8 @@ impl MetalWindow {
         unsafe {
             let () = msg_send![ca_layer, setDevice: metal_cx.device];
             let () = msg_send![ca_layer, setPixelFormat: MTLPixelFormat::BGRA8Unorm];
+            // So we can copy from the drawable in `Pass::request_capture`.
+            let () = msg_send![ca_layer, setFramebufferOnly: NO];
             let () = msg_send![ca_layer, setPresentsWithTransaction: NO];
             let () = msg_send![ca_layer, setMaximumDrawableCount: 3];
             let () = msg_send![ca_layer, setDisplaySyncEnabled: NO];
@@ -582,8 +689,10 @@ impl Cx {
     pub(crate) fn mtl_compile_shaders(&mut self, metal_cx: &MetalCx) {
         for shader_id in self.shader_recompile_ids.drain(..) {
             let shader = unsafe { self.shaders.get_unchecked_mut(shader_id) };
-            let shader_ast = shader.shader_ast.as_ref().unwrap();
-            let mtlsl = generate_metal::generate_shader(shader_ast);
+            let (mtlsl, debug) = shader.get_metal();
+            if debug {
+                println!("--------------- Shader {} --------------- \n{}\n", &shader.name, mtlsl);
+            }
             shader.platform = Some(CxPlatformShader::new(metal_cx, mtlsl));
             shader.shader_ast = None;
         }
@@ -606,7 +715,43 @@ impl MetalCx {
         }
         */
         let device = get_default_metal_device().expect("Cannot get default metal device");
-        MetalCx { command_queue: unsafe { msg_send![device, newCommandQueue] }, device }
+        MetalCx { command_queue: unsafe { msg_send![device, newCommandQueue] }, device, samplers: Default::default() }
+    }
+
+    fn get_sampler_state(&self, sampler: &SamplerDesc) -> id {
+        self.samplers
+            .borrow_mut()
+            .entry(*sampler)
+            .or_insert_with(|| {
+                let filter = |filter: TextureFilter| match filter {
+                    TextureFilter::Nearest => MTLSamplerMinMagFilter::Nearest,
+                    TextureFilter::Linear => MTLSamplerMinMagFilter::Linear,
+                };
+                let mip_filter = match (sampler.mipmaps, sampler.min_filter) {
+                    (false, _) => MTLSamplerMipFilter::NotMipmapped,
+                    (true, TextureFilter::Nearest) => MTLSamplerMipFilter::Nearest,
+                    (true, TextureFilter::Linear) => MTLSamplerMipFilter::Linear,
+                };
+                let address_mode = |wrap: TextureWrap| match wrap {
+                    TextureWrap::ClampToEdge => MTLSamplerAddressMode::ClampToEdge,
+                    TextureWrap::Repeat => MTLSamplerAddressMode::Repeat,
+                    TextureWrap::MirroredRepeat => MTLSamplerAddressMode::MirrorRepeat,
+                };
+                let descriptor =
+                    RcObjcId::from_owned(NonNull::new(unsafe { msg_send![class!(MTLSamplerDescriptor), new] }).unwrap());
+                RcObjcId::from_owned(
+                    NonNull::new(unsafe {
+                        let _: () = msg_send![descriptor.as_id(), setMinFilter: filter(sampler.min_filter)];
+                        let _: () = msg_send![descriptor.as_id(), setMagFilter: filter(sampler.mag_filter)];
+                        let _: () = msg_send![descriptor.as_id(), setMipFilter: mip_filter];
+                        let _: () = msg_send![descriptor.as_id(), setSAddressMode: address_mode(sampler.wrap_u)];
+                        let _: () = msg_send![descriptor.as_id(), setTAddressMode: address_mode(sampler.wrap_v)];
+                        msg_send![self.device, newSamplerStateWithDescriptor: descriptor]
+                    })
+                    .unwrap(),
+                )
+            })
+            .as_id()
     }
 
     pub(crate) fn update_platform_texture_image2d(&self, cxtexture: &mut CxTexture) {
@@ -623,7 +768,8 @@ impl MetalCx {
             desc_changed = inner.format != cxtexture.desc.format
                 || inner.width != width
                 || inner.height != height
-                || inner.multisample != cxtexture.desc.multisample;
+                || inner.multisample != cxtexture.desc.multisample
+                || inner.mipmapped != cxtexture.sampler.mipmaps;
         }
 
         // allocate new texture if descriptor change
@@ -636,14 +782,22 @@ impl MetalCx {
                     let _: () = msg_send![descriptor.as_id(), setHeight: height as u64];
                     let _: () = msg_send![descriptor.as_id(), setStorageMode: MTLStorageMode::Managed];
                     let _: () = msg_send![descriptor.as_id(), setUsage: MTLTextureUsage::RenderTarget];
-                    match cxtexture.desc.format {
-                        TextureFormat::ImageRGBA => {
-                            let _: () = msg_send![descriptor.as_id(), setPixelFormat: MTLPixelFormat::RGBA8Unorm];
-                        }
-                        _ => {
+                    if cxtexture.sampler.mipmaps {
+                        let mipmap_level_count = 64 - width.max(height).leading_zeros() as u64;
+                        let _: () = msg_send![descriptor.as_id(), setMipmapLevelCount: mipmap_level_count];
+                    }
+                    let pixel_format = match cxtexture.desc.format {
+                        TextureFormat::ImageRGBA => MTLPixelFormat::RGBA8Unorm,
+                        TextureFormat::R8 => MTLPixelFormat::R8Unorm,
+                        TextureFormat::RG8 => MTLPixelFormat::RG8Unorm,
+                        TextureFormat::R32F => MTLPixelFormat::R32Float,
+                        TextureFormat::RGBA16F => MTLPixelFormat::RGBA16Float,
+                        TextureFormat::RGBA32F => MTLPixelFormat::RGBA32Float,
+                        TextureFormat::Depth32Stencil8 => {
                             panic!("update_platform_texture_image2d with unsupported format");
                         }
-                    }
+                    };
+                    let _: () = msg_send![descriptor.as_id(), setPixelFormat: pixel_format];
                     msg_send![self.device, newTextureWithDescriptor: descriptor]
                 })
                 .unwrap(),
@@ -655,36 +809,39 @@ impl MetalCx {
                 height,
                 format: cxtexture.desc.format,
                 multisample: cxtexture.desc.multisample,
+                mipmapped: cxtexture.sampler.mipmaps,
                 texture,
             });
         }
 
         // always allocate new image
         let inner = cxtexture.platform.inner.as_ref().unwrap();
-        match cxtexture.desc.format {
-            TextureFormat::ImageRGBA => {
-                if cxtexture.image_u32.len() as u64 != width * height {
-                    panic!("update_platform_texture_image2d with wrong buffer_u32 size!");
-                }
-                let region = MTLRegion {
-                    origin: MTLOrigin { x: 0, y: 0, z: 0 },
-                    size: MTLSize { width: width as u64, height: height as u64, depth: 1 },
-                };
-                let mtl_texture = inner.texture.as_id();
-                let () = unsafe {
-                    msg_send![
-                        mtl_texture,
-                        replaceRegion: region
-                        mipmapLevel: 0
-                        withBytes: cxtexture.image_u32.as_ptr() as *const std::ffi::c_void
-                        bytesPerRow: (width as usize * std::mem::size_of::<u32>()) as u64
-                    ]
-                };
-            }
-            _ => {
-                println!("update_platform_texture_image2d with unsupported format");
-                return;
-            }
+        if !cxtexture.image_has_valid_len(width as usize, height as usize) {
+            panic!("update_platform_texture_image2d with wrong buffer size!");
+        }
+        let region = MTLRegion {
+            origin: MTLOrigin { x: 0, y: 0, z: 0 },
+            size: MTLSize { width: width as u64, height: height as u64, depth: 1 },
+        };
+        let mtl_texture = inner.texture.as_id();
+        let image_bytes = cxtexture.image_bytes();
+        let () = unsafe {
+            msg_send![
+                mtl_texture,
+                replaceRegion: region
+                mipmapLevel: 0
+                withBytes: image_bytes.as_ptr() as *const std::ffi::c_void
+                bytesPerRow: (width as usize * cxtexture.desc.format.bytes_per_pixel()) as u64
+            ]
+        };
+        if cxtexture.sampler.mipmaps {
+            let () = unsafe {
+                let command_buffer: id = msg_send![self.command_queue, commandBuffer];
+                let blit_encoder: id = msg_send![command_buffer, blitCommandEncoder];
+                let () = msg_send![blit_encoder, generateMipmapsForTexture: mtl_texture];
+                let () = msg_send![blit_encoder, endEncoding];
+                msg_send![command_buffer, commit]
+            };
         }
         cxtexture.update_image = false;
     }
@@ -888,6 +1045,7 @@ impl CxPlatformTexture {
             height,
             format: desc.format,
             multisample: desc.multisample,
+            mipmapped: false,
             texture,
         });
     }
@@ -899,9 +1057,20 @@ struct CxPlatformTextureInner {
     height: u64,
     format: TextureFormat,
     multisample: Option<usize>,
+    mipmapped: bool,
     texture: RcObjcId,
 }
 
+/// A copy of a rendered texture into a buffer, for [`Pass::request_capture`].
+struct MetalPassCapture {
+    capture_ids: Vec<PassCaptureId>,
+    width: usize,
+    height: usize,
+    /// Whether the texture is BGRA instead of RGBA, like the drawables of our layers.
+    is_bgra: bool,
+    buffer: RcObjcId,
+}
+
 enum AttachmentKind {
     Color,
     Depth,
@@ -7,7 +7,6 @@ use std::mem;
 use std::os::raw::{c_ulong, c_void};
 use std::ptr;
 use zaplib_glx_sys as glx_sys;
-use zaplib_shader_compiler::generate_glsl;
 use zaplib_x11_sys as X11_sys;
 
 impl Cx {
@@ -51,7 +50,9 @@ impl Cx {
 
                 if draw_call.instance_dirty {
                     draw_call.instance_dirty = false;
+                    self.profiler.begin_zone("upload_buffers");
                     draw_call.platform.inst_vb.update_with_f32_data(opengl_cx, &draw_call.instances);
+                    self.profiler.end_zone();
                 }
 
                 let geometry = &mut self.gpu_geometries[gpu_geometry_id];
@@ -69,8 +70,10 @@ impl Cx {
                 // update geometry?
                 let geometry = &mut self.gpu_geometries[gpu_geometry_id];
                 if geometry.dirty || geometry.platform.vb.gl_buffer.is_none() || geometry.platform.ib.gl_buffer.is_none() {
+                    self.profiler.begin_zone("upload_buffers");
                     geometry.platform.vb.update_with_f32_data(opengl_cx, geometry.geometry.vertices_f32_slice());
                     geometry.platform.ib.update_with_u32_data(opengl_cx, geometry.geometry.indices_u32_slice());
+                    self.profiler.end_zone();
                     geometry.dirty = false;
                 }
 
@@ -163,6 +166,10 @@ impl Cx {
                         gl::ActiveTexture(gl::TEXTURE0 + i as u32);
                         if let Some(texture) = cxtexture.platform.gl_texture {
                             gl::BindTexture(gl::TEXTURE_2D, texture);
+                            if cxtexture.platform.sampler != Some(cxtexture.sampler) {
+                                cxtexture.platform.sampler = Some(cxtexture.sampler);
+                                OpenglCx::set_bound_texture_sampler(&cxtexture.sampler, cxtexture.platform.mipmapped);
+                            }
                         } else {
                             gl::BindTexture(gl::TEXTURE_2D, 0);
                         }
@@ -257,6 +264,7 @@ impl Cx {
             &mut zbias,
             zbias_step,
         );
+        self.opengl_read_pass_captures(pass_id, pix_width as usize, pix_height as usize);
 
         unsafe {
             glx_sys::glXSwapBuffers(opengl_cx.display, window);
@@ -359,7 +367,7 @@ impl Cx {
             match color_texture.clear_color {
                 ClearColor::InitWith(color) => {
                     if opengl_cx.update_platform_render_target(
-                        &mut self.textures[color_texture.texture_id as usize],
+                        &mut self.textures[color_texture.texture_handle.texture_id as usize],
                         dpi_factor,
                         pass_size,
                         false,
@@ -370,7 +378,7 @@ impl Cx {
                 }
                 ClearColor::ClearWith(color) => {
                     opengl_cx.update_platform_render_target(
-                        &mut self.textures[color_texture.texture_id as usize],
+                        &mut self.textures[color_texture.texture_handle.texture_id as usize],
                         dpi_factor,
                         pass_size,
                         false,
@@ -379,7 +387,7 @@ impl Cx {
                     clear_flags |= gl::COLOR_BUFFER_BIT;
                 }
             }
-            if let Some(gl_texture) = self.textures[color_texture.texture_id as usize].platform.gl_texture {
+            if let Some(gl_texture) = self.textures[color_texture.texture_handle.texture_id as usize].platform.gl_texture {
                 unsafe {
                     gl::FramebufferTexture2D(
                         gl::FRAMEBUFFER,
@@ -419,18 +427,44 @@ impl Cx {
             &mut zbias,
             zbias_step,
         );
+        let (capture_width, capture_height) = match self.passes[pass_id].color_textures.first() {
+            Some(color_texture) => {
+                let platform = &self.textures[color_texture.texture_handle.texture_id as usize].platform;
+                (platform.width as usize, platform.height as usize)
+            }
+            None => (0, 0),
+        };
+        self.opengl_read_pass_captures(pass_id, capture_width, capture_height);
         unsafe {
             gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
         }
     }
 
+    /// Read back the pixels of the currently bound framebuffer, if any captures were requested for this pass.
+    fn opengl_read_pass_captures(&mut self, pass_id: usize, width: usize, height: usize) {
+        let capture_ids = self.take_pass_capture_ids(pass_id);
+        if capture_ids.is_empty() {
+            return;
+        }
+        let mut data = vec![0u32; width * height];
+        if !data.is_empty() {
+            unsafe {
+                gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+                gl::ReadPixels(0, 0, width as i32, height as i32, gl::RGBA, gl::UNSIGNED_BYTE, data.as_mut_ptr() as *mut c_void);
+            }
+            // OpenGL returns the bottom row first.
+            data = data.chunks_exact(width).rev().flatten().copied().collect();
+        }
+        self.finish_pass_captures(capture_ids, width, height, data);
+    }
+
     //let view_id = self.passes[pass_id].main_view_id.unwrap();
     //let _pass_size = self.passes[pass_id].pass_size;
 
     /*
     for (index, color_texture) in self.passes[pass_id].color_textures.iter().enumerate() {
 
-        let cxtexture = &mut self.textures[color_texture.texture_id];
+        let cxtexture = &mut self.textures[color_texture.texture_handle.texture_id];
 
         metal_cx.update_platform_render_target(cxtexture, dpi_factor, pass_size, false);
         let color_attachment = render_pass_descriptor.color_attachments().object_at(index).unwrap();
@@ -551,6 +585,58 @@ impl Cx {
         unsafe { OpenglUniform { loc: gl::GetUniformLocation(program, name0.as_ptr() as *const _), size } }
     }
 
+    /// Delete the OpenGL objects of freed [`Texture`]s, [`Pass`]es, [`View`]s, and unused [`GpuGeometry`]s.
+    pub(crate) fn opengl_release_resources(&mut self) {
+        let released = self.take_released_resources();
+        let mut deleted_buffers = Vec::new();
+        unsafe {
+            for (_, texture) in released.textures {
+                if let Some(gl_texture) = texture.gl_texture {
+                    gl::DeleteTextures(1, &gl_texture);
+                }
+                if let Some(gl_renderbuffer) = texture.gl_renderbuffer {
+                    gl::DeleteRenderbuffers(1, &gl_renderbuffer);
+                }
+            }
+            for (_, pass) in released.passes {
+                if let Some(gl_framebuffer) = pass.gl_framebuffer {
+                    gl::DeleteFramebuffers(1, &gl_framebuffer);
+                }
+                if let Some(gl_bugfix_depthbuffer) = pass.gl_bugfix_depthbuffer {
+                    gl::DeleteRenderbuffers(1, &gl_bugfix_depthbuffer);
+                }
+            }
+            for draw_call in released.draw_calls {
+                deleted_buffers.extend(draw_call.inst_vb.gl_buffer);
+                if let Some(vao) = draw_call.vao {
+                    gl::DeleteVertexArrays(1, &vao.vao);
+                }
+            }
+            for gpu_geometry in released.gpu_geometries {
+                deleted_buffers.extend(gpu_geometry.vb.gl_buffer);
+                deleted_buffers.extend(gpu_geometry.ib.gl_buffer);
+            }
+            if !deleted_buffers.is_empty() {
+                gl::DeleteBuffers(deleted_buffers.len() as i32, deleted_buffers.as_ptr());
+            }
+        }
+
+        // OpenGL can reuse the names of deleted buffers for new ones, so make sure that VAOs that pointed
+        // at a deleted buffer get rebound, even if they happen to point at a new buffer with the same name.
+        if !deleted_buffers.is_empty() {
+            for view in &mut self.views {
+                for draw_call in &mut view.draw_calls {
+                    if let Some(vao) = &mut draw_call.platform.vao {
+                        if [vao.inst_vb, vao.geom_vb, vao.geom_ib].iter().flatten().any(|buffer| deleted_buffers.contains(buffer))
+                        {
+                            vao.shader_id = None;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     pub(crate) fn opengl_compile_shaders(&mut self, opengl_cx: &OpenglCx) {
         if self.shader_recompile_ids.is_empty() {
             return;
@@ -561,10 +647,7 @@ impl Cx {
         }
         for shader_id in self.shader_recompile_ids.drain(..) {
             let shader = unsafe { self.shaders.get_unchecked_mut(shader_id) };
-            let shader_ast = shader.shader_ast.as_ref().unwrap();
-
-            let vertex = generate_glsl::generate_vertex_shader(shader_ast);
-            let fragment = generate_glsl::generate_fragment_shader(shader_ast);
+            let (vertex, fragment, debug) = shader.get_glsl();
 
             let vertex = format!(
                 "
@@ -586,7 +669,7 @@ impl Cx {
                 fragment
             );
 
-            if shader_ast.debug {
+            if debug {
                 println!("--------------- Vertex shader {} --------------- \n{}\n---------------\n", &shader.name, vertex);
                 println!("--------------- Fragment shader {} --------------- \n{}\n---------------\n", &shader.name, fragment);
             }
@@ -795,6 +878,28 @@ impl OpenglCx {
         }
     }
 
+    /// Set the [`SamplerDesc`] on the texture that is currently bound to `TEXTURE_2D`.
+    unsafe fn set_bound_texture_sampler(sampler: &SamplerDesc, mipmapped: bool) {
+        let filter = |filter: TextureFilter| match filter {
+            TextureFilter::Nearest => gl::NEAREST,
+            TextureFilter::Linear => gl::LINEAR,
+        };
+        let min_filter = match (mipmapped, sampler.min_filter) {
+            (false, min_filter) => filter(min_filter),
+            (true, TextureFilter::Nearest) => gl::NEAREST_MIPMAP_NEAREST,
+            (true, TextureFilter::Linear) => gl::LINEAR_MIPMAP_LINEAR,
+        };
+        let wrap = |wrap: TextureWrap| match wrap {
+            TextureWrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+            TextureWrap::Repeat => gl::REPEAT,
+            TextureWrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+        };
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter(sampler.mag_filter) as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap(sampler.wrap_u) as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap(sampler.wrap_v) as i32);
+    }
+
     pub(crate) fn update_platform_texture_image2d(&self, cxtexture: &mut CxTexture) {
         if cxtexture.desc.width.is_none() || cxtexture.desc.height.is_none() {
             println!("update_platform_texture_image2d without width/height");
@@ -804,42 +909,58 @@ impl OpenglCx {
         let width = cxtexture.desc.width.unwrap();
         let height = cxtexture.desc.height.unwrap();
 
-        // allocate new texture if descriptor change
-        // TODO(Paras): Change this so that we still update image_u32 data
-        // whenever this function is called, not just when the descriptor changes.
-        if cxtexture.platform.alloc_desc != cxtexture.desc {
-            cxtexture.platform.alloc_desc = cxtexture.desc.clone();
-            cxtexture.platform.width = width as u64;
-            cxtexture.platform.height = height as u64;
-
-            let gl_texture = match cxtexture.platform.gl_texture {
-                None => unsafe {
-                    let mut gl_texture = std::mem::MaybeUninit::uninit();
-                    gl::GenTextures(1, gl_texture.as_mut_ptr());
-                    let gl_texture = gl_texture.assume_init();
-                    cxtexture.platform.gl_texture = Some(gl_texture);
-                    gl_texture
-                },
-                Some(gl_texture_old) => gl_texture_old,
-            };
-            unsafe {
-                gl::BindTexture(gl::TEXTURE_2D, gl_texture);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-                gl::TexImage2D(
-                    gl::TEXTURE_2D,
-                    0,
-                    gl::RGBA as i32,
-                    width as i32,
-                    height as i32,
-                    0,
-                    gl::RGBA,
-                    gl::UNSIGNED_BYTE,
-                    cxtexture.image_u32.as_ptr() as *const _,
-                );
-                gl::BindTexture(gl::TEXTURE_2D, 0);
+        if !cxtexture.image_has_valid_len(width, height) {
+            panic!("update_platform_texture_image2d with wrong buffer size!");
+        }
+
+        cxtexture.platform.alloc_desc = cxtexture.desc.clone();
+        cxtexture.platform.width = width as u64;
+        cxtexture.platform.height = height as u64;
+
+        let gl_texture = match cxtexture.platform.gl_texture {
+            None => unsafe {
+                let mut gl_texture = std::mem::MaybeUninit::uninit();
+                gl::GenTextures(1, gl_texture.as_mut_ptr());
+                let gl_texture = gl_texture.assume_init();
+                cxtexture.platform.gl_texture = Some(gl_texture);
+                gl_texture
+            },
+            Some(gl_texture_old) => gl_texture_old,
+        };
+        let (internal_format, format, data_type) = match cxtexture.desc.format {
+            TextureFormat::ImageRGBA => (gl::RGBA, gl::RGBA, gl::UNSIGNED_BYTE),
+            TextureFormat::R8 => (gl::R8, gl::RED, gl::UNSIGNED_BYTE),
+            TextureFormat::RG8 => (gl::RG8, gl::RG, gl::UNSIGNED_BYTE),
+            TextureFormat::R32F => (gl::R32F, gl::RED, gl::FLOAT),
+            TextureFormat::RGBA16F => (gl::RGBA16F, gl::RGBA, gl::HALF_FLOAT),
+            TextureFormat::RGBA32F => (gl::RGBA32F, gl::RGBA, gl::FLOAT),
+            TextureFormat::Depth32Stencil8 => panic!("update_platform_texture_image2d with unsupported format"),
+        };
+        let image_bytes = cxtexture.image_bytes();
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, gl_texture);
+            // Rows of single and dual channel textures are not necessarily 4-byte aligned.
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                internal_format as i32,
+                width as i32,
+                height as i32,
+                0,
+                format,
+                data_type,
+                image_bytes.as_ptr() as *const _,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            if cxtexture.sampler.mipmaps {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
             }
+            gl::BindTexture(gl::TEXTURE_2D, 0);
         }
+        cxtexture.platform.mipmapped = cxtexture.sampler.mipmaps;
+        // Make sure the sampler gets set when drawing.
+        cxtexture.platform.sampler = None;
 
         cxtexture.update_image = false;
     }
@@ -869,6 +990,8 @@ impl OpenglCx {
                 gl::DeleteTextures(1, &gl_texture);
             }
             cxtexture.platform.gl_texture = None;
+            cxtexture.platform.sampler = None;
+            cxtexture.platform.mipmapped = false;
 
             if let Some(gl_renderbuffer) = cxtexture.platform.gl_renderbuffer {
                 gl::DeleteTextures(1, &gl_renderbuffer);
@@ -1040,6 +1163,10 @@ pub(crate) struct CxPlatformTexture {
     pub(crate) height: u64,
     pub(crate) gl_texture: Option<u32>,
     pub(crate) gl_renderbuffer: Option<u32>,
+    /// The sampler that was last set on `gl_texture`.
+    pub(crate) sampler: Option<SamplerDesc>,
+    /// Whether `gl_texture` has mipmaps.
+    pub(crate) mipmapped: bool,
 }
 
 #[derive(Default, Clone)]
@@ -30,7 +30,6 @@ const MSG_TYPE_TIMER_FIRED: u32 = 18;
 const MSG_TYPE_WINDOW_FOCUS: u32 = 19;
 const MSG_TYPE_XR_UPDATE: u32 = 20;
 const MSG_TYPE_PAINT_DIRTY: u32 = 21;
-const MSG_TYPE_HTTP_SEND_RESPONSE: u32 = 22;
 const MSG_TYPE_WEBSOCKET_MESSAGE: u32 = 23;
 const MSG_TYPE_WEBSOCKET_ERROR: u32 = 24;
 const MSG_TYPE_APP_OPEN_FILES: u32 = 25;
@@ -39,6 +38,7 @@ const MSG_TYPE_DRAG_ENTER: u32 = 27;
 const MSG_TYPE_DRAG_LEAVE: u32 = 28;
 const MSG_TYPE_DRAG_OVER: u32 = 29;
 const MSG_TYPE_CALL_RUST: u32 = 30;
+const MSG_TYPE_PASS_CAPTURED: u32 = 31;
 
 impl Cx {
     /// Initialize global error handlers.
@@ -350,16 +350,6 @@ impl Cx {
                     // paint_dirty, only set the passes of the main window to dirty
                     self.passes[self.windows[0].main_pass_id.unwrap()].paint_dirty = true;
                 }
-                MSG_TYPE_HTTP_SEND_RESPONSE => {
-                    let signal_id = zerde_parser.parse_u32();
-                    let success = zerde_parser.parse_u32();
-                    let mut new_set = BTreeSet::new();
-                    new_set.insert(match success {
-                        1 => Cx::STATUS_HTTP_SEND_OK,
-                        _ => Cx::STATUS_HTTP_SEND_FAIL,
-                    });
-                    self.signals.insert(Signal { signal_id: signal_id as usize }, new_set);
-                }
                 MSG_TYPE_WEBSOCKET_MESSAGE => {
                     let data = zerde_parser.parse_vec_ptr();
                     let url = zerde_parser.parse_string();
@@ -410,6 +400,23 @@ impl Cx {
                         callback_id,
                     }))));
                 }
+                MSG_TYPE_PASS_CAPTURED => {
+                    let capture_id = PassCaptureId(zerde_parser.parse_u64());
+                    let width = zerde_parser.parse_u32() as usize;
+                    let height = zerde_parser.parse_u32() as usize;
+                    let pixels = zerde_parser.parse_vec_ptr();
+                    // WebGL returns the bottom row first.
+                    let data = pixels
+                        .chunks_exact(4)
+                        .map(|pixel| u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]))
+                        .collect::<Vec<u32>>()
+                        .chunks_exact(width.max(1))
+                        .rev()
+                        .flatten()
+                        .copied()
+                        .collect();
+                    self.finish_pass_captures(vec![capture_id], width, height, data);
+                }
                 _ => {
                     panic!("Message unknown {}", msg_type);
                 }
@@ -476,8 +483,13 @@ impl Cx {
         self.compute_passes_to_repaint(&mut passes_todo, &mut windows_need_repaint);
 
         if is_animation_frame && passes_todo.len() > 0 {
+            self.collect_render_stats(&passes_todo);
+            self.profiler.begin_zone("paint");
             let mut zerde_webgl = ZerdeWebGLMessages::new();
+            self.webgl_release_resources(&mut zerde_webgl);
+            self.profiler.begin_zone("compile_shaders");
             self.webgl_compile_shaders(&mut zerde_webgl);
+            self.profiler.end_zone();
             for pass_id in &passes_todo {
                 match self.passes[*pass_id].dep_of.clone() {
                     CxPassDepOf::Window(_) => {
@@ -498,6 +510,10 @@ impl Cx {
             }
             zerde_webgl.end();
             self.platform.zerde_eventloop_msgs.run_webgl(zerde_webgl.take_ptr());
+            self.profiler.end_zone();
+        }
+        if is_animation_frame {
+            self.profile_end_frame();
         }
 
         // request animation frame if still need to redraw, or repaint
@@ -515,7 +531,10 @@ impl Cx {
 
     fn wasm_event_handler(&mut self, mut event: Event) {
         self.process_pre_event(&mut event);
-        self.call_event_handler(&mut event);
+        match &mut event {
+            Event::Signal(signal_event) => self.call_posted_signals(signal_event),
+            event => self.call_event_handler(event),
+        }
         self.process_post_event(&mut event);
     }
 
@@ -559,23 +578,10 @@ impl CxDesktopVsWasmCommon for Cx {
     }
 
     /// See [`CxDesktopVsWasmCommon::file_write`] for documentation.
-    fn file_write(&mut self, _path: &str, _data: &[u8]) {
-        unimplemented!();
-    }
-
-    /// See [`CxDesktopVsWasmCommon::http_send`] for documentation.
-    fn http_send(
-        &mut self,
-        verb: &str,
-        path: &str,
-        proto: &str,
-        domain: &str,
-        port: u16,
-        content_type: &str,
-        body: &[u8],
-        signal: Signal,
-    ) {
-        self.platform.zerde_eventloop_msgs.http_send(verb, path, proto, domain, port, content_type, body, signal);
+    fn file_write(&mut self, path: &str, data: &[u8]) -> std::io::Result<()> {
+        let mut file = UniversalFile::create(path)?;
+        std::io::Write::write_all(&mut file, data)?;
+        std::io::Write::flush(&mut file)
     }
 
     /// See [`CxDesktopVsWasmCommon::websocket_send`] for documentation.
@@ -770,28 +776,6 @@ impl ZerdeEventloopMsgs {
         self.builder.send_u32(12);
     }
 
-    pub(crate) fn http_send(
-        &mut self,
-        verb: &str,
-        path: &str,
-        proto: &str,
-        domain: &str,
-        port: u16,
-        content_type: &str,
-        body: &[u8],
-        signal: Signal,
-    ) {
-        self.builder.send_u32(13);
-        self.builder.send_u32(port as u32);
-        self.builder.send_u32(signal.signal_id as u32);
-        self.builder.send_string(verb);
-        self.builder.send_string(path);
-        self.builder.send_string(proto);
-        self.builder.send_string(domain);
-        self.builder.send_string(content_type);
-        self.builder.send_u8slice(body);
-    }
-
     pub(crate) fn fullscreen(&mut self) {
         self.builder.send_u32(14);
     }
@@ -917,8 +901,18 @@ pub(crate) const TASK_WORKER_ERROR_RETURN_VALUE: i32 = -2;
 const TASK_WORKER_MESSAGE_TYPE_HTTP_STREAM_NEW: u32 = 1;
 const TASK_WORKER_MESSAGE_TYPE_HTTP_STREAM_READ: u32 = 2;
 
-/// Opens a new HTTP stream, blocks until there's a successful response, and returns a stream id.
-pub(crate) fn send_task_worker_message_http_stream_new(url: &str, method: &str, body: &[u8], headers: &[(&str, &str)]) -> i32 {
+/// Opens a new HTTP stream, blocks until there's a response, and returns a stream id. The stream starts with
+/// the response info (status, headers, etc); see [`crate::universal_http_stream::Request::send`].
+///
+/// `timeout_ms` of 0 means no timeout.
+pub(crate) fn send_task_worker_message_http_stream_new(
+    url: &str,
+    method: &str,
+    body: &[u8],
+    headers: &[(&str, &str)],
+    follow_redirects: bool,
+    timeout_ms: u32,
+) -> i32 {
     let mut stream_id = TASK_WORKER_INITIAL_RETURN_VALUE;
     let mut zerde_builder = ZerdeBuilder::new();
     zerde_builder.send_u32(TASK_WORKER_MESSAGE_TYPE_HTTP_STREAM_NEW);
@@ -931,6 +925,8 @@ pub(crate) fn send_task_worker_message_http_stream_new(url: &str, method: &str,
         zerde_builder.send_string(name);
         zerde_builder.send_string(value);
     }
+    zerde_builder.send_u32(follow_redirects as u32);
+    zerde_builder.send_u32(timeout_ms);
     let zerde_ptr = zerde_builder.take_ptr();
     unsafe {
         sendTaskWorkerMessage(zerde_ptr);
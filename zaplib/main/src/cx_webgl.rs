@@ -3,7 +3,6 @@
 //! Communicates with main_worker.ts using some functions in `cx_wasm32.rs`.
 
 use crate::{zerde::ZerdeBuilder, *};
-use zaplib_shader_compiler::generate_glsl;
 
 impl Cx {
     pub(crate) fn render_view(
@@ -45,11 +44,13 @@ impl Cx {
                         draw_call.platform.inst_vb_id = Some(self.platform.vertex_buffers);
                         self.platform.vertex_buffers += 1;
                     }
+                    self.profiler.begin_zone("upload_buffers");
                     zerde_webgl.alloc_array_buffer(
                         draw_call.platform.inst_vb_id.unwrap(),
                         draw_call.instances.len(),
                         draw_call.instances.as_ptr() as *const f32,
                     );
+                    self.profiler.end_zone();
                     draw_call.instance_dirty = false;
                 }
 
@@ -61,6 +62,11 @@ impl Cx {
                 // update/alloc textures?
                 for texture_id in &draw_call.textures_2d {
                     let cxtexture = &mut self.textures[*texture_id as usize];
+                    // Send the sampler first, so mipmaps get generated when uploading the image.
+                    if cxtexture.platform.sampler != Some(cxtexture.sampler) {
+                        cxtexture.platform.sampler = Some(cxtexture.sampler);
+                        zerde_webgl.set_texture_sampler(*texture_id as usize, &cxtexture.sampler);
+                    }
                     if cxtexture.update_image {
                         cxtexture.update_image = false;
                         zerde_webgl.update_texture_image2d(*texture_id as usize, cxtexture);
@@ -79,6 +85,7 @@ impl Cx {
                         geometry.platform.ib_id = Some(self.platform.index_buffers);
                         self.platform.index_buffers += 1;
                     }
+                    self.profiler.begin_zone("upload_buffers");
                     let vertex_attributes = geometry.geometry.vertices_f32_slice();
                     zerde_webgl.alloc_array_buffer(
                         geometry.platform.vb_id.unwrap(),
@@ -92,6 +99,7 @@ impl Cx {
                         triangle_indices.len(),
                         triangle_indices.as_ptr(),
                     );
+                    self.profiler.end_zone();
 
                     geometry.dirty = false;
                 }
@@ -188,6 +196,7 @@ impl Cx {
             zbias_step,
             zerde_webgl,
         );
+        self.webgl_request_pass_captures(pass_id, true, zerde_webgl);
     }
 
     pub(crate) fn draw_pass_to_texture(&mut self, pass_id: usize, dpi_factor: f32, zerde_webgl: &mut ZerdeWebGLMessages) {
@@ -201,10 +210,10 @@ impl Cx {
         for color_texture in &self.passes[pass_id].color_textures {
             match color_texture.clear_color {
                 ClearColor::InitWith(color) => {
-                    zerde_webgl.add_color_target(color_texture.texture_id as usize, true, color);
+                    zerde_webgl.add_color_target(color_texture.texture_handle.texture_id as usize, true, color);
                 }
                 ClearColor::ClearWith(color) => {
-                    zerde_webgl.add_color_target(color_texture.texture_id as usize, false, color);
+                    zerde_webgl.add_color_target(color_texture.texture_handle.texture_id as usize, false, color);
                 }
             }
         }
@@ -237,15 +246,58 @@ impl Cx {
             zbias_step,
             zerde_webgl,
         );
+        let has_color_target = !self.passes[pass_id].color_textures.is_empty();
+        self.webgl_request_pass_captures(pass_id, has_color_target, zerde_webgl);
+    }
+
+    /// If any captures were requested for `pass_id`, ask JS to read back the pixels of the current render target.
+    /// They come back in `MSG_TYPE_PASS_CAPTURED`.
+    fn webgl_request_pass_captures(&mut self, pass_id: usize, has_color_target: bool, zerde_webgl: &mut ZerdeWebGLMessages) {
+        let capture_ids = self.take_pass_capture_ids(pass_id);
+        if capture_ids.is_empty() {
+            return;
+        }
+        if has_color_target {
+            for capture_id in capture_ids {
+                zerde_webgl.capture_pass(capture_id);
+            }
+        } else {
+            self.finish_pass_captures(capture_ids, 0, 0, vec![]);
+        }
+    }
+
+    /// Delete the WebGL objects of freed [`Texture`]s, [`Pass`]es, [`View`]s, and unused [`GpuGeometry`]s.
+    /// Should be sent before anything else, since [`Cx::textures`] and [`Cx::passes`] indices get reused.
+    pub(crate) fn webgl_release_resources(&mut self, zerde_webgl: &mut ZerdeWebGLMessages) {
+        let released = self.take_released_resources();
+        for (texture_id, _) in released.textures {
+            zerde_webgl.delete_texture(texture_id as usize);
+        }
+        for (pass_id, _) in released.passes {
+            zerde_webgl.delete_framebuffer(pass_id);
+        }
+        for draw_call in released.draw_calls {
+            if let Some(inst_vb_id) = draw_call.inst_vb_id {
+                zerde_webgl.delete_array_buffer(inst_vb_id);
+            }
+            if let Some(vao) = draw_call.vao {
+                zerde_webgl.delete_vao(vao.vao_id);
+            }
+        }
+        for gpu_geometry in released.gpu_geometries {
+            if let Some(vb_id) = gpu_geometry.vb_id {
+                zerde_webgl.delete_array_buffer(vb_id);
+            }
+            if let Some(ib_id) = gpu_geometry.ib_id {
+                zerde_webgl.delete_index_buffer(ib_id);
+            }
+        }
     }
 
     pub(crate) fn webgl_compile_shaders(&mut self, zerde_webgl: &mut ZerdeWebGLMessages) {
         for shader_id in self.shader_recompile_ids.drain(..) {
             let shader = unsafe { self.shaders.get_unchecked_mut(shader_id) };
-            let shader_ast = shader.shader_ast.as_ref().unwrap();
-
-            let vertex = generate_glsl::generate_vertex_shader(&shader_ast);
-            let fragment = generate_glsl::generate_fragment_shader(&shader_ast);
+            let (vertex, fragment, debug) = shader.get_glsl();
 
             let vertex = format!(
                 "
@@ -275,7 +327,7 @@ impl Cx {
                 fragment
             );
 
-            if shader_ast.debug {
+            if debug {
                 self.platform.zerde_eventloop_msgs.log(&format!(
                     "--------------- Vertex shader {} --------------- \n{}\n---------------\n--------------- Fragment shader {} \
                      --------------- \n{}\n---------------\n",
@@ -318,7 +370,10 @@ pub(crate) struct CxPlatformDrawCall {
 pub(crate) struct CxPlatformShader {}
 
 #[derive(Clone, Default)]
-pub(crate) struct CxPlatformTexture {}
+pub(crate) struct CxPlatformTexture {
+    /// The sampler that was last sent to JS.
+    sampler: Option<SamplerDesc>,
+}
 
 #[derive(Clone, Default)]
 pub(crate) struct CxPlatformGpuGeometry {
@@ -419,12 +474,22 @@ impl ZerdeWebGLMessages {
     }
 
     pub(crate) fn update_texture_image2d(&mut self, texture_id: usize, texture: &mut CxTexture) {
-        //usize, width: usize, height: usize, data: &Vec<u32>
+        // Keep in sync with `TEXTURE_FORMAT_*` in `webgl_renderer.ts`.
+        let (format, data_ptr) = match texture.desc.format {
+            TextureFormat::ImageRGBA => (0, texture.image_u32.as_ptr() as u32),
+            TextureFormat::R8 => (1, texture.image_u8.as_ptr() as u32),
+            TextureFormat::RG8 => (2, texture.image_u8.as_ptr() as u32),
+            TextureFormat::R32F => (3, texture.image_f32.as_ptr() as u32),
+            // WebGL1 doesn't make it easy to upload half floats, so we just upload these as full floats.
+            TextureFormat::RGBA16F | TextureFormat::RGBA32F => (4, texture.image_f32.as_ptr() as u32),
+            TextureFormat::Depth32Stencil8 => panic!("update_texture_image2d with unsupported format"),
+        };
         self.builder.send_u32(6);
         self.builder.send_u32(texture_id as u32);
         self.builder.send_u32(texture.desc.width.unwrap() as u32);
         self.builder.send_u32(texture.desc.height.unwrap() as u32);
-        self.builder.send_u32(texture.image_u32.as_ptr() as u32)
+        self.builder.send_u32(format);
+        self.builder.send_u32(data_ptr)
     }
 
     pub(crate) fn begin_render_targets(&mut self, pass_id: usize, width: usize, height: usize) {
@@ -467,4 +532,54 @@ impl ZerdeWebGLMessages {
         self.builder.send_f32(color.w);
         self.builder.send_f32(depth);
     }
+
+    pub(crate) fn set_texture_sampler(&mut self, texture_id: usize, sampler: &SamplerDesc) {
+        // Keep in sync with `TEXTURE_FILTER_*` and `TEXTURE_WRAP_*` in `webgl_renderer.ts`.
+        let filter = |filter: TextureFilter| match filter {
+            TextureFilter::Nearest => 0,
+            TextureFilter::Linear => 1,
+        };
+        let wrap = |wrap: TextureWrap| match wrap {
+            TextureWrap::ClampToEdge => 0,
+            TextureWrap::Repeat => 1,
+            TextureWrap::MirroredRepeat => 2,
+        };
+        self.builder.send_u32(13);
+        self.builder.send_u32(texture_id as u32);
+        self.builder.send_u32(filter(sampler.min_filter));
+        self.builder.send_u32(filter(sampler.mag_filter));
+        self.builder.send_u32(wrap(sampler.wrap_u));
+        self.builder.send_u32(wrap(sampler.wrap_v));
+        self.builder.send_u32(if sampler.mipmaps { 1 } else { 0 });
+    }
+
+    pub(crate) fn capture_pass(&mut self, capture_id: PassCaptureId) {
+        self.builder.send_u32(14);
+        self.builder.send_u64(capture_id.0);
+    }
+
+    pub(crate) fn delete_texture(&mut self, texture_id: usize) {
+        self.builder.send_u32(15);
+        self.builder.send_u32(texture_id as u32);
+    }
+
+    pub(crate) fn delete_framebuffer(&mut self, pass_id: usize) {
+        self.builder.send_u32(16);
+        self.builder.send_u32(pass_id as u32);
+    }
+
+    pub(crate) fn delete_array_buffer(&mut self, buffer_id: usize) {
+        self.builder.send_u32(17);
+        self.builder.send_u32(buffer_id as u32);
+    }
+
+    pub(crate) fn delete_index_buffer(&mut self, buffer_id: usize) {
+        self.builder.send_u32(18);
+        self.builder.send_u32(buffer_id as u32);
+    }
+
+    pub(crate) fn delete_vao(&mut self, vao_id: usize) {
+        self.builder.send_u32(19);
+        self.builder.send_u32(vao_id as u32);
+    }
 }
@@ -43,6 +43,8 @@ pub struct View {
     /// Whether this [`View`] is an overlay/popup, which means all [`DrawCall`]s underneath it
     /// will get rendered last.
     pub(crate) is_overlay: bool,
+    /// Should match [`CxView::generation`]; otherwise this [`View`] has been freed.
+    generation: u64,
 
     debugger: Debugger,
 }
@@ -80,12 +82,19 @@ impl View {
         let pass_id = *cx.pass_stack.last().expect("No pass found when begin_view");
 
         let view_id = if let Some(view_id) = self.view_id {
+            self.debug_assert_not_freed(cx);
             view_id
         } else {
             // we need a draw_list_id
-            let view_id = cx.views.len();
+            let view_id = if let Some(view_id) = cx.views_free.pop() {
+                self.generation = cx.views[view_id].generation;
+                view_id
+            } else {
+                self.generation = 0;
+                cx.views.push(CxView::default());
+                cx.views.len() - 1
+            };
             self.view_id = Some(view_id);
-            cx.views.push(CxView::default());
             let cxview = &mut cx.views[view_id];
             cxview.redraw_id = cx.redraw_id;
             cxview.pass_id = pass_id;
@@ -249,6 +258,35 @@ impl View {
             Vec2::default()
         }
     }
+
+    /// Free the [`View`] and its [`DrawCall`]s, releasing their GPU resources before the next paint, and
+    /// allowing the [`CxView`] to be reused. Calling [`View::begin_view`] afterwards creates a new [`CxView`].
+    ///
+    /// Nested [`View`]s are not freed automatically. Make sure to redraw the parent [`View`], since it
+    /// still refers to this [`View`] until then. Using a clone of a freed [`View`] is a bug, which gets
+    /// caught in debug builds.
+    pub fn free(&mut self, cx: &mut Cx) {
+        self.debug_assert_not_freed(cx);
+        if let Some(view_id) = self.view_id.take() {
+            assert!(!cx.view_stack.contains(&view_id), "Can't free a View while drawing it");
+            let generation = cx.views[view_id].generation + 1;
+            let cxview = std::mem::replace(&mut cx.views[view_id], CxView { generation, ..CxView::default() });
+            let cxpass = &mut cx.passes[cxview.pass_id];
+            if cxpass.main_view_id == Some(view_id) {
+                cxpass.main_view_id = None;
+            }
+            cx.released_resources.draw_calls.extend(cxview.draw_calls.into_iter().map(|draw_call| draw_call.platform));
+            cx.released_resources.views.push(cxview.platform);
+            cx.views_free.push(view_id);
+        }
+    }
+
+    /// Panics in debug builds if this is a clone of a [`View`] that has been freed using [`View::free`].
+    fn debug_assert_not_freed(&self, cx: &Cx) {
+        if let Some(view_id) = self.view_id {
+            debug_assert!(cx.views[view_id].generation == self.generation, "Using view {} after it has been freed", view_id);
+        }
+    }
 }
 
 impl Cx {
@@ -279,6 +317,7 @@ impl Cx {
                 assert!(cxview.draw_calls_len >= shader_group_size);
                 for index in cxview.draw_calls_len - shader_group_size..cxview.draw_calls_len {
                     if cxview.draw_calls[index].shader_id == shader_id {
+                        self.batched_draw_calls_since_paint += 1;
                         return &mut cxview.draw_calls[index];
                     }
                 }
@@ -289,6 +328,7 @@ impl Cx {
                 if cxview.draw_calls_len > 0 && !self.debug_flags.disable_draw_call_batching {
                     let dc = &mut cxview.draw_calls[cxview.draw_calls_len - 1];
                     if dc.props.is_batchable() && dc.sub_view_id == 0 && dc.shader_id == shader_id {
+                        self.batched_draw_calls_since_paint += 1;
                         return &mut cxview.draw_calls[cxview.draw_calls_len - 1];
                     }
                 }
@@ -723,8 +763,8 @@ pub struct CxView {
     /// TODO(JP): There is no way to tell if a [`CxView`] is still part of the draw tree,
     /// since merely comparing [`CxView::redraw_id`] and [`Cx::redraw_id`] is not
     /// enough, since those can also be different if the [`CxView`] was simply not
-    /// marked for redrawing recently. It would be good to have some way to clean up
-    /// old [`CxView`]s.
+    /// marked for redrawing recently. Old [`CxView`]s only get cleaned up when
+    /// calling [`View::free`].
     pub(crate) redraw_id: u64,
     /// The [`Pass`]/[`CxPass`] that this is part of.
     ///
@@ -746,9 +786,10 @@ pub struct CxView {
     /// The scroll position that gets snapped to actual pixel values (taking into account
     /// the device pixel ratio; called `dpi_factor` internally).
     pub(crate) snapped_scroll: Vec2,
+    /// Gets incremented when the [`View`] is freed, to catch use of stale [`View`] clones.
+    pub(crate) generation: u64,
 
     /// Platform-specific fields. Currently only used on Windows.
-    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
     pub(crate) platform: CxPlatformView,
 }
 
@@ -766,3 +807,73 @@ impl CxView {
         (Vec2 { x: min_x.max(clip.0.x), y: min_y.max(clip.0.y) }, Vec2 { x: max_x.min(clip.1.x), y: max_y.min(clip.1.y) })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SHADER: Shader = Shader {
+        build_geom: Some(QuadIns::build_geom),
+        code_to_concatenate: &[
+            Cx::STD_SHADER,
+            QuadIns::SHADER,
+            code_fragment!(
+                r#"
+                fn pixel() -> vec4 {
+                    return vec4(1.);
+                }
+                "#
+            ),
+        ],
+        ..Shader::DEFAULT
+    };
+
+    fn draw(cx: &mut Cx, pass: &mut Pass, view: &mut View, instances: &[QuadIns]) {
+        cx.in_redraw_cycle = true;
+        pass.begin_pass(cx, Vec4::default());
+        view.begin_view(cx, LayoutSize::FILL);
+        cx.add_instances(&SHADER, instances);
+        view.end_view(cx);
+        pass.end_pass(cx);
+        cx.in_redraw_cycle = false;
+    }
+
+    #[test]
+    fn reuses_slots_of_freed_views() {
+        let mut cx = Cx::new_test();
+        let mut pass = Pass::default();
+        let mut view = View::default();
+        draw(&mut cx, &mut pass, &mut view, &[QuadIns::default(); 2]);
+        let view_id = view.view_id.unwrap();
+        let generation = view.generation;
+        let views_len = cx.views.len();
+
+        view.free(&mut cx);
+        assert!(view.view_id.is_none());
+        assert_eq!(cx.views_free, vec![view_id]);
+        assert_eq!(cx.passes[pass.pass_id.unwrap()].main_view_id, None);
+        assert_eq!(cx.views[view_id].draw_calls_len, 0);
+        assert_eq!(cx.released_resources.views.len(), 1);
+        assert_eq!(cx.released_resources.draw_calls.len(), 1);
+
+        draw(&mut cx, &mut pass, &mut view, &[QuadIns::default(); 2]);
+        assert_eq!(view.view_id, Some(view_id));
+        assert_eq!(view.generation, generation + 1);
+        assert_eq!(cx.views.len(), views_len);
+        assert_eq!(cx.passes[pass.pass_id.unwrap()].main_view_id, Some(view_id));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "after it has been freed")]
+    fn panics_when_using_freed_view() {
+        let mut cx = Cx::new_test();
+        let mut pass = Pass::default();
+        let mut view = View::default();
+        // No instances, since a `static` `Shader` can only be used with one `Cx` at a time.
+        draw(&mut cx, &mut pass, &mut view, &[]);
+        let mut stale_view = view.clone();
+        view.free(&mut cx);
+        draw(&mut cx, &mut pass, &mut stale_view, &[]);
+    }
+}
@@ -0,0 +1,955 @@
+//! Recording the [`Event`]s that an app receives, and deterministically replaying them.
+//!
+//! This is useful for reproducing bug reports, and for running integration tests against a
+//! headless [`Cx`] (no windows get opened and nothing gets painted when replaying).
+//!
+//! On native targets you can set the `ZAPLIB_RECORD_EVENTS` environment variable to a file path
+//! to record the events of an entire session, and then replay that file by setting
+//! `ZAPLIB_REPLAY_EVENTS` to the same path. See [`Cx::run_event_loop`].
+
+use crate::*;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Error, ErrorKind, Read};
+
+/// Magic bytes at the start of a serialized [`EventRecording`], followed by [`RECORDING_VERSION`].
+const RECORDING_MAGIC: &[u8; 8] = b"ZAPLIBEV";
+/// Bump this whenever the serialization format changes.
+const RECORDING_VERSION: u32 = 1;
+
+// Tags for the different entries and events in the serialized format.
+const ENTRY_WINDOW_GEOM: u8 = 1;
+const ENTRY_EVENT: u8 = 2;
+
+const EVENT_APP_FOCUS: u8 = 1;
+const EVENT_APP_FOCUS_LOST: u8 = 2;
+const EVENT_NEXT_FRAME: u8 = 3;
+const EVENT_DRAW: u8 = 4;
+const EVENT_WINDOW_DRAG_QUERY: u8 = 5;
+const EVENT_WINDOW_CLOSE_REQUESTED: u8 = 6;
+const EVENT_WINDOW_CLOSED: u8 = 7;
+const EVENT_WINDOW_GEOM_CHANGE: u8 = 8;
+const EVENT_WINDOW_RESIZE_LOOP: u8 = 9;
+const EVENT_POINTER_DOWN: u8 = 10;
+const EVENT_POINTER_MOVE: u8 = 11;
+const EVENT_POINTER_HOVER: u8 = 12;
+const EVENT_POINTER_UP: u8 = 13;
+const EVENT_POINTER_SCROLL: u8 = 14;
+const EVENT_TIMER: u8 = 15;
+const EVENT_SIGNAL: u8 = 16;
+const EVENT_COMMAND: u8 = 17;
+const EVENT_KEY_DOWN: u8 = 18;
+const EVENT_KEY_UP: u8 = 19;
+const EVENT_TEXT_INPUT: u8 = 20;
+const EVENT_TEXT_COPY: u8 = 21;
+const EVENT_WEBSOCKET_MESSAGE: u8 = 22;
+const EVENT_FILE_DRAG_BEGIN: u8 = 23;
+const EVENT_FILE_DRAG_UPDATE: u8 = 24;
+const EVENT_FILE_DRAG_CANCEL: u8 = 25;
+
+/// A single entry in an [`EventRecording`].
+#[derive(Clone, Debug)]
+pub enum RecordingEntry {
+    /// The geometry of a window changed (or was first seen) at this point in the recording.
+    WindowGeom { window_id: usize, geom: WindowGeom },
+    /// An [`Event`] was fired at `time` (see [`Cx::last_event_time`]).
+    ///
+    /// [`Event::System`] with [`SystemEvent::Draw`] represents a call to the app's `draw` function.
+    Event { time: f64, event: Event },
+}
+
+/// A recording of [`Event`]s, created using [`Cx::start_event_recording`] and [`Cx::stop_event_recording`],
+/// and replayed using [`Cx::replay_event_recording`].
+///
+/// Only events coming from the platform are recorded; events that [`Cx`] generates itself while handling
+/// those (such as [`Event::KeyFocus`], [`Event::Signal`] from [`Cx::send_signal`], or [`Event::HttpResponse`],
+/// [`Event::TextureLoaded`] and [`Event::PassCaptured`] when handling signals) get generated again when replaying.
+/// Events that can't be serialized, such as [`Event::AppOpenFiles`], are skipped.
+#[derive(Clone, Debug, Default)]
+pub struct EventRecording {
+    pub entries: Vec<RecordingEntry>,
+}
+
+/// State for an active recording; see [`Cx::event_recorder`].
+#[derive(Default)]
+pub(crate) struct EventRecorder {
+    recording: EventRecording,
+    /// The last [`WindowGeom`] that we recorded for each window, so we only record changes.
+    window_geoms: Vec<Option<WindowGeom>>,
+}
+
+impl EventRecording {
+    /// Serialize into a compact binary format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = RecordingWriter { buf: Vec::new() };
+        writer.buf.extend_from_slice(RECORDING_MAGIC);
+        writer.u32(RECORDING_VERSION);
+        for entry in &self.entries {
+            match entry {
+                RecordingEntry::WindowGeom { window_id, geom } => {
+                    writer.u8(ENTRY_WINDOW_GEOM);
+                    writer.usize(*window_id);
+                    writer.window_geom(geom);
+                }
+                RecordingEntry::Event { time, event } => {
+                    // We filter unsupported events when recording, but you can also construct recordings manually.
+                    let entry_start = writer.buf.len();
+                    writer.u8(ENTRY_EVENT);
+                    writer.f64(*time);
+                    if !writer.event(event) {
+                        log!("Skipping event that can't be recorded: {:?}", event);
+                        writer.buf.truncate(entry_start);
+                    }
+                }
+            }
+        }
+        writer.buf
+    }
+
+    /// Parse a recording serialized using [`EventRecording::to_bytes`].
+    pub fn from_bytes(data: &[u8]) -> std::io::Result<Self> {
+        let mut reader = RecordingReader { data, pos: 0 };
+        if reader.take(RECORDING_MAGIC.len())? != RECORDING_MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "Not a Zaplib event recording"));
+        }
+        let version = reader.u32()?;
+        if version != RECORDING_VERSION {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Unsupported event recording version: {}", version)));
+        }
+        let mut entries = Vec::new();
+        while reader.pos < data.len() {
+            entries.push(match reader.u8()? {
+                ENTRY_WINDOW_GEOM => RecordingEntry::WindowGeom { window_id: reader.usize()?, geom: reader.window_geom()? },
+                ENTRY_EVENT => RecordingEntry::Event { time: reader.f64()?, event: reader.event()? },
+                tag => return Err(Error::new(ErrorKind::InvalidData, format!("Unknown event recording entry: {}", tag))),
+            });
+        }
+        Ok(Self { entries })
+    }
+
+    /// Read a recording from anything readable, e.g. a [`UniversalFile`].
+    pub fn read_from(reader: &mut impl Read) -> std::io::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::from_bytes(&data)
+    }
+
+    /// Load a recording from a local file (or a URL on the web target); see [`UniversalFile::open`].
+    pub fn load(path: &str) -> std::io::Result<Self> {
+        Self::read_from(&mut UniversalFile::open(path)?)
+    }
+
+    /// Save the recording to a local file.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+}
+
+/// Whether we can record (and serialize) a given [`Event`].
+fn is_recordable(event: &Event) -> bool {
+    !matches!(
+        event,
+        Event::None
+            | Event::Construct
+            | Event::KeyFocus(_)
+            | Event::KeyFocusLost(_)
+            | Event::HttpResponse(_)
+            | Event::TextureLoaded(_)
+            | Event::PassCaptured(_)
+            | Event::AppOpenFiles(_)
+            | Event::System(SystemEvent::WebRustCall(_))
+            | Event::System(SystemEvent::Paint)
+            | Event::System(SystemEvent::WindowSetHoverCursor(_))
+    ) && !is_cef_event(event)
+}
+
+#[cfg(feature = "cef")]
+fn is_cef_event(event: &Event) -> bool {
+    matches!(event, Event::System(SystemEvent::CefDoMessageLoopWork))
+}
+#[cfg(not(feature = "cef"))]
+fn is_cef_event(_event: &Event) -> bool {
+    false
+}
+
+impl Cx {
+    /// Start recording all [`Event`]s coming from the platform, for later replay using
+    /// [`Cx::replay_event_recording`].
+    ///
+    /// For a deterministic replay you typically want to start recording before the app receives
+    /// any events, e.g. in the `new` function of your app.
+    pub fn start_event_recording(&mut self) {
+        self.event_recorder = Some(EventRecorder::default());
+    }
+
+    /// Stop recording, and return the [`EventRecording`] if we were recording.
+    pub fn stop_event_recording(&mut self) -> Option<EventRecording> {
+        self.event_recorder.take().map(|recorder| recorder.recording)
+    }
+
+    /// Whether we're currently replaying an [`EventRecording`] using [`Cx::replay_event_recording`].
+    pub fn is_replaying_events(&self) -> bool {
+        self.is_replaying_events
+    }
+
+    /// Record an event if we're recording. Gets called for every event coming from the platform, and
+    /// for [`Event::NextFrame`] and [`SystemEvent::Draw`].
+    pub(crate) fn record_event(&mut self, event: &Event) {
+        if let Some(recorder) = &mut self.event_recorder {
+            if !is_recordable(event) {
+                return;
+            }
+            for (window_id, window) in self.windows.iter().enumerate() {
+                if !matches!(window.window_state, CxWindowState::Created) {
+                    continue;
+                }
+                if recorder.window_geoms.len() <= window_id {
+                    recorder.window_geoms.resize(window_id + 1, None);
+                }
+                if recorder.window_geoms[window_id].as_ref() != Some(&window.window_geom) {
+                    recorder.window_geoms[window_id] = Some(window.window_geom.clone());
+                    recorder.recording.entries.push(RecordingEntry::WindowGeom { window_id, geom: window.window_geom.clone() });
+                }
+            }
+            recorder.recording.entries.push(RecordingEntry::Event { time: self.last_event_time, event: event.clone() });
+        }
+    }
+
+    /// Deterministically feed an [`EventRecording`] into `event_handler`, without opening any windows or
+    /// painting anything. [`UniversalInstant::now`] and [`Cx::last_event_time`] follow the recorded times,
+    /// and timers only fire when they fire in the recording.
+    ///
+    /// Like the platform event loops, this first fires [`Event::Construct`]. The `event_handler` gets called
+    /// with [`Event::System`] / [`SystemEvent::Draw`] for calling your app's `draw` function, just like in the
+    /// `main_app!` macro. This makes it possible to use recordings as integration tests:
+    ///
+    /// ```
+    /// let mut cx = Cx::new(std::any::TypeId::of::<App>());
+    /// let mut app = App::new(&mut cx);
+    /// cx.set_finished_app_new();
+    /// cx.replay_event_recording(&EventRecording::load("recording.bin")?, |cx, event| match event {
+    ///     Event::System(SystemEvent::Draw) => app.draw(cx),
+    ///     _ => app.handle(cx, event),
+    /// });
+    /// ```
+    pub fn replay_event_recording<F>(&mut self, recording: &EventRecording, mut event_handler: F)
+    where
+        F: FnMut(&mut Cx, &mut Event),
+    {
+        self.event_handler =
+            Some(&mut event_handler as *const dyn FnMut(&mut Cx, &mut Event) as *mut dyn FnMut(&mut Cx, &mut Event));
+        self.is_replaying_events = true;
+        self.replay_event_recording_core(recording);
+        self.is_replaying_events = false;
+        set_replay_clock(None);
+        self.event_handler = None;
+    }
+
+    fn replay_event_recording_core(&mut self, recording: &EventRecording) {
+        let mut window_geoms: Vec<Option<WindowGeom>> = Vec::new();
+
+        set_replay_clock(Some(0.));
+        self.load_fonts();
+        self.call_event_handler(&mut Event::Construct);
+
+        for entry in &recording.entries {
+            match entry {
+                RecordingEntry::WindowGeom { window_id, geom } => {
+                    if window_geoms.len() <= *window_id {
+                        window_geoms.resize(window_id + 1, None);
+                    }
+                    window_geoms[*window_id] = Some(geom.clone());
+                    if let Some(window) = self.windows.get_mut(*window_id) {
+                        if matches!(window.window_state, CxWindowState::Created) {
+                            window.window_geom = geom.clone();
+                        }
+                    }
+                }
+                RecordingEntry::Event { time, event } => {
+                    self.last_event_time = *time;
+                    set_replay_clock(Some(*time));
+
+                    let mut event = event.clone();
+                    match &mut event {
+                        Event::NextFrame => self.call_next_frame_event(),
+                        Event::System(SystemEvent::Draw) => {
+                            self.call_draw_event();
+                            self.replay_create_windows(&window_geoms);
+                        }
+                        _ => {
+                            self.process_pre_event(&mut event);
+                            match &event {
+                                Event::WindowGeomChange(re) => {
+                                    if let Some(window) = self.windows.get_mut(re.window_id) {
+                                        window.window_geom = re.new_geom.clone();
+                                    }
+                                    if re.old_geom.inner_size != re.new_geom.inner_size {
+                                        self.request_draw();
+                                    }
+                                }
+                                Event::WindowClosed(wc) => {
+                                    if let Some(window) = self.windows.get_mut(wc.window_id) {
+                                        window.window_state = CxWindowState::Closed;
+                                        self.windows_free.push(wc.window_id);
+                                    }
+                                }
+                                _ => {}
+                            }
+                            match &mut event {
+                                Event::Signal(signal_event) => self.call_posted_signals(signal_event),
+                                event => self.call_event_handler(event),
+                            }
+                            self.process_post_event(&mut event);
+                        }
+                    }
+                    self.call_signals();
+                }
+            }
+        }
+    }
+
+    /// Run [`Cx::event_loop`], unless the `ZAPLIB_REPLAY_EVENTS` environment variable is set to the path of an
+    /// [`EventRecording`], in which case we replay that instead (see [`Cx::replay_event_recording`]).
+    ///
+    /// When the `ZAPLIB_RECORD_EVENTS` environment variable is set to a path, we record all events, and save the
+    /// recording to that path when the event loop exits.
+    ///
+    /// This is what the `main_app!` macro uses.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn run_event_loop<F>(&mut self, event_handler: F)
+    where
+        F: FnMut(&mut Cx, &mut Event),
+    {
+        if let Ok(path) = std::env::var("ZAPLIB_REPLAY_EVENTS") {
+            let recording =
+                EventRecording::load(&path).unwrap_or_else(|err| panic!("Could not load event recording {}: {}", path, err));
+            self.replay_event_recording(&recording, event_handler);
+        } else if let Ok(path) = std::env::var("ZAPLIB_RECORD_EVENTS") {
+            self.start_event_recording();
+            self.event_loop(event_handler);
+            if let Some(recording) = self.stop_event_recording() {
+                match recording.save(&path) {
+                    Ok(()) => log!("Saved event recording to {}", path),
+                    Err(err) => log!("Could not save event recording to {}: {}", path, err),
+                }
+            }
+        } else {
+            self.event_loop(event_handler);
+        }
+    }
+
+    /// Emulate the platform creating (and closing) windows, using the recorded [`WindowGeom`]s if available.
+    fn replay_create_windows(&mut self, window_geoms: &[Option<WindowGeom>]) {
+        let default_dpi_factor = self.default_dpi_factor;
+        for (window_id, window) in self.windows.iter_mut().enumerate() {
+            window.window_state = match &window.window_state {
+                CxWindowState::Create { inner_size, position, .. } => {
+                    window.window_geom = match window_geoms.get(window_id) {
+                        Some(Some(geom)) => geom.clone(),
+                        _ => WindowGeom {
+                            dpi_factor: default_dpi_factor,
+                            inner_size: *inner_size,
+                            outer_size: *inner_size,
+                            position: position.unwrap_or_default(),
+                            ..WindowGeom::default()
+                        },
+                    };
+                    CxWindowState::Created
+                }
+                CxWindowState::Close => CxWindowState::Closed,
+                CxWindowState::Created => CxWindowState::Created,
+                CxWindowState::Closed => CxWindowState::Closed,
+            };
+        }
+    }
+}
+
+/// Serializes [`RecordingEntry`]s. Uses little-endian, so recordings can be shared between platforms.
+struct RecordingWriter {
+    buf: Vec<u8>,
+}
+
+impl RecordingWriter {
+    fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+    fn bool(&mut self, v: bool) {
+        self.u8(v as u8);
+    }
+    fn u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+    fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+    fn usize(&mut self, v: usize) {
+        self.u64(v as u64);
+    }
+    fn f32(&mut self, v: f32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+    fn f64(&mut self, v: f64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+    fn bytes(&mut self, v: &[u8]) {
+        self.usize(v.len());
+        self.buf.extend_from_slice(v);
+    }
+    fn string(&mut self, v: &str) {
+        self.bytes(v.as_bytes());
+    }
+    fn vec2(&mut self, v: Vec2) {
+        self.f32(v.x);
+        self.f32(v.y);
+    }
+    fn rect(&mut self, v: Rect) {
+        self.vec2(v.pos);
+        self.vec2(v.size);
+    }
+    fn modifiers(&mut self, v: &KeyModifiers) {
+        self.u8((v.shift as u8) | (v.control as u8) << 1 | (v.alt as u8) << 2 | (v.logo as u8) << 3);
+    }
+    fn input_type(&mut self, v: &PointerInputType) {
+        self.u8(match v {
+            PointerInputType::Mouse => 0,
+            PointerInputType::Touch => 1,
+            PointerInputType::XR => 2,
+        });
+    }
+    fn mouse_button(&mut self, v: MouseButton) {
+        self.u8(match v {
+            MouseButton::Left => 0,
+            MouseButton::Right => 1,
+            MouseButton::Other => 2,
+        });
+    }
+    fn window_geom(&mut self, v: &WindowGeom) {
+        self.f32(v.dpi_factor);
+        self.bool(v.can_fullscreen);
+        self.bool(v.xr_can_present);
+        self.bool(v.xr_is_presenting);
+        self.bool(v.is_fullscreen);
+        self.bool(v.is_topmost);
+        self.vec2(v.position);
+        self.vec2(v.inner_size);
+        self.vec2(v.outer_size);
+    }
+    fn key_event(&mut self, v: &KeyEvent) {
+        self.u8(v.key_code as u8);
+        self.bool(v.is_repeat);
+        self.modifiers(&v.modifiers);
+        self.f64(v.time);
+    }
+
+    /// Returns `false` without writing anything if the event can't be serialized.
+    fn event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::AppFocus => self.u8(EVENT_APP_FOCUS),
+            Event::AppFocusLost => self.u8(EVENT_APP_FOCUS_LOST),
+            Event::NextFrame => self.u8(EVENT_NEXT_FRAME),
+            Event::System(SystemEvent::Draw) => self.u8(EVENT_DRAW),
+            Event::WindowDragQuery(e) => {
+                self.u8(EVENT_WINDOW_DRAG_QUERY);
+                self.usize(e.window_id);
+                self.vec2(e.abs);
+            }
+            Event::WindowCloseRequested(e) => {
+                self.u8(EVENT_WINDOW_CLOSE_REQUESTED);
+                self.usize(e.window_id);
+            }
+            Event::WindowClosed(e) => {
+                self.u8(EVENT_WINDOW_CLOSED);
+                self.usize(e.window_id);
+            }
+            Event::WindowGeomChange(e) => {
+                self.u8(EVENT_WINDOW_GEOM_CHANGE);
+                self.usize(e.window_id);
+                self.window_geom(&e.old_geom);
+                self.window_geom(&e.new_geom);
+            }
+            Event::WindowResizeLoop(e) => {
+                self.u8(EVENT_WINDOW_RESIZE_LOOP);
+                self.bool(e.was_started);
+                self.usize(e.window_id);
+            }
+            Event::PointerDown(e) => {
+                self.u8(EVENT_POINTER_DOWN);
+                self.usize(e.window_id);
+                self.vec2(e.abs);
+                self.vec2(e.rel);
+                self.rect(e.rect);
+                self.usize(e.digit);
+                self.mouse_button(e.button);
+                self.u32(e.tap_count);
+                self.input_type(&e.input_type);
+                self.modifiers(&e.modifiers);
+                self.f64(e.time);
+            }
+            Event::PointerMove(e) => {
+                self.u8(EVENT_POINTER_MOVE);
+                self.usize(e.window_id);
+                self.vec2(e.abs);
+                self.vec2(e.abs_start);
+                self.vec2(e.rel);
+                self.vec2(e.rel_start);
+                self.rect(e.rect);
+                self.bool(e.is_over);
+                self.usize(e.digit);
+                self.input_type(&e.input_type);
+                self.modifiers(&e.modifiers);
+                self.f64(e.time);
+            }
+            Event::PointerHover(e) => {
+                self.u8(EVENT_POINTER_HOVER);
+                self.usize(e.window_id);
+                self.usize(e.digit);
+                self.vec2(e.abs);
+                self.vec2(e.rel);
+                self.rect(e.rect);
+                self.bool(e.any_down);
+                self.u8(match e.hover_state {
+                    HoverState::In => 0,
+                    HoverState::Over => 1,
+                    HoverState::Out => 2,
+                });
+                self.modifiers(&e.modifiers);
+                self.f64(e.time);
+            }
+            Event::PointerUp(e) => {
+                self.u8(EVENT_POINTER_UP);
+                self.usize(e.window_id);
+                self.vec2(e.abs);
+                self.vec2(e.abs_start);
+                self.vec2(e.rel);
+                self.vec2(e.rel_start);
+                self.rect(e.rect);
+                self.usize(e.digit);
+                self.mouse_button(e.button);
+                self.bool(e.is_over);
+                self.input_type(&e.input_type);
+                self.modifiers(&e.modifiers);
+                self.f64(e.time);
+            }
+            Event::PointerScroll(e) => {
+                self.u8(EVENT_POINTER_SCROLL);
+                self.usize(e.window_id);
+                self.usize(e.digit);
+                self.vec2(e.abs);
+                self.vec2(e.rel);
+                self.rect(e.rect);
+                self.vec2(e.scroll);
+                self.input_type(&e.input_type);
+                self.modifiers(&e.modifiers);
+                self.f64(e.time);
+            }
+            Event::Timer(e) => {
+                self.u8(EVENT_TIMER);
+                self.u64(e.timer_id);
+            }
+            Event::Signal(e) => {
+                self.u8(EVENT_SIGNAL);
+                self.usize(e.signals.len());
+                for (signal, statuses) in &e.signals {
+                    self.usize(signal.signal_id);
+                    self.usize(statuses.len());
+                    for status in statuses {
+                        self.u64(status.0);
+                    }
+                }
+            }
+            Event::Command(command_id) => {
+                self.u8(EVENT_COMMAND);
+                self.u64(command_id.0);
+            }
+            Event::KeyDown(e) => {
+                self.u8(EVENT_KEY_DOWN);
+                self.key_event(e);
+            }
+            Event::KeyUp(e) => {
+                self.u8(EVENT_KEY_UP);
+                self.key_event(e);
+            }
+            Event::TextInput(e) => {
+                self.u8(EVENT_TEXT_INPUT);
+                self.string(&e.input);
+                self.bool(e.replace_last);
+                self.bool(e.was_paste);
+            }
+            Event::TextCopy => self.u8(EVENT_TEXT_COPY),
+            Event::WebSocketMessage(e) => {
+                self.u8(EVENT_WEBSOCKET_MESSAGE);
+                self.string(&e.url);
+                match &e.result {
+                    Ok(data) => {
+                        self.bool(true);
+                        self.bytes(data);
+                    }
+                    Err(err) => {
+                        self.bool(false);
+                        self.string(err);
+                    }
+                }
+            }
+            Event::FileDragBegin => self.u8(EVENT_FILE_DRAG_BEGIN),
+            Event::FileDragUpdate(e) => {
+                self.u8(EVENT_FILE_DRAG_UPDATE);
+                self.vec2(e.abs);
+            }
+            Event::FileDragCancel => self.u8(EVENT_FILE_DRAG_CANCEL),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Parses data written by [`RecordingWriter`], returning errors instead of panicking on invalid data.
+struct RecordingReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RecordingReader<'a> {
+    fn take(&mut self, len: usize) -> std::io::Result<&'a [u8]> {
+        if self.data.len() - self.pos < len {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "Event recording ended unexpectedly"));
+        }
+        let slice = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+    fn u8(&mut self) -> std::io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+    fn bool(&mut self) -> std::io::Result<bool> {
+        Ok(self.u8()? != 0)
+    }
+    fn u32(&mut self) -> std::io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn u64(&mut self) -> std::io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    fn usize(&mut self) -> std::io::Result<usize> {
+        Ok(self.u64()? as usize)
+    }
+    fn f32(&mut self) -> std::io::Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn f64(&mut self) -> std::io::Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    fn bytes(&mut self) -> std::io::Result<Vec<u8>> {
+        let len = self.usize()?;
+        Ok(self.take(len)?.to_vec())
+    }
+    fn string(&mut self) -> std::io::Result<String> {
+        String::from_utf8(self.bytes()?).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+    fn vec2(&mut self) -> std::io::Result<Vec2> {
+        Ok(Vec2 { x: self.f32()?, y: self.f32()? })
+    }
+    fn rect(&mut self) -> std::io::Result<Rect> {
+        Ok(Rect { pos: self.vec2()?, size: self.vec2()? })
+    }
+    fn modifiers(&mut self) -> std::io::Result<KeyModifiers> {
+        let v = self.u8()?;
+        Ok(KeyModifiers { shift: v & 1 != 0, control: v & 2 != 0, alt: v & 4 != 0, logo: v & 8 != 0 })
+    }
+    fn input_type(&mut self) -> std::io::Result<PointerInputType> {
+        Ok(match self.u8()? {
+            0 => PointerInputType::Mouse,
+            1 => PointerInputType::Touch,
+            _ => PointerInputType::XR,
+        })
+    }
+    fn mouse_button(&mut self) -> std::io::Result<MouseButton> {
+        Ok(match self.u8()? {
+            0 => MouseButton::Left,
+            1 => MouseButton::Right,
+            _ => MouseButton::Other,
+        })
+    }
+    fn window_geom(&mut self) -> std::io::Result<WindowGeom> {
+        Ok(WindowGeom {
+            dpi_factor: self.f32()?,
+            can_fullscreen: self.bool()?,
+            xr_can_present: self.bool()?,
+            xr_is_presenting: self.bool()?,
+            is_fullscreen: self.bool()?,
+            is_topmost: self.bool()?,
+            position: self.vec2()?,
+            inner_size: self.vec2()?,
+            outer_size: self.vec2()?,
+        })
+    }
+    fn key_event(&mut self) -> std::io::Result<KeyEvent> {
+        let key_code = self.u8()?;
+        if key_code > KeyCode::Unknown as u8 {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Invalid key code: {}", key_code)));
+        }
+        Ok(KeyEvent {
+            // Safe since `KeyCode` is `#[repr(u8)]` with consecutive discriminants, ending in `KeyCode::Unknown`.
+            key_code: unsafe { std::mem::transmute::<u8, KeyCode>(key_code) },
+            is_repeat: self.bool()?,
+            modifiers: self.modifiers()?,
+            time: self.f64()?,
+        })
+    }
+
+    fn event(&mut self) -> std::io::Result<Event> {
+        Ok(match self.u8()? {
+            EVENT_APP_FOCUS => Event::AppFocus,
+            EVENT_APP_FOCUS_LOST => Event::AppFocusLost,
+            EVENT_NEXT_FRAME => Event::NextFrame,
+            EVENT_DRAW => Event::System(SystemEvent::Draw),
+            EVENT_WINDOW_DRAG_QUERY => Event::WindowDragQuery(WindowDragQueryEvent {
+                window_id: self.usize()?,
+                abs: self.vec2()?,
+                response: WindowDragQueryResponse::NoAnswer,
+            }),
+            EVENT_WINDOW_CLOSE_REQUESTED => {
+                Event::WindowCloseRequested(WindowCloseRequestedEvent { window_id: self.usize()?, accept_close: true })
+            }
+            EVENT_WINDOW_CLOSED => Event::WindowClosed(WindowClosedEvent { window_id: self.usize()? }),
+            EVENT_WINDOW_GEOM_CHANGE => Event::WindowGeomChange(WindowGeomChangeEvent {
+                window_id: self.usize()?,
+                old_geom: self.window_geom()?,
+                new_geom: self.window_geom()?,
+            }),
+            EVENT_WINDOW_RESIZE_LOOP => {
+                Event::WindowResizeLoop(WindowResizeLoopEvent { was_started: self.bool()?, window_id: self.usize()? })
+            }
+            EVENT_POINTER_DOWN => Event::PointerDown(PointerDownEvent {
+                window_id: self.usize()?,
+                abs: self.vec2()?,
+                rel: self.vec2()?,
+                rect: self.rect()?,
+                digit: self.usize()?,
+                button: self.mouse_button()?,
+                tap_count: self.u32()?,
+                handled: false,
+                input_type: self.input_type()?,
+                modifiers: self.modifiers()?,
+                time: self.f64()?,
+            }),
+            EVENT_POINTER_MOVE => Event::PointerMove(PointerMoveEvent {
+                window_id: self.usize()?,
+                abs: self.vec2()?,
+                abs_start: self.vec2()?,
+                rel: self.vec2()?,
+                rel_start: self.vec2()?,
+                rect: self.rect()?,
+                is_over: self.bool()?,
+                digit: self.usize()?,
+                input_type: self.input_type()?,
+                modifiers: self.modifiers()?,
+                time: self.f64()?,
+            }),
+            EVENT_POINTER_HOVER => Event::PointerHover(PointerHoverEvent {
+                window_id: self.usize()?,
+                digit: self.usize()?,
+                abs: self.vec2()?,
+                rel: self.vec2()?,
+                rect: self.rect()?,
+                any_down: self.bool()?,
+                handled: false,
+                hover_state: match self.u8()? {
+                    0 => HoverState::In,
+                    1 => HoverState::Over,
+                    _ => HoverState::Out,
+                },
+                modifiers: self.modifiers()?,
+                time: self.f64()?,
+            }),
+            EVENT_POINTER_UP => Event::PointerUp(PointerUpEvent {
+                window_id: self.usize()?,
+                abs: self.vec2()?,
+                abs_start: self.vec2()?,
+                rel: self.vec2()?,
+                rel_start: self.vec2()?,
+                rect: self.rect()?,
+                digit: self.usize()?,
+                button: self.mouse_button()?,
+                is_over: self.bool()?,
+                input_type: self.input_type()?,
+                modifiers: self.modifiers()?,
+                time: self.f64()?,
+            }),
+            EVENT_POINTER_SCROLL => Event::PointerScroll(PointerScrollEvent {
+                window_id: self.usize()?,
+                digit: self.usize()?,
+                abs: self.vec2()?,
+                rel: self.vec2()?,
+                rect: self.rect()?,
+                scroll: self.vec2()?,
+                input_type: self.input_type()?,
+                handled_x: false,
+                handled_y: false,
+                modifiers: self.modifiers()?,
+                time: self.f64()?,
+            }),
+            EVENT_TIMER => Event::Timer(TimerEvent { timer_id: self.u64()? }),
+            EVENT_SIGNAL => {
+                let mut signals = HashMap::new();
+                for _ in 0..self.usize()? {
+                    let signal = Signal { signal_id: self.usize()? };
+                    let mut statuses = BTreeSet::new();
+                    for _ in 0..self.usize()? {
+                        statuses.insert(LocationHash(self.u64()?));
+                    }
+                    signals.insert(signal, statuses);
+                }
+                Event::Signal(SignalEvent { signals })
+            }
+            EVENT_COMMAND => Event::Command(LocationHash(self.u64()?)),
+            EVENT_KEY_DOWN => Event::KeyDown(self.key_event()?),
+            EVENT_KEY_UP => Event::KeyUp(self.key_event()?),
+            EVENT_TEXT_INPUT => {
+                Event::TextInput(TextInputEvent { input: self.string()?, replace_last: self.bool()?, was_paste: self.bool()? })
+            }
+            EVENT_TEXT_COPY => Event::TextCopy,
+            EVENT_WEBSOCKET_MESSAGE => {
+                let url = self.string()?;
+                let result = if self.bool()? { Ok(self.bytes()?) } else { Err(self.string()?) };
+                Event::WebSocketMessage(WebSocketMessageEvent { url, result })
+            }
+            EVENT_FILE_DRAG_BEGIN => Event::FileDragBegin,
+            EVENT_FILE_DRAG_UPDATE => Event::FileDragUpdate(FileDragUpdateEvent { abs: self.vec2()? }),
+            EVENT_FILE_DRAG_CANCEL => Event::FileDragCancel,
+            tag => return Err(Error::new(ErrorKind::InvalidData, format!("Unknown event in event recording: {}", tag))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_decoding::STATUS_TEXTURE_LOADED;
+
+    fn pointer_down(abs: Vec2, time: f64) -> Event {
+        Event::PointerDown(PointerDownEvent { abs, time, ..PointerDownEvent::default() })
+    }
+
+    #[test]
+    fn serializes_and_parses_recordings() {
+        let recording = EventRecording {
+            entries: vec![
+                RecordingEntry::WindowGeom {
+                    window_id: 0,
+                    geom: WindowGeom { dpi_factor: 2., inner_size: vec2(800., 600.), ..WindowGeom::default() },
+                },
+                RecordingEntry::Event { time: 0.5, event: pointer_down(vec2(10., 20.), 0.5) },
+                RecordingEntry::Event {
+                    time: 0.75,
+                    event: Event::KeyDown(KeyEvent {
+                        key_code: KeyCode::KeyZ,
+                        is_repeat: false,
+                        modifiers: KeyModifiers { control: true, ..KeyModifiers::default() },
+                        time: 0.75,
+                    }),
+                },
+                RecordingEntry::Event {
+                    time: 1.,
+                    event: Event::TextInput(TextInputEvent { input: "héllo".to_string(), replace_last: false, was_paste: true }),
+                },
+                RecordingEntry::Event { time: 1.25, event: Event::System(SystemEvent::Draw) },
+            ],
+        };
+
+        let parsed = EventRecording::from_bytes(&recording.to_bytes()).unwrap();
+        assert_eq!(parsed.entries.len(), recording.entries.len());
+        match &parsed.entries[0] {
+            RecordingEntry::WindowGeom { window_id, geom } => {
+                assert_eq!(*window_id, 0);
+                assert_eq!(geom.inner_size, vec2(800., 600.));
+                assert_eq!(geom.dpi_factor, 2.);
+            }
+            entry => panic!("Unexpected entry {:?}", entry),
+        }
+        match &parsed.entries[2] {
+            RecordingEntry::Event { time, event: Event::KeyDown(ke) } => {
+                assert_eq!(*time, 0.75);
+                assert_eq!(ke.key_code, KeyCode::KeyZ);
+                assert!(ke.modifiers.control);
+            }
+            entry => panic!("Unexpected entry {:?}", entry),
+        }
+        match &parsed.entries[3] {
+            RecordingEntry::Event { event: Event::TextInput(te), .. } => assert_eq!(te.input, "héllo"),
+            entry => panic!("Unexpected entry {:?}", entry),
+        }
+
+        assert!(EventRecording::from_bytes(b"not a recording").is_err());
+        let bytes = recording.to_bytes();
+        assert!(EventRecording::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn replays_recordings() {
+        let recording = EventRecording {
+            entries: vec![
+                RecordingEntry::Event { time: 0.1, event: Event::System(SystemEvent::Draw) },
+                RecordingEntry::Event { time: 0.2, event: pointer_down(vec2(1., 1.), 0.2) },
+                RecordingEntry::Event { time: 0.3, event: pointer_down(vec2(1., 1.), 0.3) },
+                RecordingEntry::Event { time: 0.4, event: Event::Timer(TimerEvent { timer_id: 5 }) },
+            ],
+        };
+
+        let mut cx = Cx::new_test();
+        let mut seen = Vec::new();
+        cx.replay_event_recording(&recording, |cx, event| {
+            assert!(cx.is_replaying_events());
+            match event {
+                Event::Construct => seen.push("construct".to_string()),
+                Event::System(SystemEvent::Draw) => seen.push(format!("draw {}", cx.last_event_time)),
+                Event::PointerDown(pe) => seen.push(format!("down {} {}", cx.last_event_time, pe.tap_count)),
+                Event::Timer(te) => {
+                    assert!((UniversalInstant::now() - UniversalInstant::now()).as_micros() == 0);
+                    seen.push(format!("timer {}", te.timer_id))
+                }
+                _ => {}
+            }
+        });
+        assert!(!cx.is_replaying_events());
+        assert_eq!(seen, vec!["construct", "draw 0.1", "down 0.2 1", "down 0.3 2", "timer 5"]);
+    }
+    #[test]
+    fn skips_events_that_cant_be_recorded() {
+        let http_response =
+            Event::HttpResponse(HttpResponseEvent { request_id: HttpRequestId(1), result: Err("Could not connect".to_string()) });
+        let recording = EventRecording {
+            entries: vec![
+                RecordingEntry::Event { time: 0.1, event: http_response },
+                RecordingEntry::Event { time: 0.2, event: Event::KeyFocus(KeyFocusEvent { prev: None, focus: None }) },
+                RecordingEntry::Event { time: 0.3, event: Event::AppFocus },
+            ],
+        };
+
+        let parsed = EventRecording::from_bytes(&recording.to_bytes()).unwrap();
+        assert_eq!(parsed.entries.len(), 1);
+        assert!(matches!(parsed.entries[0], RecordingEntry::Event { time, event: Event::AppFocus } if time == 0.3));
+    }
+
+    #[test]
+    fn replays_posted_signals_through_signal_handling() {
+        let mut cx = Cx::new_test();
+        cx.texture_loads.signal = cx.new_signal();
+        let loaded = universal_thread::spawn(|| Ok(DecodedImage { width: 1, height: 1, data: vec![0xff0000ff] }));
+        while !loaded.is_finished() {
+            std::thread::yield_now();
+        }
+        cx.texture_loads.running.push((TextureLoadId(1), loaded));
+
+        let signals = HashMap::from([(cx.texture_loads.signal, BTreeSet::from([STATUS_TEXTURE_LOADED]))]);
+        let recording =
+            EventRecording { entries: vec![RecordingEntry::Event { time: 0.1, event: Event::Signal(SignalEvent { signals }) }] };
+
+        let mut seen = Vec::new();
+        cx.replay_event_recording(&recording, |_, event| match event {
+            Event::Construct => {}
+            Event::TextureLoaded(tl) => seen.push(format!("texture loaded {}", tl.result.is_ok())),
+            event => panic!("Unexpected event: {:?}", event),
+        });
+        assert_eq!(seen, vec!["texture loaded true"]);
+    }
+}
@@ -273,6 +273,75 @@ pub struct WebSocketMessageEvent {
     pub result: Result<Vec<u8>, String>,
 }
 
+/// Identifies a request made using [`Cx::http_request`], so you can match it with its [`HttpResponseEvent`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HttpRequestId(pub(crate) u64);
+
+/// The response to a request made using [`Cx::http_request`], with the entire body read into memory.
+///
+/// See [`universal_http_stream::Response`] for reading the body as it comes in instead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub status_text: String,
+    /// The final URL, after following redirects.
+    pub url: String,
+    /// See [`universal_http_stream::Response::headers`].
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Get the value of the first header with the given name (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        universal_http_stream::find_header(&self.headers, name)
+    }
+
+    /// Whether the status code is in the 2xx range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// See [`Event::HttpResponse`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpResponseEvent {
+    pub request_id: HttpRequestId,
+    /// Only an error if we didn't get a response at all (e.g. a network error or timeout); responses with
+    /// error status codes (like 404) are returned as a regular [`HttpResponse`].
+    pub result: Result<HttpResponse, String>,
+}
+
+/// Identifies an image load started using [`Texture::load`], so you can match it with its [`TextureLoadedEvent`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureLoadId(pub(crate) u64);
+
+/// See [`Event::TextureLoaded`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureLoadedEvent {
+    pub load_id: TextureLoadId,
+    /// The new texture, or an error if the file couldn't be read or decoded.
+    pub result: Result<TextureHandle, String>,
+}
+
+/// Identifies a capture requested using [`Pass::request_capture`], so you can match it with its [`PassCapturedEvent`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PassCaptureId(pub(crate) u64);
+
+/// See [`Event::PassCaptured`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassCapturedEvent {
+    pub capture_id: PassCaptureId,
+    /// Width of the captured image in physical pixels.
+    pub width: usize,
+    /// Height of the captured image in physical pixels.
+    pub height: usize,
+    /// Pixels in the same format as [`TextureHandle::get_image_mut`], starting with the top row.
+    ///
+    /// Note that the alpha is premultiplied, like everything else that gets rendered.
+    pub data: Vec<u32>,
+}
+
 /// A file that was supplied by a user, as opposed to by the application itself (like font resources
 /// and such).
 #[derive(Clone, Debug)]
@@ -380,6 +449,12 @@ pub enum Event {
     TextCopy,
     /// A websocket message was received.
     WebSocketMessage(WebSocketMessageEvent),
+    /// A request made using [`Cx::http_request`] finished.
+    HttpResponse(HttpResponseEvent),
+    /// An image load started using [`Texture::load`] finished.
+    TextureLoaded(TextureLoadedEvent),
+    /// The pixels of a [`Pass`] got read back after a [`Pass::request_capture`].
+    PassCaptured(PassCapturedEvent),
     /// Intended for platforms that can register a file type to an application.
     /// Fires:
     /// - when application starts with a file
@@ -419,6 +494,19 @@ impl Event {
     /// in [`None`], we always return [`Event::None`].
     #[must_use]
     pub fn hits_pointer(&mut self, cx: &mut Cx, component_id: ComponentId, rect: Option<Rect>) -> Event {
+        self.hits_pointer_core(cx, component_id, rect, false)
+    }
+
+    /// Like [`Event::hits_pointer`], but allows multiple pointers (e.g. fingers) to be captured by
+    /// the same [`ComponentId`] at the same time, instead of ignoring [`Event::PointerDown`] while
+    /// another pointer is down. Use [`PointerDownEvent::digit`] to tell the pointers apart, or feed
+    /// the result into a [`GestureRecognizer`].
+    #[must_use]
+    pub fn hits_pointer_multi_touch(&mut self, cx: &mut Cx, component_id: ComponentId, rect: Option<Rect>) -> Event {
+        self.hits_pointer_core(cx, component_id, rect, true)
+    }
+
+    fn hits_pointer_core(&mut self, cx: &mut Cx, component_id: ComponentId, rect: Option<Rect>, multi_touch: bool) -> Event {
         if let Some(rect) = rect {
             match self {
                 Event::PointerScroll(pe) => {
@@ -497,10 +585,11 @@ impl Event {
                 Event::PointerDown(pe) => {
                     if !pe.handled && rect.contains(pe.abs) {
                         // Scan if any of the pointers already captured this area.
-                        // TODO(JP): We might want to skip this in cases where we want to support multi-touch.
-                        for pointer in &cx.pointers {
-                            if pointer.captured == Some(component_id) {
-                                return Event::None;
+                        if !multi_touch {
+                            for pointer in &cx.pointers {
+                                if pointer.captured == Some(component_id) {
+                                    return Event::None;
+                                }
                             }
                         }
                         cx.pointers[pe.digit].captured = Some(component_id);
@@ -599,7 +688,10 @@ impl Timer {
 }
 
 /// Lowest common denominator keymap between desktop and web.
+///
+/// This is `#[repr(u8)]` so we can serialize it in an [`crate::EventRecording`]; keep [`KeyCode::Unknown`] last.
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[repr(u8)]
 pub enum KeyCode {
     Escape,
 
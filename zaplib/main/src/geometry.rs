@@ -9,7 +9,7 @@ use crate::*;
 /// For example, you can define that a quad has 4 vertices, spanning 2 triangles
 /// (for use in e.g. [`crate::QuadIns`]), so you don't have to manually create
 /// them every time you want to render a quad.
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Geometry {
     vertex_attributes: Vec<f32>,
     triangle_indices: Vec<u32>,
@@ -34,11 +34,13 @@ impl Geometry {
         }
     }
 
-    pub(crate) fn vertices_f32_slice(&self) -> &[f32] {
+    /// All vertex attributes, as passed into [`Geometry::new`] but flattened into [`f32`]s.
+    pub fn vertices_f32_slice(&self) -> &[f32] {
         &self.vertex_attributes
     }
 
-    pub(crate) fn indices_u32_slice(&self) -> &[u32] {
+    /// All triangle indices, as passed into [`Geometry::new`] but flattened.
+    pub fn indices_u32_slice(&self) -> &[u32] {
         &self.triangle_indices
     }
 }
@@ -47,11 +49,8 @@ impl Geometry {
 ///
 /// Cloning a [`GpuGeometry`] doesn't copy the underlying buffer; it just adds a reference count to the existing buffer.
 ///
-/// The corresponding GPU buffer ([`CxGpuGeometry`]) gets marked for reuse when there are no more references to it.
-///
-/// TODO(JP): When creating a big [`GpuGeometry`] and then dropping it, we don't clear out the data until we reuse it
-/// (which might be never). We might want to add a cleanup sweep at the end of the draw cycle. Also, in some renderers
-/// we might not shrink GPU buffers when we reuse a previous [`CxGpuGeometry`].
+/// The corresponding GPU buffer ([`CxGpuGeometry`]) gets marked for reuse when there are no more references to it,
+/// and its data and GPU buffers get released before the next paint.
 #[derive(Clone)]
 pub struct GpuGeometry {
     gpu_geometry_id: usize,
@@ -72,6 +71,7 @@ impl GpuGeometry {
         let gpu_geometry = &mut cx.gpu_geometries[gpu_geometry_id];
         gpu_geometry.geometry = geometry;
         gpu_geometry.dirty = true;
+        gpu_geometry.released = false;
         Self { gpu_geometry_id, usage_count: Rc::clone(&gpu_geometry.usage_count) }
     }
 
@@ -106,6 +106,9 @@ impl GpuGeometry {
 pub(crate) struct CxGpuGeometry {
     pub(crate) geometry: Geometry,
     pub(crate) dirty: bool,
+    /// Whether [`CxGpuGeometry::geometry`] and [`CxGpuGeometry::platform`] have been cleared out after
+    /// this stopped being used. See [`Cx::release_unused_gpu_geometries`].
+    released: bool,
     usage_count: Rc<()>,
     pub(crate) platform: CxPlatformGpuGeometry,
 }
@@ -119,3 +122,17 @@ impl CxGpuGeometry {
         Rc::strong_count(&self.usage_count) - 1
     }
 }
+
+impl Cx {
+    /// Clear out the data and GPU buffers of [`CxGpuGeometry`]s that are no longer used by any [`GpuGeometry`],
+    /// so they don't stick around until the [`CxGpuGeometry`] gets reused (which might be never).
+    pub(crate) fn release_unused_gpu_geometries(&mut self) {
+        for gpu_geometry in &mut self.gpu_geometries {
+            if !gpu_geometry.released && gpu_geometry.usage_count() == 0 {
+                gpu_geometry.released = true;
+                gpu_geometry.geometry = Geometry::default();
+                self.released_resources.gpu_geometries.push(std::mem::take(&mut gpu_geometry.platform));
+            }
+        }
+    }
+}
@@ -0,0 +1,390 @@
+//! Recognizing multi-touch gestures (pinch, rotate, pan, taps, long presses) from pointer events.
+
+use crate::*;
+use std::f32::consts::PI;
+
+/// How long (in seconds) a pointer has to be held down without moving for a [`GestureEvent::LongPress`].
+const LONG_PRESS_DURATION: f64 = 0.5;
+
+/// How far (in logical pixels) a pointer can move before it no longer counts as a tap or long press.
+const TAP_SLOP: f32 = 10.;
+
+/// A tap (or click) with a single pointer. See [`GestureEvent::Tap`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TapGestureEvent {
+    pub abs: Vec2,
+    pub rel: Vec2,
+    /// Number of consecutive taps, e.g. 2 for a double tap. See [`PointerDownEvent::tap_count`].
+    pub tap_count: u32,
+    pub input_type: PointerInputType,
+}
+
+/// A single pointer was held down without moving. See [`GestureEvent::LongPress`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LongPressGestureEvent {
+    pub abs: Vec2,
+    pub rel: Vec2,
+    pub input_type: PointerInputType,
+}
+
+/// Change in the position of two fingers since the last event. See [`GestureEvent::Transform`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransformGestureEvent {
+    /// The point in between the two fingers, relative to the [`Rect`] passed into [`Event::hits_pointer_multi_touch`].
+    pub rel_center: Vec2,
+    /// How much the center moved (two-finger pan).
+    pub pan: Vec2,
+    /// Relative change in the distance between the fingers (pinch); larger than 1 when moving the fingers apart.
+    pub scale: f32,
+    /// Change in the angle between the fingers (rotate), in radians; positive is clockwise on the screen.
+    pub rotation: f32,
+}
+
+/// Result of [`GestureRecognizer::handle`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum GestureEvent {
+    None,
+    /// A single pointer went down and up again without moving much, and without triggering a
+    /// [`GestureEvent::LongPress`].
+    Tap(TapGestureEvent),
+    /// A single pointer has been held down for a while without moving much.
+    LongPress(LongPressGestureEvent),
+    /// A second finger went down, so a [`GestureEvent::Transform`] gesture started. It's a good idea to
+    /// cancel any single-pointer interactions (like dragging) when receiving this.
+    TransformStart,
+    /// Two fingers moved; contains pinch, rotation, and two-finger pan at the same time.
+    Transform(TransformGestureEvent),
+    /// One of the two fingers of a [`GestureEvent::Transform`] gesture was lifted.
+    TransformEnd,
+}
+
+#[derive(Clone, Debug)]
+struct GestureTouch {
+    digit: usize,
+    abs: Vec2,
+    rel: Vec2,
+    abs_start: Vec2,
+    tap_count: u32,
+    input_type: PointerInputType,
+}
+
+/// Turns pointer events for a single [`ComponentId`] into [`GestureEvent`]s.
+///
+/// Pass every [`Event`] through [`Event::hits_pointer_multi_touch`] and feed the result into
+/// [`GestureRecognizer::handle`], including [`Event::None`] (which is what other events such as
+/// [`Event::Timer`] turn into), since we use a timer for detecting long presses:
+///
+/// ```
+/// let hit_event = event.hits_pointer_multi_touch(cx, self.component_id, self.area.get_rect_for_first_instance(cx));
+/// match self.gestures.handle(cx, &hit_event) {
+///     GestureEvent::Transform(te) => self.zoom(te.rel_center, te.scale),
+///     _ => {}
+/// }
+/// match hit_event {
+///     // Handle regular pointer events.
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct GestureRecognizer {
+    /// Pointers that are currently down, in the order in which they went down.
+    touches: Vec<GestureTouch>,
+    /// The [`Cx::last_event_time`] at which the first pointer went down.
+    down_time: f64,
+    /// Whether the current pointer interaction can still become a [`GestureEvent::Tap`] or [`GestureEvent::LongPress`].
+    tap_possible: bool,
+    long_press_timer: Timer,
+    /// Whether we're currently in a [`GestureEvent::Transform`] gesture.
+    transforming: bool,
+    /// Whether more than one pointer went down since all pointers were last up.
+    was_multi_touch: bool,
+    last_center: Vec2,
+    last_distance: f32,
+    last_angle: f32,
+}
+
+impl GestureRecognizer {
+    /// Process the result of [`Event::hits_pointer_multi_touch`]; see [`GestureRecognizer`].
+    pub fn handle(&mut self, cx: &mut Cx, hit_event: &Event) -> GestureEvent {
+        match hit_event {
+            Event::PointerDown(pe) => {
+                // E.g. pressing another mouse button while dragging; that's still the same pointer.
+                if self.touches.iter().any(|touch| touch.digit == pe.digit) {
+                    return GestureEvent::None;
+                }
+                self.touches.push(GestureTouch {
+                    digit: pe.digit,
+                    abs: pe.abs,
+                    rel: pe.rel,
+                    abs_start: pe.abs,
+                    tap_count: pe.tap_count,
+                    input_type: pe.input_type.clone(),
+                });
+                if self.touches.len() == 1 {
+                    self.down_time = cx.last_event_time;
+                    self.tap_possible = true;
+                    self.was_multi_touch = false;
+                    self.long_press_timer = cx.start_timer(LONG_PRESS_DURATION, false);
+                } else {
+                    self.cancel_tap(cx);
+                    self.was_multi_touch = true;
+                    if self.touches.len() == 2 {
+                        self.transforming = true;
+                        self.update_transform_state();
+                        return GestureEvent::TransformStart;
+                    }
+                }
+            }
+            Event::PointerMove(pe) => {
+                if let Some(touch) = self.touches.iter_mut().find(|touch| touch.digit == pe.digit) {
+                    touch.abs = pe.abs;
+                    touch.rel = pe.rel;
+                    if touch.abs.distance(&touch.abs_start) > TAP_SLOP && self.tap_possible {
+                        self.tap_possible = false;
+                        cx.stop_timer(&mut self.long_press_timer);
+                    }
+                }
+                if self.transforming && self.touches.iter().take(2).any(|touch| touch.digit == pe.digit) {
+                    let (last_center, last_distance, last_angle) = (self.last_center, self.last_distance, self.last_angle);
+                    self.update_transform_state();
+                    let mut rotation = self.last_angle - last_angle;
+                    if rotation > PI {
+                        rotation -= 2. * PI;
+                    } else if rotation < -PI {
+                        rotation += 2. * PI;
+                    }
+                    return GestureEvent::Transform(TransformGestureEvent {
+                        rel_center: self.last_center,
+                        pan: self.last_center - last_center,
+                        scale: if last_distance > 0. { self.last_distance / last_distance } else { 1. },
+                        rotation,
+                    });
+                }
+            }
+            Event::PointerUp(pe) => {
+                let tap_count = self.touches.iter().find(|touch| touch.digit == pe.digit).map_or(1, |touch| touch.tap_count);
+                self.touches.retain(|touch| touch.digit != pe.digit);
+                if self.transforming {
+                    if self.touches.len() < 2 {
+                        self.transforming = false;
+                        return GestureEvent::TransformEnd;
+                    }
+                    // The transform might now be between a different pair of pointers, so start measuring from
+                    // here to avoid a jump.
+                    self.update_transform_state();
+                }
+                if self.touches.is_empty() && self.tap_possible && !self.was_multi_touch {
+                    self.cancel_tap(cx);
+                    if pe.is_over {
+                        return GestureEvent::Tap(TapGestureEvent {
+                            abs: pe.abs,
+                            rel: pe.rel,
+                            tap_count,
+                            input_type: pe.input_type.clone(),
+                        });
+                    }
+                }
+            }
+            // Other events (such as our timer firing) turn into `Event::None` when passed through `hits_pointer`.
+            Event::None
+                if self.tap_possible && self.touches.len() == 1 && cx.last_event_time - self.down_time >= LONG_PRESS_DURATION =>
+            {
+                self.cancel_tap(cx);
+                let touch = &self.touches[0];
+                return GestureEvent::LongPress(LongPressGestureEvent {
+                    abs: touch.abs,
+                    rel: touch.rel,
+                    input_type: touch.input_type.clone(),
+                });
+            }
+            _ => {}
+        }
+        GestureEvent::None
+    }
+
+    /// Whether we're currently in a [`GestureEvent::Transform`] gesture (in between [`GestureEvent::TransformStart`]
+    /// and [`GestureEvent::TransformEnd`]).
+    pub fn is_transforming(&self) -> bool {
+        self.transforming
+    }
+
+    fn cancel_tap(&mut self, cx: &mut Cx) {
+        self.tap_possible = false;
+        cx.stop_timer(&mut self.long_press_timer);
+    }
+
+    /// Set the center, distance, and angle of the first two touches.
+    fn update_transform_state(&mut self) {
+        let (a, b) = (&self.touches[0], &self.touches[1]);
+        let delta = b.abs - a.abs;
+        self.last_center = (a.rel + b.rel) / 2.;
+        self.last_distance = delta.length();
+        self.last_angle = delta.y.atan2(delta.x);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn down(gestures: &mut GestureRecognizer, cx: &mut Cx, digit: usize, abs: Vec2, time: f64) -> GestureEvent {
+        cx.last_event_time = time;
+        let event = Event::PointerDown(PointerDownEvent {
+            abs,
+            rel: abs,
+            digit,
+            tap_count: 1,
+            input_type: PointerInputType::Touch,
+            time,
+            ..PointerDownEvent::default()
+        });
+        gestures.handle(cx, &event)
+    }
+
+    fn move_to(gestures: &mut GestureRecognizer, cx: &mut Cx, digit: usize, abs: Vec2, time: f64) -> GestureEvent {
+        cx.last_event_time = time;
+        let event = Event::PointerMove(PointerMoveEvent {
+            abs,
+            rel: abs,
+            is_over: true,
+            digit,
+            input_type: PointerInputType::Touch,
+            time,
+            ..PointerMoveEvent::default()
+        });
+        gestures.handle(cx, &event)
+    }
+
+    fn up(gestures: &mut GestureRecognizer, cx: &mut Cx, digit: usize, abs: Vec2, time: f64) -> GestureEvent {
+        cx.last_event_time = time;
+        let event = Event::PointerUp(PointerUpEvent {
+            abs,
+            rel: abs,
+            digit,
+            is_over: true,
+            input_type: PointerInputType::Touch,
+            time,
+            ..PointerUpEvent::default()
+        });
+        gestures.handle(cx, &event)
+    }
+
+    /// Handle what timer events turn into when passed through [`Event::hits_pointer_multi_touch`].
+    fn timer(gestures: &mut GestureRecognizer, cx: &mut Cx, time: f64) -> GestureEvent {
+        cx.last_event_time = time;
+        gestures.handle(cx, &Event::None)
+    }
+
+    #[test]
+    fn recognizes_tap() {
+        let mut cx = Cx::new_test();
+        let mut gestures = GestureRecognizer::default();
+        assert_eq!(down(&mut gestures, &mut cx, 0, vec2(10., 10.), 1.), GestureEvent::None);
+        assert_eq!(move_to(&mut gestures, &mut cx, 0, vec2(13., 14.), 1.1), GestureEvent::None);
+        assert_eq!(
+            up(&mut gestures, &mut cx, 0, vec2(13., 14.), 1.2),
+            GestureEvent::Tap(TapGestureEvent {
+                abs: vec2(13., 14.),
+                rel: vec2(13., 14.),
+                tap_count: 1,
+                input_type: PointerInputType::Touch
+            })
+        );
+    }
+
+    #[test]
+    fn movement_cancels_tap_and_long_press() {
+        let mut cx = Cx::new_test();
+        let mut gestures = GestureRecognizer::default();
+        down(&mut gestures, &mut cx, 0, vec2(10., 10.), 1.);
+        assert_eq!(move_to(&mut gestures, &mut cx, 0, vec2(30., 10.), 1.1), GestureEvent::None);
+        // Moving back doesn't make it a tap again.
+        move_to(&mut gestures, &mut cx, 0, vec2(10., 10.), 1.2);
+        assert_eq!(timer(&mut gestures, &mut cx, 2.), GestureEvent::None);
+        assert_eq!(up(&mut gestures, &mut cx, 0, vec2(10., 10.), 2.1), GestureEvent::None);
+    }
+
+    #[test]
+    fn recognizes_long_press_after_duration() {
+        let mut cx = Cx::new_test();
+        let mut gestures = GestureRecognizer::default();
+        down(&mut gestures, &mut cx, 0, vec2(10., 10.), 1.);
+        assert_eq!(timer(&mut gestures, &mut cx, 1. + LONG_PRESS_DURATION / 2.), GestureEvent::None);
+        assert_eq!(
+            timer(&mut gestures, &mut cx, 1. + LONG_PRESS_DURATION),
+            GestureEvent::LongPress(LongPressGestureEvent {
+                abs: vec2(10., 10.),
+                rel: vec2(10., 10.),
+                input_type: PointerInputType::Touch
+            })
+        );
+        // Only fires once, and lifting the pointer afterwards is not a tap.
+        assert_eq!(timer(&mut gestures, &mut cx, 2. + LONG_PRESS_DURATION), GestureEvent::None);
+        assert_eq!(up(&mut gestures, &mut cx, 0, vec2(10., 10.), 3.), GestureEvent::None);
+    }
+
+    #[test]
+    fn recognizes_two_finger_transform() {
+        let mut cx = Cx::new_test();
+        let mut gestures = GestureRecognizer::default();
+        down(&mut gestures, &mut cx, 0, vec2(0., 0.), 1.);
+        assert_eq!(down(&mut gestures, &mut cx, 1, vec2(10., 0.), 1.), GestureEvent::TransformStart);
+        assert!(gestures.is_transforming());
+
+        // Move the second finger from (10, 0) to (0, 20): twice the distance, rotated a quarter turn clockwise.
+        match move_to(&mut gestures, &mut cx, 1, vec2(0., 20.), 1.1) {
+            GestureEvent::Transform(te) => {
+                assert_eq!(te.rel_center, vec2(0., 10.));
+                assert_eq!(te.pan, vec2(-5., 10.));
+                assert!((te.scale - 2.).abs() < 1e-5);
+                assert!((te.rotation - PI / 2.).abs() < 1e-5);
+            }
+            other => panic!("Expected a transform, got {:?}", other),
+        }
+
+        // Rotating past the negative x-axis still gives the shortest rotation.
+        move_to(&mut gestures, &mut cx, 1, vec2(-20., 1.), 1.2);
+        match move_to(&mut gestures, &mut cx, 1, vec2(-20., -1.), 1.3) {
+            GestureEvent::Transform(te) => assert!(te.rotation.abs() < 0.2),
+            other => panic!("Expected a transform, got {:?}", other),
+        }
+
+        assert_eq!(up(&mut gestures, &mut cx, 1, vec2(-20., -1.), 1.4), GestureEvent::TransformEnd);
+        assert!(!gestures.is_transforming());
+        // Lifting the last finger after a multi-touch gesture is not a tap.
+        assert_eq!(up(&mut gestures, &mut cx, 0, vec2(0., 0.), 1.5), GestureEvent::None);
+    }
+
+    #[test]
+    fn ignores_pointer_down_for_pointers_that_are_already_down() {
+        let mut cx = Cx::new_test();
+        let mut gestures = GestureRecognizer::default();
+        down(&mut gestures, &mut cx, 0, vec2(0., 0.), 1.);
+        move_to(&mut gestures, &mut cx, 0, vec2(30., 0.), 1.1);
+        // E.g. pressing the right mouse button while dragging with the left one.
+        assert_eq!(down(&mut gestures, &mut cx, 0, vec2(30., 0.), 1.2), GestureEvent::None);
+        assert!(!gestures.is_transforming());
+        assert_eq!(move_to(&mut gestures, &mut cx, 0, vec2(40., 0.), 1.3), GestureEvent::None);
+        assert_eq!(up(&mut gestures, &mut cx, 0, vec2(40., 0.), 1.4), GestureEvent::None);
+    }
+
+    #[test]
+    fn keeps_transforming_without_jumps_when_lifting_one_of_three_fingers() {
+        let mut cx = Cx::new_test();
+        let mut gestures = GestureRecognizer::default();
+        down(&mut gestures, &mut cx, 0, vec2(0., 0.), 1.);
+        assert_eq!(down(&mut gestures, &mut cx, 1, vec2(10., 0.), 1.), GestureEvent::TransformStart);
+        assert_eq!(down(&mut gestures, &mut cx, 2, vec2(100., 100.), 1.), GestureEvent::None);
+
+        // Now the transform is between the fingers at (10, 0) and (100, 100).
+        assert_eq!(up(&mut gestures, &mut cx, 0, vec2(0., 0.), 1.1), GestureEvent::None);
+        assert!(gestures.is_transforming());
+        match move_to(&mut gestures, &mut cx, 1, vec2(11., 0.), 1.2) {
+            GestureEvent::Transform(te) => {
+                assert_eq!(te.pan, vec2(0.5, 0.));
+                assert!((te.scale - 1.).abs() < 0.01);
+                assert!(te.rotation.abs() < 0.01);
+            }
+            other => panic!("Expected a transform, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//! Decoding PNG and JPEG images into [`Texture`]s.
+//!
+//! Use [`Texture::from_image_bytes`] to decode an image right away, or [`Texture::load`] to read and decode
+//! a [`UniversalFile`] on a separate thread, which fires an [`Event::TextureLoaded`] when done.
+
+use crate::universal_file::UniversalFile;
+use crate::*;
+use std::io::{Cursor, Read};
+
+pub(crate) const STATUS_TEXTURE_LOADED: StatusId = location_hash!();
+
+/// An image decoded by [`decode_image`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedImage {
+    pub width: usize,
+    pub height: usize,
+    /// Pixels in the same format as [`TextureHandle::get_image_mut`], with the alpha premultiplied.
+    pub data: Vec<u32>,
+}
+
+/// Decode a PNG or JPEG image, detected using the first bytes of the file.
+///
+/// The image gets rotated and flipped according to its EXIF orientation (for JPEGs), and the alpha gets
+/// premultiplied, which is what our default blend mode expects.
+pub fn decode_image(bytes: &[u8]) -> Result<DecodedImage, String> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        decode_png(bytes)
+    } else if bytes.starts_with(&[0xff, 0xd8]) {
+        decode_jpeg(bytes)
+    } else {
+        Err("Unsupported image format; only PNG and JPEG are supported".to_string())
+    }
+}
+
+fn pack_rgba(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    let premultiply = |channel: u8| ((channel as u32 * a as u32 + 127) / 255) as u8;
+    u32::from_le_bytes([premultiply(r), premultiply(g), premultiply(b), a])
+}
+
+fn decode_png(bytes: &[u8]) -> Result<DecodedImage, String> {
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| format!("Could not decode PNG: {}", err))?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|err| format!("Could not decode PNG: {}", err))?;
+    let buf = &buf[..info.buffer_size()];
+
+    let data = match info.color_type {
+        png::ColorType::Grayscale => buf.iter().map(|&l| pack_rgba(l, l, l, 255)).collect(),
+        png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).map(|p| pack_rgba(p[0], p[0], p[0], p[1])).collect(),
+        png::ColorType::Rgb => buf.chunks_exact(3).map(|p| pack_rgba(p[0], p[1], p[2], 255)).collect(),
+        png::ColorType::Rgba => buf.chunks_exact(4).map(|p| pack_rgba(p[0], p[1], p[2], p[3])).collect(),
+        png::ColorType::Indexed => return Err("Could not decode PNG: palette was not expanded".to_string()),
+    };
+    Ok(DecodedImage { width: info.width as usize, height: info.height as usize, data })
+}
+
+fn decode_jpeg(bytes: &[u8]) -> Result<DecodedImage, String> {
+    let mut decoder = jpeg_decoder::Decoder::new(Cursor::new(bytes));
+    let buf = decoder.decode().map_err(|err| format!("Could not decode JPEG: {}", err))?;
+    let info = decoder.info().ok_or_else(|| "Could not decode JPEG: missing image info".to_string())?;
+
+    let data = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => buf.iter().map(|&l| pack_rgba(l, l, l, 255)).collect(),
+        jpeg_decoder::PixelFormat::RGB24 => buf.chunks_exact(3).map(|p| pack_rgba(p[0], p[1], p[2], 255)).collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => buf
+            .chunks_exact(4)
+            .map(|p| {
+                let k = 255 - p[3] as u32;
+                let channel = |c: u8| ((255 - c as u32) * k / 255) as u8;
+                pack_rgba(channel(p[0]), channel(p[1]), channel(p[2]), 255)
+            })
+            .collect(),
+        jpeg_decoder::PixelFormat::L16 => return Err("Could not decode JPEG: 16-bit images are not supported".to_string()),
+    };
+    let image = DecodedImage { width: info.width as usize, height: info.height as usize, data };
+    let orientation = decoder.exif_data().and_then(exif_orientation).unwrap_or(1);
+    Ok(apply_orientation(image, orientation))
+}
+
+/// Get the orientation tag from EXIF data (starting at the TIFF header), as a number from 1 to 8.
+fn exif_orientation(exif: &[u8]) -> Option<u16> {
+    let big_endian = match exif.get(0..4)? {
+        b"MM\0*" => true,
+        b"II*\0" => false,
+        _ => return None,
+    };
+    let read_u16 = |offset: usize| -> Option<u16> {
+        let bytes = [*exif.get(offset)?, *exif.get(offset + 1)?];
+        Some(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    };
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes = [*exif.get(offset)?, *exif.get(offset + 1)?, *exif.get(offset + 2)?, *exif.get(offset + 3)?];
+        Some(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    };
+
+    let ifd_offset = read_u32(4)? as usize;
+    let entry_count = read_u16(ifd_offset)? as usize;
+    (0..entry_count).map(|index| ifd_offset + 2 + index * 12).find_map(|entry_offset| {
+        const ORIENTATION_TAG: u16 = 0x0112;
+        if read_u16(entry_offset)? == ORIENTATION_TAG {
+            read_u16(entry_offset + 8).filter(|orientation| (1..=8).contains(orientation))
+        } else {
+            None
+        }
+    })
+}
+
+/// Rotate and flip an image so it's displayed upright, given an EXIF orientation from 1 to 8.
+fn apply_orientation(image: DecodedImage, orientation: u16) -> DecodedImage {
+    if orientation <= 1 || orientation > 8 {
+        return image;
+    }
+    let DecodedImage { width, height, data } = image;
+    // Orientations 5 to 8 swap width and height.
+    let transposed = orientation >= 5;
+    let (new_width, new_height) = if transposed { (height, width) } else { (width, height) };
+    let mut new_data = vec![0; data.len()];
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let (source_x, source_y) = match orientation {
+                2 => (width - 1 - x, y),
+                3 => (width - 1 - x, height - 1 - y),
+                4 => (x, height - 1 - y),
+                5 => (y, x),
+                6 => (y, height - 1 - x),
+                7 => (width - 1 - y, height - 1 - x),
+                8 => (width - 1 - y, x),
+                _ => unreachable!(),
+            };
+            new_data[y * new_width + x] = data[source_y * width + source_x];
+        }
+    }
+    DecodedImage { width: new_width, height: new_height, data: new_data }
+}
+
+#[derive(Default)]
+pub(crate) struct CxTextureLoads {
+    /// Gets posted when any of the loads finishes; created on the first load.
+    pub(crate) signal: Signal,
+    last_load_id: u64,
+    pub(crate) running: Vec<(TextureLoadId, universal_thread::JoinHandle<Result<DecodedImage, String>>)>,
+}
+
+impl Texture {
+    /// Create a texture from a [`DecodedImage`].
+    pub fn from_decoded_image(cx: &mut Cx, image: DecodedImage) -> Self {
+        let mut texture = Texture::default();
+        let handle = texture.get_with_dimensions(cx, image.width, image.height);
+        let cx_texture = &mut cx.textures[handle.texture_id as usize];
+        cx_texture.image_u32 = image.data;
+        cx_texture.update_image = true;
+        texture
+    }
+
+    /// Decode a PNG or JPEG image into a new texture; see [`decode_image`].
+    pub fn from_image_bytes(cx: &mut Cx, bytes: &[u8]) -> Result<Self, String> {
+        Ok(Self::from_decoded_image(cx, decode_image(bytes)?))
+    }
+
+    /// Read and decode a PNG or JPEG image on a separate thread. When done, you get an
+    /// [`Event::TextureLoaded`] with the returned [`TextureLoadId`], containing a [`TextureHandle`] that is
+    /// ready to be drawn.
+    ///
+    /// ```
+    /// self.load_id = Some(Texture::load(cx, UniversalFile::open("image.jpg")?));
+    /// ```
+    ///
+    /// And then in `handle`:
+    ///
+    /// ```
+    /// if let Event::TextureLoaded(TextureLoadedEvent { load_id, result }) = event {
+    ///     if Some(*load_id) == self.load_id {
+    ///         match result {
+    ///             Ok(texture_handle) => self.texture_handle = Some(*texture_handle),
+    ///             Err(err) => log!("Could not load image: {}", err),
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn load(cx: &mut Cx, mut file: UniversalFile) -> TextureLoadId {
+        if cx.texture_loads.signal == Signal::default() {
+            cx.texture_loads.signal = cx.new_signal();
+        }
+        cx.texture_loads.last_load_id += 1;
+        let load_id = TextureLoadId(cx.texture_loads.last_load_id);
+
+        let join_handle = universal_thread::spawn(move || {
+            let mut bytes = vec![];
+            file.read_to_end(&mut bytes).map_err(|err| format!("Could not read image: {}", err))?;
+            decode_image(&bytes)
+        });
+        join_handle.post_signal_when_finished(cx.texture_loads.signal, STATUS_TEXTURE_LOADED);
+        cx.texture_loads.running.push((load_id, join_handle));
+        load_id
+    }
+}
+
+impl Cx {
+    /// Called when handling signals; fires [`Event::TextureLoaded`] for loads that have finished, and
+    /// returns whether `signal` was meant for us.
+    pub(crate) fn call_texture_loaded_events(&mut self, signal: &Signal) -> bool {
+        if *signal != self.texture_loads.signal || self.texture_loads.signal == Signal::default() {
+            return false;
+        }
+        let mut finished = vec![];
+        self.texture_loads.running.retain(|(load_id, join_handle)| match join_handle.try_join() {
+            Some(result) => {
+                finished.push((*load_id, result));
+                false
+            }
+            None => true,
+        });
+        for (load_id, result) in finished {
+            let result = match result {
+                Ok(Ok(image)) => Ok(Texture::from_decoded_image(self, image).unwrap_texture_handle()),
+                Ok(Err(err)) => Err(err),
+                Err(_) => Err("Panicked while decoding image".to_string()),
+            };
+            self.call_event_handler(&mut Event::TextureLoaded(TextureLoadedEvent { load_id, result }));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeSet, HashMap};
+
+    #[test]
+    fn applies_exif_orientation() {
+        // 2x1 image: [a, b].
+        let image = DecodedImage { width: 2, height: 1, data: vec![1, 2] };
+        assert_eq!(apply_orientation(image.clone(), 1), image);
+        assert_eq!(apply_orientation(image.clone(), 2).data, vec![2, 1]);
+        assert_eq!(apply_orientation(image.clone(), 3).data, vec![2, 1]);
+        // Rotated 90 degrees clockwise: a on top, b below.
+        let rotated = apply_orientation(image.clone(), 6);
+        assert_eq!((rotated.width, rotated.height, rotated.data), (1, 2, vec![1, 2]));
+        // Rotated 90 degrees counter-clockwise: b on top, a below.
+        assert_eq!(apply_orientation(image, 8).data, vec![2, 1]);
+
+        let mut exif = b"II*\0\x08\0\0\0\x01\0".to_vec();
+        exif.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0]);
+        assert_eq!(exif_orientation(&exif), Some(6));
+        assert_eq!(exif_orientation(b"MM\0*\0\0\0\x08\0\0"), None);
+    }
+
+    #[test]
+    fn premultiplies_alpha() {
+        assert_eq!(pack_rgba(255, 128, 0, 255).to_le_bytes(), [255, 128, 0, 255]);
+        assert_eq!(pack_rgba(255, 128, 0, 128).to_le_bytes(), [128, 64, 0, 128]);
+        assert_eq!(pack_rgba(255, 255, 255, 0), 0);
+        assert!(decode_image(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn fires_texture_loaded_events_for_posted_signals() {
+        let mut cx = Cx::new_test();
+        cx.texture_loads.signal = cx.new_signal();
+        let loaded = universal_thread::spawn(|| Ok(DecodedImage { width: 1, height: 1, data: vec![0xff0000ff] }));
+        let failed = universal_thread::spawn(|| Err("Could not decode image".to_string()));
+        while !loaded.is_finished() || !failed.is_finished() {
+            std::thread::yield_now();
+        }
+        cx.texture_loads.running.push((TextureLoadId(1), loaded));
+        cx.texture_loads.running.push((TextureLoadId(2), failed));
+
+        // What the platform event loops receive when the decoding threads call `Cx::post_signal`.
+        let signals = HashMap::from([(cx.texture_loads.signal, BTreeSet::from([STATUS_TEXTURE_LOADED]))]);
+        let events = cx.collect_events(|cx| cx.call_posted_signals(&SignalEvent { signals }));
+
+        assert_eq!(events.len(), 2);
+        match &events[0] {
+            Event::TextureLoaded(TextureLoadedEvent { load_id, result: Ok(texture_handle) }) => {
+                assert_eq!(*load_id, TextureLoadId(1));
+                assert_eq!(cx.textures[texture_handle.texture_id as usize].image_u32, vec![0xff0000ff]);
+            }
+            event => panic!("Unexpected event: {:?}", event),
+        }
+        match &events[1] {
+            Event::TextureLoaded(TextureLoadedEvent { load_id, result: Err(err) }) => {
+                assert_eq!(*load_id, TextureLoadId(2));
+                assert_eq!(err, "Could not decode image");
+            }
+            event => panic!("Unexpected event: {:?}", event),
+        }
+        assert!(cx.texture_loads.running.is_empty());
+    }
+}
@@ -183,7 +183,10 @@ impl Cx {
     /// during its lifetime. The parent [`CxLayoutBox`] will be made to walk this [`Rect`].
     pub(crate) fn end_typed_box(&mut self, box_type: CxBoxType) -> Rect {
         self.assert_last_box_type_matches(box_type);
-        self.end_last_box_unchecked()
+        self.profiler.begin_zone("layout");
+        let rect = self.end_last_box_unchecked();
+        self.profiler.end_zone();
+        rect
     }
 
     pub(crate) fn assert_last_box_type_matches(&self, box_type: CxBoxType) {
@@ -80,6 +80,7 @@ mod cx_web;
 #[cfg(any(target_arch = "wasm32", feature = "cef"))]
 mod zerde;
 
+mod accessibility;
 mod animator;
 mod area;
 pub mod byte_extract;
@@ -91,17 +92,23 @@ mod cx;
 pub mod debug_log;
 mod debugger;
 mod draw_tree;
+mod event_recording;
 mod events;
 mod fonts;
 mod geometry;
+mod gestures;
 mod hash;
+mod image_decoding;
 mod layout;
 mod layout_api;
 mod layout_internal;
 mod param;
 mod pass;
+mod post_processing;
+mod precompiled_shader;
 mod profile;
 mod read_seek;
+mod render_stats;
 mod shader;
 mod texture;
 pub mod universal_file;
@@ -110,6 +117,7 @@ mod universal_instant;
 pub mod universal_rand;
 pub mod universal_thread;
 mod window;
+mod worker_pool;
 
 mod cube_ins;
 mod image_ins;
@@ -126,9 +134,12 @@ pub use cube_ins::*;
 pub use cursor::*;
 pub use cx::*;
 pub use debugger::*;
+pub use event_recording::*;
 pub use events::*;
+pub use image_decoding::*;
 pub use image_ins::*;
 pub use param::*;
+pub use precompiled_shader::*;
 pub use quad_ins::*;
 pub use std_shader::*;
 pub use text_ins::*;
@@ -137,13 +148,16 @@ pub use window::*;
 pub use zaplib_shader_compiler::code_fragment::CodeFragment;
 pub use zaplib_shader_compiler::math::*;
 pub use zaplib_shader_compiler::ty::Ty;
+pub use zaplib_shader_compiler::ty::TyLit;
 
+pub use accessibility::*;
 pub use animator::*;
 pub use colors::*;
 pub use component_id::*;
 pub use draw_tree::*;
 pub use fonts::*;
 pub use geometry::*;
+pub use gestures::*;
 pub use hash::*;
 pub use layout::*;
 pub use layout_api::*;
@@ -151,7 +165,11 @@ pub use layout_internal::*;
 pub use macros::*;
 pub use menu::*;
 pub use pass::*;
+pub use post_processing::*;
+pub use profile::*;
 pub use read_seek::*;
+pub use render_stats::*;
 pub use shader::*;
 pub use universal_file::*;
 pub use universal_instant::*;
+pub use worker_pool::*;
@@ -14,7 +14,7 @@ macro_rules! main_app {
             let mut app = $app::new(&mut cx);
             let mut cxafterdraw = CxAfterDraw::new(&mut cx);
             cx.set_finished_app_new();
-            cx.event_loop(|cx, mut event| {
+            cx.run_event_loop(|cx, mut event| {
                 match event {
                     Event::System(e) => {
                         match e {
@@ -2,6 +2,8 @@
 
 use crate::*;
 
+const STATUS_PASS_CAPTURED: StatusId = location_hash!();
+
 /// A rendering context e.g. for doing 3d rendering.
 ///
 /// Useful if you need:
@@ -13,6 +15,8 @@ use crate::*;
 #[derive(Default, Clone)]
 pub struct Pass {
     pub pass_id: Option<usize>,
+    /// Should match [`CxPass::generation`]; otherwise this [`Pass`] has been freed.
+    generation: u64,
 }
 
 impl Pass {
@@ -26,26 +30,37 @@ impl Pass {
     /// cause a repaint. Similarly you can still write to shaders within this [`Pass`] using [`Area::get_slice_mut`],
     /// which will also cause a repaint.
     ///
-    /// TODO(JP): Decide whether this is a bug or a feature. At the very least there currently no way to clean up a
-    /// [`Pass`], so that is definitely a bug. We might want to make that the default behavior, and have an explicit
-    /// method to keep a cached [`Pass`] around?
+    /// TODO(JP): Decide whether this is a bug or a feature. To clean up a [`Pass`] you currently have to call
+    /// [`Pass::free`] explicitly. We might want to make that the default behavior, and have an explicit method to
+    /// keep a cached [`Pass`] around?
     pub fn begin_pass(&mut self, cx: &mut Cx, background_color: Vec4) {
         self.begin_pass_without_textures(cx);
-        let cxpass = unsafe { cx.passes.get_unchecked_mut(self.pass_id.unwrap()) };
-        if cxpass.color_textures.is_empty() {
-            let color_texture_handle = Texture::default().get_color(cx);
-            self.add_color_texture(cx, color_texture_handle, ClearColor::ClearWith(background_color));
-            let depth_texture_handle = Texture::default().get_depth(cx);
-            self.set_depth_texture(cx, depth_texture_handle, ClearDepth::ClearWith(1.0));
-        }
+        let pass_id = self.pass_id.unwrap();
+        let (color_texture_handle, depth_texture_handle) = match cx.passes[pass_id].default_textures {
+            Some(default_textures) => default_textures,
+            None => {
+                let default_textures = (Texture::default().get_color(cx), Texture::default().get_depth(cx));
+                cx.passes[pass_id].default_textures = Some(default_textures);
+                default_textures
+            }
+        };
+        self.add_color_texture(cx, color_texture_handle, ClearColor::ClearWith(background_color));
+        self.set_depth_texture(cx, depth_texture_handle, ClearDepth::ClearWith(1.0));
     }
 
     /// Same as [`Pass::begin_pass`], but doesn't add [`Texture`]s automatically.
     pub fn begin_pass_without_textures(&mut self, cx: &mut Cx) {
         if self.pass_id.is_none() {
-            self.pass_id = Some(cx.passes.len());
-            cx.passes.push(CxPass::default());
+            if let Some(pass_id) = cx.passes_free.pop() {
+                self.pass_id = Some(pass_id);
+                self.generation = cx.passes[pass_id].generation;
+            } else {
+                self.pass_id = Some(cx.passes.len());
+                self.generation = 0;
+                cx.passes.push(CxPass::default());
+            }
         }
+        self.debug_assert_not_freed(cx);
         let pass_id = self.pass_id.unwrap();
 
         if let Some(window_id) = cx.window_stack.last() {
@@ -79,6 +94,7 @@ impl Pass {
     }
 
     pub fn override_dpi_factor(&mut self, cx: &mut Cx, dpi_factor: f32) {
+        self.debug_assert_not_freed(cx);
         if let Some(pass_id) = self.pass_id {
             cx.passes[pass_id].override_dpi_factor = Some(dpi_factor);
             cx.current_dpi_factor = dpi_factor;
@@ -95,24 +111,30 @@ impl Pass {
         if pass_size.y < 1.0 {
             pass_size.y = 1.0
         };
+        self.debug_assert_not_freed(cx);
         let cxpass = &mut cx.passes[self.pass_id.unwrap()];
         cxpass.pass_size = pass_size;
     }
 
     pub fn add_color_texture(&mut self, cx: &mut Cx, texture_handle: TextureHandle, clear_color: ClearColor) {
         let pass_id = self.pass_id.expect("Please call add_color_texture after begin_pass");
+        self.debug_assert_not_freed(cx);
+        texture_handle.debug_assert_not_freed(cx);
         let cxpass = &mut cx.passes[pass_id];
-        cxpass.color_textures.push(CxPassColorTexture { texture_id: texture_handle.texture_id, clear_color })
+        cxpass.color_textures.push(CxPassColorTexture { texture_handle, clear_color })
     }
 
     pub fn set_depth_texture(&mut self, cx: &mut Cx, texture_handle: TextureHandle, clear_depth: ClearDepth) {
         let pass_id = self.pass_id.expect("Please call set_depth_texture after begin_pass");
+        self.debug_assert_not_freed(cx);
+        texture_handle.debug_assert_not_freed(cx);
         let cxpass = &mut cx.passes[pass_id];
         cxpass.depth_texture = Some(texture_handle.texture_id);
         cxpass.clear_depth = clear_depth;
     }
 
     pub fn set_matrix_mode(&mut self, cx: &mut Cx, pmm: PassMatrixMode) {
+        self.debug_assert_not_freed(cx);
         if let Some(pass_id) = self.pass_id {
             let cxpass = &mut cx.passes[pass_id];
             cxpass.paint_dirty = true;
@@ -126,6 +148,70 @@ impl Pass {
             cx.current_dpi_factor = cx.get_delegated_dpi_factor(*cx.pass_stack.last().unwrap());
         }
     }
+
+    /// Read back the pixels of this [`Pass`] after it gets painted again. When done, you get an
+    /// [`Event::PassCaptured`] with the returned [`PassCaptureId`], containing the pixels of the first color
+    /// [`Texture`], or of the window if this is the main [`Pass`] of a window.
+    ///
+    /// Useful for screenshots, thumbnails, and pixel-level tests. Reading back pixels from the GPU is relatively
+    /// slow, so don't do this on every frame.
+    ///
+    /// ```
+    /// self.capture_id = Some(self.pass.request_capture(cx));
+    /// ```
+    ///
+    /// And then in `handle`:
+    ///
+    /// ```
+    /// if let Event::PassCaptured(PassCapturedEvent { capture_id, width, height, data }) = event {
+    ///     if Some(*capture_id) == self.capture_id {
+    ///         log!("Captured {}x{} pixels", width, height);
+    ///     }
+    /// }
+    /// ```
+    pub fn request_capture(&self, cx: &mut Cx) -> PassCaptureId {
+        let pass_id = self.pass_id.expect("Please call request_capture after begin_pass");
+        self.debug_assert_not_freed(cx);
+        if cx.pass_captures.signal == Signal::default() {
+            cx.pass_captures.signal = cx.new_signal();
+        }
+        cx.pass_captures.last_capture_id += 1;
+        let capture_id = PassCaptureId(cx.pass_captures.last_capture_id);
+
+        let cxpass = &mut cx.passes[pass_id];
+        cxpass.capture_ids.push(capture_id);
+        cxpass.paint_dirty = true;
+        capture_id
+    }
+
+    /// Free the [`Pass`], releasing its GPU resources before the next paint (including the [`Texture`]s
+    /// that [`Pass::begin_pass`] created, but not ones you added yourself), and allowing its slot to be reused.
+    /// Calling [`Pass::begin_pass`] afterwards creates a new [`Pass`].
+    ///
+    /// Make sure to also free or redraw the [`View`]s in this [`Pass`], and to redraw the [`View`] that
+    /// showed its [`Texture`]. Using a clone of a freed [`Pass`] is a bug, which gets caught in debug builds.
+    /// The main [`Pass`] of a window can't be freed.
+    pub fn free(&mut self, cx: &mut Cx) {
+        self.debug_assert_not_freed(cx);
+        if let Some(pass_id) = self.pass_id.take() {
+            assert!(!matches!(cx.passes[pass_id].dep_of, CxPassDepOf::Window(_)), "Can't free the main pass of a window");
+            let generation = cx.passes[pass_id].generation + 1;
+            let cxpass = std::mem::replace(&mut cx.passes[pass_id], CxPass { generation, ..CxPass::default() });
+            if let Some((color_texture_handle, depth_texture_handle)) = cxpass.default_textures {
+                color_texture_handle.free(cx);
+                depth_texture_handle.free(cx);
+            }
+            cx.released_resources.passes.push((pass_id, cxpass.platform));
+            cx.passes_free.push(pass_id);
+        }
+    }
+
+    /// Panics in debug builds if this is a clone of a [`Pass`] that has been freed using [`Pass::free`].
+    fn debug_assert_not_freed(&self, cx: &Cx) {
+        if let Some(pass_id) = self.pass_id {
+            debug_assert!(cx.passes[pass_id].generation == self.generation, "Using pass {} after it has been freed", pass_id);
+        }
+    }
 }
 
 /// The color to either initialize a [`Texture`] with (when rendering it for the very first time),
@@ -150,10 +236,10 @@ pub enum ClearDepth {
     ClearWith(f64),
 }
 
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub(crate) struct CxPassColorTexture {
     pub(crate) clear_color: ClearColor,
-    pub(crate) texture_id: u32,
+    pub(crate) texture_handle: TextureHandle,
 }
 
 #[derive(Default, Clone)]
@@ -186,8 +272,12 @@ impl PassUniforms {
 /// See e.g. <https://en.wikipedia.org/wiki/3D_projection>
 #[derive(Clone)]
 pub enum PassMatrixMode {
+    /// 2d projection, where units are logical pixels. The default.
     Ortho,
+    /// 3d perspective projection, with a vertical field of view in degrees.
     Projection { fov_y: f32, near: f32, far: f32, cam: Mat4 },
+    /// 3d orthographic projection, showing `height` units vertically; e.g. for top/front/side views.
+    Orthographic { height: f32, near: f32, far: f32, cam: Mat4 },
 }
 
 #[derive(Clone)]
@@ -203,6 +293,12 @@ pub(crate) struct CxPass {
     pub(crate) pass_size: Vec2,
     pub(crate) pass_uniforms: PassUniforms,
     pub(crate) zbias_step: f32,
+    /// Captures requested using [`Pass::request_capture`] that will be read back on the next paint.
+    pub(crate) capture_ids: Vec<PassCaptureId>,
+    /// The color and depth textures created by [`Pass::begin_pass`], which get reused on every draw.
+    pub(crate) default_textures: Option<(TextureHandle, TextureHandle)>,
+    /// Gets incremented when the [`Pass`] is freed, to catch use of stale [`Pass`] clones.
+    pub(crate) generation: u64,
     #[allow(dead_code)] // Not used in all platforms currently.
     pub(crate) platform: CxPlatformPass,
 }
@@ -221,11 +317,54 @@ impl Default for CxPass {
             dep_of: CxPassDepOf::None,
             paint_dirty: false,
             pass_size: Vec2::default(),
+            capture_ids: Vec::new(),
+            default_textures: None,
+            generation: 0,
             platform: CxPlatformPass::default(),
         }
     }
 }
 
+/// Captures that were read back from the GPU, but that we haven't fired an [`Event::PassCaptured`] for yet.
+#[derive(Default)]
+pub(crate) struct CxPassCaptures {
+    /// Gets posted when pixels have been read back; created on the first capture.
+    signal: Signal,
+    last_capture_id: u64,
+    finished: Vec<PassCapturedEvent>,
+}
+
+impl Cx {
+    /// Take the captures requested for `pass_id`. Platforms call this when painting a [`Pass`], and if
+    /// this returns anything, they read back the pixels and call [`Cx::finish_pass_captures`].
+    pub(crate) fn take_pass_capture_ids(&mut self, pass_id: usize) -> Vec<PassCaptureId> {
+        std::mem::take(&mut self.passes[pass_id].capture_ids)
+    }
+
+    /// Queue up an [`Event::PassCaptured`] for each of `capture_ids`. The rows in `data` should start at the top.
+    ///
+    /// Platforms call this on the main thread while painting, so we use [`Cx::send_signal`] instead of
+    /// [`Cx::post_signal`]; the events get fired the next time we handle signals.
+    pub(crate) fn finish_pass_captures(&mut self, capture_ids: Vec<PassCaptureId>, width: usize, height: usize, data: Vec<u32>) {
+        for capture_id in capture_ids {
+            self.pass_captures.finished.push(PassCapturedEvent { capture_id, width, height, data: data.clone() });
+        }
+        self.send_signal(self.pass_captures.signal, STATUS_PASS_CAPTURED);
+    }
+
+    /// Called when handling signals; fires [`Event::PassCaptured`] for captures that have been read back, and
+    /// returns whether `signal` was meant for us.
+    pub(crate) fn call_pass_captured_events(&mut self, signal: &Signal) -> bool {
+        if *signal != self.pass_captures.signal || self.pass_captures.signal == Signal::default() {
+            return false;
+        }
+        for pass_captured_event in std::mem::take(&mut self.pass_captures.finished) {
+            self.call_event_handler(&mut Event::PassCaptured(pass_captured_event));
+        }
+        true
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) enum CxPassDepOf {
     Window(usize),
@@ -279,6 +418,80 @@ impl CxPass {
                 // rotation matrices are orthogonal, meaning that their inverse is equal to their tranpose.
                 self.uniform_inv_camera_rot(&cam.as_rotation().transpose());
             }
+            PassMatrixMode::Orthographic { height, near, far, cam } => {
+                let proj = Mat4::orthographic(height, size.x / size.y, near, far);
+                self.uniform_camera_projection(&proj);
+                self.uniform_camera_view(&cam);
+                self.uniform_inv_camera_rot(&cam.as_rotation().transpose());
+            }
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fires_pass_captured_events_after_painting() {
+        let mut cx = Cx::new_test();
+        let mut pass = Pass::default();
+        pass.begin_pass(&mut cx, Vec4::default());
+        let capture_id = pass.request_capture(&mut cx);
+        pass.end_pass(&mut cx);
+
+        // What the platforms do when painting the pass.
+        let pass_id = pass.pass_id.unwrap();
+        let capture_ids = cx.take_pass_capture_ids(pass_id);
+        assert_eq!(capture_ids, vec![capture_id]);
+        cx.finish_pass_captures(capture_ids, 1, 1, vec![0xff0000ff]);
+
+        let events = cx.collect_events(|cx| cx.call_signals());
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            Event::PassCaptured(pass_captured_event) => {
+                assert_eq!(*pass_captured_event, PassCapturedEvent { capture_id, width: 1, height: 1, data: vec![0xff0000ff] });
+            }
+            event => panic!("Unexpected event: {:?}", event),
+        }
+    }
+
+    #[test]
+    fn reuses_slots_and_default_textures_of_freed_passes() {
+        let mut cx = Cx::new_test();
+        let mut pass = Pass::default();
+        pass.begin_pass(&mut cx, Vec4::default());
+        pass.end_pass(&mut cx);
+        let pass_id = pass.pass_id.unwrap();
+        let generation = pass.generation;
+        let (passes_len, textures_len) = (cx.passes.len(), cx.textures.len());
+
+        for _ in 0..3 {
+            pass.free(&mut cx);
+            assert!(pass.pass_id.is_none());
+            pass.begin_pass(&mut cx, Vec4::default());
+            pass.end_pass(&mut cx);
+        }
+
+        assert_eq!(pass.pass_id, Some(pass_id));
+        assert_eq!(pass.generation, generation + 3);
+        assert_eq!(cx.passes.len(), passes_len);
+        // The default color and depth textures get freed along with the pass, so they don't leak.
+        assert_eq!(cx.textures.len(), textures_len);
+        assert_eq!(cx.released_resources.passes.len(), 3);
+        assert_eq!(cx.released_resources.textures.len(), 6);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "after it has been freed")]
+    fn panics_when_using_freed_pass() {
+        let mut cx = Cx::new_test();
+        let mut pass = Pass::default();
+        pass.begin_pass(&mut cx, Vec4::default());
+        pass.end_pass(&mut cx);
+        let mut stale_pass = pass.clone();
+        pass.free(&mut cx);
+        stale_pass.set_size(&mut cx, vec2(10., 10.));
+    }
+}
@@ -0,0 +1,431 @@
+//! Chaining full-screen shader effects over the output of a [`Pass`].
+
+use crate::*;
+
+/// A full-screen shader effect, like a blur or color grading, that gets applied to a [`Texture`] by
+/// [`PostProcessChain::draw`].
+///
+/// The shader should include [`PostProcessEffect::SHADER`], and implement `fn pixel() -> vec4`. See the
+/// shaders of the built-in effects (like [`PostProcessEffect::fxaa`]) for examples.
+#[derive(Clone, Copy)]
+pub struct PostProcessEffect {
+    pub shader: &'static Shader,
+    /// Available as `params` in the shader, for effects that can be configured.
+    pub params: Vec4,
+}
+
+impl PostProcessEffect {
+    /// Common [`Shader`] code for effects.
+    ///
+    /// * `input_texture` contains the output of the previous effect (or of the source [`Pass`] for the first
+    ///   effect), and `source_texture` the output of the source [`Pass`].
+    /// * `pos` is the position in these textures, from 0 to 1.
+    /// * `texel_size` is the size of a single pixel in these textures, e.g. for sampling neighbouring pixels.
+    pub const SHADER: CodeFragment = code_fragment!(
+        r#"
+        texture input_texture: texture2D;
+        texture source_texture: texture2D;
+        instance texel_size: vec2;
+        uniform params: vec4;
+        "#
+    );
+
+    pub fn new(shader: &'static Shader) -> Self {
+        Self { shader, params: Vec4::default() }
+    }
+
+    #[must_use]
+    pub fn with_params(self, params: Vec4) -> Self {
+        Self { params, ..self }
+    }
+
+    /// Blur horizontally; use together with [`PostProcessEffect::blur_vertical`]. `spread` is the distance in
+    /// pixels between samples, so larger values give a larger (but lower quality) blur.
+    pub fn blur_horizontal(spread: f32) -> Self {
+        Self::new(&BLUR_HORIZONTAL_SHADER).with_params(vec4(spread, 0., 0., 0.))
+    }
+
+    /// Blur vertically; see [`PostProcessEffect::blur_horizontal`].
+    pub fn blur_vertical(spread: f32) -> Self {
+        Self::new(&BLUR_VERTICAL_SHADER).with_params(vec4(spread, 0., 0., 0.))
+    }
+
+    /// Only keep pixels that are brighter than `threshold` (from 0 to 1). This is the first step of a bloom
+    /// effect, followed by blurring and then [`PostProcessEffect::bloom_combine`]; see
+    /// [`PostProcessEffect::bloom`].
+    pub fn bloom_threshold(threshold: f32) -> Self {
+        Self::new(&BLOOM_THRESHOLD_SHADER).with_params(vec4(threshold, 0., 0., 0.))
+    }
+
+    /// Add the input to the output of the source [`Pass`], multiplied by `intensity`.
+    pub fn bloom_combine(intensity: f32) -> Self {
+        Self::new(&BLOOM_COMBINE_SHADER).with_params(vec4(intensity, 0., 0., 0.))
+    }
+
+    /// A complete bloom effect, which makes bright parts of the image glow.
+    pub fn bloom(threshold: f32, spread: f32, intensity: f32) -> [Self; 4] {
+        [
+            Self::bloom_threshold(threshold),
+            Self::blur_horizontal(spread),
+            Self::blur_vertical(spread),
+            Self::bloom_combine(intensity),
+        ]
+    }
+
+    /// Adjust the colors. `exposure` multiplies all colors, `contrast` pushes them away from (or towards) grey,
+    /// and `saturation` pushes them away from (or towards) their luminance. Use 1 for no change.
+    pub fn color_grading(exposure: f32, contrast: f32, saturation: f32) -> Self {
+        Self::new(&COLOR_GRADING_SHADER).with_params(vec4(exposure, contrast, saturation, 0.))
+    }
+
+    /// Fast approximate anti-aliasing, which smooths out jagged edges. Best applied last, or just before
+    /// [`PostProcessEffect::color_grading`].
+    pub fn fxaa() -> Self {
+        Self::new(&FXAA_SHADER)
+    }
+}
+
+/// Instance data for drawing a [`PostProcessEffect`].
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct PostProcessIns {
+    base: QuadIns,
+    texel_size: Vec2,
+}
+
+/// A 9-tap gaussian blur in a single direction; blurring horizontally and then vertically gives the same result
+/// as a (much slower) 81-tap blur.
+const BLUR_SHADER: CodeFragment = code_fragment!(
+    r#"
+    fn blur(direction: vec2) -> vec4 {
+        let offset = direction * texel_size * params.x;
+        let result = sample2d(input_texture, pos) * 0.2270270270;
+        result += (sample2d(input_texture, pos + offset) + sample2d(input_texture, pos - offset)) * 0.1945945946;
+        result += (sample2d(input_texture, pos + 2. * offset) + sample2d(input_texture, pos - 2. * offset)) * 0.1216216216;
+        result += (sample2d(input_texture, pos + 3. * offset) + sample2d(input_texture, pos - 3. * offset)) * 0.0540540541;
+        result += (sample2d(input_texture, pos + 4. * offset) + sample2d(input_texture, pos - 4. * offset)) * 0.0162162162;
+        return result;
+    }
+    "#
+);
+
+static BLUR_HORIZONTAL_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        PostProcessEffect::SHADER,
+        BLUR_SHADER,
+        code_fragment!(
+            r#"
+            fn pixel() -> vec4 {
+                return blur(vec2(1., 0.));
+            }
+            "#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+static BLUR_VERTICAL_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        PostProcessEffect::SHADER,
+        BLUR_SHADER,
+        code_fragment!(
+            r#"
+            fn pixel() -> vec4 {
+                return blur(vec2(0., 1.));
+            }
+            "#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+static BLOOM_THRESHOLD_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        PostProcessEffect::SHADER,
+        code_fragment!(
+            r#"
+            fn pixel() -> vec4 {
+                let color = sample2d(input_texture, pos);
+                let brightness = max(color.r, max(color.g, color.b));
+                return color * step(params.x, brightness);
+            }
+            "#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+static BLOOM_COMBINE_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        PostProcessEffect::SHADER,
+        code_fragment!(
+            r#"
+            fn pixel() -> vec4 {
+                return sample2d(source_texture, pos) + sample2d(input_texture, pos) * params.x;
+            }
+            "#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+static COLOR_GRADING_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        PostProcessEffect::SHADER,
+        code_fragment!(
+            r#"
+            fn pixel() -> vec4 {
+                let color = sample2d(input_texture, pos);
+                // Colors are premultiplied, so grey is at half the alpha.
+                let grey = 0.5 * color.a;
+                let rgb = color.rgb * params.x;
+                rgb = (rgb - grey) * params.y + grey;
+                let luminance = dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+                rgb = mix(vec3(luminance), rgb, params.z);
+                return vec4(clamp(rgb, vec3(0.), vec3(color.a)), color.a);
+            }
+            "#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+/// Based on the well-known FXAA implementation by Timothy Lottes, simplified to use only 9 samples.
+static FXAA_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        PostProcessEffect::SHADER,
+        code_fragment!(
+            r#"
+            fn fxaa_luma(color: vec4) -> float {
+                return dot(color.rgb, vec3(0.299, 0.587, 0.114));
+            }
+
+            fn pixel() -> vec4 {
+                let color_m = sample2d(input_texture, pos);
+                let luma_nw = fxaa_luma(sample2d(input_texture, pos + vec2(-1., -1.) * texel_size));
+                let luma_ne = fxaa_luma(sample2d(input_texture, pos + vec2(1., -1.) * texel_size));
+                let luma_sw = fxaa_luma(sample2d(input_texture, pos + vec2(-1., 1.) * texel_size));
+                let luma_se = fxaa_luma(sample2d(input_texture, pos + vec2(1., 1.) * texel_size));
+                let luma_m = fxaa_luma(color_m);
+                let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+                let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+                if luma_max - luma_min < max(0.0312, luma_max * 0.125) {
+                    return color_m;
+                }
+
+                // Blur along the edge, which is perpendicular to the gradient.
+                let dir = vec2((luma_sw + luma_se) - (luma_nw + luma_ne), (luma_nw + luma_sw) - (luma_ne + luma_se));
+                let dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.03125, 0.0078125);
+                let dir_scale = 1. / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+                dir = clamp(dir * dir_scale, vec2(-8.), vec2(8.)) * texel_size;
+
+                let color_a = 0.5 * (
+                    sample2d(input_texture, pos + dir * (1. / 3. - 0.5)) +
+                    sample2d(input_texture, pos + dir * (2. / 3. - 0.5))
+                );
+                let color_b = color_a * 0.5 + 0.25 * (
+                    sample2d(input_texture, pos - dir * 0.5) +
+                    sample2d(input_texture, pos + dir * 0.5)
+                );
+                let luma_b = fxaa_luma(color_b);
+                if luma_b < luma_min || luma_b > luma_max {
+                    return color_a;
+                }
+                return color_b;
+            }
+            "#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+/// A stage of [`PostProcessChain`], which renders a single [`PostProcessEffect`] into its own [`Texture`].
+#[derive(Default)]
+struct PostProcessStage {
+    pass: Pass,
+    view: View,
+    texture: Texture,
+    area: Area,
+}
+
+impl PostProcessStage {
+    fn free(&mut self, cx: &mut Cx) {
+        self.view.free(cx);
+        self.pass.free(cx);
+        self.texture.free(cx);
+    }
+}
+
+/// Applies a chain of [`PostProcessEffect`]s to the output of a [`Pass`], each rendering into their own
+/// [`Texture`], which get created, resized, and freed automatically.
+///
+/// ```
+/// self.pass.begin_pass(cx, COLOR_BLACK);
+/// // Draw the scene..
+/// self.pass.end_pass(cx);
+///
+/// let mut effects = PostProcessEffect::bloom(0.8, 2., 1.).to_vec();
+/// effects.push(PostProcessEffect::fxaa());
+/// let texture_handle = self.post_process_chain.draw(cx, &self.pass, &effects);
+/// ImageIns::draw(cx, rect, texture_handle);
+/// ```
+#[derive(Default)]
+pub struct PostProcessChain {
+    stages: Vec<PostProcessStage>,
+}
+
+impl PostProcessChain {
+    /// Draw `effects` over the first color [`Texture`] of `source`, and return the [`Texture`] with the final
+    /// result (or the one of `source` if `effects` is empty). Call this after [`Pass::end_pass`], in the same
+    /// [`Pass`] where you draw the result.
+    ///
+    /// The [`Texture`]s have the same size as `source`, and get repainted whenever `source` gets repainted.
+    pub fn draw(&mut self, cx: &mut Cx, source: &Pass, effects: &[PostProcessEffect]) -> TextureHandle {
+        let source_pass_id = source.pass_id.expect("Please call PostProcessChain::draw after begin_pass");
+        let source_texture_handle =
+            cx.passes[source_pass_id].color_textures.first().expect("Source pass has no color texture").texture_handle;
+        let size = cx.passes[source_pass_id].pass_size;
+
+        if effects.is_empty() && !self.stages.is_empty() {
+            // Undo making `source` part of a stage that is about to be freed.
+            cx.passes[source_pass_id].dep_of = match cx.pass_stack.last() {
+                Some(&pass_id) => CxPassDepOf::Pass(pass_id),
+                None => CxPassDepOf::None,
+            };
+        }
+        for mut stage in self.stages.drain(effects.len().min(self.stages.len())..) {
+            stage.free(cx);
+        }
+        self.stages.resize_with(effects.len(), PostProcessStage::default);
+
+        // Passes that are drawn as part of another pass get painted first, so let each pass be part of the
+        // next one.
+        let mut previous_pass_id = source_pass_id;
+        let mut input_texture_handle = source_texture_handle;
+        for (stage, effect) in self.stages.iter_mut().zip(effects) {
+            stage.pass.begin_pass_without_textures(cx);
+            stage.pass.set_size(cx, size);
+            let texture_handle = stage.texture.get_color(cx);
+            stage.pass.add_color_texture(cx, texture_handle, ClearColor::ClearWith(Vec4::default()));
+
+            stage.view.begin_view(cx, LayoutSize::FILL);
+            let rect = Rect { pos: cx.get_box_origin(), size };
+            let texel_size = vec2(1., 1.) / (size * cx.current_dpi_factor);
+            stage.area = cx.add_instances(effect.shader, &[PostProcessIns { base: QuadIns::from_rect(rect), texel_size }]);
+            stage.area.write_texture_2d(cx, "input_texture", input_texture_handle);
+            stage.area.write_texture_2d(cx, "source_texture", source_texture_handle);
+            stage.area.write_user_uniforms(cx, effect.params);
+            stage.view.end_view(cx);
+            stage.pass.end_pass(cx);
+
+            let pass_id = stage.pass.pass_id.unwrap();
+            cx.passes[previous_pass_id].dep_of = CxPassDepOf::Pass(pass_id);
+            previous_pass_id = pass_id;
+            input_texture_handle = texture_handle;
+        }
+        input_texture_handle
+    }
+
+    /// The [`Area`] of the effect at `index` in the last call to [`PostProcessChain::draw`], e.g. for
+    /// animating its [`PostProcessEffect::params`] using [`Area::write_user_uniforms`].
+    pub fn effect_area(&self, index: usize) -> Area {
+        self.stages.get(index).map_or(Area::Empty, |stage| stage.area)
+    }
+
+    /// The [`Texture`] with the final result of the last call to [`PostProcessChain::draw`], if there
+    /// were any effects.
+    pub fn output_texture(&self) -> Option<TextureHandle> {
+        self.stages.last().map(|stage| stage.texture.unwrap_texture_handle())
+    }
+
+    /// Free all the [`Pass`]es, [`View`]s, and [`Texture`]s of the chain. See [`Pass::free`].
+    pub fn free(&mut self, cx: &mut Cx) {
+        for mut stage in self.stages.drain(..) {
+            stage.free(cx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct DrawnChain {
+        texture_handle: TextureHandle,
+        passes_todo: Vec<usize>,
+        source_pass_id: usize,
+        main_pass_id: usize,
+        stage_pass_ids: Vec<usize>,
+        output_texture: Option<TextureHandle>,
+    }
+
+    #[test]
+    fn paints_the_chain_in_order() {
+        let mut cx = Cx::new_test();
+        let mut window = Window::default();
+        let mut main_pass = Pass::default();
+        let mut main_view = View::default();
+        let mut source = Pass::default();
+        let mut source_view = View::default();
+        let mut chain = PostProcessChain::default();
+
+        let mut draw = |cx: &mut Cx, effects: &[PostProcessEffect]| {
+            cx.in_redraw_cycle = true;
+            window.begin_window(cx);
+            main_pass.begin_pass(cx, Vec4::default());
+            main_view.begin_view(cx, LayoutSize::FILL);
+            source.begin_pass(cx, Vec4::default());
+            source_view.begin_view(cx, LayoutSize::FILL);
+            source_view.end_view(cx);
+            source.end_pass(cx);
+            let texture_handle = chain.draw(cx, &source, effects);
+            main_view.end_view(cx);
+            main_pass.end_pass(cx);
+            window.end_window(cx);
+            cx.in_redraw_cycle = false;
+
+            let mut passes_todo = Vec::new();
+            cx.compute_passes_to_repaint(&mut passes_todo, &mut 0);
+            DrawnChain {
+                texture_handle,
+                passes_todo,
+                source_pass_id: source.pass_id.unwrap(),
+                main_pass_id: main_pass.pass_id.unwrap(),
+                stage_pass_ids: chain.stages.iter().map(|stage| stage.pass.pass_id.unwrap()).collect(),
+                output_texture: chain.output_texture(),
+            }
+        };
+
+        let drawn = draw(&mut cx, &[PostProcessEffect::color_grading(1., 1., 1.), PostProcessEffect::fxaa()]);
+        assert_eq!(Some(drawn.texture_handle), drawn.output_texture);
+        // The source gets painted first, then each stage, and finally the pass that shows the result.
+        let chain_pass_ids = [&[drawn.source_pass_id, drawn.main_pass_id][..], &drawn.stage_pass_ids].concat();
+        let painted: Vec<usize> = drawn.passes_todo.into_iter().filter(|pass_id| chain_pass_ids.contains(pass_id)).collect();
+        assert_eq!(painted, vec![drawn.source_pass_id, drawn.stage_pass_ids[0], drawn.stage_pass_ids[1], drawn.main_pass_id]);
+
+        // Without effects we get the source texture, and the source is part of the main pass again.
+        let drawn = draw(&mut cx, &[]);
+        assert_eq!(drawn.texture_handle, cx.passes[drawn.source_pass_id].color_textures[0].texture_handle);
+        assert!(matches!(cx.passes[drawn.source_pass_id].dep_of, CxPassDepOf::Pass(pass_id) if pass_id == drawn.main_pass_id));
+        assert!(drawn.stage_pass_ids.is_empty());
+        assert_eq!(drawn.output_texture, None);
+    }
+}
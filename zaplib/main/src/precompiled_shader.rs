@@ -0,0 +1,199 @@
+//! Compiling [`Shader`]s at build time instead of at startup.
+//!
+//! This saves parsing and generating code when the app starts, and catches mistakes in shader code before
+//! running the app. In your `build.rs` (with `zaplib` also added to `[build-dependencies]`):
+//!
+//! ```
+//! use zaplib::*;
+//!
+//! fn main() {
+//!     precompile_shaders(&[("MY_SHADER", &[Cx::STD_SHADER, QuadIns::SHADER, code_fragment_from_file("src/my_shader.zaplib")])]);
+//! }
+//! ```
+//!
+//! And then in your code:
+//!
+//! ```
+//! include!(concat!(env!("OUT_DIR"), "/zaplib_shaders.rs"));
+//!
+//! static SHADER: Shader = Shader { build_geom: Some(QuadIns::build_geom), precompiled: Some(&MY_SHADER), ..Shader::DEFAULT };
+//! ```
+
+// The `build.rs` example above needs its `fn main`.
+#![allow(clippy::needless_doctest_main)]
+
+use crate::*;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use zaplib_shader_compiler::ty::TyLit;
+#[cfg(not(target_arch = "wasm32"))]
+use zaplib_shader_compiler::{generate_glsl, generate_hlsl, generate_metal, generate_shader_ast::ShaderAstGenerator, ty::Ty};
+
+/// Which kind of input to a [`Shader`] a [`PrecompiledShaderProp`] is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShaderPropKind {
+    Geometry,
+    Instance,
+    /// Uniform without a block, as set using [`Area::write_user_uniforms`].
+    UserUniform,
+    /// Uniform `in draw`; see [`DrawUniforms`].
+    DrawUniform,
+    /// Uniform `in view`.
+    ViewUniform,
+    /// Uniform `in pass`; see [`PassUniforms`].
+    PassUniform,
+    Texture,
+}
+
+/// An input of a [`PrecompiledShader`].
+#[derive(Debug)]
+pub struct PrecompiledShaderProp {
+    pub kind: ShaderPropKind,
+    pub name: &'static str,
+    pub ty: TyLit,
+}
+
+/// Code for a [`Shader`] that was generated at build time by [`precompile_shaders`]; see [`Shader::precompiled`].
+///
+/// You shouldn't have to create this yourself.
+#[derive(Debug)]
+pub struct PrecompiledShader {
+    /// File, line, and column of the last [`CodeFragment`], like shaders that are compiled at runtime.
+    pub name: &'static str,
+    pub props: &'static [PrecompiledShaderProp],
+    /// If the shader code should be printed when it gets compiled by the GPU driver.
+    pub debug: bool,
+    pub glsl_vertex: &'static str,
+    pub glsl_fragment: &'static str,
+    pub metal: &'static str,
+    pub hlsl: &'static str,
+}
+
+/// Name of the file that [`precompile_shaders`] writes to in `OUT_DIR`.
+#[cfg(not(target_arch = "wasm32"))]
+const PRECOMPILED_SHADERS_FILENAME: &str = "zaplib_shaders.rs";
+
+/// Compile shaders in a build script, writing a `pub static` [`PrecompiledShader`] for each one to
+/// `zaplib_shaders.rs` in `OUT_DIR`. Every shader is a name for the `static`, and the [`CodeFragment`]s that
+/// would otherwise go in [`Shader::code_to_concatenate`]. See [`crate::precompiled_shader`] for an example.
+///
+/// If a shader doesn't compile, this fails the build with the file, line, and column of the error.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn precompile_shaders(shaders: &[(&str, &[CodeFragment])]) {
+    match generate_precompiled_shaders(shaders) {
+        Ok(code) => {
+            let out_dir = std::env::var("OUT_DIR").expect("precompile_shaders should be called from a build script");
+            std::fs::write(Path::new(&out_dir).join(PRECOMPILED_SHADERS_FILENAME), code)
+                .expect("Could not write precompiled shaders");
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Load shader code from a file in a build script, to pass into [`precompile_shaders`]. The `path` is relative
+/// to the crate root. Makes the build script rerun when the file changes.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn code_fragment_from_file(path: &str) -> CodeFragment {
+    println!("cargo:rerun-if-changed={}", path);
+    let code = std::fs::read_to_string(path).unwrap_or_else(|err| panic!("Could not read {}: {}", path, err));
+    // Build scripts run only once, so it's fine to leak these.
+    CodeFragment::Static {
+        filename: Box::leak(path.to_string().into_boxed_str()),
+        line: 1,
+        col: 1,
+        code: Box::leak(code.into_boxed_str()),
+    }
+}
+
+/// The Rust code that [`precompile_shaders`] writes, or the error of the first shader that doesn't compile.
+#[cfg(not(target_arch = "wasm32"))]
+fn generate_precompiled_shaders(shaders: &[(&str, &[CodeFragment])]) -> Result<String, String> {
+    let shader_ast_generator = ShaderAstGenerator::new();
+    let mut code = String::from("// Generated by `zaplib::precompile_shaders`.\n");
+    for (static_name, code_fragments) in shaders {
+        let main_code_fragment = code_fragments.last().ok_or_else(|| format!("No code fragments found for {}", static_name))?;
+        let name = main_code_fragment.name_line_col_at_offset(0);
+        let shader_ast =
+            shader_ast_generator.generate_shader_ast(code_fragments).map_err(|err| err.format_for_console(code_fragments))?;
+
+        let mut props = String::new();
+        for (kind, prop_def) in get_shader_props(&shader_ast) {
+            let ty = match prop_def.ty {
+                Ty::Texture2D => TyLit::Texture2D,
+                ty => ty.maybe_ty_lit().ok_or_else(|| format!("{}: can't precompile the type of {}", name, prop_def.name))?,
+            };
+            props += &format!(
+                "        zaplib::PrecompiledShaderProp {{ kind: zaplib::ShaderPropKind::{:?}, name: {:?}, ty: \
+                 zaplib::TyLit::{:?} }},\n",
+                kind, prop_def.name, ty
+            );
+        }
+
+        // `{:?}` gives us valid Rust string literals.
+        code += &format!(
+            "\npub static {}: zaplib::PrecompiledShader = zaplib::PrecompiledShader {{\n    name: {:?},\n    props: &[\n{}    \
+             ],\n    debug: {},\n    glsl_vertex: {:?},\n    glsl_fragment: {:?},\n    metal: {:?},\n    hlsl: {:?},\n}};\n",
+            static_name,
+            name,
+            props,
+            shader_ast.debug,
+            generate_glsl::generate_vertex_shader(&shader_ast),
+            generate_glsl::generate_fragment_shader(&shader_ast),
+            generate_metal::generate_shader(&shader_ast),
+            generate_hlsl::generate_shader(&shader_ast),
+        );
+    }
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_precompiled_shaders() {
+        let code = generate_precompiled_shaders(&[(
+            "TEST_SHADER",
+            &[
+                Cx::STD_SHADER,
+                code_fragment!(
+                    r#"
+                    geometry geom: vec2;
+                    instance color: vec4;
+                    fn vertex() -> vec4 {
+                        return camera_projection * vec4(geom, 0., 1.);
+                    }
+                    fn pixel() -> vec4 {
+                        return color;
+                    }"#
+                ),
+            ],
+        )])
+        .unwrap();
+        assert!(code.contains("pub static TEST_SHADER: zaplib::PrecompiledShader"));
+        assert!(code.contains(&format!("name: \"{}:", file!())));
+        assert!(code.contains("kind: zaplib::ShaderPropKind::Instance, name: \"color\", ty: zaplib::TyLit::Vec4"));
+        assert!(code.contains("kind: zaplib::ShaderPropKind::PassUniform, name: \"camera_projection\""));
+
+        let err = generate_precompiled_shaders(&[(
+            "BROKEN_SHADER",
+            &[
+                Cx::STD_SHADER,
+                code_fragment!(
+                    r#"
+                    fn vertex() -> vec4 {
+                        return vec4(0.);
+                    }
+                    fn pixel() -> vec4 {
+                        return colour;
+                    }"#
+                ),
+            ],
+        )])
+        .unwrap_err();
+        assert!(err.contains(file!()));
+    }
+}
@@ -1,6 +1,131 @@
 //! Performance profiling.
+//!
+//! Besides the simple [`Cx::profile_start`] and [`Cx::profile_end`], there is a hierarchical frame profiler,
+//! which records nested zones (event handling, drawing, layout, shader compilation, buffer uploads, painting,
+//! and any zones you add yourself using [`Cx::profile_begin_zone`] and [`Cx::profile_end_zone`]) for the
+//! last [`MAX_PROFILE_FRAMES`] frames. Enable it using [`Cx::set_profiler_enabled`], and then look at the
+//! recorded frames using [`Cx::get_profile_frames`] (or an overlay like `zaplib_components::ProfilerOverlay`),
+//! or export them using [`Cx::profile_to_chrome_trace_json`].
 
 use crate::*;
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+/// Number of frames that the frame profiler keeps around.
+pub const MAX_PROFILE_FRAMES: usize = 300;
+
+/// Maximum number of zones per frame, so we don't run out of memory when zones don't get ended.
+const MAX_PROFILE_ZONES_PER_FRAME: usize = 10_000;
+
+/// A single (possibly nested) zone in a [`ProfileFrame`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileZone {
+    pub name: &'static str,
+    /// Number of zones that this zone is nested in.
+    pub depth: usize,
+    /// In seconds since the profiler was created.
+    pub start: f64,
+    /// In seconds.
+    pub duration: f64,
+}
+
+/// All the zones recorded in between two paints. See [`Cx::get_profile_frames`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProfileFrame {
+    /// Increasing number, also counting frames that are no longer kept around.
+    pub frame_index: u64,
+    /// In seconds since the profiler was created.
+    pub start: f64,
+    /// In seconds.
+    pub duration: f64,
+    /// All zones, in the order in which they started. Zones that haven't ended yet at the end of
+    /// the frame are cut off there.
+    pub zones: Vec<ProfileZone>,
+}
+
+/// State of the frame profiler. This is a separate struct (instead of just fields on [`Cx`]) so platform code
+/// can record zones while borrowing other parts of [`Cx`].
+pub(crate) struct CxProfiler {
+    enabled: bool,
+    epoch: UniversalInstant,
+    frames: VecDeque<ProfileFrame>,
+    current_frame: ProfileFrame,
+    /// Indices into `current_frame.zones` of the zones that haven't ended yet.
+    zone_stack: Vec<usize>,
+    /// Number of zones that were begun but not recorded because of [`MAX_PROFILE_ZONES_PER_FRAME`], and
+    /// haven't ended yet. Their [`CxProfiler::end_zone`] calls shouldn't end any recorded zones.
+    skipped_depth: usize,
+}
+
+impl Default for CxProfiler {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            epoch: UniversalInstant::now(),
+            frames: VecDeque::new(),
+            current_frame: ProfileFrame::default(),
+            zone_stack: vec![],
+            skipped_depth: 0,
+        }
+    }
+}
+
+impl CxProfiler {
+    fn now(&self) -> f64 {
+        self.epoch.elapsed().as_secs_f64()
+    }
+
+    pub(crate) fn begin_zone(&mut self, name: &'static str) {
+        if !self.enabled {
+            return;
+        }
+        if self.current_frame.zones.len() >= MAX_PROFILE_ZONES_PER_FRAME {
+            self.skipped_depth += 1;
+            return;
+        }
+        let start = self.now();
+        if self.current_frame.zones.is_empty() {
+            self.current_frame.start = start;
+        }
+        self.zone_stack.push(self.current_frame.zones.len());
+        self.current_frame.zones.push(ProfileZone { name, depth: self.zone_stack.len() - 1, start, duration: 0. });
+    }
+
+    pub(crate) fn end_zone(&mut self) {
+        // No need to check `enabled`, since the stack is empty when disabled. This also makes sure we don't mess
+        // up when enabling the profiler in the middle of a zone.
+        if self.skipped_depth > 0 {
+            self.skipped_depth -= 1;
+            return;
+        }
+        if let Some(index) = self.zone_stack.pop() {
+            let now = self.now();
+            let zone = &mut self.current_frame.zones[index];
+            zone.duration = now - zone.start;
+        }
+    }
+
+    /// Move the zones recorded so far into a new [`ProfileFrame`], if there are any.
+    pub(crate) fn end_frame(&mut self) {
+        if self.current_frame.zones.is_empty() {
+            return;
+        }
+        let now = self.now();
+        self.skipped_depth = 0;
+        for index in self.zone_stack.drain(..) {
+            let zone = &mut self.current_frame.zones[index];
+            zone.duration = now - zone.start;
+        }
+        let frame_index = self.current_frame.frame_index;
+        let mut frame =
+            std::mem::replace(&mut self.current_frame, ProfileFrame { frame_index: frame_index + 1, ..Default::default() });
+        frame.duration = now - frame.start;
+        if self.frames.len() >= MAX_PROFILE_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+}
 
 impl Cx {
     pub fn profile_start(&mut self, id: u64) {
@@ -12,4 +137,141 @@ impl Cx {
             log!("Profile {} time {}ms", id, inst.elapsed().as_millis());
         }
     }
+
+    /// Turn the frame profiler on or off. When turning it off, the recorded frames are kept around.
+    pub fn set_profiler_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.profiler.end_frame();
+        }
+        self.profiler.enabled = enabled;
+    }
+
+    pub fn is_profiler_enabled(&self) -> bool {
+        self.profiler.enabled
+    }
+
+    /// Start a new zone in the frame profiler, nested in the zone that is currently open (if any). Make sure
+    /// to always call [`Cx::profile_end_zone`] afterwards. Does nothing if the profiler is disabled.
+    ///
+    /// ```
+    /// cx.profile_begin_zone("parse_data");
+    /// self.parse_data();
+    /// cx.profile_end_zone();
+    /// ```
+    pub fn profile_begin_zone(&mut self, name: &'static str) {
+        self.profiler.begin_zone(name);
+    }
+
+    /// End the zone started using [`Cx::profile_begin_zone`].
+    pub fn profile_end_zone(&mut self) {
+        self.profiler.end_zone();
+    }
+
+    /// End the current frame of the frame profiler; should be called by platforms after painting.
+    pub(crate) fn profile_end_frame(&mut self) {
+        self.profiler.end_frame();
+    }
+
+    /// The last [`MAX_PROFILE_FRAMES`] frames recorded by the frame profiler, oldest first.
+    pub fn get_profile_frames(&self) -> &VecDeque<ProfileFrame> {
+        &self.profiler.frames
+    }
+
+    /// Export the frames returned by [`Cx::get_profile_frames`] in the
+    /// [Trace Event Format](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU),
+    /// which you can load in `chrome://tracing`, [Perfetto](https://ui.perfetto.dev), or
+    /// [Speedscope](https://www.speedscope.app). Each frame gets its own "frame" zone around its other zones.
+    pub fn profile_to_chrome_trace_json(&self) -> String {
+        let mut json = String::new();
+        json.push_str("{\"traceEvents\":[");
+        let mut is_first = true;
+        let mut write_event = |json: &mut String, name: &str, start: f64, duration: f64| {
+            if !is_first {
+                json.push(',');
+            }
+            is_first = false;
+            json.push_str("{\"name\":");
+            crate::accessibility::write_json_string(json, name);
+            write!(
+                json,
+                ",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":0,\"tid\":0}}",
+                start * 1_000_000.,
+                duration * 1_000_000.
+            )
+            .unwrap();
+        };
+        for frame in &self.profiler.frames {
+            write_event(&mut json, &format!("frame {}", frame.frame_index), frame.start, frame.duration);
+            for zone in &frame.zones {
+                write_event(&mut json, zone.name, zone.start, zone.duration);
+            }
+        }
+        json.push_str("],\"displayTimeUnit\":\"ms\"}");
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_nested_zones_per_frame() {
+        let mut cx = Cx::new_test();
+        cx.profile_begin_zone("ignored");
+        cx.profile_end_zone();
+        cx.profile_end_frame();
+        assert!(cx.get_profile_frames().is_empty());
+
+        cx.set_profiler_enabled(true);
+        cx.profile_begin_zone("draw");
+        cx.profile_begin_zone("layout");
+        cx.profile_end_zone();
+        cx.profile_end_zone();
+        cx.profile_begin_zone("paint");
+        cx.profile_end_frame();
+        // Empty frames are skipped.
+        cx.profile_end_frame();
+        cx.profile_begin_zone("draw");
+        cx.profile_end_zone();
+        cx.profile_end_frame();
+
+        let frames = cx.get_profile_frames();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].frame_index, 0);
+        assert_eq!(frames[1].frame_index, 1);
+        let zones: Vec<_> = frames[0].zones.iter().map(|zone| (zone.name, zone.depth)).collect();
+        assert_eq!(zones, vec![("draw", 0), ("layout", 1), ("paint", 0)]);
+        assert!(frames[0].zones.iter().all(|zone| zone.duration >= 0. && zone.start >= frames[0].start));
+
+        let json = cx.profile_to_chrome_trace_json();
+        assert!(json.starts_with("{\"traceEvents\":[{\"name\":\"frame 0\",\"ph\":\"X\""));
+        assert_eq!(json.matches("\"ph\":\"X\"").count(), 6);
+    }
+
+    #[test]
+    fn zones_over_the_limit_dont_end_outer_zones() {
+        let mut cx = Cx::new_test();
+        cx.set_profiler_enabled(true);
+        cx.profile_begin_zone("draw");
+        for _ in 0..MAX_PROFILE_ZONES_PER_FRAME + 10 {
+            cx.profile_begin_zone("layout");
+            cx.profile_begin_zone("nested");
+            cx.profile_end_zone();
+            cx.profile_end_zone();
+        }
+        // Still nested in "draw".
+        cx.profile_begin_zone("paint");
+        cx.profile_end_zone();
+        assert_eq!(cx.profiler.zone_stack, vec![0]);
+        assert_eq!(cx.profiler.skipped_depth, 0);
+        cx.profile_end_zone();
+        cx.profile_end_frame();
+
+        let frame = &cx.get_profile_frames()[0];
+        assert_eq!(frame.zones.len(), MAX_PROFILE_ZONES_PER_FRAME);
+        assert_eq!(frame.zones[0].name, "draw");
+        let last_zone = frame.zones.last().unwrap();
+        assert!(frame.zones[0].start + frame.zones[0].duration >= last_zone.start + last_zone.duration);
+    }
 }
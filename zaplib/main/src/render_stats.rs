@@ -0,0 +1,174 @@
+//! Counters for what happened during the last paint, for catching rendering regressions.
+
+use crate::*;
+use std::collections::{BTreeMap, HashSet};
+
+/// Counters for the last frame that was painted; see [`Cx::get_render_stats`].
+///
+/// Useful for catching regressions, like a component accidentally creating a [`DrawCall`] per item,
+/// or re-uploading instance data that didn't change.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    /// Number of [`Pass`]es that were painted.
+    pub passes: usize,
+    /// Number of [`View`]s that were painted, including nested ones.
+    pub views: usize,
+    /// Number of actual draw calls, so [`DrawCall`]s with instances.
+    pub draw_calls: usize,
+    /// Total number of instances in [`RenderStats::draw_calls`].
+    pub instances: usize,
+    /// Number of instances per [`Shader`] name (the last code fragment of the [`Shader`]).
+    pub instances_per_shader: BTreeMap<String, usize>,
+    /// Bytes of instance data that were uploaded to the GPU, since they changed.
+    pub instance_bytes_uploaded: usize,
+    /// Bytes of uniform data that were uploaded to the GPU, including the uniforms that every [`DrawCall`] has.
+    pub uniform_bytes_uploaded: usize,
+    /// Number of [`Texture`]s that were uploaded to the GPU, since they changed.
+    pub texture_uploads: usize,
+    /// Number of [`Shader`]s that were (re)compiled.
+    pub shader_compiles: usize,
+    /// Number of times instances were added to an existing [`DrawCall`] instead of creating a new one, since
+    /// the last paint. See [`CxDebugFlags::disable_draw_call_batching`].
+    pub batched_draw_calls: usize,
+    /// Number of instances that were skipped on the CPU because they were not visible, since the last paint.
+    /// See [`Cx::add_culled_instances`].
+    pub culled_instances: usize,
+}
+
+impl Cx {
+    /// Get the [`RenderStats`] of the last frame that was painted.
+    pub fn get_render_stats(&self) -> &RenderStats {
+        &self.render_stats
+    }
+
+    /// Report that `count` instances were not added since they were not visible, e.g. using frustum culling.
+    /// Shows up in [`RenderStats::culled_instances`].
+    pub fn add_culled_instances(&mut self, count: usize) {
+        self.culled_instances_since_paint += count;
+    }
+
+    /// Compute [`Cx::render_stats`] for the passes that we're about to paint. Should be called by
+    /// platforms before compiling shaders and painting, since that resets all the dirty flags.
+    pub(crate) fn collect_render_stats(&mut self, passes_todo: &[usize]) {
+        let mut stats = RenderStats {
+            passes: passes_todo.len(),
+            shader_compiles: self.shader_recompile_ids.len(),
+            batched_draw_calls: std::mem::take(&mut self.batched_draw_calls_since_paint),
+            culled_instances: std::mem::take(&mut self.culled_instances_since_paint),
+            ..RenderStats::default()
+        };
+        let mut texture_ids = HashSet::new();
+        for &pass_id in passes_todo {
+            if let Some(view_id) = self.passes[pass_id].main_view_id {
+                self.collect_render_stats_for_view(view_id, &mut stats, &mut texture_ids);
+            }
+        }
+        stats.texture_uploads = texture_ids
+            .into_iter()
+            .filter(|&texture_id| matches!(self.textures.get(texture_id), Some(texture) if texture.update_image))
+            .count();
+        self.render_stats = stats;
+    }
+
+    fn collect_render_stats_for_view(&self, view_id: usize, stats: &mut RenderStats, texture_ids: &mut HashSet<usize>) {
+        stats.views += 1;
+        let cxview = &self.views[view_id];
+        for draw_call in &cxview.draw_calls[..cxview.draw_calls_len] {
+            if draw_call.sub_view_id != 0 {
+                self.collect_render_stats_for_view(draw_call.sub_view_id, stats, texture_ids);
+                continue;
+            }
+            let sh = &self.shaders[draw_call.shader_id];
+            let instances = draw_call.instances.len() / sh.mapping.instance_props.total_slots.max(1);
+            if instances == 0 {
+                continue;
+            }
+            stats.draw_calls += 1;
+            stats.instances += instances;
+            *stats.instances_per_shader.entry(sh.name.clone()).or_default() += instances;
+            if draw_call.instance_dirty {
+                stats.instance_bytes_uploaded += draw_call.instances.len() * std::mem::size_of::<f32>();
+            }
+            stats.uniform_bytes_uploaded += std::mem::size_of::<DrawUniforms>();
+            if draw_call.uniforms_dirty {
+                stats.uniform_bytes_uploaded += draw_call.user_uniforms.len() * std::mem::size_of::<f32>();
+            }
+            texture_ids.extend(draw_call.textures_2d.iter().map(|&texture_id| texture_id as usize));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static RED_SHADER: Shader = Shader {
+        build_geom: Some(QuadIns::build_geom),
+        code_to_concatenate: &[
+            Cx::STD_SHADER,
+            QuadIns::SHADER,
+            code_fragment!(
+                r#"
+                fn pixel() -> vec4 {
+                    return vec4(1., 0., 0., 1.);
+                }
+                "#
+            ),
+        ],
+        ..Shader::DEFAULT
+    };
+
+    static BLUE_SHADER: Shader = Shader {
+        build_geom: Some(QuadIns::build_geom),
+        code_to_concatenate: &[
+            Cx::STD_SHADER,
+            QuadIns::SHADER,
+            code_fragment!(
+                r#"
+                fn pixel() -> vec4 {
+                    return vec4(0., 0., 1., 1.);
+                }
+                "#
+            ),
+        ],
+        ..Shader::DEFAULT
+    };
+
+    #[test]
+    fn counts_draw_calls_and_instances() {
+        let mut cx = Cx::new_test();
+        let mut pass = Pass::default();
+        let mut view = View::default();
+        let quad = QuadIns::from_rect(Rect { pos: vec2(0., 0.), size: vec2(10., 10.) });
+
+        cx.in_redraw_cycle = true;
+        pass.begin_pass(&mut cx, Vec4::default());
+        view.begin_view(&mut cx, LayoutSize::FILL);
+        cx.add_instances(&RED_SHADER, &[quad; 3]);
+        // Gets batched into the previous draw call.
+        cx.add_instances(&RED_SHADER, &[quad; 2]);
+        cx.add_instances(&BLUE_SHADER, &[quad; 4]);
+        cx.add_culled_instances(7);
+        view.end_view(&mut cx);
+        pass.end_pass(&mut cx);
+        cx.in_redraw_cycle = false;
+
+        cx.collect_render_stats(&[pass.pass_id.unwrap()]);
+        let stats = cx.get_render_stats();
+        assert_eq!(stats.passes, 1);
+        assert_eq!(stats.views, 1);
+        assert_eq!(stats.draw_calls, 2);
+        assert_eq!(stats.instances, 9);
+        assert_eq!(stats.instances_per_shader.values().copied().collect::<Vec<_>>(), vec![5, 4]);
+        assert_eq!(stats.instance_bytes_uploaded, 9 * std::mem::size_of::<QuadIns>());
+        assert_eq!(stats.shader_compiles, 2);
+        assert_eq!(stats.texture_uploads, 0);
+        assert_eq!(stats.batched_draw_calls, 1);
+        assert_eq!(stats.culled_instances, 7);
+
+        // Counters since the last paint get reset.
+        cx.collect_render_stats(&[pass.pass_id.unwrap()]);
+        assert_eq!(cx.get_render_stats().batched_draw_calls, 0);
+        assert_eq!(cx.get_render_stats().culled_instances, 0);
+    }
+}
@@ -3,6 +3,12 @@
 use crate::*;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use zaplib_shader_compiler::error::ParseError;
+#[cfg(any(target_arch = "wasm32", target_os = "linux"))]
+use zaplib_shader_compiler::generate_glsl;
+#[cfg(target_os = "windows")]
+use zaplib_shader_compiler::generate_hlsl;
+#[cfg(target_os = "macos")]
+use zaplib_shader_compiler::generate_metal;
 use zaplib_shader_compiler::span::{CodeFragmentId, Span};
 use zaplib_shader_compiler::ty::Ty;
 use zaplib_shader_compiler::{Decl, ShaderAst};
@@ -19,13 +25,15 @@ use zaplib_shader_compiler::{Decl, ShaderAst};
 /// convenience. (We could instead have used a single [`slice`] but they are
 /// annoying to get through concatenation..)
 ///
-/// TODO(JP): Would be good to instead compile shaders beforehand, ie. during
-/// compile time. Should look into that at some point.
+/// To compile shaders beforehand instead of at startup, see [`precompile_shaders`].
 pub struct Shader {
     /// The [`Geometry`] that we will draw with, if any. Can be overridden using [`DrawCallProps::gpu_geometry`].
     pub build_geom: Option<fn() -> Geometry>,
     /// A bunch of [`CodeFragment`]s that will get concatenated.
     pub code_to_concatenate: &'static [CodeFragment],
+    /// Code generated at build time using [`precompile_shaders`], which then gets used instead of
+    /// [`Shader::code_to_concatenate`].
+    pub precompiled: Option<&'static PrecompiledShader>,
     /// The id of the shader (index into [`Cx::shaders`]), or [`Shader::UNCOMPILED_SHADER_ID`] if uninitialized.
     /// You should never read or modify this manually (see TODO below).
     ///
@@ -43,8 +51,12 @@ impl Shader {
     /// We suppress `clippy::declare_interior_mutable_const` here since we don't actually want shader_id in this constant
     /// to be editable.
     #[allow(clippy::declare_interior_mutable_const)]
-    pub const DEFAULT: Shader =
-        Shader { build_geom: None, code_to_concatenate: &[], shader_id: AtomicUsize::new(Self::UNCOMPILED_SHADER_ID) };
+    pub const DEFAULT: Shader = Shader {
+        build_geom: None,
+        code_to_concatenate: &[],
+        precompiled: None,
+        shader_id: AtomicUsize::new(Self::UNCOMPILED_SHADER_ID),
+    };
 
     const UNCOMPILED_SHADER_ID: usize = usize::MAX;
 
@@ -53,7 +65,7 @@ impl Shader {
 
         let shader = &mut cx.shaders[shader_id];
         let shader_ast = cx.shader_ast_generator.generate_shader_ast(new_code_to_concatenate)?;
-        if shader.mapping != CxShaderMapping::from_shader_ast(shader_ast.clone()) {
+        if shader.mapping != CxShaderMapping::from_shader_ast(&shader_ast) {
             return Err(ParseError {
                 span: Span { code_fragment_id: CodeFragmentId(0), start: 0, end: 0 },
                 message: "Mismatch in shader mapping".to_string(),
@@ -103,7 +115,21 @@ pub(crate) struct CxShaderMapping {
 }
 
 impl CxShaderMapping {
-    fn from_shader_ast(shader_ast: ShaderAst) -> Self {
+    fn from_shader_ast(shader_ast: &ShaderAst) -> Self {
+        Self::from_props(get_shader_props(shader_ast))
+    }
+
+    fn from_precompiled(precompiled: &PrecompiledShader) -> Self {
+        Self::from_props(
+            precompiled
+                .props
+                .iter()
+                .map(|prop| (prop.kind, PropDef { name: prop.name.to_string(), ty: prop.ty.to_ty() }))
+                .collect(),
+        )
+    }
+
+    fn from_props(props: Vec<(ShaderPropKind, PropDef)>) -> Self {
         let mut instances = Vec::new();
         let mut geometries = Vec::new();
         let mut user_uniforms = Vec::new();
@@ -111,39 +137,15 @@ impl CxShaderMapping {
         let mut view_uniforms = Vec::new();
         let mut pass_uniforms = Vec::new();
         let mut textures = Vec::new();
-        for decl in shader_ast.decls {
-            match decl {
-                Decl::Geometry(decl) => {
-                    let prop_def = PropDef { name: decl.ident.to_string(), ty: decl.ty_expr.ty.borrow().clone().unwrap() };
-                    geometries.push(prop_def);
-                }
-                Decl::Instance(decl) => {
-                    let prop_def = PropDef { name: decl.ident.to_string(), ty: decl.ty_expr.ty.borrow().clone().unwrap() };
-                    instances.push(prop_def);
-                }
-                Decl::Uniform(decl) => {
-                    let prop_def = PropDef { name: decl.ident.to_string(), ty: decl.ty_expr.ty.borrow().clone().unwrap() };
-                    match decl.block_ident {
-                        Some(bi) if bi.with(|string| string == "draw") => {
-                            draw_uniforms.push(prop_def);
-                        }
-                        Some(bi) if bi.with(|string| string == "view") => {
-                            view_uniforms.push(prop_def);
-                        }
-                        Some(bi) if bi.with(|string| string == "pass") => {
-                            pass_uniforms.push(prop_def);
-                        }
-                        None => {
-                            user_uniforms.push(prop_def);
-                        }
-                        _ => (),
-                    }
-                }
-                Decl::Texture(decl) => {
-                    let prop_def = PropDef { name: decl.ident.to_string(), ty: decl.ty_expr.ty.borrow().clone().unwrap() };
-                    textures.push(prop_def);
-                }
-                _ => (),
+        for (kind, prop_def) in props {
+            match kind {
+                ShaderPropKind::Geometry => geometries.push(prop_def),
+                ShaderPropKind::Instance => instances.push(prop_def),
+                ShaderPropKind::UserUniform => user_uniforms.push(prop_def),
+                ShaderPropKind::DrawUniform => draw_uniforms.push(prop_def),
+                ShaderPropKind::ViewUniform => view_uniforms.push(prop_def),
+                ShaderPropKind::PassUniform => pass_uniforms.push(prop_def),
+                ShaderPropKind::Texture => textures.push(prop_def),
             }
         }
 
@@ -169,6 +171,31 @@ impl CxShaderMapping {
     }
 }
 
+/// All the inputs of a [`ShaderAst`], in order.
+pub(crate) fn get_shader_props(shader_ast: &ShaderAst) -> Vec<(ShaderPropKind, PropDef)> {
+    let mut props = Vec::new();
+    for decl in &shader_ast.decls {
+        let (kind, ident, ty_expr) = match decl {
+            Decl::Geometry(decl) => (ShaderPropKind::Geometry, decl.ident, &decl.ty_expr),
+            Decl::Instance(decl) => (ShaderPropKind::Instance, decl.ident, &decl.ty_expr),
+            Decl::Uniform(decl) => {
+                let kind = match decl.block_ident {
+                    Some(bi) if bi.with(|string| string == "draw") => ShaderPropKind::DrawUniform,
+                    Some(bi) if bi.with(|string| string == "view") => ShaderPropKind::ViewUniform,
+                    Some(bi) if bi.with(|string| string == "pass") => ShaderPropKind::PassUniform,
+                    None => ShaderPropKind::UserUniform,
+                    _ => continue,
+                };
+                (kind, decl.ident, &decl.ty_expr)
+            }
+            Decl::Texture(decl) => (ShaderPropKind::Texture, decl.ident, &decl.ty_expr),
+            _ => continue,
+        };
+        props.push((kind, PropDef { name: ident.to_string(), ty: ty_expr.ty.borrow().clone().unwrap() }));
+    }
+    props
+}
+
 /// The raw definition of an input property to a [`Shader`].
 #[derive(Debug, Clone, Hash, PartialEq)]
 pub(crate) struct PropDef {
@@ -294,6 +321,46 @@ pub(crate) struct CxShader {
     pub(crate) platform: Option<CxPlatformShader>,
     pub(crate) mapping: CxShaderMapping,
     pub(crate) shader_ast: Option<ShaderAst>,
+    /// Used instead of [`CxShader::shader_ast`] when that is [`None`], so we don't have to generate code at runtime.
+    pub(crate) precompiled: Option<&'static PrecompiledShader>,
+}
+
+impl CxShader {
+    /// GLSL code for the vertex and fragment shaders, and if they should be printed for debugging.
+    #[cfg(any(target_arch = "wasm32", target_os = "linux"))]
+    pub(crate) fn get_glsl(&self) -> (String, String, bool) {
+        match (&self.shader_ast, self.precompiled) {
+            (Some(shader_ast), _) => (
+                generate_glsl::generate_vertex_shader(shader_ast),
+                generate_glsl::generate_fragment_shader(shader_ast),
+                shader_ast.debug,
+            ),
+            (None, Some(precompiled)) => {
+                (precompiled.glsl_vertex.to_string(), precompiled.glsl_fragment.to_string(), precompiled.debug)
+            }
+            (None, None) => panic!("Shader {} has no code to compile", self.name),
+        }
+    }
+
+    /// Metal code for the shader, and if it should be printed for debugging.
+    #[cfg(target_os = "macos")]
+    pub(crate) fn get_metal(&self) -> (String, bool) {
+        match (&self.shader_ast, self.precompiled) {
+            (Some(shader_ast), _) => (generate_metal::generate_shader(shader_ast), shader_ast.debug),
+            (None, Some(precompiled)) => (precompiled.metal.to_string(), precompiled.debug),
+            (None, None) => panic!("Shader {} has no code to compile", self.name),
+        }
+    }
+
+    /// HLSL code for the shader, and if it should be printed for debugging.
+    #[cfg(target_os = "windows")]
+    pub(crate) fn get_hlsl(&self) -> (String, bool) {
+        match (&self.shader_ast, self.precompiled) {
+            (Some(shader_ast), _) => (generate_hlsl::generate_shader(shader_ast), shader_ast.debug),
+            (None, Some(precompiled)) => (precompiled.hlsl.to_string(), precompiled.debug),
+            (None, None) => panic!("Shader {} has no code to compile", self.name),
+        }
+    }
 }
 
 impl Cx {
@@ -305,28 +372,36 @@ impl Cx {
         if shader_id != Shader::UNCOMPILED_SHADER_ID {
             shader_id
         } else {
-            // Use the last code fragment as the shader name.
-            let main_code_fragment = shader.code_to_concatenate.last().expect("No code fragments found");
-            match self.shader_ast_generator.generate_shader_ast(shader.code_to_concatenate) {
-                Err(err) => panic!("{}", err.format_for_console(shader.code_to_concatenate)),
-                Ok(shader_ast) => {
-                    let gpu_geometry = shader.build_geom.map(|build_geom| GpuGeometry::new(self, (build_geom)()));
+            let (name, mapping, shader_ast) = if let Some(precompiled) = shader.precompiled {
+                (precompiled.name.to_string(), CxShaderMapping::from_precompiled(precompiled), None)
+            } else {
+                // Use the last code fragment as the shader name.
+                let main_code_fragment = shader.code_to_concatenate.last().expect("No code fragments found");
+                match self.shader_ast_generator.generate_shader_ast(shader.code_to_concatenate) {
+                    Err(err) => panic!("{}", err.format_for_console(shader.code_to_concatenate)),
+                    Ok(shader_ast) => (
+                        main_code_fragment.name_line_col_at_offset(0),
+                        CxShaderMapping::from_shader_ast(&shader_ast),
+                        Some(shader_ast),
+                    ),
+                }
+            };
+            let gpu_geometry = shader.build_geom.map(|build_geom| GpuGeometry::new(self, (build_geom)()));
 
-                    let shader_id = self.shaders.len();
-                    self.shaders.push(CxShader {
-                        name: main_code_fragment.name_line_col_at_offset(0),
-                        gpu_geometry,
-                        mapping: CxShaderMapping::from_shader_ast(shader_ast.clone()),
-                        platform: None,
-                        shader_ast: Some(shader_ast),
-                    });
-                    self.shader_recompile_ids.push(shader_id);
+            let shader_id = self.shaders.len();
+            self.shaders.push(CxShader {
+                name,
+                gpu_geometry,
+                mapping,
+                platform: None,
+                shader_ast,
+                precompiled: shader.precompiled,
+            });
+            self.shader_recompile_ids.push(shader_id);
 
-                    shader.shader_id.store(shader_id, Ordering::Relaxed);
+            shader.shader_id.store(shader_id, Ordering::Relaxed);
 
-                    shader_id
-                }
-            }
+            shader_id
         }
     }
 }
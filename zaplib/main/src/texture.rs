@@ -4,9 +4,8 @@ use crate::*;
 
 /// A persistent reference to a GPU texture.
 ///
-/// TODO(JP): The way this works is a bit inconsistent with other draw tree structures, like
-/// [`View`], [`Area`], and so on. These never get reused. It might be nicer to allocate these
-/// dynamically and clean them up if they don't get used in a draw tree.
+/// The texture lives until you call [`Texture::free`] (or [`TextureHandle::free`]), after which its
+/// slot in [`Cx::textures`] gets reused.
 #[derive(Debug, Default)]
 pub struct Texture {
     pub(crate) handle: Option<TextureHandle>,
@@ -17,12 +16,7 @@ impl Texture {
         if let Some(handle) = self.handle {
             handle
         } else {
-            let handle = TextureHandle {
-                texture_id: {
-                    cx.textures.push(CxTexture::default());
-                    (cx.textures.len() - 1) as u32
-                },
-            };
+            let handle = cx.alloc_texture(CxTexture::default());
             self.handle = Some(handle);
             handle
         }
@@ -32,35 +26,39 @@ impl Texture {
         if let Some(handle) = self.handle {
             handle
         } else {
-            let handle = TextureHandle {
-                texture_id: {
-                    cx.textures.push(CxTexture {
-                        desc: TextureDesc { format: TextureFormat::Depth32Stencil8, ..TextureDesc::default() },
-                        ..CxTexture::default()
-                    });
-                    (cx.textures.len() - 1) as u32
-                },
-            };
+            let handle = cx.alloc_texture(CxTexture {
+                desc: TextureDesc { format: TextureFormat::Depth32Stencil8, ..TextureDesc::default() },
+                ..CxTexture::default()
+            });
             self.handle = Some(handle);
             handle
         }
     }
 
     pub fn get_with_dimensions(&mut self, cx: &mut Cx, width: usize, height: usize) -> TextureHandle {
+        self.get_with_format(cx, TextureFormat::ImageRGBA, width, height)
+    }
+
+    /// Get a texture with a CPU-side buffer of the given [`TextureFormat`] and dimensions, which you can write to
+    /// using [`TextureHandle::get_image_mut`], [`TextureHandle::get_image_u8_mut`], or
+    /// [`TextureHandle::get_image_f32_mut`], depending on the format.
+    pub fn get_with_format(&mut self, cx: &mut Cx, format: TextureFormat, width: usize, height: usize) -> TextureHandle {
+        assert!(format != TextureFormat::Depth32Stencil8, "Use Texture::get_depth for depth textures");
         if let Some(handle) = self.handle {
             handle
         } else {
-            let handle = TextureHandle {
-                texture_id: {
-                    let cx_texture = CxTexture {
-                        desc: TextureDesc { width: Some(width), height: Some(height), ..Default::default() },
-                        image_u32: vec![0; width * height],
-                        ..CxTexture::default()
-                    };
-                    cx.textures.push(cx_texture);
-                    (cx.textures.len() - 1) as u32
-                },
+            let mut cx_texture = CxTexture {
+                desc: TextureDesc { format, width: Some(width), height: Some(height), ..Default::default() },
+                ..CxTexture::default()
             };
+            let len = width * height * format.channels();
+            match format.cpu_storage() {
+                TextureStorage::U32 => cx_texture.image_u32 = vec![0; width * height],
+                TextureStorage::U8 => cx_texture.image_u8 = vec![0; len],
+                TextureStorage::F32 => cx_texture.image_f32 = vec![0.; len],
+                TextureStorage::None => {}
+            }
+            let handle = cx.alloc_texture(cx_texture);
             self.handle = Some(handle);
             handle
         }
@@ -69,28 +67,229 @@ impl Texture {
     pub fn unwrap_texture_handle(&self) -> TextureHandle {
         self.handle.unwrap()
     }
+
+    /// Free the texture (if any), releasing its GPU resources. Calling one of the `get_*` functions
+    /// afterwards creates a new texture.
+    ///
+    /// Make sure it's no longer used by any [`Pass`] or drawn anywhere, by redrawing the [`View`]s that used it.
+    pub fn free(&mut self, cx: &mut Cx) {
+        if let Some(handle) = self.handle.take() {
+            handle.free(cx);
+        }
+    }
 }
 
 /// A pointer to a [`CxTexture`] (indexed in [`Cx::textures`] using [`TextureHandle::texture_id`]),
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub struct TextureHandle {
     pub(crate) texture_id: u32,
+    /// Should match [`CxTexture::generation`]; otherwise this handle has been freed.
+    pub(crate) generation: u64,
 }
 
 impl TextureHandle {
+    /// The pixels of a [`TextureFormat::ImageRGBA`] texture, one [`u32`] per pixel.
     pub fn get_image_mut<'a>(&self, cx: &'a mut Cx) -> &'a mut [u32] {
+        let cx_texture = self.get_cx_texture_for_update(cx, TextureStorage::U32);
+        &mut cx_texture.image_u32
+    }
+
+    /// The pixels of a [`TextureFormat::R8`] or [`TextureFormat::RG8`] texture, with one [`u8`] per channel.
+    pub fn get_image_u8_mut<'a>(&self, cx: &'a mut Cx) -> &'a mut [u8] {
+        let cx_texture = self.get_cx_texture_for_update(cx, TextureStorage::U8);
+        &mut cx_texture.image_u8
+    }
+
+    /// The pixels of a [`TextureFormat::R32F`], [`TextureFormat::RGBA16F`], or [`TextureFormat::RGBA32F`]
+    /// texture, with one [`f32`] per channel.
+    pub fn get_image_f32_mut<'a>(&self, cx: &'a mut Cx) -> &'a mut [f32] {
+        let cx_texture = self.get_cx_texture_for_update(cx, TextureStorage::F32);
+        &mut cx_texture.image_f32
+    }
+
+    /// Set how the texture gets sampled in shaders (using `sample2d`), like the filtering and wrapping.
+    /// Defaults to [`SamplerDesc::default`].
+    pub fn set_sampler(&self, cx: &mut Cx, sampler: SamplerDesc) {
+        self.debug_assert_not_freed(cx);
+        let cx_texture = &mut cx.textures[self.texture_id as usize];
+        if cx_texture.sampler != sampler {
+            // Mipmaps get generated when uploading the image.
+            if cx_texture.sampler.mipmaps != sampler.mipmaps && cx_texture.desc.format.cpu_storage() != TextureStorage::None {
+                cx_texture.update_image = true;
+            }
+            cx_texture.sampler = sampler;
+        }
+    }
+
+    /// Free the texture, releasing its GPU resources before the next paint, and allowing its slot to be
+    /// reused by a new texture. Using this handle (or any copies of it) afterwards is a bug, which gets
+    /// caught in debug builds.
+    ///
+    /// Usually you want to use [`Texture::free`] instead, which also clears out the [`Texture`].
+    pub fn free(self, cx: &mut Cx) {
+        self.debug_assert_not_freed(cx);
+        let cx_texture = &mut cx.textures[self.texture_id as usize];
+        let generation = cx_texture.generation + 1;
+        let cx_texture = std::mem::replace(cx_texture, CxTexture { generation, ..CxTexture::default() });
+        cx.released_resources.textures.push((self.texture_id, cx_texture.platform));
+        cx.textures_free.push(self.texture_id);
+    }
+
+    /// Panics in debug builds if the texture has been freed using [`TextureHandle::free`].
+    pub(crate) fn debug_assert_not_freed(&self, cx: &Cx) {
+        debug_assert!(
+            cx.textures[self.texture_id as usize].generation == self.generation,
+            "Using texture {} after it has been freed",
+            self.texture_id
+        );
+    }
+
+    fn get_cx_texture_for_update<'a>(&self, cx: &'a mut Cx, storage: TextureStorage) -> &'a mut CxTexture {
+        self.debug_assert_not_freed(cx);
         let cx_texture = cx.textures.get_mut(self.texture_id as usize).unwrap();
+        assert!(
+            cx_texture.desc.format.cpu_storage() == storage,
+            "Texture format {:?} doesn't use this type of buffer",
+            cx_texture.desc.format
+        );
         cx_texture.update_image = true;
-        &mut cx_texture.image_u32
+        cx_texture
+    }
+}
+
+impl Cx {
+    /// Add a [`CxTexture`] to [`Cx::textures`], reusing the slot of a freed texture if possible.
+    pub(crate) fn alloc_texture(&mut self, cx_texture: CxTexture) -> TextureHandle {
+        if let Some(texture_id) = self.textures_free.pop() {
+            let generation = self.textures[texture_id as usize].generation;
+            self.textures[texture_id as usize] = CxTexture { generation, ..cx_texture };
+            TextureHandle { texture_id, generation }
+        } else {
+            self.textures.push(cx_texture);
+            TextureHandle { texture_id: (self.textures.len() - 1) as u32, generation: 0 }
+        }
     }
 }
 
-// TODO(Paras): Standardize and test all platforms on RGBA.
-// TODO(Paras): Make image_u32 updating work on Linux.
-#[derive(Copy, Clone, PartialEq)]
-pub(crate) enum TextureFormat {
+/// The format of the pixels in a [`Texture`].
+///
+/// TODO(Paras): Standardize and test all platforms on RGBA.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextureFormat {
+    /// 8-bit RGBA, stored as one [`u32`] per pixel on the CPU; see [`TextureHandle::get_image_mut`].
     ImageRGBA,
+    /// Only for depth buffers of [`Pass`]es.
     Depth32Stencil8,
+    /// Single 8-bit channel, read as `r` in shaders. See [`TextureHandle::get_image_u8_mut`].
+    R8,
+    /// Two 8-bit channels, read as `rg` in shaders. See [`TextureHandle::get_image_u8_mut`].
+    RG8,
+    /// Single 32-bit float channel, read as `r` in shaders. See [`TextureHandle::get_image_f32_mut`].
+    R32F,
+    /// 16-bit float RGBA. Stored as [`f32`]s on the CPU and converted when uploading, so this only saves
+    /// GPU memory. See [`TextureHandle::get_image_f32_mut`].
+    RGBA16F,
+    /// 32-bit float RGBA. See [`TextureHandle::get_image_f32_mut`].
+    RGBA32F,
+}
+
+/// Which buffer in [`CxTexture`] holds the pixels of a [`TextureFormat`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum TextureStorage {
+    U32,
+    U8,
+    F32,
+    None,
+}
+
+impl TextureFormat {
+    /// Number of channels per pixel.
+    pub fn channels(&self) -> usize {
+        match self {
+            TextureFormat::ImageRGBA | TextureFormat::RGBA16F | TextureFormat::RGBA32F => 4,
+            TextureFormat::RG8 => 2,
+            TextureFormat::R8 | TextureFormat::R32F | TextureFormat::Depth32Stencil8 => 1,
+        }
+    }
+
+    /// Number of bytes per pixel on the GPU.
+    #[cfg_attr(any(target_arch = "wasm32", target_os = "linux"), allow(dead_code))]
+    pub(crate) fn bytes_per_pixel(&self) -> usize {
+        match self {
+            TextureFormat::R8 => 1,
+            TextureFormat::RG8 => 2,
+            TextureFormat::ImageRGBA | TextureFormat::R32F => 4,
+            TextureFormat::RGBA16F | TextureFormat::Depth32Stencil8 => 8,
+            TextureFormat::RGBA32F => 16,
+        }
+    }
+
+    pub(crate) fn cpu_storage(&self) -> TextureStorage {
+        match self {
+            TextureFormat::ImageRGBA => TextureStorage::U32,
+            TextureFormat::R8 | TextureFormat::RG8 => TextureStorage::U8,
+            TextureFormat::R32F | TextureFormat::RGBA16F | TextureFormat::RGBA32F => TextureStorage::F32,
+            TextureFormat::Depth32Stencil8 => TextureStorage::None,
+        }
+    }
+}
+
+/// How to filter a [`Texture`] when it's sampled in between pixels, or when it's drawn smaller or
+/// larger than its actual size. See [`SamplerDesc`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextureFilter {
+    /// Use the closest pixel; useful for pixel art and for data that shouldn't be interpolated.
+    Nearest,
+    /// Interpolate between the closest pixels.
+    Linear,
+}
+
+/// What to do when sampling a [`Texture`] outside of the 0 to 1 range. See [`SamplerDesc`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextureWrap {
+    /// Use the pixel at the edge.
+    ClampToEdge,
+    /// Tile the texture.
+    Repeat,
+    /// Tile the texture, flipping every other tile.
+    MirroredRepeat,
+}
+
+/// How a [`Texture`] gets sampled in shaders. Set using [`TextureHandle::set_sampler`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SamplerDesc {
+    /// Filter to use when the texture is drawn smaller than its actual size.
+    pub min_filter: TextureFilter,
+    /// Filter to use when the texture is drawn larger than its actual size.
+    pub mag_filter: TextureFilter,
+    /// Horizontal wrapping.
+    pub wrap_u: TextureWrap,
+    /// Vertical wrapping.
+    pub wrap_v: TextureWrap,
+    /// Generate mipmaps when uploading the image, which makes the texture look a lot better when it's drawn
+    /// much smaller than its actual size. [`SamplerDesc::min_filter`] is then also used for filtering between
+    /// mipmap levels. Only works for textures with CPU-side data (not for [`Pass`] render targets), and in
+    /// WebAssembly only for textures with power-of-two dimensions.
+    pub mipmaps: bool,
+}
+
+impl SamplerDesc {
+    pub const DEFAULT: Self = Self {
+        min_filter: TextureFilter::Linear,
+        mag_filter: TextureFilter::Linear,
+        wrap_u: TextureWrap::ClampToEdge,
+        wrap_v: TextureWrap::ClampToEdge,
+        mipmaps: false,
+    };
+
+    /// Nearest filtering, which is what you typically want for pixel art.
+    pub const NEAREST: Self = Self { min_filter: TextureFilter::Nearest, mag_filter: TextureFilter::Nearest, ..Self::DEFAULT };
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
 }
 
 #[derive(Clone, PartialEq)]
@@ -111,9 +310,142 @@ impl Default for TextureDesc {
 #[derive(Default)]
 pub(crate) struct CxTexture {
     pub(crate) desc: TextureDesc,
+    /// Pixels for [`TextureStorage::U32`] formats.
     pub(crate) image_u32: Vec<u32>,
+    /// Channels for [`TextureStorage::U8`] formats.
+    pub(crate) image_u8: Vec<u8>,
+    /// Channels for [`TextureStorage::F32`] formats.
+    pub(crate) image_f32: Vec<f32>,
     pub(crate) update_image: bool,
-    // Not used on wasm
-    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    /// See [`TextureHandle::set_sampler`].
+    pub(crate) sampler: SamplerDesc,
+    /// Gets incremented when the texture is freed, to catch use of stale [`TextureHandle`]s.
+    pub(crate) generation: u64,
     pub(crate) platform: CxPlatformTexture,
 }
+
+impl CxTexture {
+    /// The image data in the layout that the GPU expects for [`TextureDesc::format`], so with [`f32`]s converted
+    /// to half floats for [`TextureFormat::RGBA16F`].
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub(crate) fn image_bytes(&self) -> std::borrow::Cow<'_, [u8]> {
+        match self.desc.format {
+            TextureFormat::RGBA16F => {
+                std::borrow::Cow::Owned(self.image_f32.iter().flat_map(|&value| f32_to_f16_bits(value).to_ne_bytes()).collect())
+            }
+            format => std::borrow::Cow::Borrowed(match format.cpu_storage() {
+                TextureStorage::U32 => cast_slice(&self.image_u32),
+                TextureStorage::U8 => &self.image_u8,
+                TextureStorage::F32 => cast_slice(&self.image_f32),
+                TextureStorage::None => &[],
+            }),
+        }
+    }
+
+    /// Whether the CPU-side buffer has the right length for the dimensions of the texture.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub(crate) fn image_has_valid_len(&self, width: usize, height: usize) -> bool {
+        let format = self.desc.format;
+        match format.cpu_storage() {
+            TextureStorage::U32 => self.image_u32.len() == width * height,
+            TextureStorage::U8 => self.image_u8.len() == width * height * format.channels(),
+            TextureStorage::F32 => self.image_f32.len() == width * height * format.channels(),
+            TextureStorage::None => true,
+        }
+    }
+}
+
+/// Convert an [`f32`] to the bits of an IEEE 754 half float, rounding to nearest even.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub(crate) fn f32_to_f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // Infinity or NaN (keeping NaNs NaN).
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        // Too large; becomes infinity.
+        return sign | 0x7c00;
+    }
+    if half_exponent <= 0 {
+        // Subnormal or zero.
+        if half_exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - half_exponent) as u32;
+        let half_mantissa = mantissa >> shift;
+        let remainder = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round_up = remainder > halfway || (remainder == halfway && half_mantissa & 1 == 1);
+        return sign | (half_mantissa + round_up as u32) as u16;
+    }
+    let half = ((half_exponent as u32) << 10) | (mantissa >> 13);
+    let remainder = mantissa & 0x1fff;
+    let round_up = remainder > 0x1000 || (remainder == 0x1000 && half & 1 == 1);
+    // Rounding up can overflow into the exponent, which correctly results in the next power of two (or infinity).
+    sign | (half + round_up as u32) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_f32_to_f16_bits() {
+        assert_eq!(f32_to_f16_bits(0.), 0x0000);
+        assert_eq!(f32_to_f16_bits(-0.), 0x8000);
+        assert_eq!(f32_to_f16_bits(1.), 0x3c00);
+        assert_eq!(f32_to_f16_bits(-2.), 0xc000);
+        assert_eq!(f32_to_f16_bits(0.5), 0x3800);
+        assert_eq!(f32_to_f16_bits(65504.), 0x7bff);
+        assert_eq!(f32_to_f16_bits(65520.), 0x7c00);
+        assert_eq!(f32_to_f16_bits(f32::INFINITY), 0x7c00);
+        assert_eq!(f32_to_f16_bits(f32::NEG_INFINITY), 0xfc00);
+        assert_eq!(f32_to_f16_bits(f32::NAN) & 0x7fff, 0x7e00);
+        // Smallest subnormal.
+        assert_eq!(f32_to_f16_bits(5.960_464_5e-8), 0x0001);
+        assert_eq!(f32_to_f16_bits(1e-10), 0x0000);
+        // Rounds to nearest even.
+        assert_eq!(f32_to_f16_bits(1. + 1. / 2048.), 0x3c00);
+        assert_eq!(f32_to_f16_bits(1. + 3. / 2048.), 0x3c02);
+    }
+
+    #[test]
+    fn reuses_slots_of_freed_textures() {
+        let mut cx = Cx::new_test();
+        let mut texture = Texture::default();
+        let handle = texture.get_with_dimensions(&mut cx, 2, 2);
+        let textures_len = cx.textures.len();
+
+        texture.free(&mut cx);
+        assert!(texture.handle.is_none());
+        assert_eq!(cx.textures_free, vec![handle.texture_id]);
+        assert_eq!(cx.released_resources.textures.len(), 1);
+
+        let new_handle = texture.get_with_format(&mut cx, TextureFormat::R8, 4, 4);
+        assert_eq!(cx.textures.len(), textures_len);
+        assert_eq!(new_handle.texture_id, handle.texture_id);
+        assert_eq!(new_handle.generation, handle.generation + 1);
+        assert_eq!(new_handle.get_image_u8_mut(&mut cx).len(), 16);
+        assert!(cx.textures_free.is_empty());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "after it has been freed")]
+    fn panics_when_using_freed_texture_handle() {
+        let mut cx = Cx::new_test();
+        let handle = Texture::default().get_with_dimensions(&mut cx, 2, 2);
+        let stale_handle = handle;
+        handle.free(&mut cx);
+        // Even when the slot has been reused.
+        Texture::default().get_with_dimensions(&mut cx, 2, 2);
+        stale_handle.get_image_mut(&mut cx);
+    }
+}
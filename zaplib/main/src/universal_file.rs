@@ -1,3 +1,6 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
 #[cfg(target_arch = "wasm32")]
 extern "C" {
     /// Synchronously read data from a "user file", a handle in JS, e.g. from dragging in a file.
@@ -5,6 +8,22 @@ extern "C" {
     /// Synchronously read data from a URL, returning a new buffer. Return value is 0 or 1 depending
     /// on whether the data was successfully read.
     fn readUrlSync(url_ptr: usize, url_len: usize, buf_ptr_out: *mut u32, buf_len_out: *mut u32) -> u32;
+    /// Synchronously read an (inclusive) byte range from a URL using an HTTP Range Request, returning a new
+    /// buffer. Return value is 0 on error, 1 if we got the requested range (in which case `total_size_out` is
+    /// set to the size of the entire file, or [`u64::MAX`] if unknown), or 2 if the server ignored the range
+    /// and sent the entire file.
+    fn readUrlRangeSync(
+        url_ptr: usize,
+        url_len: usize,
+        range_start: u64,
+        range_end: u64,
+        buf_ptr_out: *mut u32,
+        buf_len_out: *mut u32,
+        total_size_out: *mut u64,
+    ) -> u32;
+    /// Save data to a file chosen by the user (using the File System Access API where available), or
+    /// otherwise trigger a browser download. Doesn't block; the data gets copied.
+    fn saveFile(name_ptr: usize, name_len: usize, data_ptr: u64, data_len: u64);
 }
 
 enum UniversalFileInner {
@@ -16,11 +35,182 @@ enum UniversalFileInner {
     /// The [`std::fs::File`] handle itself gets set lazily when cloning, so that
     /// [`std::clone::Clone::clone`] always succeeds.
     #[cfg(any(doc, not(target_arch = "wasm32")))]
-    LocalFile { path: String, file: Option<std::fs::File> },
+    LocalFile { path: String, options: UniversalOpenOptions, file: Option<std::fs::File> },
 
     /// An actual file handle in JavaScript, e.g. from dragging in a file.
     #[cfg(any(doc, target_arch = "wasm32"))]
     WasmFile { id: usize, size: u64, pos: u64 },
+
+    /// File opened for writing in WebAssembly; see [`UniversalOpenOptions::open`].
+    #[cfg(any(doc, target_arch = "wasm32"))]
+    WasmSaveFile(WasmSaveFile),
+
+    /// URL that gets loaded in blocks using HTTP Range Requests; see [`UniversalFile::open_url_lazy`].
+    LazyUrl { file: Arc<LazyUrlFile>, pos: u64 },
+}
+
+/// Options for [`UniversalFile::open_url_lazy`].
+#[derive(Clone, Debug)]
+pub struct LazyUrlOptions {
+    /// Number of bytes to fetch per HTTP Range Request.
+    pub block_size: u64,
+    /// Maximum number of blocks to keep in memory. When more blocks get loaded, the least recently
+    /// used blocks are evicted.
+    pub max_cached_blocks: usize,
+}
+
+impl Default for LazyUrlOptions {
+    fn default() -> Self {
+        Self { block_size: 1024 * 1024, max_cached_blocks: 64 }
+    }
+}
+
+/// Least recently used cache of blocks for [`LazyUrlFile`]. The most recently used block is at the back.
+///
+/// Since the number of blocks is typically small, we just do a linear scan.
+#[derive(Default)]
+struct BlockCache {
+    blocks: VecDeque<(u64, Arc<Vec<u8>>)>,
+}
+
+impl BlockCache {
+    fn get(&mut self, block_index: u64) -> Option<Arc<Vec<u8>>> {
+        let position = self.blocks.iter().position(|(index, _)| *index == block_index)?;
+        let entry = self.blocks.remove(position).unwrap();
+        let block = Arc::clone(&entry.1);
+        self.blocks.push_back(entry);
+        Some(block)
+    }
+
+    fn insert(&mut self, block_index: u64, block: Arc<Vec<u8>>, max_cached_blocks: usize) {
+        self.blocks.retain(|(index, _)| *index != block_index);
+        self.blocks.push_back((block_index, block));
+        while self.blocks.len() > max_cached_blocks.max(1) {
+            self.blocks.pop_front();
+        }
+    }
+}
+
+/// Shared state of all clones of a [`UniversalFileInner::LazyUrl`].
+struct LazyUrlFile {
+    url: String,
+    size: u64,
+    options: LazyUrlOptions,
+    cache: Mutex<BlockCache>,
+}
+
+impl LazyUrlFile {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        if pos >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+        let block_index = pos / self.options.block_size;
+        let block = self.get_block(block_index)?;
+        let offset = (pos - block_index * self.options.block_size) as usize;
+        if offset >= block.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("Server returned fewer bytes than expected for {}", self.url),
+            ));
+        }
+        let bytes_read = std::cmp::min(buf.len(), block.len() - offset);
+        buf[..bytes_read].copy_from_slice(&block[offset..offset + bytes_read]);
+        Ok(bytes_read)
+    }
+
+    fn get_block(&self, block_index: u64) -> std::io::Result<Arc<Vec<u8>>> {
+        if let Some(block) = self.cache.lock().unwrap().get(block_index) {
+            return Ok(block);
+        }
+        // Don't hold the lock while fetching, so other threads can still read cached blocks in the meantime.
+        let start = block_index * self.options.block_size;
+        let end = std::cmp::min(start + self.options.block_size, self.size) - 1;
+        match fetch_url_range(&self.url, start, end)? {
+            RangeResponse::Partial { data, total_size: _ } => {
+                let block = Arc::new(data);
+                self.cache.lock().unwrap().insert(block_index, Arc::clone(&block), self.options.max_cached_blocks);
+                Ok(block)
+            }
+            RangeResponse::Full(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("Server stopped supporting range requests for {}", self.url),
+            )),
+        }
+    }
+}
+
+/// Result of [`fetch_url_range`].
+enum RangeResponse {
+    /// The server returned the requested range, with the size of the entire file if it told us.
+    Partial { data: Vec<u8>, total_size: Option<u64> },
+    /// The server doesn't support range requests, and returned the entire file instead.
+    Full(Vec<u8>),
+}
+
+/// Get the total size from a `Content-Range` header, e.g. `bytes 0-1023/146515`.
+fn parse_content_range_total_size(content_range: &str) -> Option<u64> {
+    content_range.trim().strip_prefix("bytes ")?.split('/').nth(1)?.parse().ok()
+}
+
+/// Synchronously fetch an inclusive byte range from a URL.
+fn fetch_url_range(url: &str, start: u64, end: u64) -> std::io::Result<RangeResponse> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut request = crate::universal_http_stream::Request::new("GET", url);
+        // Ask for the data as-is, since ranges of compressed data can't be decompressed by themselves.
+        request.headers = vec![
+            ("range".to_string(), format!("bytes={}-{}", start, end)),
+            ("accept-encoding".to_string(), "identity".to_string()),
+        ];
+        let mut response = request.send()?;
+        if !response.is_success() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("Error while loading {}: {} {}", url, response.status, response.status_text),
+            ));
+        }
+        let total_size = response.header("content-range").and_then(parse_content_range_total_size);
+        let mut data = Vec::new();
+        std::io::Read::read_to_end(&mut response, &mut data)?;
+        if response.status == 206 {
+            Ok(RangeResponse::Partial { data, total_size })
+        } else {
+            Ok(RangeResponse::Full(data))
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let chars = url.chars().collect::<Vec<char>>();
+        unsafe {
+            let mut buf_ptr_out: u32 = 0;
+            let mut buf_len_out: u32 = 0;
+            let mut total_size_out: u64 = 0;
+            let result = readUrlRangeSync(
+                chars.as_ptr() as usize,
+                chars.len() as usize,
+                start,
+                end,
+                &mut buf_ptr_out,
+                &mut buf_len_out,
+                &mut total_size_out,
+            );
+            if result == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    format!("Error while loading {}; check the browser console for details", url),
+                ));
+            }
+            let data = Vec::<u8>::from_raw_parts(buf_ptr_out as *mut u8, buf_len_out as usize, buf_len_out as usize);
+            if result == 1 {
+                Ok(RangeResponse::Partial {
+                    data,
+                    total_size: if total_size_out == u64::MAX { None } else { Some(total_size_out) },
+                })
+            } else {
+                Ok(RangeResponse::Full(data))
+            }
+        }
+    }
 }
 
 /// A file handle that abstracts over the different ways we have to deal with different kinds of
@@ -29,14 +219,9 @@ enum UniversalFileInner {
 /// It tries to somewhat follow the [`std::fs::File`] API, but there are some major differences:
 /// * For the WebAssembly target, opening a file will read the entire file in memory synchronously,
 ///   over HTTP(S). This is quite different from the native behavior. This is required if we never
-///   want [`std::io::Seek::seek`] to fail.
-///   TODO(JP): We should consider different behaviors here -- potentially configurable by the user --
-///   such as completely disallowing seeking, or seeking into a buffer with a certain size, or even using
-///   HTTP Range Requests when available to fetch the data starting at a seek point (when not buffered
-///   already). This can somewhat mirror [`std::fs::OpenOptions`]. We can take some inspiration from
-///   <https://github.com/cruise-automation/webviz/blob/4dcd47d/packages/webviz-core/src/util/CachedFilelike.js>
-///   and <https://github.com/cruise-automation/webviz/blob/4dcd47d/packages/webviz-core/src/dataProviders/BrowserHttpReader.js>.
-///   Even just an option to defer loading until the first read would be useful, so you can open a file
+///   want [`std::io::Seek::seek`] to fail. For large files, use [`UniversalFile::open_url_lazy`] instead,
+///   which fetches blocks on demand using HTTP Range Requests.
+///   TODO(JP): An option to defer loading until the first read would be useful, so you can open a file
 ///   on the main thread and pass it to another thread for processing (without having to create multiple
 ///   functions for processing e.g. handles from [`crate::AppOpenFilesEvent`] differently).
 /// * [`UniversalFile::open_url`] exists, which is not available in the regular [`std::fs::File`] API. This matches
@@ -46,8 +231,8 @@ enum UniversalFileInner {
 ///   state. Also note that it's not a `try_clone` -- it will always succeed. This means that if you
 ///   clone a handle to a file that doesn't exist anymore, then you'll get that error on the next
 ///   read, not while cloning.
-/// * Currently it only supports reading, not writing. This might change in the future, but requires
-///   some thinking about how that should work in WebAssembly.
+/// * Writing is supported through [`UniversalFile::create`] and [`UniversalOpenOptions`], but in WebAssembly
+///   this works quite differently: the user gets to save the file when flushing.
 ///
 /// Note that you typically want to load files in a thread. Even on native targets the file system
 /// can be slow, e.g. when the user has mounted a remote file system, so you want to avoid blocking
@@ -58,6 +243,158 @@ enum UniversalFileInner {
 /// around this, or to prevent (at compile time) from using these file handles in older threads.
 pub struct UniversalFile(UniversalFileInner);
 
+/// Options for opening a [`UniversalFile`], e.g. for writing. Mirrors [`std::fs::OpenOptions`].
+///
+/// In WebAssembly we can't write to arbitrary paths, so when opening a file for writing, we keep the data in
+/// memory, and when calling [`std::io::Write::flush`] (or when dropping the [`UniversalFile`]) we let the user
+/// save the file using the File System Access API if the browser supports it, or otherwise trigger a download.
+/// In that case only the last component of the path is used (as the suggested file name), and the file always
+/// starts out empty, regardless of [`UniversalOpenOptions::truncate`] and [`UniversalOpenOptions::append`].
+///
+/// ```
+/// let mut file = UniversalOpenOptions::new().write(true).create(true).truncate(true).open("export.csv")?;
+/// file.write_all(csv.as_bytes())?;
+/// file.flush()?;
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UniversalOpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+}
+
+impl UniversalOpenOptions {
+    /// Creates a blank set of options, with everything set to `false`. See [`std::fs::OpenOptions::new`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [`std::fs::OpenOptions::read`].
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    /// See [`std::fs::OpenOptions::write`].
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    /// See [`std::fs::OpenOptions::append`].
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// See [`std::fs::OpenOptions::truncate`].
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// See [`std::fs::OpenOptions::create`].
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    /// See [`std::fs::OpenOptions::create_new`].
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Open a local/relative file with these options. URLs can't be opened for writing.
+    ///
+    /// See [`std::fs::OpenOptions::open`] and [`UniversalFile::open`].
+    pub fn open(&self, path: &str) -> std::io::Result<UniversalFile> {
+        if is_absolute_url(path) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("'path' is an absolute URL, use 'UniversalFile::open_url' instead: {}", path),
+            ));
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let file = self.to_std().open(path)?;
+            Ok(UniversalFile(UniversalFileInner::LocalFile { path: path.to_string(), options: *self, file: Some(file) }))
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            if self.write || self.append {
+                let name = path.rsplit('/').next().unwrap_or(path).to_string();
+                Ok(UniversalFile(UniversalFileInner::WasmSaveFile(WasmSaveFile { name, data: vec![], pos: 0, is_dirty: true })))
+            } else {
+                UniversalFile::open(path)
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn to_std(self) -> std::fs::OpenOptions {
+        let mut options = std::fs::OpenOptions::new();
+        options
+            .read(self.read)
+            .write(self.write)
+            .append(self.append)
+            .truncate(self.truncate)
+            .create(self.create)
+            .create_new(self.create_new);
+        options
+    }
+}
+
+/// Data of [`UniversalFileInner::WasmSaveFile`], which gets saved when flushing or dropping.
+#[cfg(any(doc, target_arch = "wasm32"))]
+struct WasmSaveFile {
+    /// Suggested file name when saving.
+    name: String,
+    data: Vec<u8>,
+    pos: u64,
+    /// Whether there are changes that haven't been saved yet.
+    is_dirty: bool,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl WasmSaveFile {
+    fn save(&mut self) {
+        let chars = self.name.chars().collect::<Vec<char>>();
+        unsafe {
+            saveFile(chars.as_ptr() as usize, chars.len(), self.data.as_ptr() as u64, self.data.len() as u64);
+        }
+        self.is_dirty = false;
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Drop for WasmSaveFile {
+    fn drop(&mut self) {
+        if self.is_dirty {
+            self.save();
+        }
+    }
+}
+
+/// Write `buf` into `data` at `pos`, overwriting existing data and growing `data` as needed. Like
+/// [`std::io::Cursor`] for a [`Vec`].
+#[cfg(any(test, target_arch = "wasm32"))]
+fn write_to_vec(data: &mut Vec<u8>, pos: &mut u64, buf: &[u8]) -> usize {
+    let start = *pos as usize;
+    if data.len() < start {
+        data.resize(start, 0);
+    }
+    let overlap = std::cmp::min(buf.len(), data.len() - start);
+    data[start..start + overlap].copy_from_slice(&buf[..overlap]);
+    data.extend_from_slice(&buf[overlap..]);
+    *pos += buf.len() as u64;
+    buf.len()
+}
+
 /// Hacky function for determining what is a URL and what isn't.
 fn is_absolute_url(path: &str) -> bool {
     path.starts_with("http://") || path.starts_with("https://")
@@ -65,9 +402,13 @@ fn is_absolute_url(path: &str) -> bool {
 
 /// Actually set [`UniversalFileInner::LocalFile::file`] if it hasn't been set yet.
 #[cfg(not(target_arch = "wasm32"))]
-fn get_local_file<'a>(path: &'a str, file: &'a mut Option<std::fs::File>) -> std::io::Result<&'a std::fs::File> {
+fn get_local_file<'a>(
+    path: &'a str,
+    options: &UniversalOpenOptions,
+    file: &'a mut Option<std::fs::File>,
+) -> std::io::Result<&'a std::fs::File> {
     if file.is_none() {
-        *file = Some(std::fs::File::open(path)?);
+        *file = Some(options.to_std().open(path)?);
     }
     Ok(file.as_ref().unwrap())
 }
@@ -92,7 +433,11 @@ impl UniversalFile {
 
         #[cfg(not(target_arch = "wasm32"))]
         {
-            Ok(Self(UniversalFileInner::LocalFile { path: path.to_string(), file: Some(std::fs::File::open(path)?) }))
+            Ok(Self(UniversalFileInner::LocalFile {
+                path: path.to_string(),
+                options: *UniversalOpenOptions::new().read(true),
+                file: Some(std::fs::File::open(path)?),
+            }))
         }
         #[cfg(target_arch = "wasm32")]
         {
@@ -100,6 +445,19 @@ impl UniversalFile {
         }
     }
 
+    /// Open a local/relative file for writing, creating it if it doesn't exist, and truncating it if it does.
+    /// In WebAssembly this lets the user save the file when flushing; see [`UniversalOpenOptions`].
+    ///
+    /// See [`std::fs::File::create`].
+    pub fn create(path: &str) -> std::io::Result<Self> {
+        UniversalOpenOptions::new().write(true).create(true).truncate(true).open(path)
+    }
+
+    /// Returns a new [`UniversalOpenOptions`]. See [`std::fs::File::options`].
+    pub fn options() -> UniversalOpenOptions {
+        UniversalOpenOptions::new()
+    }
+
     /// Open an absolute URL. This will always block until the entire file is loaded.
     ///
     /// Will return an error if the file does not exist.
@@ -121,6 +479,45 @@ impl UniversalFile {
         }
     }
 
+    /// Open an absolute URL, but only load the parts that are actually read, in blocks of
+    /// [`LazyUrlOptions::block_size`] using HTTP Range Requests. This makes it possible to read
+    /// files that are too large to keep in memory, e.g. in the browser.
+    ///
+    /// Blocks are kept in a least recently used cache, which is shared between clones of the
+    /// returned [`UniversalFile`]. If the server doesn't support Range Requests (or doesn't tell us
+    /// the size of the file), this falls back to loading the entire file, like [`UniversalFile::open_url`].
+    ///
+    /// Opening the file already fetches the first block, so this blocks, and returns an error if the
+    /// file does not exist. Subsequent reads block as well when a block needs to be fetched, so like
+    /// [`UniversalFile::open_url`] it's best to use this on a thread. Inspired by
+    /// <https://github.com/cruise-automation/webviz/blob/4dcd47d/packages/webviz-core/src/util/CachedFilelike.js>.
+    ///
+    /// It's highly recommended to wrap this in a [`std::io::BufReader`] with a capacity that is smaller than
+    /// [`LazyUrlOptions::block_size`], since every [`std::io::Read::read`] call returns data from at most one block.
+    pub fn open_url_lazy(url: &str, options: LazyUrlOptions) -> std::io::Result<Self> {
+        if !is_absolute_url(url) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("'url' is not an absolute URL, use 'open' instead: {}", url),
+            ));
+        }
+        if options.block_size == 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "'block_size' must be larger than 0"));
+        }
+
+        match fetch_url_range(url, 0, options.block_size - 1) {
+            Ok(RangeResponse::Partial { data, total_size: Some(size) }) => {
+                let mut cache = BlockCache::default();
+                cache.insert(0, Arc::new(data), options.max_cached_blocks);
+                let file = LazyUrlFile { url: url.to_string(), size, options, cache: Mutex::new(cache) };
+                Ok(Self(UniversalFileInner::LazyUrl { file: Arc::new(file), pos: 0 }))
+            }
+            Ok(RangeResponse::Full(data)) => Ok(Self(UniversalFileInner::FullyLoaded { data: Arc::new(data), pos: 0 })),
+            // E.g. an unknown total size, or a "416 Range Not Satisfiable" for empty files.
+            _ => Self::open_url(url),
+        }
+    }
+
     /// Create a new [`UniversalFile`] from a JS file handle.
     #[cfg(target_arch = "wasm32")]
     pub(crate) fn from_wasm_file(id: usize, size: u64) -> Self {
@@ -149,16 +546,19 @@ impl UniversalFile {
     /// Synchronously load a URL on native targets.
     #[cfg(not(target_arch = "wasm32"))]
     fn open_url_sync_native(url: &str) -> std::io::Result<Self> {
-        if let Ok(resp) = ureq::get(url).call() {
-            let mut buffer: Vec<u8> = Vec::new();
-            if std::io::Read::read_to_end(&mut resp.into_reader(), &mut buffer).is_ok() {
-                Ok(Self(UniversalFileInner::FullyLoaded { data: std::sync::Arc::new(buffer), pos: 0 }))
-            } else {
-                Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Error while reading {}", url)))
-            }
-        } else {
-            Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Error while loading {}", url)))
+        let response = crate::universal_http_stream::Request::new("GET", url).send()?;
+        if !response.is_success() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("Error while loading {}: {} {}", url, response.status, response.status_text),
+            ));
         }
+        response.into_universal_file()
+    }
+
+    /// Create a [`UniversalFile`] from data that is already in memory.
+    pub(crate) fn from_data(data: Vec<u8>) -> Self {
+        Self(UniversalFileInner::FullyLoaded { data: Arc::new(data), pos: 0 })
     }
 }
 
@@ -192,13 +592,23 @@ impl Clone for UniversalFile {
                 Self(UniversalFileInner::FullyLoaded { data: std::sync::Arc::clone(data), pos: 0 })
             }
             #[cfg(not(target_arch = "wasm32"))]
-            UniversalFileInner::LocalFile { path, file: _ } => {
-                Self(UniversalFileInner::LocalFile { path: path.clone(), file: None })
+            UniversalFileInner::LocalFile { path, options, file: _ } => {
+                // Don't truncate or try to create the file again.
+                let options = UniversalOpenOptions { truncate: false, create: false, create_new: false, ..*options };
+                Self(UniversalFileInner::LocalFile { path: path.clone(), options, file: None })
             }
             #[cfg(target_arch = "wasm32")]
             UniversalFileInner::WasmFile { id, size, pos: _ } => {
                 Self(UniversalFileInner::WasmFile { id: *id, size: *size, pos: 0 })
             }
+            UniversalFileInner::LazyUrl { file, pos: _ } => Self(UniversalFileInner::LazyUrl { file: Arc::clone(file), pos: 0 }),
+            #[cfg(target_arch = "wasm32")]
+            UniversalFileInner::WasmSaveFile(file) => Self(UniversalFileInner::WasmSaveFile(WasmSaveFile {
+                name: file.name.clone(),
+                data: file.data.clone(),
+                pos: 0,
+                is_dirty: false,
+            })),
         }
     }
 }
@@ -215,13 +625,59 @@ impl std::io::Read for UniversalFile {
                 Ok(bytes_read)
             }
             #[cfg(not(target_arch = "wasm32"))]
-            UniversalFileInner::LocalFile { path, file } => get_local_file(path, file)?.read(buf),
+            UniversalFileInner::LocalFile { path, options, file } => get_local_file(path, options, file)?.read(buf),
             #[cfg(target_arch = "wasm32")]
             UniversalFileInner::WasmFile { id, size: _, pos } => unsafe {
                 let bytes_read: u64 = readUserFileRange(*id as u32, buf.as_ptr() as u64, buf.len() as u64, *pos);
                 *pos += bytes_read;
                 Ok(bytes_read as usize)
             },
+            UniversalFileInner::LazyUrl { file, pos } => {
+                let bytes_read = file.read_at(*pos, buf)?;
+                *pos += bytes_read as u64;
+                Ok(bytes_read)
+            }
+            #[cfg(target_arch = "wasm32")]
+            UniversalFileInner::WasmSaveFile(file) => {
+                let amt = std::cmp::min(file.pos, file.data.len() as u64);
+                let mut read_buf = &file.data[(amt as usize)..];
+                let bytes_read = std::io::Read::read(&mut read_buf, buf)?;
+                file.pos += bytes_read as u64;
+                Ok(bytes_read)
+            }
+        }
+    }
+}
+
+impl std::io::Write for UniversalFile {
+    /// Returns an error if the file was not opened for writing, e.g. for URLs and files from
+    /// [`crate::AppOpenFilesEvent`].
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match &mut self.0 {
+            #[cfg(not(target_arch = "wasm32"))]
+            UniversalFileInner::LocalFile { path, options, file } => get_local_file(path, options, file)?.write(buf),
+            #[cfg(target_arch = "wasm32")]
+            UniversalFileInner::WasmSaveFile(file) => {
+                file.is_dirty = true;
+                Ok(write_to_vec(&mut file.data, &mut file.pos, buf))
+            }
+            _ => Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "This UniversalFile can't be written to")),
+        }
+    }
+
+    /// In WebAssembly this lets the user save the file; see [`UniversalOpenOptions`].
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.0 {
+            #[cfg(not(target_arch = "wasm32"))]
+            UniversalFileInner::LocalFile { path, options, file } => get_local_file(path, options, file)?.flush(),
+            #[cfg(target_arch = "wasm32")]
+            UniversalFileInner::WasmSaveFile(file) => {
+                if file.is_dirty {
+                    file.save();
+                }
+                Ok(())
+            }
+            _ => Ok(()),
         }
     }
 }
@@ -251,9 +707,12 @@ impl std::io::Seek for UniversalFile {
         match &mut self.0 {
             UniversalFileInner::FullyLoaded { data, pos } => update_pos(pos, data.len() as u64, style),
             #[cfg(not(target_arch = "wasm32"))]
-            UniversalFileInner::LocalFile { path, file } => get_local_file(path, file)?.seek(style),
+            UniversalFileInner::LocalFile { path, options, file } => get_local_file(path, options, file)?.seek(style),
             #[cfg(target_arch = "wasm32")]
             UniversalFileInner::WasmFile { id: _, size, pos } => update_pos(pos, *size, style),
+            UniversalFileInner::LazyUrl { file, pos } => update_pos(pos, file.size, style),
+            #[cfg(target_arch = "wasm32")]
+            UniversalFileInner::WasmSaveFile(file) => update_pos(&mut file.pos, file.data.len() as u64, style),
         }
     }
 }
@@ -265,3 +724,57 @@ impl std::fmt::Debug for UniversalFile {
         write!(f, "<UniversalFile>")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_content_range_total_size() {
+        assert_eq!(parse_content_range_total_size("bytes 0-1023/146515"), Some(146515));
+        assert_eq!(parse_content_range_total_size("bytes 0-1023/*"), None);
+        assert_eq!(parse_content_range_total_size("bytes */146515"), Some(146515));
+        assert_eq!(parse_content_range_total_size("146515"), None);
+    }
+
+    #[test]
+    fn block_cache_evicts_least_recently_used() {
+        let mut cache = BlockCache::default();
+        cache.insert(0, Arc::new(vec![0]), 2);
+        cache.insert(1, Arc::new(vec![1]), 2);
+        assert!(cache.get(0).is_some());
+        cache.insert(2, Arc::new(vec![2]), 2);
+        assert!(cache.get(1).is_none());
+        assert_eq!(*cache.get(0).unwrap(), vec![0]);
+        assert_eq!(*cache.get(2).unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn writes_to_vec() {
+        let mut data = vec![];
+        let mut pos = 0;
+        write_to_vec(&mut data, &mut pos, b"hello");
+        pos = 1;
+        write_to_vec(&mut data, &mut pos, b"ipp");
+        assert_eq!(data, b"hippo");
+        pos = 7;
+        write_to_vec(&mut data, &mut pos, b"!");
+        assert_eq!(data, b"hippo\0\0!");
+        assert_eq!(pos, 8);
+    }
+
+    #[test]
+    fn writes_local_file() {
+        let path = std::env::temp_dir().join(format!("zaplib_universal_file_{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut file = UniversalFile::create(path).unwrap();
+        std::io::Write::write_all(&mut file, b"hello").unwrap();
+        std::io::Write::flush(&mut file).unwrap();
+
+        let mut contents = String::new();
+        std::io::Read::read_to_string(&mut UniversalFile::open(path).unwrap(), &mut contents).unwrap();
+        assert_eq!(contents, "hello");
+        assert!(std::io::Write::write_all(&mut UniversalFile::open(path).unwrap(), b"bye").is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,53 +1,276 @@
-/// Create an HTTP stream that you can synchronously read from as data comes in.
-///
-/// Automatically supports gzip (native) or whatever your browser supports (wasm).
-/// TODO(JP): Maybe make compression support optional for native builds?
-///
-/// Returns a [`std::io::Read`]er that blocks until there is data available. It is
-/// highly recommended to only use this in a dedicated thread, and to wrap it in
-/// [`std::io::BufReader`].
+//! Making HTTP requests, and reading the responses as data comes in.
+//!
+//! Uses [ureq](https://docs.rs/ureq) on native targets, and `fetch` in WebAssembly.
+//!
+//! If you don't want to deal with threads yourself, use [`crate::Cx::http_request`], which sends a [`Request`]
+//! on a separate thread and reads the entire body, and then fires an [`crate::Event::HttpResponse`].
+
+use crate::*;
+use std::io::Read;
+use std::time::Duration;
+
+/// What to do when the server responds with a redirect (a 3xx status code).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RedirectPolicy {
+    /// Don't follow redirects, but return the 3xx [`Response`] itself. Browsers don't expose redirect
+    /// responses, so in WebAssembly this returns an error instead.
+    None,
+    /// Follow at most this many redirects, and return an error when there are more. In WebAssembly this
+    /// limit is ignored and the browser's limit is used instead (typically 20).
+    Limit(u32),
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self::Limit(5)
+    }
+}
+
+/// An HTTP request; use [`Request::send`] to get a [`Response`].
 ///
-/// TODO(JP): See if there is some way to unify this with [`crate::universal_file::UniversalFile`].
-pub fn request(url: &str, method: &str, body: &[u8], headers: &[(&str, &str)]) -> std::io::Result<Box<dyn std::io::Read + Send>> {
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        let mut request = ureq::request(&method.to_ascii_uppercase(), url);
-
-        request = request.set("accept-encoding", "gzip");
-        for (name, value) in headers {
-            request = request.set(name, value);
+/// ```
+/// let mut request = universal_http_stream::Request::new("GET", "https://example.com/data.csv");
+/// request.timeout = Some(Duration::from_secs(30));
+/// let response = request.send()?;
+/// if !response.is_success() {
+///     log!("Unexpected status: {} {}", response.status, response.status_text);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Timeout for the entire request, including following redirects and reading the body.
+    pub timeout: Option<Duration>,
+    pub redirect_policy: RedirectPolicy,
+}
+
+impl Request {
+    pub fn new(method: &str, url: &str) -> Self {
+        Self {
+            method: method.to_ascii_uppercase(),
+            url: url.to_string(),
+            headers: vec![],
+            body: vec![],
+            timeout: None,
+            redirect_policy: RedirectPolicy::default(),
         }
+    }
 
-        match request.send_bytes(body) {
-            Ok(response) => {
-                if let Some(encoding) = response.header("content-encoding") {
-                    if encoding == "gzip" {
-                        Ok(Box::new(flate2::read::GzDecoder::new(response.into_reader())))
-                    } else {
-                        Err(std::io::Error::new(
-                            std::io::ErrorKind::Other,
-                            format!("Unsupported response encoding: {}", encoding),
-                        ))
-                    }
-                } else {
-                    Ok(Box::new(response.into_reader()))
+    /// Send the request, and block until we have the status and headers of the response. The body can then
+    /// be read from the [`Response`] as it comes in. It is highly recommended to only use this in a dedicated
+    /// thread.
+    ///
+    /// Only returns an error if we didn't get a response at all (e.g. a network error, timeout, or too many
+    /// redirects); responses with error status codes (like 404) are returned as a regular [`Response`].
+    ///
+    /// The body is automatically decompressed; natively we support gzip, deflate, and brotli, and in
+    /// WebAssembly whatever the browser supports.
+    pub fn send(&self) -> std::io::Result<Response> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut agent_builder = ureq::AgentBuilder::new().redirects(match self.redirect_policy {
+                RedirectPolicy::None => 0,
+                // ureq counts the original request as well.
+                RedirectPolicy::Limit(limit) => limit + 1,
+            });
+            if let Some(timeout) = self.timeout {
+                agent_builder = agent_builder.timeout(timeout);
+            }
+            let mut request = agent_builder.build().request(&self.method, &self.url);
+            if !self.headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("accept-encoding")) {
+                request = request.set("accept-encoding", "gzip, deflate, br");
+            }
+            for (name, value) in &self.headers {
+                request = request.set(name, value);
+            }
+
+            let response = match request.send_bytes(&self.body) {
+                Ok(response) => response,
+                // Error status codes are not an error for us.
+                Err(ureq::Error::Status(_, response)) => response,
+                Err(error) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        format!("Error sending request to {}: {}", self.url, error),
+                    ))
                 }
+            };
+
+            let status = response.status();
+            let status_text = response.status_text().to_string();
+            let url = response.get_url().to_string();
+            let headers = response
+                .headers_names()
+                .into_iter()
+                .flat_map(|name| {
+                    response.all(&name).into_iter().map(|value| (name.clone(), value.to_string())).collect::<Vec<_>>()
+                })
+                .collect();
+            let body = decode_body(
+                response.header("content-encoding").map(|encoding| encoding.to_string()),
+                Box::new(response.into_reader()),
+            )?;
+            Ok(Response { status, status_text, url, headers, body })
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let headers: Vec<(&str, &str)> = self.headers.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+            let stream_id = crate::send_task_worker_message_http_stream_new(
+                &self.url,
+                &self.method,
+                &self.body,
+                &headers,
+                self.redirect_policy != RedirectPolicy::None,
+                self.timeout.map_or(0, |timeout| timeout.as_millis().max(1) as u32),
+            );
+            if stream_id == crate::TASK_WORKER_ERROR_RETURN_VALUE {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    format!("Error sending request to {}; check the browser console for details", self.url),
+                ));
             }
-            Err(error) => Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Error opening stream: {}", error))),
+
+            // The task worker sends the response info before the body, prefixed with its length.
+            let mut reader = UniversalHttpStreamReader(stream_id);
+            let mut info_len = [0; 4];
+            reader.read_exact(&mut info_len)?;
+            let mut info = vec![0; u32::from_le_bytes(info_len) as usize];
+            reader.read_exact(&mut info)?;
+            let ResponseInfo { status, status_text, url, headers } = parse_response_info(&String::from_utf8_lossy(&info))?;
+            Ok(Response { status, status_text, url, headers, body: Box::new(reader) })
         }
     }
+}
 
-    #[cfg(target_arch = "wasm32")]
-    {
-        let stream_id = crate::send_task_worker_message_http_stream_new(url, method, body, headers);
-        if stream_id == crate::TASK_WORKER_ERROR_RETURN_VALUE {
-            Err(std::io::Error::new(std::io::ErrorKind::Other, "Error opening stream"))
-        } else {
-            Ok(Box::new(UniversalHttpStreamReader(stream_id)))
+/// Response to a [`Request`]. Implements [`std::io::Read`] for reading the body as it comes in, which
+/// blocks until there is data available. It's highly recommended to wrap it in a [`std::io::BufReader`].
+pub struct Response {
+    pub status: u16,
+    pub status_text: String,
+    /// The final URL, after following redirects.
+    pub url: String,
+    /// Names are lowercase in WebAssembly, but not necessarily on native targets, so use
+    /// [`Response::header`] for looking up headers.
+    pub headers: Vec<(String, String)>,
+    body: Box<dyn Read + Send>,
+}
+
+impl Response {
+    /// Get the value of the first header with the given name (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Whether the status code is in the 2xx range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Get the body as a [`std::io::Read`]er.
+    pub fn into_body(self) -> Box<dyn Read + Send> {
+        self.body
+    }
+
+    /// Read the entire body into a [`crate::UniversalFile`], e.g. so you can seek in it.
+    pub fn into_universal_file(self) -> std::io::Result<crate::UniversalFile> {
+        let mut data = vec![];
+        self.into_body().read_to_end(&mut data)?;
+        Ok(crate::UniversalFile::from_data(data))
+    }
+}
+
+impl Read for Response {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.body.read(buf)
+    }
+}
+
+impl std::fmt::Debug for Response {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Response")
+            .field("status", &self.status)
+            .field("status_text", &self.status_text)
+            .field("url", &self.url)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Get the value of the first header with the given name (case-insensitive).
+pub(crate) fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter().find(|(header_name, _)| header_name.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+}
+
+/// Wrap the body in a decoder based on the `Content-Encoding` header.
+#[cfg(not(target_arch = "wasm32"))]
+fn decode_body(encoding: Option<String>, reader: Box<dyn Read + Send>) -> std::io::Result<Box<dyn Read + Send>> {
+    match encoding.map(|encoding| encoding.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("") | Some("identity") => Ok(reader),
+        Some("gzip") | Some("x-gzip") => Ok(Box::new(flate2::read::GzDecoder::new(reader))),
+        // Despite the name, "deflate" in HTTP means the zlib format.
+        Some("deflate") => Ok(Box::new(flate2::read::ZlibDecoder::new(reader))),
+        Some("br") => Ok(Box::new(brotli_decompressor::Decompressor::new(reader, 4096))),
+        Some(encoding) => {
+            Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Unsupported response encoding: {}", encoding)))
         }
     }
 }
 
+/// Everything in a [`Response`] except for the body.
+#[cfg(any(test, target_arch = "wasm32"))]
+#[derive(Debug, PartialEq)]
+struct ResponseInfo {
+    status: u16,
+    status_text: String,
+    url: String,
+    headers: Vec<(String, String)>,
+}
+
+/// Parse the response info that the task worker sends before the body: the status, status text, and URL,
+/// followed by the headers, all on separate lines.
+#[cfg(any(test, target_arch = "wasm32"))]
+fn parse_response_info(info: &str) -> std::io::Result<ResponseInfo> {
+    let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Invalid response info: {}", info));
+    let mut lines = info.lines();
+    let status = lines.next().and_then(|status| status.parse().ok()).ok_or_else(invalid)?;
+    let status_text = lines.next().ok_or_else(invalid)?.to_string();
+    let url = lines.next().ok_or_else(invalid)?.to_string();
+    let headers = lines
+        .map(|line| {
+            let (name, value) = line.split_once(':').ok_or_else(invalid)?;
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect::<std::io::Result<_>>()?;
+    Ok(ResponseInfo { status, status_text, url, headers })
+}
+
+/// Create an HTTP stream that you can synchronously read from as data comes in.
+///
+/// Returns an error for responses that don't have a 2xx status code. Use [`Request`] if you need
+/// more control, such as looking at the status code or headers, or setting a timeout.
+///
+/// Returns a [`std::io::Read`]er that blocks until there is data available. It is
+/// highly recommended to only use this in a dedicated thread, and to wrap it in
+/// [`std::io::BufReader`].
+pub fn request(url: &str, method: &str, body: &[u8], headers: &[(&str, &str)]) -> std::io::Result<Box<dyn Read + Send>> {
+    let mut request = Request::new(method, url);
+    request.body = body.to_vec();
+    request.headers = headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+    let response = request.send()?;
+    if response.is_success() {
+        Ok(response.into_body())
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("Error opening stream: {} {} for {}", response.status, response.status_text, url),
+        ))
+    }
+}
+
 /// Contains just a `stream_id` to make [`std::io::Read::read`] calls with in WebAssembly.
 #[cfg(target_arch = "wasm32")]
 struct UniversalHttpStreamReader(i32);
@@ -66,3 +289,147 @@ impl std::io::Read for UniversalHttpStreamReader {
         }
     }
 }
+
+const STATUS_HTTP_REQUEST_FINISHED: StatusId = location_hash!();
+
+/// Requests made using [`Cx::http_request`] that we haven't fired an [`Event::HttpResponse`] for yet.
+#[derive(Default)]
+pub(crate) struct CxHttpRequests {
+    /// Gets posted when any of the requests finishes; created on the first request.
+    signal: Signal,
+    last_request_id: u64,
+    running: Vec<(HttpRequestId, universal_thread::JoinHandle<std::io::Result<HttpResponse>>)>,
+}
+
+impl Cx {
+    /// Send a [`Request`] on a separate thread, and read the entire body. When done, you get an
+    /// [`Event::HttpResponse`] with the returned [`HttpRequestId`].
+    ///
+    /// ```
+    /// self.request_id = Some(cx.http_request(universal_http_stream::Request::new("GET", "https://example.com/data.json")));
+    /// ```
+    ///
+    /// And then in `handle`:
+    ///
+    /// ```
+    /// if let Event::HttpResponse(HttpResponseEvent { request_id, result }) = event {
+    ///     if Some(*request_id) == self.request_id {
+    ///         match result {
+    ///             Ok(response) if response.is_success() => self.parse_data(&response.body),
+    ///             Ok(response) => log!("Unexpected status: {} {}", response.status, response.status_text),
+    ///             Err(err) => log!("Request failed: {}", err),
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn http_request(&mut self, request: Request) -> HttpRequestId {
+        if self.http_requests.signal == Signal::default() {
+            self.http_requests.signal = self.new_signal();
+        }
+        self.http_requests.last_request_id += 1;
+        let request_id = HttpRequestId(self.http_requests.last_request_id);
+
+        let join_handle = universal_thread::spawn(move || {
+            let mut response = request.send()?;
+            let mut body = vec![];
+            response.read_to_end(&mut body)?;
+            let Response { status, status_text, url, headers, .. } = response;
+            Ok(HttpResponse { status, status_text, url, headers, body })
+        });
+        join_handle.post_signal_when_finished(self.http_requests.signal, STATUS_HTTP_REQUEST_FINISHED);
+        self.http_requests.running.push((request_id, join_handle));
+        request_id
+    }
+
+    /// Called when handling signals; fires [`Event::HttpResponse`] for requests that have finished, and
+    /// returns whether `signal` was meant for us.
+    pub(crate) fn call_http_response_events(&mut self, signal: &Signal) -> bool {
+        if *signal != self.http_requests.signal || self.http_requests.signal == Signal::default() {
+            return false;
+        }
+        let mut finished = vec![];
+        self.http_requests.running.retain(|(request_id, join_handle)| match join_handle.try_join() {
+            Some(result) => {
+                finished.push((*request_id, result));
+                false
+            }
+            None => true,
+        });
+        for (request_id, result) in finished {
+            let result = match result {
+                Ok(result) => result.map_err(|err| err.to_string()),
+                Err(_) => Err("Panicked while sending request".to_string()),
+            };
+            self.call_event_handler(&mut Event::HttpResponse(HttpResponseEvent { request_id, result }));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeSet, HashMap};
+
+    #[test]
+    fn parses_response_info() {
+        assert_eq!(
+            parse_response_info("404\nNot Found\nhttps://example.com/a\ncontent-type: text/html\nx-time: 12:00").unwrap(),
+            ResponseInfo {
+                status: 404,
+                status_text: "Not Found".to_string(),
+                url: "https://example.com/a".to_string(),
+                headers: vec![("content-type".to_string(), "text/html".to_string()), ("x-time".to_string(), "12:00".to_string())],
+            }
+        );
+        assert!(parse_response_info("200").is_err());
+    }
+
+    #[test]
+    fn decodes_deflate() {
+        let mut encoder = flate2::read::ZlibEncoder::new(&b"hello"[..], flate2::Compression::default());
+        let mut encoded = vec![];
+        encoder.read_to_end(&mut encoded).unwrap();
+
+        let mut decoded = String::new();
+        decode_body(Some("deflate".to_string()), Box::new(std::io::Cursor::new(encoded)))
+            .unwrap()
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "hello");
+        assert!(decode_body(Some("zstd".to_string()), Box::new(std::io::empty())).is_err());
+    }
+
+    #[test]
+    fn fires_http_response_events_for_posted_signals() {
+        let mut cx = Cx::new_test();
+        cx.http_requests.signal = cx.new_signal();
+        let join_handle = universal_thread::spawn(|| {
+            Ok(HttpResponse {
+                status: 200,
+                status_text: "OK".to_string(),
+                url: "https://example.com/".to_string(),
+                headers: vec![],
+                body: b"hello".to_vec(),
+            })
+        });
+        while !join_handle.is_finished() {
+            std::thread::yield_now();
+        }
+        cx.http_requests.running.push((HttpRequestId(1), join_handle));
+
+        // What the platform event loops receive when the request thread calls `Cx::post_signal`.
+        let signals = HashMap::from([(cx.http_requests.signal, BTreeSet::from([STATUS_HTTP_REQUEST_FINISHED]))]);
+        let events = cx.collect_events(|cx| cx.call_posted_signals(&SignalEvent { signals }));
+
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            Event::HttpResponse(HttpResponseEvent { request_id, result: Ok(response) }) => {
+                assert_eq!(*request_id, HttpRequestId(1));
+                assert_eq!(response.body, b"hello");
+            }
+            event => panic!("Unexpected event: {:?}", event),
+        }
+        assert!(cx.http_requests.running.is_empty());
+    }
+}
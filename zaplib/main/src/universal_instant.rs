@@ -3,8 +3,46 @@
 //! Adapted from <https://github.com/rust-lang/rust/issues/48564#issuecomment-698712971>
 
 use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
+/// Sentinel value for [`REPLAY_CLOCK_MICROS`] when we're not replaying.
+const REPLAY_CLOCK_INACTIVE: u64 = u64::MAX;
+
+/// When replaying an [`crate::EventRecording`], this contains the recorded time (in microseconds since
+/// the start of the recording), which [`UniversalInstant::now`] will then use instead of the system clock.
+static REPLAY_CLOCK_MICROS: AtomicU64 = AtomicU64::new(REPLAY_CLOCK_INACTIVE);
+
+/// The system time when we started replaying, so we can still return actual [`std::time::Instant`]s.
+///
+/// Only written to in [`set_replay_clock`] before [`REPLAY_CLOCK_MICROS`] gets activated, so it's safe to read when
+/// [`REPLAY_CLOCK_MICROS`] is active.
+#[cfg(not(target_arch = "wasm32"))]
+static mut REPLAY_CLOCK_BASE: Option<std::time::Instant> = None;
+
+/// Drive [`UniversalInstant::now`] using a recorded time (in seconds), or go back to the system clock by passing in
+/// [`None`]. See [`crate::Cx::replay_event_recording`].
+pub(crate) fn set_replay_clock(time: Option<f64>) {
+    match time {
+        Some(time) => {
+            #[cfg(not(target_arch = "wasm32"))]
+            if REPLAY_CLOCK_MICROS.load(Ordering::Acquire) == REPLAY_CLOCK_INACTIVE {
+                unsafe { REPLAY_CLOCK_BASE = Some(std::time::Instant::now()) };
+            }
+            REPLAY_CLOCK_MICROS.store((time.max(0.) * 1_000_000.) as u64, Ordering::Release);
+        }
+        None => REPLAY_CLOCK_MICROS.store(REPLAY_CLOCK_INACTIVE, Ordering::Release),
+    }
+}
+
+/// Returns the recorded time (in microseconds) if we're replaying.
+fn replay_clock_micros() -> Option<u64> {
+    match REPLAY_CLOCK_MICROS.load(Ordering::Acquire) {
+        REPLAY_CLOCK_INACTIVE => None,
+        micros => Some(micros),
+    }
+}
+
 /// Version of [`std::time::Instant`] that also works in WebAssembly.
 pub trait Instant<S>
 where
@@ -25,7 +63,14 @@ pub struct UniversalInstant(std::time::Instant);
 #[cfg(not(target_arch = "wasm32"))]
 impl Instant<Self> for UniversalInstant {
     /// See [`std::time::Instant::now`]
+    ///
+    /// When replaying an [`crate::EventRecording`], this returns the recorded time instead.
     fn now() -> Self {
+        if let Some(micros) = replay_clock_micros() {
+            if let Some(base) = unsafe { REPLAY_CLOCK_BASE } {
+                return Self(base + Duration::from_micros(micros));
+            }
+        }
         Self(std::time::Instant::now())
     }
     /// See [`std::time::Instant::duration_since`]
@@ -57,7 +102,12 @@ pub struct UniversalInstant(u64);
 #[cfg(target_arch = "wasm32")]
 impl Instant<Self> for UniversalInstant {
     /// See [`std::time::Instant::now`]
+    ///
+    /// When replaying an [`crate::EventRecording`], this returns the recorded time instead.
     fn now() -> Self {
+        if let Some(micros) = replay_clock_micros() {
+            return Self(micros);
+        }
         Self((unsafe { crate::cx_wasm32::performanceNow() } * 1000.0) as u64)
     }
     /// See [`std::time::Instant::duration_since`]
@@ -1,5 +1,8 @@
 //! Version of [`std::thread`] that also works in WebAssembly.
 
+use crate::*;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
 use std::time::Duration;
 /// See [`Thread`].
@@ -11,10 +14,8 @@ struct UniversalThread();
 trait Thread {
     /// Run function in a non-blocking thread.
     ///
-    /// Check out the `test_multithread` for sample usage.
-    ///
-    /// TODO(Paras): Implement join handles. When we do, we can use the same
-    /// function signature here as [`std::thread::spawn`].
+    /// Check out the `test_multithread` for sample usage. Join handles are implemented
+    /// on top of this in the module-level [`spawn`], so this can stay as simple as possible.
     fn spawn(f: impl FnOnce() + Send + 'static);
 
     /// See [`std::thread::sleep`].
@@ -68,11 +69,135 @@ unsafe extern "C" fn fn_to_run_in_worker(ctx_ptr: u64) {
     (ctx.func)();
 }
 
+/// State of a thread, shared between the thread and its [`JoinHandle`].
+enum JoinState<T> {
+    Running {
+        /// Set using [`JoinHandle::post_signal_when_finished`].
+        signal: Option<(Signal, StatusId)>,
+    },
+    Finished(thread::Result<T>),
+    /// The result has been returned by [`JoinHandle::try_join`].
+    Taken,
+}
+
+pub(crate) struct JoinInner<T> {
+    state: Mutex<JoinState<T>>,
+    finished: Condvar,
+}
+
+impl<T> JoinInner<T> {
+    /// Store the result of the thread, wake up [`JoinHandle::join`], and post the signal
+    /// set using [`JoinHandle::post_signal_when_finished`] (if any).
+    pub(crate) fn finish(&self, result: thread::Result<T>) {
+        let mut state = self.state.lock().unwrap();
+        if let JoinState::Running { signal: Some((signal, status)) } = *state {
+            Cx::post_signal(signal, status);
+        }
+        *state = JoinState::Finished(result);
+        self.finished.notify_all();
+    }
+}
+
+/// Handle to a thread started with [`spawn`]. Version of [`std::thread::JoinHandle`]
+/// that also works in WebAssembly.
+///
+/// Unlike [`std::thread::JoinHandle`] you typically don't want to block on the main thread
+/// using [`JoinHandle::join`] (which isn't even allowed in browsers). Instead, use
+/// [`JoinHandle::post_signal_when_finished`] and get the result using [`JoinHandle::try_join`]
+/// when receiving the corresponding [`SignalEvent`]:
+///
+/// ```
+/// // When starting the work:
+/// let handle = universal_thread::spawn(|| expensive_computation());
+/// handle.post_signal_when_finished(self.signal, Self::STATUS_COMPUTATION_FINISHED);
+/// self.computation = Some(handle);
+///
+/// // In `handle`:
+/// if let Event::Signal(sig) = event {
+///     if let Some(statuses) = sig.signals.get(&self.signal) {
+///         if statuses.contains(&Self::STATUS_COMPUTATION_FINISHED) {
+///             let result = self.computation.take().unwrap().try_join().unwrap().unwrap();
+///         }
+///     }
+/// }
+/// ```
+///
+/// Dropping a [`JoinHandle`] detaches the thread, just like in [`std::thread`].
+pub struct JoinHandle<T> {
+    inner: Arc<JoinInner<T>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Create a new [`JoinHandle`] for a thread that is still running, along with the
+    /// [`JoinInner`] to call [`JoinInner::finish`] on when it's done.
+    pub(crate) fn new_running() -> (Self, Arc<JoinInner<T>>) {
+        let inner = Arc::new(JoinInner { state: Mutex::new(JoinState::Running { signal: None }), finished: Condvar::new() });
+        (Self { inner: Arc::clone(&inner) }, inner)
+    }
+
+    /// Block until the thread has finished, and return its result. Returns an [`Err`]
+    /// if the thread panicked, just like [`std::thread::JoinHandle::join`].
+    ///
+    /// Don't call this on the main thread in WebAssembly, since browsers don't allow
+    /// blocking it; use [`JoinHandle::post_signal_when_finished`] instead.
+    ///
+    /// Panics if the result was already returned by [`JoinHandle::try_join`].
+    pub fn join(self) -> thread::Result<T> {
+        let mut state = self.inner.state.lock().unwrap();
+        while let JoinState::Running { .. } = *state {
+            state = self.inner.finished.wait(state).unwrap();
+        }
+        match std::mem::replace(&mut *state, JoinState::Taken) {
+            JoinState::Finished(result) => result,
+            _ => panic!("JoinHandle result was already taken using try_join"),
+        }
+    }
+
+    /// Return the result of the thread if it has finished, without blocking. Returns [`None`]
+    /// if the thread is still running, or if the result was already returned earlier.
+    pub fn try_join(&self) -> Option<thread::Result<T>> {
+        let mut state = self.inner.state.lock().unwrap();
+        if let JoinState::Finished(_) = *state {
+            if let JoinState::Finished(result) = std::mem::replace(&mut *state, JoinState::Taken) {
+                return Some(result);
+            }
+        }
+        None
+    }
+
+    /// Whether the thread has finished running (successfully or not). See also
+    /// [`std::thread::JoinHandle::is_finished`].
+    pub fn is_finished(&self) -> bool {
+        !matches!(*self.inner.state.lock().unwrap(), JoinState::Running { .. })
+    }
+
+    /// Fire a [`SignalEvent`] on the main thread with the given [`Signal`] and [`StatusId`] once the
+    /// thread has finished, so you can get the result using [`JoinHandle::try_join`]. If the thread
+    /// has already finished, the signal is posted right away.
+    pub fn post_signal_when_finished(&self, signal: Signal, status: StatusId) {
+        let mut state = self.inner.state.lock().unwrap();
+        match &mut *state {
+            JoinState::Running { signal: running_signal } => *running_signal = Some((signal, status)),
+            _ => Cx::post_signal(signal, status),
+        }
+    }
+}
+
 /// Version of [`std::thread::spawn`] that also works in WebAssembly.
 ///
-/// See also [`Thread::spawn`].
-pub fn spawn(f: impl FnOnce() + Send + 'static) {
-    UniversalThread::spawn(f);
+/// See also [`Thread::spawn`] and [`JoinHandle`].
+pub fn spawn<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (join_handle, inner) = JoinHandle::new_running();
+    UniversalThread::spawn(move || {
+        // Catch panics so that `JoinHandle::join` doesn't block forever. The panic message
+        // still gets printed by the panic hook.
+        inner.finish(catch_unwind(AssertUnwindSafe(f)));
+    });
+    join_handle
 }
 
 /// Universal version of [`std::thread::sleep`].
@@ -82,3 +207,33 @@ pub fn spawn(f: impl FnOnce() + Send + 'static) {
 pub fn sleep(dur: Duration) {
     UniversalThread::sleep(dur);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join_returns_result() {
+        let handle = spawn(|| {
+            sleep(Duration::from_millis(10));
+            42
+        });
+        assert_eq!(handle.join().unwrap(), 42);
+    }
+
+    #[test]
+    fn join_returns_panic() {
+        let handle = spawn(|| panic!("oops"));
+        assert!(handle.join().is_err());
+    }
+
+    #[test]
+    fn try_join_returns_result_once() {
+        let handle = spawn(|| "done");
+        while !handle.is_finished() {
+            sleep(Duration::from_millis(1));
+        }
+        assert_eq!(handle.try_join().unwrap().unwrap(), "done");
+        assert!(handle.try_join().is_none());
+    }
+}
@@ -0,0 +1,296 @@
+//! Running lots of tasks on a bounded number of threads, both natively and in WebAssembly.
+
+use crate::*;
+use std::any::Any;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Order in which queued tasks of a [`WorkerPool`] get picked up. Tasks with the same
+/// priority run in the order in which they were spawned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskPriority {
+    Low,
+    Normal,
+    High,
+}
+
+/// Shared flag for cancelling a task; see [`TaskHandle::cancel`].
+///
+/// Tasks that haven't started yet when they get cancelled don't run at all. Tasks that are
+/// already running get passed the token, so they can check [`CancellationToken::is_cancelled`]
+/// every now and then, and stop early.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, AtomicOrdering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(AtomicOrdering::Relaxed)
+    }
+}
+
+/// Why a task spawned on a [`WorkerPool`] didn't return a value.
+#[derive(Debug)]
+pub enum TaskError {
+    /// The task was cancelled before it started.
+    Cancelled,
+    /// The task panicked; contains the panic payload, like [`std::thread::Result`].
+    Panicked(Box<dyn Any + Send + 'static>),
+}
+
+/// Result of a task spawned on a [`WorkerPool`].
+pub type TaskResult<T> = Result<T, TaskError>;
+
+/// Handle to a task spawned with [`WorkerPool::spawn`]. Works just like a
+/// [`universal_thread::JoinHandle`], but also allows for cancelling the task.
+pub struct TaskHandle<T> {
+    /// Contains [`None`] if the task was cancelled before it started.
+    join_handle: universal_thread::JoinHandle<Option<T>>,
+    cancellation_token: CancellationToken,
+}
+
+impl<T> TaskHandle<T> {
+    /// Cancel the task; see [`CancellationToken`].
+    ///
+    /// Note that a cancelled task still finishes (and posts its signal) when a worker picks it up, so
+    /// if all workers are busy this can take a while.
+    pub fn cancel(&self) {
+        self.cancellation_token.cancel();
+    }
+
+    /// The [`CancellationToken`] that gets passed into the task.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation_token
+    }
+
+    /// See [`universal_thread::JoinHandle::is_finished`].
+    pub fn is_finished(&self) -> bool {
+        self.join_handle.is_finished()
+    }
+
+    /// See [`universal_thread::JoinHandle::join`].
+    pub fn join(self) -> TaskResult<T> {
+        to_task_result(self.join_handle.join())
+    }
+
+    /// See [`universal_thread::JoinHandle::try_join`].
+    pub fn try_join(&self) -> Option<TaskResult<T>> {
+        self.join_handle.try_join().map(to_task_result)
+    }
+
+    /// See [`universal_thread::JoinHandle::post_signal_when_finished`].
+    pub fn post_signal_when_finished(&self, signal: Signal, status: StatusId) {
+        self.join_handle.post_signal_when_finished(signal, status);
+    }
+}
+
+fn to_task_result<T>(result: std::thread::Result<Option<T>>) -> TaskResult<T> {
+    match result {
+        Ok(Some(value)) => Ok(value),
+        Ok(None) => Err(TaskError::Cancelled),
+        Err(payload) => Err(TaskError::Panicked(payload)),
+    }
+}
+
+struct QueuedTask {
+    priority: TaskPriority,
+    /// Increasing number, for running tasks with the same priority in order.
+    sequence: u64,
+    run: Box<dyn FnOnce() + Send>,
+}
+
+impl PartialEq for QueuedTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedTask {}
+
+impl PartialOrd for QueuedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedTask {
+    /// [`BinaryHeap`] pops the largest element first, so the highest priority and lowest sequence is largest.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority).then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+#[derive(Default)]
+struct WorkerPoolQueue {
+    tasks: BinaryHeap<QueuedTask>,
+    next_sequence: u64,
+    num_workers: usize,
+    num_idle_workers: usize,
+    shut_down: bool,
+}
+
+struct WorkerPoolInner {
+    max_workers: usize,
+    queue: Mutex<WorkerPoolQueue>,
+    task_available: Condvar,
+}
+
+impl WorkerPoolInner {
+    fn run_worker(&self) {
+        loop {
+            let task = {
+                let mut queue = self.queue.lock().unwrap();
+                loop {
+                    if let Some(task) = queue.tasks.pop() {
+                        break task;
+                    }
+                    if queue.shut_down {
+                        queue.num_workers -= 1;
+                        return;
+                    }
+                    queue.num_idle_workers += 1;
+                    queue = self.task_available.wait(queue).unwrap();
+                    queue.num_idle_workers -= 1;
+                }
+            };
+            (task.run)();
+        }
+    }
+}
+
+/// Runs tasks on at most a fixed number of threads (using [`universal_thread::spawn`], so Web Workers
+/// in WebAssembly). Use this instead of spawning a thread per task when there can be a lot of tasks,
+/// e.g. when loading files that the user dropped onto the window.
+///
+/// Threads are started as needed, and are kept around for subsequent tasks (since starting Web Workers
+/// is expensive). Results are reported back to the main thread the same way as for
+/// [`universal_thread::spawn`]:
+///
+/// ```
+/// let handle = self.worker_pool.spawn(TaskPriority::Normal, move |cancellation_token| parse_file(file, cancellation_token));
+/// handle.post_signal_when_finished(self.signal, Self::STATUS_FILE_PARSED);
+/// self.parse_handles.push(handle);
+/// ```
+///
+/// When dropping the [`WorkerPool`], tasks that are already queued still run, after which the threads stop.
+pub struct WorkerPool {
+    inner: Arc<WorkerPoolInner>,
+}
+
+impl WorkerPool {
+    /// Create a new [`WorkerPool`] that uses at most `max_workers` threads.
+    pub fn new(max_workers: usize) -> Self {
+        assert!(max_workers > 0, "WorkerPool needs at least one worker");
+        Self {
+            inner: Arc::new(WorkerPoolInner {
+                max_workers,
+                queue: Mutex::new(WorkerPoolQueue::default()),
+                task_available: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Queue a task, which gets passed a [`CancellationToken`] that is cancelled when calling
+    /// [`TaskHandle::cancel`].
+    pub fn spawn<F, T>(&self, priority: TaskPriority, f: F) -> TaskHandle<T>
+    where
+        F: FnOnce(&CancellationToken) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (join_handle, join_inner) = universal_thread::JoinHandle::new_running();
+        let cancellation_token = CancellationToken::default();
+        let task_cancellation_token = cancellation_token.clone();
+        let run = Box::new(move || {
+            let result = if task_cancellation_token.is_cancelled() {
+                Ok(None)
+            } else {
+                catch_unwind(AssertUnwindSafe(|| Some(f(&task_cancellation_token))))
+            };
+            join_inner.finish(result);
+        });
+
+        let mut queue = self.inner.queue.lock().unwrap();
+        let sequence = queue.next_sequence;
+        queue.next_sequence += 1;
+        queue.tasks.push(QueuedTask { priority, sequence, run });
+        if queue.tasks.len() > queue.num_idle_workers && queue.num_workers < self.inner.max_workers {
+            queue.num_workers += 1;
+            let inner = Arc::clone(&self.inner);
+            universal_thread::spawn(move || inner.run_worker());
+        } else {
+            self.inner.task_available.notify_one();
+        }
+
+        TaskHandle { join_handle, cancellation_token }
+    }
+}
+
+impl Default for WorkerPool {
+    /// Uses as many workers as there are CPU cores (or 4 if we can't tell, e.g. in WebAssembly).
+    fn default() -> Self {
+        Self::new(std::thread::available_parallelism().map_or(4, |n| n.get()))
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.inner.queue.lock().unwrap().shut_down = true;
+        self.inner.task_available.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn runs_tasks_in_priority_order() {
+        let pool = WorkerPool::new(1);
+        let (started_sender, started_receiver) = channel();
+        let (continue_sender, continue_receiver) = channel::<()>();
+        let blocking_handle = pool.spawn(TaskPriority::Normal, move |_| {
+            started_sender.send(()).unwrap();
+            continue_receiver.recv().unwrap();
+        });
+        started_receiver.recv().unwrap();
+
+        let order = Arc::new(Mutex::new(vec![]));
+        let handles: Vec<_> =
+            [(TaskPriority::Low, 1), (TaskPriority::High, 2), (TaskPriority::Normal, 3), (TaskPriority::High, 4)]
+                .into_iter()
+                .map(|(priority, index)| {
+                    let order = Arc::clone(&order);
+                    pool.spawn(priority, move |_| order.lock().unwrap().push(index))
+                })
+                .collect();
+        continue_sender.send(()).unwrap();
+
+        blocking_handle.join().unwrap();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![2, 4, 3, 1]);
+    }
+
+    #[test]
+    fn skips_cancelled_tasks() {
+        let pool = WorkerPool::new(1);
+        let (continue_sender, continue_receiver) = channel::<()>();
+        let blocking_handle = pool.spawn(TaskPriority::Normal, move |_| continue_receiver.recv().unwrap());
+        let cancelled_handle = pool.spawn(TaskPriority::Normal, |_| 1);
+        let handle = pool.spawn(TaskPriority::Normal, |_| 2);
+        cancelled_handle.cancel();
+        continue_sender.send(()).unwrap();
+
+        blocking_handle.join().unwrap();
+        assert!(matches!(cancelled_handle.join(), Err(TaskError::Cancelled)));
+        assert_eq!(handle.join().unwrap(), 2);
+    }
+}
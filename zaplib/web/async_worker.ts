@@ -44,6 +44,10 @@ rpc.receive(
       });
     };
 
+    const saveFile = (name: string, data: Uint8Array) => {
+      rpc.send(AsyncWorkerEvent.SaveFile, { name, data }, [data.buffer]);
+    };
+
     const getExports = () => {
       return exports;
     };
@@ -54,6 +58,7 @@ rpc.receive(
       fileHandles,
       sendEventFromAnyThread,
       threadSpawn,
+      saveFile,
       baseUri,
     });
 
@@ -431,6 +431,7 @@ export const getWasmEnv = ({
   fileHandles,
   sendEventFromAnyThread,
   threadSpawn,
+  saveFile,
   baseUri,
 }: {
   getExports: () => WasmExports;
@@ -439,6 +440,7 @@ export const getWasmEnv = ({
   fileHandles: FileHandle[];
   sendEventFromAnyThread: (eventPtr: BigInt) => void;
   threadSpawn: (ctxPtr: BigInt) => void;
+  saveFile: (name: string, data: Uint8Array) => void;
   baseUri: string;
 }): WasmEnv => {
   const parseString = (ptr: number, len: number) => {
@@ -531,6 +533,65 @@ export const getWasmEnv = ({
         return 0;
       }
     },
+    readUrlRangeSync: (
+      urlPtr,
+      urlLen,
+      rangeStart,
+      rangeEnd,
+      bufPtrOut,
+      bufLenOut,
+      totalSizeOut
+    ) => {
+      if (!inWorker) {
+        // Main browser thread doesn't support synchronous+arraybuffer XMLHttpRequest.
+        throw new Error("Not yet implemented");
+      }
+
+      const url = parseString(urlPtr, urlLen);
+      const request = new XMLHttpRequest();
+      request.responseType = "arraybuffer";
+      request.open("GET", new URL(url, baseUri).href, false /* synchronous */);
+      request.setRequestHeader("Range", `bytes=${rangeStart}-${rangeEnd}`);
+      request.send(null);
+
+      if (request.status !== 200 && request.status !== 206) {
+        return 0;
+      }
+
+      const exports = getExports();
+      const outputBufPtr = createWasmBuffer(
+        memory,
+        exports,
+        new Uint8Array(request.response)
+      );
+      new Uint32Array(memory.buffer, bufPtrOut, 1)[0] = outputBufPtr;
+      new Uint32Array(memory.buffer, bufLenOut, 1)[0] =
+        request.response.byteLength;
+
+      if (request.status === 200) {
+        // The server doesn't support range requests, so we got the whole file.
+        return 2;
+      }
+      // E.g. "bytes 0-1023/146515". Note that for cross-origin requests the server has
+      // to send "Access-Control-Expose-Headers: Content-Range" for us to be able to read this.
+      const totalSize = request
+        .getResponseHeader("Content-Range")
+        ?.match(/\/(\d+)$/)?.[1];
+      new BigUint64Array(memory.buffer, totalSizeOut, 1)[0] =
+        totalSize === undefined
+          ? BigInt.asUintN(64, BigInt(-1))
+          : BigInt(totalSize);
+      return 1;
+    },
+    saveFile: (namePtr, nameLen, dataPtr, dataLen) => {
+      // Copy the data, since we can't transfer (parts of) the shared wasm memory.
+      const data = new Uint8Array(
+        memory.buffer,
+        Number(dataPtr),
+        Number(dataLen)
+      ).slice();
+      saveFile(parseString(namePtr, nameLen), data);
+    },
     randomU64: () =>
       new BigUint64Array(
         globalThis.crypto.getRandomValues(new Uint32Array(2)).buffer
@@ -16,6 +16,7 @@ import {
   WasmExports,
   SizingData,
   MutableBufferData,
+  PassCapture,
   RustZapParam,
 } from "types";
 import { ZerdeParser } from "zerde";
@@ -589,37 +590,6 @@ export class WasmApp {
     //console.log("Timer ID not found!")
   }
 
-  private httpSend(
-    verb: string,
-    path: string,
-    proto: string,
-    domain: string,
-    port: number,
-    contentType: string,
-    body: Uint8Array,
-    signalId: number
-  ): void {
-    const req = new XMLHttpRequest();
-    req.addEventListener("error", (_) => {
-      // signal fail
-      this.zerdeEventloopEvents.httpSendResponse(signalId, 2);
-      this.doWasmIo();
-    });
-    req.addEventListener("load", (_) => {
-      if (req.status !== 200) {
-        // signal fail
-        this.zerdeEventloopEvents.httpSendResponse(signalId, 2);
-      } else {
-        //signal success
-        this.zerdeEventloopEvents.httpSendResponse(signalId, 1);
-      }
-      this.doWasmIo();
-    });
-    req.open(verb, proto + "://" + domain + ":" + port + path, true);
-    console.log(verb, proto + "://" + domain + ":" + port + path, body);
-    req.send(body.buffer);
-  }
-
   private websocketSend(url: string, data: Uint8Array): void {
     // TODO(Paras): Stop patching sendStack onto websockets
     // and maintain our own structure instead.
@@ -808,6 +778,28 @@ export class WasmApp {
     // ignore for now
   }
 
+  private sendPassCaptures(captures: PassCapture[]): void {
+    if (captures.length === 0) {
+      return;
+    }
+    // We might be in the middle of `doWasmIo`, so send the captures afterwards.
+    setTimeout(() => {
+      try {
+        for (const capture of captures) {
+          this.zerdeEventloopEvents.passCaptured(capture);
+        }
+        this.doWasmIo();
+      } catch (e) {
+        if (e instanceof Error && e.name === "RustPanic") {
+          Atomics.store(wasmOnline, 0, 0);
+          rpc.send(WorkerEvent.Panic, e);
+        } else {
+          throw e;
+        }
+      }
+    });
+  }
+
   sendEventFromAnyThread(eventPtr: BigInt): void {
     // Prevent an infinite loop when calling this from an event handler.
     setTimeout(() => {
@@ -840,12 +832,14 @@ export class WasmApp {
       if (zelf.webglRenderer) {
         zelf.webglRenderer.processMessages(Number(zerdeParserPtr));
         zelf.exports.deallocWasmMessage(zerdeParserPtr);
+        zelf.sendPassCaptures(zelf.webglRenderer.takeCaptures());
       } else {
         zelf.runWebGLPromise = rpc
           .send(WorkerEvent.RunWebGL, Number(zerdeParserPtr))
-          .then(() => {
+          .then((captures) => {
             zelf.exports.deallocWasmMessage(zerdeParserPtr);
             zelf.runWebGLPromise = undefined;
+            zelf.sendPassCaptures(captures);
           });
       }
     },
@@ -900,27 +894,8 @@ export class WasmApp {
     function xrStopPresenting12(zelf) {
       zelf.xrStopPresenting();
     },
-    // http_send
-    function httpSend13(zelf) {
-      const port = zelf.zerdeParser.parseU32();
-      const signalId = zelf.zerdeParser.parseU32();
-      const verb = zelf.zerdeParser.parseString();
-      const path = zelf.zerdeParser.parseString();
-      const proto = zelf.zerdeParser.parseString();
-      const domain = zelf.zerdeParser.parseString();
-      const contentType = zelf.zerdeParser.parseString();
-      const body = zelf.zerdeParser.parseU8Slice();
-      zelf.httpSend(
-        verb,
-        path,
-        proto,
-        domain,
-        port,
-        contentType,
-        body,
-        signalId
-      );
-    },
+    // unused (used to be http_send)
+    function unused13(_zelf) {},
     // fullscreen
     function fullscreen14(_zelf) {
       rpc.send(WorkerEvent.Fullscreen);
@@ -980,6 +955,10 @@ rpc.receive(
         });
       };
 
+      const saveFile = (name: string, data: Uint8Array) => {
+        rpc.send(WorkerEvent.SaveFile, { name, data }, [data.buffer]);
+      };
+
       const getExports = () => {
         return wasmapp.exports;
       };
@@ -995,6 +974,7 @@ rpc.receive(
           wasmapp.sendEventFromAnyThread(eventPtr);
         },
         threadSpawn,
+        saveFile,
         baseUri,
       });
 
@@ -8,6 +8,7 @@ import {
 import {
   FileHandle,
   MutableBufferData,
+  PassCapture,
   PostMessageTypedArray,
   RustZapParam,
   SizingData,
@@ -73,6 +74,7 @@ export enum WorkerEvent {
   Init = "WorkerEvent.Init",
   RunWebGL = "WorkerEvent.RunWebGL",
   ThreadSpawn = "WorkerEvent.ThreadSpawn",
+  SaveFile = "WorkerEvent.SaveFile",
   WindowTouchStart = "WorkerEvent.WindowTouchStart",
   WindowTouchMove = "WorkerEvent.WindowTouchMove",
   WindowTouchEndCancelLeave = "WorkerEvent.WindowTouchEndCancelLeave",
@@ -142,7 +144,7 @@ export type WasmWorkerRpc = {
       void
     ];
     [WorkerEvent.ShowTextIME]: [{ x: number; y: number }, void];
-    [WorkerEvent.RunWebGL]: [number, void];
+    [WorkerEvent.RunWebGL]: [number, PassCapture[]];
     [WorkerEvent.ThreadSpawn]: [
       {
         ctxPtr: BigInt;
@@ -150,6 +152,7 @@ export type WasmWorkerRpc = {
       },
       void
     ];
+    [WorkerEvent.SaveFile]: [{ name: string; data: Uint8Array }, void];
     [WorkerEvent.Panic]: [Error, void];
   };
 };
@@ -173,6 +176,7 @@ export type TaskWorkerRpc = {
 export enum AsyncWorkerEvent {
   Run = "AsyncWorkerEvent.Run",
   ThreadSpawn = "AsyncWorkerEvent.ThreadSpawn",
+  SaveFile = "AsyncWorkerEvent.SaveFile",
 }
 export type AsyncWorkerRpc = {
   send: {
@@ -198,6 +202,7 @@ export type AsyncWorkerRpc = {
       },
       void
     ];
+    [AsyncWorkerEvent.SaveFile]: [{ name: string; data: Uint8Array }, void];
   };
 };
 
@@ -4,7 +4,6 @@ import {
   TW_SAB_MESSAGE_COUNT_PTR,
   mutexLock,
   mutexUnlock,
-  assertNotNull,
 } from "common";
 import { Worker, TaskWorkerRpc, TaskWorkerEvent } from "rpc_types";
 import { ZerdeParser } from "zerde";
@@ -83,7 +82,8 @@ rpc.receive(TaskWorkerEvent.Init, ({ taskWorkerSab, wasmMemory }) => {
   const streams: Record<
     number,
     {
-      reader: ReadableStreamDefaultReader<any>;
+      // Undefined if the response doesn't have a body.
+      reader: ReadableStreamDefaultReader<any> | undefined;
       done: boolean;
       values: Uint8Array[];
       error: boolean;
@@ -108,6 +108,10 @@ rpc.receive(TaskWorkerEvent.Init, ({ taskWorkerSab, wasmMemory }) => {
   // TODO(JP): We might want to set a limit to how much we buffer ahead? Or make it configurable per stream?
   function readDataIntoValuesBuffer(streamId: number) {
     const stream = streams[streamId];
+    if (!stream.reader) {
+      stream.done = true;
+      return;
+    }
     asyncTasks++;
     stream.reader
       .read()
@@ -223,38 +227,64 @@ rpc.receive(TaskWorkerEvent.Init, ({ taskWorkerSab, wasmMemory }) => {
       for (let headerIndex = 0; headerIndex < numberOfHeaders; headerIndex++) {
         headers[zerdeParser.parseString()] = zerdeParser.parseString();
       }
+      const redirect = zerdeParser.parseU32() ? "follow" : "error";
+      const timeoutMs = zerdeParser.parseU32();
+
+      // Aborting also makes reads of the body fail, so the timeout covers the entire request.
+      const abortController = new AbortController();
+      if (timeoutMs > 0) {
+        setTimeout(() => abortController.abort(), timeoutMs);
+      }
 
       asyncTasks++;
-      fetch(url, { method, body, headers })
+      fetch(url, {
+        method,
+        body,
+        headers,
+        redirect,
+        signal: abortController.signal,
+      })
         .then((response) => {
           asyncTasks--;
 
-          if (response.ok) {
-            const streamId = nextStreamId++;
-            streams[streamId] = {
-              // An asynchronous reader, which returns "chunks"/"values" of data.
-              // TODO(JP): Switch to "byob" when that's supported here; see
-              // https://bugs.chromium.org/p/chromium/issues/detail?id=614302#c23
-              reader: assertNotNull(response.body).getReader(),
-              // The buffered "chunks"/"values".
-              values: [],
-              // Whether we've read the whole stream into `values`.
-              done: false,
-              // Whether we encountered an error during reading.
-              error: false,
-              // The current read message to return data for, if any.
-              currentTwMessage: undefined,
-            };
-            readDataIntoValuesBuffer(streamId);
-            sendi32ReturnValue(streamIdReturnValPtr, streamId);
-          } else {
-            // TODO(JP): Actually return the status code to Rust at some point. For now you'll just
-            // have to look at the Network tab of the browser's developer tools.
-            sendi32ReturnValue(
-              streamIdReturnValPtr,
-              TASK_WORKER_ERROR_RETURN_VALUE
-            );
-          }
+          // Send the response info to Rust as the first bytes of the stream: status, status text, and URL,
+          // followed by the headers, all on separate lines, and prefixed with the length in bytes.
+          // See `parse_response_info` in Rust.
+          const responseInfoLines = [
+            response.status,
+            response.statusText,
+            response.url,
+          ];
+          response.headers.forEach((value, name) => {
+            responseInfoLines.push(`${name}: ${value}`);
+          });
+          const responseInfo = new TextEncoder().encode(
+            responseInfoLines.join("\n")
+          );
+          const responseInfoLength = new Uint8Array(4);
+          new DataView(responseInfoLength.buffer).setUint32(
+            0,
+            responseInfo.byteLength,
+            true
+          );
+
+          const streamId = nextStreamId++;
+          streams[streamId] = {
+            // An asynchronous reader, which returns "chunks"/"values" of data.
+            // TODO(JP): Switch to "byob" when that's supported here; see
+            // https://bugs.chromium.org/p/chromium/issues/detail?id=614302#c23
+            reader: response.body?.getReader(),
+            // The buffered "chunks"/"values".
+            values: [responseInfoLength, responseInfo],
+            // Whether we've read the whole stream into `values`.
+            done: false,
+            // Whether we encountered an error during reading.
+            error: false,
+            // The current read message to return data for, if any.
+            currentTwMessage: undefined,
+          };
+          readDataIntoValuesBuffer(streamId);
+          sendi32ReturnValue(streamIdReturnValPtr, streamId);
         })
         .catch((error) => {
           asyncTasks--;
@@ -33,9 +33,28 @@ export type ShaderAttributes = {
   textureSlots: Uniform[];
 };
 
+// Pixels read back by `WebGLRenderer` for `Pass::request_capture`, with the bottom row first.
+export type PassCapture = {
+  captureId: BigInt;
+  width: number;
+  height: number;
+  data: Uint8Array;
+};
+
+export type TextureSampler = {
+  minFilter: number;
+  magFilter: number;
+  wrapU: number;
+  wrapV: number;
+  mipmaps: boolean;
+};
+
 export type Texture = WebGLTexture & {
   mpWidth: number;
   mpHeight: number;
+  mpIsFloat?: boolean;
+  mpHasMipmaps?: boolean;
+  mpSampler?: TextureSampler;
 };
 
 export type FileHandle = {
@@ -65,6 +84,21 @@ export type WasmEnv = {
     bufPtrOut: number,
     bufLenOut: number
   ) => 1 | 0;
+  readUrlRangeSync: (
+    urlPtr: number,
+    urlLen: number,
+    rangeStart: BigInt,
+    rangeEnd: BigInt,
+    bufPtrOut: number,
+    bufLenOut: number,
+    totalSizeOut: number
+  ) => 2 | 1 | 0;
+  saveFile: (
+    namePtr: number,
+    nameLen: number,
+    dataPtr: BigInt,
+    dataLen: BigInt
+  ) => void;
   randomU64: () => BigInt;
   sendTaskWorkerMessage: (twMessagePtr: string) => void;
 };
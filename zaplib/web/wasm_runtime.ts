@@ -290,6 +290,40 @@ export const deserializeZapArrayFromPostMessage = (
   );
 };
 
+// Let the user save a file written from Rust, using the File System Access API if available, and
+// otherwise by triggering a download.
+const saveFile = async (name: string, data: Uint8Array): Promise<void> => {
+  const showSaveFilePicker = (globalThis as any).showSaveFilePicker;
+  if (showSaveFilePicker) {
+    try {
+      const handle = await showSaveFilePicker({ suggestedName: name });
+      const writable = await handle.createWritable();
+      await writable.write(data);
+      await writable.close();
+      return;
+    } catch (e) {
+      if (e instanceof DOMException && e.name === "AbortError") {
+        // The user cancelled the dialog.
+        return;
+      }
+      // E.g. a SecurityError when we're not handling a user gesture; fall back to a download.
+      console.warn(
+        "Saving using showSaveFilePicker failed; downloading instead",
+        e
+      );
+    }
+  }
+
+  const url = URL.createObjectURL(new Blob([data]));
+  const link = document.createElement("a");
+  link.href = url;
+  link.download = name;
+  document.body.appendChild(link);
+  link.click();
+  document.body.removeChild(link);
+  setTimeout(() => URL.revokeObjectURL(url), 0);
+};
+
 function initializeCanvas(
   canvas: HTMLCanvasElement,
   initParams: InitParams
@@ -481,9 +515,10 @@ function initializeCanvas(
     );
     rpc.receive(WorkerEvent.RunWebGL, (zerdeParserPtr) => {
       webglRenderer.processMessages(zerdeParserPtr);
+      const captures = webglRenderer.takeCaptures();
       return new Promise((resolve) => {
         requestAnimationFrame(() => {
-          resolve(undefined);
+          resolve(captures);
         });
       });
     });
@@ -618,6 +653,10 @@ export const initialize: Initialize = (initParams) => {
           "Sorry, we need browser support for WebGL to run<br/>Please update your browser to a more modern one<br/>Update to at least iOS 10, Safari 10, latest Chrome, Edge or Firefox<br/>Go and update and come back, your browser will be better, faster and more secure!<br/>If you are using chrome on OSX on a 2011/2012 mac please enable your GPU at: Override software rendering list:Enable (the top item) in: <a href='about://flags'>about://flags</a>. Or switch to Firefox or Safari.";
       });
 
+      rpc.receive(WorkerEvent.SaveFile, ({ name, data }) =>
+        saveFile(name, data)
+      );
+
       rpc.receive(WorkerEvent.SetDocumentTitle, (title: string) => {
         if (globalThis.document) document.title = title;
       });
@@ -786,6 +825,9 @@ export const initialize: Initialize = (initParams) => {
             .catch(onPanic);
 
           workerRpc.receive(AsyncWorkerEvent.ThreadSpawn, threadSpawn);
+          workerRpc.receive(AsyncWorkerEvent.SaveFile, ({ name, data }) =>
+            saveFile(name, data)
+          );
 
           workerRpc
             .send(
@@ -828,6 +870,7 @@ export const initialize: Initialize = (initParams) => {
           threadSpawn: () => {
             throw new Error("Not yet implemented");
           },
+          saveFile,
           baseUri,
         });
 
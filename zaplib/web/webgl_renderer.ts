@@ -2,12 +2,26 @@ import { assertNotNull } from "common";
 import {
   ShaderAttributes,
   SizingData,
+  PassCapture,
   Texture,
+  TextureSampler,
   Uniform,
   UniformType,
 } from "types";
 import { ZerdeParser } from "zerde";
 
+// Keep in sync with `update_texture_image2d` in `cx_webgl.rs`.
+const TEXTURE_FORMAT_RGBA = 0;
+const TEXTURE_FORMAT_R8 = 1;
+const TEXTURE_FORMAT_RG8 = 2;
+const TEXTURE_FORMAT_R32F = 3;
+const TEXTURE_FORMAT_RGBA_FLOAT = 4;
+
+// Keep in sync with `set_texture_sampler` in `cx_webgl.rs`.
+const TEXTURE_FILTER_NEAREST = 0;
+const TEXTURE_WRAP_REPEAT = 1;
+const TEXTURE_WRAP_MIRRORED_REPEAT = 2;
+
 type UniformLocation = {
   name: string;
   offset: number;
@@ -47,6 +61,8 @@ export class WebGLRenderer {
   private OESVertexArrayObject!: OES_vertex_array_object;
   // eslint-disable-next-line camelcase
   private ANGLEInstancedArrays!: ANGLE_instanced_arrays;
+  private hasFloatTextures = false;
+  private hasFloatTexturesLinear = false;
   private targetWidth: number;
   private targetHeight: number;
   private clearFlags: number;
@@ -55,6 +71,8 @@ export class WebGLRenderer {
   private clearB: number;
   private clearA: number;
   private clearDepth: number;
+  // Captures that have been read back, but not yet taken using `takeCaptures`.
+  private captures: PassCapture[] = [];
 
   private zerdeParser!: ZerdeParser;
   private basef32!: Float32Array;
@@ -113,6 +131,10 @@ export class WebGLRenderer {
     );
     this.gl.getExtension("OES_standard_derivatives");
     this.gl.getExtension("OES_element_index_uint");
+    this.hasFloatTextures = !!this.gl.getExtension("OES_texture_float");
+    this.hasFloatTexturesLinear = !!this.gl.getExtension(
+      "OES_texture_float_linear"
+    );
     this.resize(sizingData);
   }
 
@@ -131,6 +153,13 @@ export class WebGLRenderer {
     }
   }
 
+  // Get the captures that were read back during `processMessages`, so they can be sent to wasm.
+  takeCaptures(): PassCapture[] {
+    const captures = this.captures;
+    this.captures = [];
+    return captures;
+  }
+
   resize(sizingData: SizingData): void {
     this.sizingData = sizingData;
     this.canvas.width = sizingData.width * sizingData.dpiFactor;
@@ -431,22 +460,49 @@ export class WebGLRenderer {
     textureId: number,
     width: number,
     height: number,
+    format: number,
     dataPtr: number
   ): void {
     const gl = this.gl;
-    const glTex = this.textures[textureId] || gl.createTexture();
+    const glTex =
+      this.textures[textureId] ||
+      (this.textures[textureId] = gl.createTexture() as Texture);
 
+    const isFloat =
+      format === TEXTURE_FORMAT_R32F || format === TEXTURE_FORMAT_RGBA_FLOAT;
+    if (isFloat && !this.hasFloatTextures) {
+      console.error("Float textures are not supported in this browser");
+      return;
+    }
     gl.bindTexture(gl.TEXTURE_2D, glTex);
-    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MAG_FILTER, gl.LINEAR);
-    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.LINEAR);
-    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, gl.CLAMP_TO_EDGE);
-    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, gl.CLAMP_TO_EDGE);
-
-    const data = new Uint8Array(
-      this.memory.buffer,
-      dataPtr,
-      width * height * 4
-    );
+
+    const pixels = width * height;
+    let data: Uint8Array | Float32Array;
+    if (format === TEXTURE_FORMAT_RGBA) {
+      data = new Uint8Array(this.memory.buffer, dataPtr, pixels * 4);
+    } else if (format === TEXTURE_FORMAT_RGBA_FLOAT) {
+      data = new Float32Array(this.memory.buffer, dataPtr, pixels * 4);
+    } else {
+      // WebGL1 has no single or dual channel formats that we can sample as `r` and `rg`, so we
+      // expand those to RGBA.
+      const channels =
+        format === TEXTURE_FORMAT_R8 || format === TEXTURE_FORMAT_R32F ? 1 : 2;
+      const source =
+        format === TEXTURE_FORMAT_R32F
+          ? new Float32Array(this.memory.buffer, dataPtr, pixels)
+          : new Uint8Array(this.memory.buffer, dataPtr, pixels * channels);
+      data = isFloat
+        ? new Float32Array(pixels * 4)
+        : new Uint8Array(pixels * 4);
+      const one = isFloat ? 1 : 255;
+      for (let i = 0; i < pixels; i++) {
+        data[i * 4] = source[i * channels];
+        data[i * 4 + 1] = channels === 2 ? source[i * channels + 1] : 0;
+        data[i * 4 + 2] = 0;
+        data[i * 4 + 3] = one;
+      }
+    }
+
     gl.texImage2D(
       gl.TEXTURE_2D,
       0,
@@ -455,10 +511,120 @@ export class WebGLRenderer {
       height,
       0,
       gl.RGBA,
-      gl.UNSIGNED_BYTE,
+      isFloat ? gl.FLOAT : gl.UNSIGNED_BYTE,
       data
     );
-    this.textures[textureId] = glTex as Texture;
+    glTex.mpWidth = width;
+    glTex.mpHeight = height;
+    glTex.mpIsFloat = isFloat;
+    // WebGL1 can only generate mipmaps for power-of-two textures, and not for float textures.
+    glTex.mpHasMipmaps =
+      !!glTex.mpSampler?.mipmaps &&
+      !isFloat &&
+      isPowerOfTwo(width) &&
+      isPowerOfTwo(height);
+    if (glTex.mpHasMipmaps) {
+      gl.generateMipmap(gl.TEXTURE_2D);
+    }
+    this.applyTextureSampler(glTex);
+  }
+
+  // Set the parameters of `glTex` based on its `mpSampler`, assuming that it's currently bound.
+  private applyTextureSampler(glTex: Texture): void {
+    const gl = this.gl;
+    const sampler: TextureSampler = glTex.mpSampler || {
+      minFilter: 1,
+      magFilter: 1,
+      wrapU: 0,
+      wrapV: 0,
+      mipmaps: false,
+    };
+    // Float textures can't always be filtered linearly in WebGL1.
+    const canFilterLinear = !glTex.mpIsFloat || this.hasFloatTexturesLinear;
+    const filter = (value: number) =>
+      value === TEXTURE_FILTER_NEAREST || !canFilterLinear
+        ? gl.NEAREST
+        : gl.LINEAR;
+    let minFilter = filter(sampler.minFilter);
+    if (glTex.mpHasMipmaps) {
+      minFilter =
+        minFilter === gl.NEAREST
+          ? gl.NEAREST_MIPMAP_NEAREST
+          : gl.LINEAR_MIPMAP_LINEAR;
+    }
+    // WebGL1 only supports wrapping for power-of-two textures.
+    const canWrap =
+      isPowerOfTwo(glTex.mpWidth) && isPowerOfTwo(glTex.mpHeight);
+    const wrap = (value: number) =>
+      !canWrap
+        ? gl.CLAMP_TO_EDGE
+        : value === TEXTURE_WRAP_REPEAT
+        ? gl.REPEAT
+        : value === TEXTURE_WRAP_MIRRORED_REPEAT
+        ? gl.MIRRORED_REPEAT
+        : gl.CLAMP_TO_EDGE;
+    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, minFilter);
+    gl.texParameteri(
+      gl.TEXTURE_2D,
+      gl.TEXTURE_MAG_FILTER,
+      filter(sampler.magFilter)
+    );
+    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, wrap(sampler.wrapU));
+    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, wrap(sampler.wrapV));
+  }
+
+  private setTextureSampler(textureId: number, sampler: TextureSampler): void {
+    const gl = this.gl;
+    const glTex =
+      this.textures[textureId] ||
+      (this.textures[textureId] = gl.createTexture() as Texture);
+    glTex.mpSampler = sampler;
+    gl.bindTexture(gl.TEXTURE_2D, glTex);
+    this.applyTextureSampler(glTex);
+  }
+
+  private deleteTexture(textureId: number): void {
+    const gl = this.gl;
+    const glTex = this.textures[textureId];
+    if (glTex instanceof WebGLRenderbuffer) {
+      // Depth textures are actually renderbuffers; see `setDepthTarget`.
+      gl.deleteRenderbuffer(glTex);
+    } else if (glTex) {
+      gl.deleteTexture(glTex);
+    }
+    delete this.textures[textureId];
+  }
+
+  private deleteFramebuffer(passId: number): void {
+    const glFramebuffer = this.framebuffers[passId];
+    if (glFramebuffer) {
+      this.gl.deleteFramebuffer(glFramebuffer);
+    }
+    delete this.framebuffers[passId];
+  }
+
+  private deleteArrayBuffer(arrayBufferId: number): void {
+    const buf = this.arrayBuffers[arrayBufferId];
+    if (buf) {
+      this.gl.deleteBuffer(buf.glBuf);
+    }
+    delete this.arrayBuffers[arrayBufferId];
+  }
+
+  private deleteIndexBuffer(indexBufferId: number): void {
+    const buf = this.indexBuffers[indexBufferId];
+    if (buf) {
+      this.gl.deleteBuffer(buf.glBuf);
+    }
+    delete this.indexBuffers[indexBufferId];
+  }
+
+  private deleteVao(vaoId: number): void {
+    const vao = this.vaos[vaoId];
+    if (vao) {
+      this.OESVertexArrayObject.deleteVertexArrayOES(vao.glVao);
+    }
+    delete this.vaos[vaoId];
   }
 
   private beginRenderTargets(
@@ -506,10 +672,9 @@ export class WebGLRenderer {
 
       glTex.mpWidth = this.targetWidth;
       glTex.mpHeight = this.targetHeight;
-      gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MAG_FILTER, gl.LINEAR);
-      gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.LINEAR);
-      gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, gl.CLAMP_TO_EDGE);
-      gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, gl.CLAMP_TO_EDGE);
+      glTex.mpIsFloat = false;
+      glTex.mpHasMipmaps = false;
+      this.applyTextureSampler(glTex);
 
       gl.texImage2D(
         gl.TEXTURE_2D,
@@ -643,6 +808,15 @@ export class WebGLRenderer {
     gl.clear(gl.COLOR_BUFFER_BIT | gl.DEPTH_BUFFER_BIT);
   }
 
+  // Read back the pixels of the current render target, which has the same size as the viewport.
+  private capturePass(captureId: BigInt): void {
+    const gl = this.gl;
+    const [, , width, height] = gl.getParameter(gl.VIEWPORT) as Int32Array;
+    const data = new Uint8Array(width * height * 4);
+    gl.readPixels(0, 0, width, height, gl.RGBA, gl.UNSIGNED_BYTE, data);
+    this.captures.push({ captureId, width, height, data });
+  }
+
   private uniformFnTable: Record<
     string,
     (zelf: WebGLRenderer, loc: WebGLUniformLocation | null, off: number) => void
@@ -783,8 +957,9 @@ export class WebGLRenderer {
       const textureId = zelf.zerdeParser.parseU32();
       const width = zelf.zerdeParser.parseU32();
       const height = zelf.zerdeParser.parseU32();
+      const format = zelf.zerdeParser.parseU32();
       const dataPtr = zelf.zerdeParser.parseU32();
-      zelf.allocTexture(textureId, width, height, dataPtr);
+      zelf.allocTexture(textureId, width, height, format, dataPtr);
     },
     // begin_render_targets
     function beginRenderTargets7(zelf) {
@@ -827,6 +1002,46 @@ export class WebGLRenderer {
       const depth = zelf.zerdeParser.parseF32();
       zelf.beginMainCanvas(r, g, b, a, depth);
     },
+    // set_texture_sampler
+    function setTextureSampler13(zelf) {
+      const textureId = zelf.zerdeParser.parseU32();
+      const minFilter = zelf.zerdeParser.parseU32();
+      const magFilter = zelf.zerdeParser.parseU32();
+      const wrapU = zelf.zerdeParser.parseU32();
+      const wrapV = zelf.zerdeParser.parseU32();
+      const mipmaps = zelf.zerdeParser.parseU32() === 1;
+      zelf.setTextureSampler(textureId, {
+        minFilter,
+        magFilter,
+        wrapU,
+        wrapV,
+        mipmaps,
+      });
+    },
+    // capture_pass
+    function capturePass14(zelf) {
+      zelf.capturePass(zelf.zerdeParser.parseU64());
+    },
+    // delete_texture
+    function deleteTexture15(zelf) {
+      zelf.deleteTexture(zelf.zerdeParser.parseU32());
+    },
+    // delete_framebuffer
+    function deleteFramebuffer16(zelf) {
+      zelf.deleteFramebuffer(zelf.zerdeParser.parseU32());
+    },
+    // delete_array_buffer
+    function deleteArrayBuffer17(zelf) {
+      zelf.deleteArrayBuffer(zelf.zerdeParser.parseU32());
+    },
+    // delete_index_buffer
+    function deleteIndexBuffer18(zelf) {
+      zelf.deleteIndexBuffer(zelf.zerdeParser.parseU32());
+    },
+    // delete_vao
+    function deleteVao19(zelf) {
+      zelf.deleteVao(zelf.zerdeParser.parseU32());
+    },
   ];
 }
 
@@ -840,6 +1055,10 @@ const uniformSizeTable = {
   mat4: 16,
 };
 
+function isPowerOfTwo(value: number) {
+  return value > 0 && (value & (value - 1)) === 0;
+}
+
 function addLineNumbersToString(code: string) {
   const lines = code.split("\n");
   let out = "";
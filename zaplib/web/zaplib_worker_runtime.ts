@@ -105,6 +105,9 @@ export const initializeWorker = (zapWorkerPort: MessagePort): Promise<void> => {
             threadSpawn: () => {
               throw new Error("Not yet implemented");
             },
+            saveFile: () => {
+              throw new Error("Not yet implemented");
+            },
             baseUri,
           });
 
@@ -7,6 +7,7 @@ import {
 } from "make_textarea";
 import {
   FileHandle,
+  PassCapture,
   PostMessageTypedArray,
   ZapArray,
   ZapParamType,
@@ -29,7 +30,6 @@ const MSG_TYPE_POINTER_OUT = 11;
 const MSG_TYPE_TIMER_FIRED = 18;
 const MSG_TYPE_WINDOW_FOCUS = 19;
 const MSG_TYPE_PAINT_DIRTY = 21;
-const MSG_TYPE_HTTP_SEND_RESPONSE = 22;
 const MSG_TYPE_WEBSOCKET_MESSAGE = 23;
 const MSG_TYPE_WEBSOCKET_ERROR = 24;
 const MSG_TYPE_APP_OPEN_FILES = 25;
@@ -38,6 +38,7 @@ const MSG_TYPE_DRAG_ENTER = 27;
 const MSG_TYPE_DRAG_LEAVE = 28;
 const MSG_TYPE_DRAG_OVER = 29;
 const MSG_TYPE_CALL_RUST = 30;
+const MSG_TYPE_PASS_CAPTURED = 31;
 
 // A set of events. Each event starts with a u32 representing the event type, with 0 indicating the end. And
 // it is prefixed by a timestamp.
@@ -261,12 +262,6 @@ export class ZerdeEventloopEvents {
     this._zerdeBuilder.sendU32(MSG_TYPE_PAINT_DIRTY);
   }
 
-  httpSendResponse(signalId: number, success: 1 | 2): void {
-    this._zerdeBuilder.sendU32(MSG_TYPE_HTTP_SEND_RESPONSE);
-    this._zerdeBuilder.sendU32(signalId);
-    this._zerdeBuilder.sendU32(success);
-  }
-
   sendEventFromAnyThread(eventPtr: BigInt): void {
     this._zerdeBuilder.sendU32(MSG_TYPE_SEND_EVENT_FROM_ANY_THREAD);
     this._zerdeBuilder.sendU64(eventPtr);
@@ -281,6 +276,17 @@ export class ZerdeEventloopEvents {
     this._zerdeBuilder.sendString(url);
   }
 
+  passCaptured(capture: PassCapture): void {
+    const vecLen = capture.data.byteLength;
+    const vecPtr = this.createWasmBuffer(capture.data);
+    this._zerdeBuilder.sendU32(MSG_TYPE_PASS_CAPTURED);
+    this._zerdeBuilder.sendU64(capture.captureId);
+    this._zerdeBuilder.sendU32(capture.width);
+    this._zerdeBuilder.sendU32(capture.height);
+    this._zerdeBuilder.sendU32(vecPtr);
+    this._zerdeBuilder.sendU32(vecLen);
+  }
+
   websocketError(url: string, error: string): void {
     this._zerdeBuilder.sendU32(MSG_TYPE_WEBSOCKET_ERROR);
     this._zerdeBuilder.sendString(url);